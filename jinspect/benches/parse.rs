@@ -0,0 +1,112 @@
+// Compares the streaming parser with the zero-copy `ClassView`, both reading files and parsing
+// bytes that are already in memory. Every pass resolves the class name and the method names so
+// that the lazy parser does not get away with skipping the pool.
+//
+//     cargo bench --bench parse -- [directory of class files, default samples]
+
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use jinspect::view::ClassView;
+use jinspect::ClassFile;
+use memmap2::Mmap;
+
+// Classes parsed per measurement, small directories are parsed repeatedly.
+const WORK: usize = 20_000;
+
+fn class_files(dir: &Path, acc: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).expect("readable directory") {
+        let path = entry.expect("directory entry").path();
+        if path.is_dir() {
+            class_files(&path, acc);
+        } else if path.extension().is_some_and(|ext| ext == "class") {
+            acc.push(path);
+        }
+    }
+}
+
+fn streaming(class: &ClassFile) -> usize {
+    let pool = &class.constant_pool;
+    let names: usize = class.methods.iter().map(|m| m.name(pool).len()).sum();
+    class.this_class_name().len() + names
+}
+
+fn view(class: &ClassView) -> usize {
+    let names: usize = class
+        .methods
+        .iter()
+        .map(|m| m.name(class).map_or(0, |name| name.len()))
+        .sum();
+    class.this_class_name().len() + names
+}
+
+fn measure<F: FnMut() -> usize>(name: &str, classes: usize, rounds: usize, mut pass: F) {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        black_box(pass());
+    }
+    let elapsed = start.elapsed();
+    let per_class = elapsed / (classes * rounds) as u32;
+    println!(
+        "{:<24} {:>10.2?} total {:>10.2?} per class",
+        name,
+        elapsed,
+        per_class.max(Duration::from_nanos(1))
+    );
+}
+
+fn main() {
+    // `cargo bench` passes `--bench` to benchmarks without the default harness.
+    let dir = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("samples"));
+    let mut paths = Vec::new();
+    class_files(&dir, &mut paths);
+    assert!(!paths.is_empty(), "no class files in {}", dir.display());
+    let contents: Vec<Vec<u8>> = paths.iter().map(|path| fs::read(path).unwrap()).collect();
+    let rounds = WORK.div_ceil(paths.len());
+    println!(
+        "{} classes from {}, {} rounds",
+        paths.len(),
+        dir.display(),
+        rounds
+    );
+
+    measure("streaming file", paths.len(), rounds, || {
+        paths
+            .iter()
+            .map(|path| {
+                let file = BufReader::new(File::open(path).unwrap());
+                streaming(&ClassFile::parse(file).unwrap())
+            })
+            .sum()
+    });
+    measure("view mmap", paths.len(), rounds, || {
+        paths
+            .iter()
+            .map(|path| {
+                let file = File::open(path).unwrap();
+                // SAFETY: the benchmark owns the files and nothing modifies them while mapped.
+                let map = unsafe { Mmap::map(&file) }.unwrap();
+                view(&ClassView::parse(&map).unwrap())
+            })
+            .sum()
+    });
+    measure("streaming in memory", paths.len(), rounds, || {
+        contents
+            .iter()
+            .map(|bytes| streaming(&ClassFile::parse(bytes.as_slice()).unwrap()))
+            .sum()
+    });
+    measure("view in memory", paths.len(), rounds, || {
+        contents
+            .iter()
+            .map(|bytes| view(&ClassView::parse(bytes).unwrap()))
+            .sum()
+    });
+}
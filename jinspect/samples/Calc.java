@@ -0,0 +1,68 @@
+// Static-only program for the interpreter tests: arithmetic of every primitive type, arrays,
+// recursion, switches, static fields and caught exceptions.
+public class Calc {
+    static final int LIMIT = 10;
+    static long calls;
+    static int[] squares = new int[LIMIT];
+
+    static int fib(int n) {
+        calls++;
+        return n < 2 ? n : fib(n - 1) + fib(n - 2);
+    }
+
+    static long factorial(int n) {
+        long acc = 1;
+        for (int i = 2; i <= n; i++) {
+            acc *= i;
+        }
+        return acc;
+    }
+
+    static double mean(double[] values) {
+        double sum = 0;
+        for (double value : values) {
+            sum += value;
+        }
+        return sum / values.length;
+    }
+
+    static String name(int day) {
+        switch (day) {
+            case 1: return "mon";
+            case 2: return "tue";
+            case 3: return "wed";
+            default: return "day" + day;
+        }
+    }
+
+    static int divide(int a, int b) {
+        try {
+            return a / b;
+        } catch (ArithmeticException e) {
+            System.out.println("caught " + e);
+            return -1;
+        }
+    }
+
+    public static void main(String[] args) {
+        for (int i = 0; i < LIMIT; i++) {
+            squares[i] = i * i;
+        }
+        System.out.println("fib(20) = " + fib(20) + " in " + calls + " calls");
+        System.out.println("20! = " + factorial(20));
+        System.out.println(mean(new double[] {1.5, 2.5, 4.0}));
+        System.out.println(1.0f / 3 + " " + (float) Math.PI + " " + 1e10);
+        System.out.println(name(2) + " " + name(1000));
+        System.out.println(divide(7, 2) + " " + divide(1, 0));
+        char[] word = {'j', 'v', 'm'};
+        word[1] = (char) (word[1] - 21);
+        System.out.println(word);
+        int[][] grid = new int[3][4];
+        grid[2][3] = squares[9] >> 2;
+        System.out.println(grid[2][3] + " " + grid.length + " " + grid[0].length);
+        System.out.println(Integer.MAX_VALUE + 1);
+        System.out.println(-7 % 3 + " " + (-7 >>> 28) + " " + (5L << 62) + " " + (byte) 200);
+        System.out.println(0.1 + 0.2 == 0.3);
+        System.out.println(args.length);
+    }
+}
@@ -0,0 +1,56 @@
+import java.util.*;
+import java.util.function.*;
+
+public class Sample<T extends Comparable<T>> implements Runnable {
+    public static final int ANSWER = 42;
+    public static final String NAME = "rich\u0000😀é";
+    private List<? extends T> items = new ArrayList<>();
+    static final long BIG = 1234567890123L;
+    static final double PI = 3.14159;
+    static final float F = Float.NaN;
+
+    record Point(int x, int y) {}
+    sealed interface Shape permits Circle, Square {}
+    static final class Circle implements Shape {}
+    static final class Square implements Shape {}
+
+    class Inner { int v = ANSWER; }
+
+    public void run() {
+        Runnable r = () -> System.out.println("lambda " + items.size());
+        r.run();
+        Object o = new Object() { public String toString() { return "anon"; } };
+    }
+
+    @Deprecated
+    public static int sw(int k) throws java.io.IOException {
+        switch (k) {
+            case 1: return 10;
+            case 2: return 20;
+            case 3: return 30;
+            case 100: return 1000;
+        }
+        switch (k) {
+            case 1: return 5;
+            case 1000: return 6;
+            case 100000: return 7;
+        }
+        try {
+            if (k < 0) throw new java.io.IOException("neg");
+        } catch (IllegalStateException e) {
+            return -1;
+        } finally {
+            k++;
+        }
+        int[] arr = new int[300];
+        long acc = 0;
+        for (int i = 0; i < arr.length; i++) { arr[i] = i * 2; acc += arr[i]; }
+        double d = acc / 3.0;
+        return (int) d;
+    }
+
+    public static void main(String[] args) throws Exception {
+        System.out.println(sw(3));
+        new Sample<String>().run();
+    }
+}
@@ -0,0 +1,699 @@
+// Annotations as stored in the Runtime(In)Visible(Parameter|Type)Annotations and AnnotationDefault
+// attributes (JVMS 4.7.16 - 4.7.22). Values keep their pool indexes, `to_java` renders them the
+// way they are written in source code.
+
+use std::io::{self, Read, Write};
+
+use crate::error::{ErrorKind, ParseError};
+use crate::literal;
+use crate::pool::{ConstantPool, ConstantPoolItem};
+use crate::reader::ClassReader;
+use crate::signature::{self, java_name};
+use crate::writer::ClassWriter;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub type_index: u16, // field descriptor of the annotation interface
+    pub elements: Vec<ElementPair>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementPair {
+    pub name_index: u16,
+    pub value: ElementValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementValue {
+    Const(u8, u16), // tag (one of BCDFIJSZs), constValueIndex
+    Enum(u16, u16), // typeNameIndex, constNameIndex
+    Class(u16),     // classInfoIndex, a return descriptor
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotation {
+    pub target_type: u8,
+    pub target: TypeAnnotationTarget,
+    pub path: Vec<TypePathEntry>,
+    pub annotation: Annotation,
+}
+
+/// The `target_info` union, which variant is used follows from the target type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeAnnotationTarget {
+    TypeParameter(u8),               // typeParameterIndex
+    Supertype(u16),                  // supertypeIndex, 65535 is the superclass
+    TypeParameterBound(u8, u8),      // typeParameterIndex, boundIndex
+    Empty,                           // field, return type or receiver
+    FormalParameter(u8),             // formalParameterIndex
+    Throws(u16),                     // throwsTypeIndex
+    LocalVariable(Vec<LocalTarget>), // table
+    Catch(u16),                      // exceptionTableIndex
+    Offset(u16),                     // offset of the instruction
+    TypeArgument(u16, u8),           // offset, typeArgumentIndex
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalTarget {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypePathEntry {
+    pub kind: u8, // 0 array, 1 nested type, 2 wildcard bound, 3 type argument
+    pub argument_index: u8,
+}
+
+/// Nesting of annotation and array element values accepted before a class is rejected, deeper
+/// values would only serve to exhaust the stack of the recursive parser.
+const MAX_ELEMENT_DEPTH: usize = 256;
+
+pub(crate) fn parse_annotation<R: Read>(
+    rdr: &mut ClassReader<R>,
+) -> Result<Annotation, ParseError> {
+    parse_nested_annotation(rdr, 0)
+}
+
+fn parse_nested_annotation<R: Read>(
+    rdr: &mut ClassReader<R>,
+    depth: usize,
+) -> Result<Annotation, ParseError> {
+    let type_index = rdr.read_u16()?;
+    let count = rdr.read_u16()?;
+    let elements = (0..count)
+        .map(|_| {
+            Ok(ElementPair {
+                name_index: rdr.read_u16()?,
+                value: parse_nested_element_value(rdr, depth)?,
+            })
+        })
+        .collect::<Result<_, ParseError>>()?;
+    Ok(Annotation {
+        type_index,
+        elements,
+    })
+}
+
+pub(crate) fn parse_element_value<R: Read>(
+    rdr: &mut ClassReader<R>,
+) -> Result<ElementValue, ParseError> {
+    parse_nested_element_value(rdr, 0)
+}
+
+fn parse_nested_element_value<R: Read>(
+    rdr: &mut ClassReader<R>,
+    depth: usize,
+) -> Result<ElementValue, ParseError> {
+    let tag = rdr.read_u8()?;
+    if depth >= MAX_ELEMENT_DEPTH && matches!(tag, b'@' | b'[') {
+        return Err(ParseError::new(
+            rdr.offset() - 1,
+            ErrorKind::Malformed(format!(
+                "element values nested deeper than {}",
+                MAX_ELEMENT_DEPTH
+            )),
+        ));
+    }
+    let value = match tag {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
+            ElementValue::Const(tag, rdr.read_u16()?)
+        }
+        b'e' => ElementValue::Enum(rdr.read_u16()?, rdr.read_u16()?),
+        b'c' => ElementValue::Class(rdr.read_u16()?),
+        b'@' => ElementValue::Annotation(parse_nested_annotation(rdr, depth + 1)?),
+        b'[' => {
+            let count = rdr.read_u16()?;
+            ElementValue::Array(
+                (0..count)
+                    .map(|_| parse_nested_element_value(rdr, depth + 1))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        _ => {
+            return Err(ParseError::new(
+                rdr.offset() - 1,
+                ErrorKind::Malformed(format!("unknown element value tag 0x{:02X}", tag)),
+            ))
+        }
+    };
+    Ok(value)
+}
+
+pub(crate) fn parse_annotations<R: Read>(
+    rdr: &mut ClassReader<R>,
+) -> Result<Vec<Annotation>, ParseError> {
+    let count = rdr.read_u16()?;
+    (0..count).map(|_| parse_annotation(rdr)).collect()
+}
+
+pub(crate) fn parse_parameter_annotations<R: Read>(
+    rdr: &mut ClassReader<R>,
+) -> Result<Vec<Vec<Annotation>>, ParseError> {
+    let count = rdr.read_u8()?;
+    (0..count).map(|_| parse_annotations(rdr)).collect()
+}
+
+fn parse_target<R: Read>(
+    rdr: &mut ClassReader<R>,
+    target_type: u8,
+) -> Result<TypeAnnotationTarget, ParseError> {
+    let target = match target_type {
+        0x00 | 0x01 => TypeAnnotationTarget::TypeParameter(rdr.read_u8()?),
+        0x10 => TypeAnnotationTarget::Supertype(rdr.read_u16()?),
+        0x11 | 0x12 => TypeAnnotationTarget::TypeParameterBound(rdr.read_u8()?, rdr.read_u8()?),
+        0x13..=0x15 => TypeAnnotationTarget::Empty,
+        0x16 => TypeAnnotationTarget::FormalParameter(rdr.read_u8()?),
+        0x17 => TypeAnnotationTarget::Throws(rdr.read_u16()?),
+        0x40 | 0x41 => {
+            let count = rdr.read_u16()?;
+            TypeAnnotationTarget::LocalVariable(
+                (0..count)
+                    .map(|_| {
+                        Ok(LocalTarget {
+                            start_pc: rdr.read_u16()?,
+                            length: rdr.read_u16()?,
+                            index: rdr.read_u16()?,
+                        })
+                    })
+                    .collect::<Result<_, ParseError>>()?,
+            )
+        }
+        0x42 => TypeAnnotationTarget::Catch(rdr.read_u16()?),
+        0x43..=0x46 => TypeAnnotationTarget::Offset(rdr.read_u16()?),
+        0x47..=0x4B => TypeAnnotationTarget::TypeArgument(rdr.read_u16()?, rdr.read_u8()?),
+        _ => {
+            return Err(ParseError::new(
+                rdr.offset() - 1,
+                ErrorKind::Malformed(format!(
+                    "unknown type annotation target 0x{:02X}",
+                    target_type
+                )),
+            ))
+        }
+    };
+    Ok(target)
+}
+
+pub(crate) fn parse_type_annotations<R: Read>(
+    rdr: &mut ClassReader<R>,
+) -> Result<Vec<TypeAnnotation>, ParseError> {
+    let count = rdr.read_u16()?;
+    (0..count)
+        .map(|_| {
+            let target_type = rdr.read_u8()?;
+            let target = parse_target(rdr, target_type)?;
+            let length = rdr.read_u8()?;
+            let path = (0..length)
+                .map(|_| {
+                    Ok(TypePathEntry {
+                        kind: rdr.read_u8()?,
+                        argument_index: rdr.read_u8()?,
+                    })
+                })
+                .collect::<Result<_, ParseError>>()?;
+            Ok(TypeAnnotation {
+                target_type,
+                target,
+                path,
+                annotation: parse_annotation(rdr)?,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn write_annotation<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    annotation: &Annotation,
+) -> io::Result<()> {
+    wtr.write_u16(annotation.type_index)?;
+    wtr.write_count("annotation elements", annotation.elements.len())?;
+    annotation.elements.iter().try_for_each(|pair| {
+        wtr.write_u16(pair.name_index)?;
+        write_element_value(wtr, &pair.value)
+    })
+}
+
+pub(crate) fn write_element_value<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    value: &ElementValue,
+) -> io::Result<()> {
+    match value {
+        ElementValue::Const(tag, index) => {
+            wtr.write_u8(*tag)?;
+            wtr.write_u16(*index)
+        }
+        ElementValue::Enum(type_index, name_index) => {
+            wtr.write_u8(b'e')?;
+            wtr.write_u16(*type_index)?;
+            wtr.write_u16(*name_index)
+        }
+        ElementValue::Class(index) => {
+            wtr.write_u8(b'c')?;
+            wtr.write_u16(*index)
+        }
+        ElementValue::Annotation(annotation) => {
+            wtr.write_u8(b'@')?;
+            write_annotation(wtr, annotation)
+        }
+        ElementValue::Array(values) => {
+            wtr.write_u8(b'[')?;
+            wtr.write_count("array values", values.len())?;
+            values
+                .iter()
+                .try_for_each(|value| write_element_value(wtr, value))
+        }
+    }
+}
+
+pub(crate) fn write_annotations<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    annotations: &[Annotation],
+) -> io::Result<()> {
+    wtr.write_count("annotations", annotations.len())?;
+    annotations
+        .iter()
+        .try_for_each(|annotation| write_annotation(wtr, annotation))
+}
+
+pub(crate) fn write_parameter_annotations<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    parameters: &[Vec<Annotation>],
+) -> io::Result<()> {
+    let count = u8::try_from(parameters.len()).map_err(|_| {
+        let msg = format!("{} annotated parameters", parameters.len());
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    })?;
+    wtr.write_u8(count)?;
+    parameters
+        .iter()
+        .try_for_each(|annotations| write_annotations(wtr, annotations))
+}
+
+pub(crate) fn write_type_annotations<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    annotations: &[TypeAnnotation],
+) -> io::Result<()> {
+    wtr.write_count("type annotations", annotations.len())?;
+    for item in annotations.iter() {
+        wtr.write_u8(item.target_type)?;
+        match &item.target {
+            TypeAnnotationTarget::TypeParameter(index)
+            | TypeAnnotationTarget::FormalParameter(index) => wtr.write_u8(*index)?,
+            TypeAnnotationTarget::Supertype(index)
+            | TypeAnnotationTarget::Throws(index)
+            | TypeAnnotationTarget::Catch(index)
+            | TypeAnnotationTarget::Offset(index) => wtr.write_u16(*index)?,
+            TypeAnnotationTarget::TypeParameterBound(parameter, bound) => {
+                wtr.write_u8(*parameter)?;
+                wtr.write_u8(*bound)?;
+            }
+            TypeAnnotationTarget::Empty => {}
+            TypeAnnotationTarget::LocalVariable(table) => {
+                wtr.write_count("local variable targets", table.len())?;
+                for local in table.iter() {
+                    wtr.write_u16(local.start_pc)?;
+                    wtr.write_u16(local.length)?;
+                    wtr.write_u16(local.index)?;
+                }
+            }
+            TypeAnnotationTarget::TypeArgument(offset, index) => {
+                wtr.write_u16(*offset)?;
+                wtr.write_u8(*index)?;
+            }
+        }
+        let length = u8::try_from(item.path.len()).map_err(|_| {
+            let msg = format!("type path of length {}", item.path.len());
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        })?;
+        wtr.write_u8(length)?;
+        for entry in item.path.iter() {
+            wtr.write_u8(entry.kind)?;
+            wtr.write_u8(entry.argument_index)?;
+        }
+        write_annotation(wtr, &item.annotation)?;
+    }
+    Ok(())
+}
+
+/// `Ljava/lang/Deprecated;` => `java.lang.Deprecated`, `V` => `void`.
+fn descriptor_name(pool: &ConstantPool, index: u16) -> String {
+    let descriptor = pool.resolve(index);
+    match descriptor.as_str() {
+        "V" => "void".to_string(),
+        _ => signature::parse_field_descriptor(&descriptor)
+            .map(|item| item.to_string())
+            .unwrap_or(descriptor),
+    }
+}
+
+impl Annotation {
+    /// Internal name of the annotation interface, e.g. `java/lang/Deprecated`.
+    pub fn type_name(&self, pool: &ConstantPool) -> String {
+        let descriptor = pool.resolve(self.type_index);
+        match descriptor
+            .strip_prefix('L')
+            .and_then(|rest| rest.strip_suffix(';'))
+        {
+            Some(name) => name.to_string(),
+            None => descriptor,
+        }
+    }
+
+    /// e.g. `@java.lang.Deprecated(since="9", forRemoval=true)`, a lone `value` element is
+    /// written without its name.
+    pub fn to_java(&self, pool: &ConstantPool) -> String {
+        let name = java_name(&self.type_name(pool));
+        match self.elements.as_slice() {
+            [] => format!("@{}", name),
+            [pair] if pool.resolve(pair.name_index) == "value" => {
+                format!("@{}({})", name, pair.value.to_java(pool))
+            }
+            pairs => {
+                let acc: Vec<String> = pairs
+                    .iter()
+                    .map(|pair| {
+                        format!(
+                            "{}={}",
+                            pool.resolve(pair.name_index),
+                            pair.value.to_java(pool)
+                        )
+                    })
+                    .collect();
+                format!("@{}({})", name, acc.join(", "))
+            }
+        }
+    }
+}
+
+impl ElementValue {
+    /// Source form of the value, e.g. `10L`, `java.lang.annotation.ElementType.FIELD` or
+    /// `{"a", "b"}`.
+    pub fn to_java(&self, pool: &ConstantPool) -> String {
+        match self {
+            Self::Const(tag, index) => match (tag, pool.get(*index)) {
+                (b'Z', Ok(ConstantPoolItem::Integer(value))) => (*value != 0).to_string(),
+                (b'C', Ok(ConstantPoolItem::Integer(value))) => {
+                    literal::char_literal(*value as u16)
+                }
+                (b'B', Ok(ConstantPoolItem::Integer(value))) => format!("(byte) {}", value),
+                (b'S', Ok(ConstantPoolItem::Integer(value))) => format!("(short) {}", value),
+                (_, Ok(ConstantPoolItem::Integer(value))) => literal::int_literal(*value),
+                (_, Ok(ConstantPoolItem::Long(value))) => format!("{}L", value),
+                (_, Ok(ConstantPoolItem::Float(value))) => literal::float_literal(*value),
+                (_, Ok(ConstantPoolItem::Double(value))) => literal::double_to_string(*value),
+                (b's', Ok(ConstantPoolItem::Utf8(value))) => literal::string_literal(value),
+                _ => pool.resolve(*index),
+            },
+            Self::Enum(type_index, name_index) => format!(
+                "{}.{}",
+                descriptor_name(pool, *type_index),
+                pool.resolve(*name_index)
+            ),
+            Self::Class(index) => format!("{}.class", descriptor_name(pool, *index)),
+            Self::Annotation(annotation) => annotation.to_java(pool),
+            Self::Array(values) => {
+                let acc: Vec<String> = values.iter().map(|value| value.to_java(pool)).collect();
+                format!("{{{}}}", acc.join(", "))
+            }
+        }
+    }
+}
+
+impl TypeAnnotation {
+    /// The JLS name of the target type, e.g. `METHOD_FORMAL_PARAMETER`.
+    pub fn target_name(&self) -> &'static str {
+        match self.target_type {
+            0x00 => "CLASS_TYPE_PARAMETER",
+            0x01 => "METHOD_TYPE_PARAMETER",
+            0x10 => "CLASS_EXTENDS",
+            0x11 => "CLASS_TYPE_PARAMETER_BOUND",
+            0x12 => "METHOD_TYPE_PARAMETER_BOUND",
+            0x13 => "FIELD",
+            0x14 => "METHOD_RETURN",
+            0x15 => "METHOD_RECEIVER",
+            0x16 => "METHOD_FORMAL_PARAMETER",
+            0x17 => "THROWS",
+            0x40 => "LOCAL_VARIABLE",
+            0x41 => "RESOURCE_VARIABLE",
+            0x42 => "EXCEPTION_PARAMETER",
+            0x43 => "INSTANCEOF",
+            0x44 => "NEW",
+            0x45 => "CONSTRUCTOR_REFERENCE",
+            0x46 => "METHOD_REFERENCE",
+            0x47 => "CAST",
+            0x48 => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+            0x49 => "METHOD_INVOCATION_TYPE_ARGUMENT",
+            0x4A => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+            0x4B => "METHOD_REFERENCE_TYPE_ARGUMENT",
+            _ => "UNKNOWN",
+        }
+    }
+
+    /// The target with its operands, e.g. `METHOD_FORMAL_PARAMETER, param_index=0`.
+    pub fn target_to_string(&self) -> String {
+        let name = self.target_name();
+        match &self.target {
+            TypeAnnotationTarget::TypeParameter(index) => {
+                format!("{}, param_index={}", name, index)
+            }
+            TypeAnnotationTarget::Supertype(index) => format!("{}, type_index={}", name, index),
+            TypeAnnotationTarget::TypeParameterBound(index, bound) => {
+                format!("{}, param_index={}, bound_index={}", name, index, bound)
+            }
+            TypeAnnotationTarget::Empty => name.to_string(),
+            TypeAnnotationTarget::FormalParameter(index) => {
+                format!("{}, param_index={}", name, index)
+            }
+            TypeAnnotationTarget::Throws(index) => format!("{}, throws_index={}", name, index),
+            TypeAnnotationTarget::LocalVariable(table) => {
+                let acc: Vec<String> = table
+                    .iter()
+                    .map(|local| {
+                        format!(
+                            "start_pc={}, length={}, index={}",
+                            local.start_pc, local.length, local.index
+                        )
+                    })
+                    .collect();
+                format!("{}, {{{}}}", name, acc.join("; "))
+            }
+            TypeAnnotationTarget::Catch(index) => {
+                format!("{}, exception_index={}", name, index)
+            }
+            TypeAnnotationTarget::Offset(offset) => format!("{}, offset={}", name, offset),
+            TypeAnnotationTarget::TypeArgument(offset, index) => {
+                format!("{}, offset={}, type_index={}", name, offset, index)
+            }
+        }
+    }
+
+    /// The type path in javap notation, e.g. `[ARRAY, TYPE_ARGUMENT(0)]`, empty for the type
+    /// itself.
+    pub fn location(&self) -> Vec<String> {
+        self.path
+            .iter()
+            .map(|entry| match entry.kind {
+                0 => "ARRAY".to_string(),
+                1 => "INNER_TYPE".to_string(),
+                2 => "WILDCARD".to_string(),
+                3 => format!("TYPE_ARGUMENT({})", entry.argument_index),
+                kind => format!("UNKNOWN({})", kind),
+            })
+            .collect()
+    }
+
+    /// e.g. `@org.example.NonNull FIELD, location=[TYPE_ARGUMENT(0)]`
+    pub fn resolve(&self, pool: &ConstantPool) -> String {
+        let location = self.location();
+        if location.is_empty() {
+            format!(
+                "{} {}",
+                self.annotation.to_java(pool),
+                self.target_to_string()
+            )
+        } else {
+            format!(
+                "{} {}, location=[{}]",
+                self.annotation.to_java(pool),
+                self.target_to_string(),
+                location.join(", ")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Annotation, ElementPair, ElementValue, TypeAnnotation, TypeAnnotationTarget, TypePathEntry,
+    };
+    use crate::assembler::assemble;
+    use crate::attribute::{Attribute, AttributeInfo};
+    use crate::classfile::ClassFile;
+    use crate::pool::ConstantPoolItem;
+
+    const SOURCE: &str = r#"
+.class public p/Api
+.field public names Ljava/util/List;
+"#;
+
+    #[test]
+    fn test_annotations_round_trip() {
+        let mut class = assemble(SOURCE).unwrap();
+        let pool = &mut class.constant_pool;
+        let name_index = pool.add_utf8("RuntimeVisibleAnnotations").unwrap();
+        let annotation = Annotation {
+            type_index: pool.add_utf8("Lp/Info;").unwrap(),
+            elements: vec![
+                ElementPair {
+                    name_index: pool.add_utf8("n").unwrap(),
+                    value: ElementValue::Const(b'J', pool.add(ConstantPoolItem::Long(7)).unwrap()),
+                },
+                ElementPair {
+                    name_index: pool.add_utf8("kinds").unwrap(),
+                    value: ElementValue::Array(vec![ElementValue::Enum(
+                        pool.add_utf8("Ljava/lang/annotation/ElementType;").unwrap(),
+                        pool.add_utf8("FIELD").unwrap(),
+                    )]),
+                },
+                ElementPair {
+                    name_index: pool.add_utf8("type").unwrap(),
+                    value: ElementValue::Class(pool.add_utf8("[Ljava/lang/String;").unwrap()),
+                },
+                ElementPair {
+                    name_index: pool.add_utf8("nested").unwrap(),
+                    value: ElementValue::Annotation(Annotation {
+                        type_index: pool.add_utf8("Lp/Tag;").unwrap(),
+                        elements: vec![ElementPair {
+                            name_index: pool.add_utf8("value").unwrap(),
+                            value: ElementValue::Const(b's', pool.add_utf8("a\"b").unwrap()),
+                        }],
+                    }),
+                },
+            ],
+        };
+        class.fields[0].attributes.push(AttributeInfo {
+            name_index,
+            attribute: Attribute::RuntimeVisibleAnnotations(vec![annotation.clone()]),
+        });
+
+        let class = ClassFile::parse(class.to_bytes().unwrap().as_slice()).unwrap();
+        let parsed = match &class.fields[0].attributes[0].attribute {
+            Attribute::RuntimeVisibleAnnotations(items) => items[0].clone(),
+            attribute => panic!("unexpected attribute {:?}", attribute),
+        };
+        assert_eq!(parsed, annotation);
+        assert_eq!(
+            parsed.to_java(&class.constant_pool),
+            "@p.Info(n=7L, kinds={java.lang.annotation.ElementType.FIELD}, \
+             type=java.lang.String[].class, nested=@p.Tag(\"a\\\"b\"))"
+        );
+    }
+
+    #[test]
+    fn test_type_annotation_targets() {
+        let mut class = assemble(SOURCE).unwrap();
+        let pool = &mut class.constant_pool;
+        let name_index = pool.add_utf8("RuntimeInvisibleTypeAnnotations").unwrap();
+        let annotation = Annotation {
+            type_index: pool.add_utf8("Lp/NonNull;").unwrap(),
+            elements: Vec::new(),
+        };
+        let items = vec![
+            TypeAnnotation {
+                target_type: 0x13,
+                target: TypeAnnotationTarget::Empty,
+                path: vec![TypePathEntry {
+                    kind: 3,
+                    argument_index: 0,
+                }],
+                annotation: annotation.clone(),
+            },
+            TypeAnnotation {
+                target_type: 0x48,
+                target: TypeAnnotationTarget::TypeArgument(12, 1),
+                path: vec![
+                    TypePathEntry {
+                        kind: 0,
+                        argument_index: 0,
+                    },
+                    TypePathEntry {
+                        kind: 2,
+                        argument_index: 0,
+                    },
+                ],
+                annotation,
+            },
+        ];
+        class.fields[0].attributes.push(AttributeInfo {
+            name_index,
+            attribute: Attribute::RuntimeInvisibleTypeAnnotations(items.clone()),
+        });
+
+        let class = ClassFile::parse(class.to_bytes().unwrap().as_slice()).unwrap();
+        let attribute = &class.fields[0].attributes[0].attribute;
+        assert!(
+            matches!(attribute, Attribute::RuntimeInvisibleTypeAnnotations(parsed) if *parsed == items)
+        );
+        assert_eq!(
+            attribute.details(&class.constant_pool),
+            [
+                "@p.NonNull FIELD, location=[TYPE_ARGUMENT(0)]",
+                "@p.NonNull CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT, offset=12, type_index=1, \
+                 location=[ARRAY, WILDCARD]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_element_tag_is_an_error() {
+        let mut class = assemble(SOURCE).unwrap();
+        let name_index = class.constant_pool.add_utf8("AnnotationDefault").unwrap();
+        let type_index = class.constant_pool.add_utf8("Lp/Info;").unwrap();
+        class.attributes.push(AttributeInfo {
+            name_index,
+            attribute: Attribute::AnnotationDefault(ElementValue::Annotation(Annotation {
+                type_index,
+                elements: Vec::new(),
+            })),
+        });
+        let mut bytes = class.to_bytes().unwrap();
+        // The last attribute ends with `@`, type index and an empty element table.
+        let tag = bytes.len() - 5;
+        assert_eq!(bytes[tag], b'@');
+        bytes[tag] = b'x';
+        let e = ClassFile::parse(bytes.as_slice()).unwrap_err();
+        assert!(
+            e.to_string().contains("unknown element value tag 0x78"),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_element_values_are_an_error() {
+        let nested = |depth: usize| {
+            let mut class = assemble(SOURCE).unwrap();
+            let name_index = class.constant_pool.add_utf8("AnnotationDefault").unwrap();
+            let mut body = [b'[', 0x00, 0x01].repeat(depth);
+            body.extend_from_slice(&[b'I', 0x00, 0x01]);
+            class.attributes.push(AttributeInfo {
+                name_index,
+                attribute: Attribute::Unknown("AnnotationDefault".to_string(), body),
+            });
+            ClassFile::parse(class.to_bytes().unwrap().as_slice())
+        };
+        assert!(nested(200).is_ok());
+        let e = nested(20_000).unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("element values nested deeper than 256"),
+            "{}",
+            e
+        );
+    }
+}
@@ -0,0 +1,331 @@
+// Reading class files straight out of JAR/ZIP archives. Only the central directory is parsed
+// up front, entries are located through it and inflated in memory on demand. Stored and
+// deflated entries are supported, ZIP64 and encrypted archives are rejected.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use flate2::read::DeflateDecoder;
+use flate2::Crc;
+
+use crate::classfile::ClassFile;
+use crate::error::{Context, ErrorKind, ParseError};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const MAX_COMMENT_SIZE: usize = 0xFFFF;
+
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+const FLAG_ENCRYPTED: u16 = 0x0001;
+
+/// First bytes of every ZIP archive that starts with an entry (JARs always do).
+pub const ZIP_MAGIC: [u8; 4] = [b'P', b'K', 0x03, 0x04];
+
+/// An entry of the archive's central directory.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub method: u16,
+    pub flags: u16,
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub size: u32,
+    pub header_offset: u32,
+}
+
+impl Entry {
+    pub fn is_class(&self) -> bool {
+        self.name.ends_with(".class")
+    }
+}
+
+pub struct Archive<R> {
+    inner: R,
+    entries: Vec<Entry>,
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]])
+}
+
+fn io_error(offset: u64, e: io::Error) -> ParseError {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        ParseError::new(offset, ErrorKind::Truncated)
+    } else {
+        ParseError::new(offset, ErrorKind::Io(e.to_string()))
+    }
+}
+
+fn malformed(offset: u64, msg: &str) -> ParseError {
+    ParseError::new(offset, ErrorKind::Malformed(msg.to_string()))
+}
+
+impl<R: Read + Seek> Archive<R> {
+    /// Reads the central directory, entry contents are only read by `read`.
+    pub fn open(mut inner: R) -> Result<Self, ParseError> {
+        let entries = Self::read_central_directory(&mut inner).context("central directory")?;
+        Ok(Archive { inner, entries })
+    }
+
+    fn read_at(inner: &mut R, offset: u64, length: usize) -> Result<Vec<u8>, ParseError> {
+        inner
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| io_error(offset, e))?;
+        // Lengths come from the archive, the buffer only grows with the bytes actually there.
+        let mut buf = Vec::<u8>::new();
+        inner
+            .take(length as u64)
+            .read_to_end(&mut buf)
+            .map_err(|e| io_error(offset, e))?;
+        if buf.len() != length {
+            return Err(ParseError::new(offset, ErrorKind::Truncated));
+        }
+        Ok(buf)
+    }
+
+    fn read_central_directory(inner: &mut R) -> Result<Vec<Entry>, ParseError> {
+        let length = inner.seek(SeekFrom::End(0)).map_err(|e| io_error(0, e))?;
+        // The end of central directory record is followed by a comment of at most 64k.
+        let tail_size = length.min((END_OF_CENTRAL_DIRECTORY_SIZE + MAX_COMMENT_SIZE) as u64);
+        let tail_offset = length - tail_size;
+        let tail = Self::read_at(inner, tail_offset, tail_size as usize)?;
+        let eocd = (0..tail.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE - 1))
+            .rev()
+            .find(|pos| u32_at(&tail, *pos) == END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+            .ok_or_else(|| malformed(length, "not a ZIP archive, no end of central directory"))?;
+        let eocd_offset = tail_offset + eocd as u64;
+        let count = u16_at(&tail, eocd + 10);
+        let directory_size = u32_at(&tail, eocd + 12);
+        let directory_offset = u32_at(&tail, eocd + 16);
+        if count == 0xFFFF || directory_offset == 0xFFFF_FFFF {
+            return Err(malformed(eocd_offset, "ZIP64 archives are not supported"));
+        }
+        if directory_offset as u64 + directory_size as u64 > eocd_offset {
+            return Err(malformed(eocd_offset, "central directory out of bounds"));
+        }
+        let directory = Self::read_at(inner, directory_offset as u64, directory_size as usize)?;
+        let mut entries = Vec::<Entry>::with_capacity(count as usize);
+        let mut pos = 0;
+        for i in 0..count {
+            let offset = directory_offset as u64 + pos as u64;
+            let header = directory
+                .get(pos..pos + CENTRAL_HEADER_SIZE)
+                .ok_or_else(|| ParseError::new(offset, ErrorKind::Truncated))
+                .with_context(|| format!("entry #{}", i))?;
+            if u32_at(header, 0) != CENTRAL_HEADER_SIGNATURE {
+                return Err(malformed(offset, "bad central directory header signature"))
+                    .with_context(|| format!("entry #{}", i));
+            }
+            let name_length = u16_at(header, 28) as usize;
+            let extra_length = u16_at(header, 30) as usize;
+            let comment_length = u16_at(header, 32) as usize;
+            let name_start = pos + CENTRAL_HEADER_SIZE;
+            let name = directory
+                .get(name_start..name_start + name_length)
+                .ok_or_else(|| ParseError::new(offset, ErrorKind::Truncated))
+                .with_context(|| format!("entry #{}", i))?;
+            entries.push(Entry {
+                name: String::from_utf8_lossy(name).to_string(),
+                flags: u16_at(header, 8),
+                method: u16_at(header, 10),
+                crc32: u32_at(header, 16),
+                compressed_size: u32_at(header, 20),
+                size: u32_at(header, 24),
+                header_offset: u32_at(header, 42),
+            });
+            pos = name_start + name_length + extra_length + comment_length;
+        }
+        Ok(entries)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Names of the `.class` entries, in archive order.
+    pub fn class_names(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_class())
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    fn read_entry(&mut self, entry: &Entry) -> Result<Vec<u8>, ParseError> {
+        let offset = entry.header_offset as u64;
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(malformed(offset, "encrypted entries are not supported"));
+        }
+        let header = Self::read_at(&mut self.inner, offset, LOCAL_HEADER_SIZE)?;
+        if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE {
+            return Err(malformed(offset, "bad local header signature"));
+        }
+        // Sizes in the local header may be zero when a data descriptor follows the data,
+        // the central directory is authoritative.
+        let data_offset = offset
+            + LOCAL_HEADER_SIZE as u64
+            + u16_at(&header, 26) as u64
+            + u16_at(&header, 28) as u64;
+        let compressed =
+            Self::read_at(&mut self.inner, data_offset, entry.compressed_size as usize)?;
+        let data = match entry.method {
+            METHOD_STORED => compressed,
+            METHOD_DEFLATED => {
+                let mut acc = Vec::<u8>::new();
+                // Never inflate past the declared size, a corrupt entry can not blow up memory.
+                DeflateDecoder::new(compressed.as_slice())
+                    .take(entry.size as u64 + 1)
+                    .read_to_end(&mut acc)
+                    .map_err(|e| malformed(data_offset, &format!("bad deflate data: {}", e)))?;
+                acc
+            }
+            method => {
+                let msg = format!("unsupported compression method {}", method);
+                return Err(malformed(offset, &msg));
+            }
+        };
+        if data.len() != entry.size as usize {
+            return Err(malformed(
+                data_offset,
+                "entry size does not match its content",
+            ));
+        }
+        let mut crc = Crc::new();
+        crc.update(&data);
+        if crc.sum() != entry.crc32 {
+            return Err(malformed(data_offset, "entry CRC-32 mismatch"));
+        }
+        Ok(data)
+    }
+
+    /// Reads and decompresses the entry with the given name.
+    pub fn read(&mut self, name: &str) -> Result<Vec<u8>, ParseError> {
+        let entry = self
+            .find(name)
+            .cloned()
+            .ok_or_else(|| malformed(0, &format!("no entry named {}", name)))?;
+        self.read_entry(&entry)
+            .with_context(|| format!("entry {}", name))
+    }
+
+    /// Reads and parses a class entry, e.g. `com/foo/Bar.class`.
+    pub fn parse_class(&mut self, name: &str) -> Result<ClassFile, ParseError> {
+        let bytes = self.read(name)?;
+        ClassFile::parse(bytes.as_slice()).with_context(|| format!("entry {}", name))
+    }
+
+    /// Iterates over all class entries, parsing them one at a time.
+    pub fn classes(&mut self) -> Classes<'_, R> {
+        Classes {
+            archive: self,
+            next: 0,
+        }
+    }
+}
+
+pub struct Classes<'a, R> {
+    archive: &'a mut Archive<R>,
+    next: usize,
+}
+
+impl<'a, R: Read + Seek> Iterator for Classes<'a, R> {
+    type Item = (String, Result<ClassFile, ParseError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.archive.entries[self.next..]
+            .iter()
+            .position(|entry| entry.is_class())?
+            + self.next;
+        self.next = index + 1;
+        let name = self.archive.entries[index].name.clone();
+        let class = self.archive.parse_class(&name);
+        Some((name, class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::Archive;
+    use crate::error::ErrorKind;
+    use crate::testdata::{zip, MINIMAL_CLASS};
+
+    #[test]
+    fn test_archive_stored_and_deflated_entries() {
+        let bytes = zip(&[
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\r\n", true),
+            ("com/foo/", b"", false),
+            ("com/foo/A.class", &MINIMAL_CLASS, false),
+            ("com/foo/B.class", &MINIMAL_CLASS, true),
+        ]);
+        let mut archive = Archive::open(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.entries().len(), 4);
+        assert_eq!(
+            archive.class_names(),
+            vec!["com/foo/A.class", "com/foo/B.class"]
+        );
+        assert_eq!(
+            archive.read("META-INF/MANIFEST.MF").unwrap(),
+            b"Manifest-Version: 1.0\r\n"
+        );
+        let class = archive.parse_class("com/foo/B.class").unwrap();
+        assert_eq!(class.this_class_name(), "A");
+        let names: Vec<String> = archive
+            .classes()
+            .map(|(name, class)| {
+                assert!(class.is_ok());
+                name
+            })
+            .collect();
+        assert_eq!(names, vec!["com/foo/A.class", "com/foo/B.class"]);
+    }
+
+    #[test]
+    fn test_archive_corrupt_entry() {
+        let mut bytes = zip(&[("A.class", &MINIMAL_CLASS, false)]);
+        bytes[40] ^= 0xFF; // inside the stored class data
+        let mut archive = Archive::open(Cursor::new(bytes)).unwrap();
+        let e = archive.read("A.class").unwrap_err();
+        assert_eq!(
+            e.kind,
+            ErrorKind::Malformed("entry CRC-32 mismatch".to_string())
+        );
+        assert_eq!(e.context, vec!["entry A.class"]);
+        assert!(archive.read("B.class").is_err());
+    }
+
+    #[test]
+    fn test_archive_entry_larger_than_archive() {
+        let mut bytes = zip(&[("A.class", &MINIMAL_CLASS, false)]);
+        // compressed_size of the only central directory header
+        let eocd = bytes.len() - 22;
+        let directory = u32::from_le_bytes(bytes[eocd + 16..eocd + 20].try_into().unwrap());
+        let size = directory as usize + 20;
+        bytes[size..size + 4].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let mut archive = Archive::open(Cursor::new(bytes)).unwrap();
+        let e = archive.read("A.class").unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Truncated);
+        assert_eq!(e.context, vec!["entry A.class"]);
+    }
+
+    #[test]
+    fn test_archive_rejects_non_zip_input() {
+        assert!(Archive::open(Cursor::new(MINIMAL_CLASS.to_vec())).is_err());
+        assert!(Archive::open(Cursor::new(Vec::<u8>::new())).is_err());
+    }
+}
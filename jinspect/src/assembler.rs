@@ -0,0 +1,1313 @@
+// Assembler for a small Jasmin-like text format. Directives describe the class and its members,
+// method bodies are instructions with labels and symbolic constant pool references, the pool is
+// built as entries are referenced:
+//
+//     .class public Hello
+//     .super java/lang/Object
+//
+//     .method public static main([Ljava/lang/String;)V
+//         .limit stack 2
+//         getstatic java/lang/System/out Ljava/io/PrintStream;
+//         ldc "hello"
+//         invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+//         return
+//     .end method
+//
+// StackMapTable attributes are not generated, so branches and exception handlers are rejected
+// for class file versions that need them (51 and later, version 50 classes fall back to the
+// type-inferring verifier, the default is 49).
+
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+use crate::attribute::{
+    Attribute, AttributeInfo, Code, ExceptionHandler, LineNumber, ATTR_CODE, ATTR_CONSTANT_VALUE,
+    ATTR_EXCEPTIONS, ATTR_LINE_NUMBER_TABLE, ATTR_SIGNATURE, ATTR_SOURCE_FILE,
+};
+use crate::bytecode::{self, Instruction, Operand};
+use crate::classfile::{ClassFile, Header, Member, MAGIC};
+use crate::error::ErrorKind;
+use crate::flag::{AccessFlag, ACC_INTERFACE};
+use crate::opcode::*;
+use crate::pool::{ConstantPool, ConstantPoolItem};
+use crate::signature::{self, BaseType, JavaType};
+use crate::verifier::STACK_MAP_VERSION;
+
+const DEFAULT_MAJOR: u16 = 49;
+const DEFAULT_SUPER: &str = "java/lang/Object";
+const ACC_SUPER: u16 = 0x0020;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_NATIVE: u16 = 0x0100;
+
+/// Error in the assembler source, `line` is 1-based.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, message: String) -> Self {
+        AsmError { line, message }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String), // quoted string with escapes resolved
+}
+
+fn unescape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    let mut acc = String::new();
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => return Ok(acc),
+            Some('\\') => match chars.next() {
+                Some('n') => acc.push('\n'),
+                Some('t') => acc.push('\t'),
+                Some('r') => acc.push('\r'),
+                Some('0') => acc.push('\0'),
+                Some('"') => acc.push('"'),
+                Some('\\') => acc.push('\\'),
+                Some('u') => {
+                    let hex: String = (0..4).filter_map(|_| chars.next()).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("bad unicode escape \\u{}", hex))?;
+                    acc.push(c);
+                }
+                other => return Err(format!("bad escape \\{}", other.unwrap_or(' '))),
+            },
+            Some(c) => acc.push(c),
+        }
+    }
+}
+
+/// Splits a line into words and quoted strings. `;` starts a comment at the beginning of a
+/// token only, descriptors such as `Ljava/lang/String;` contain it.
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut acc = Vec::<Token>::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '"' {
+            chars.next();
+            acc.push(Token::Str(unescape(&mut chars)?));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            acc.push(Token::Word(word));
+        }
+    }
+    Ok(acc)
+}
+
+/// The tokens of one line, consumed from the front.
+struct Tokens {
+    line: usize,
+    items: VecDeque<Token>,
+}
+
+impl Tokens {
+    fn error(&self, message: String) -> AsmError {
+        AsmError::new(self.line, message)
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.items.front() {
+            Some(Token::Word(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self, what: &str) -> Result<Token, AsmError> {
+        self.items
+            .pop_front()
+            .ok_or_else(|| self.error(format!("missing {}", what)))
+    }
+
+    fn word(&mut self, what: &str) -> Result<String, AsmError> {
+        match self.next(what)? {
+            Token::Word(word) => Ok(word),
+            Token::Str(_) => Err(self.error(format!("expected {}, found a string", what))),
+        }
+    }
+
+    fn string(&mut self, what: &str) -> Result<String, AsmError> {
+        match self.next(what)? {
+            Token::Word(word) | Token::Str(word) => Ok(word),
+        }
+    }
+
+    fn number<T: TryFrom<i64>>(&mut self, what: &str) -> Result<T, AsmError> {
+        let word = self.word(what)?;
+        parse_integer(&word)
+            .and_then(|value| T::try_from(value).ok())
+            .ok_or_else(|| self.error(format!("bad {} {}", what, word)))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), AsmError> {
+        match self.word(keyword)? {
+            word if word == keyword => Ok(()),
+            word => Err(self.error(format!("expected {}, found {}", keyword, word))),
+        }
+    }
+
+    fn finish(&self) -> Result<(), AsmError> {
+        match self.items.front() {
+            None => Ok(()),
+            Some(Token::Word(word)) | Some(Token::Str(word)) => {
+                Err(self.error(format!("unexpected {}", word)))
+            }
+        }
+    }
+}
+
+fn parse_integer(word: &str) -> Option<i64> {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, word),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i64>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn parse_float(word: &str) -> Option<f64> {
+    let digits = word.trim_end_matches(['f', 'F', 'd', 'D']);
+    match digits {
+        "NaN" => Some(f64::NAN),
+        "Infinity" => Some(f64::INFINITY),
+        "-Infinity" => Some(f64::NEG_INFINITY),
+        _ => digits.parse::<f64>().ok(),
+    }
+}
+
+/// `java/io/PrintStream/println` is class `java/io/PrintStream` and member `println`.
+fn split_member(word: &str) -> Option<(&str, &str)> {
+    let (class, name) = word.rsplit_once('/')?;
+    if class.is_empty() || name.is_empty() {
+        None
+    } else {
+        Some((class, name))
+    }
+}
+
+fn slots(item: &JavaType) -> u16 {
+    match item {
+        JavaType::Base(BaseType::Long) | JavaType::Base(BaseType::Double) => 2,
+        JavaType::Void => 0,
+        _ => 1,
+    }
+}
+
+/// Local variable slots used by an instruction, e.g. 4 for `lstore_2`.
+fn locals_used(opcode: u8, operand: &Operand) -> u16 {
+    let wide_type = |kind: u8| if kind == 1 || kind == 3 { 2 } else { 1 };
+    match (opcode, operand) {
+        (ILOAD_0..=ALOAD_3, _) => (opcode - ILOAD_0) as u16 % 4 + wide_type((opcode - ILOAD_0) / 4),
+        (ISTORE_0..=ASTORE_3, _) => {
+            (opcode - ISTORE_0) as u16 % 4 + wide_type((opcode - ISTORE_0) / 4)
+        }
+        (ILOAD..=ALOAD, Operand::Local(index)) => index.saturating_add(wide_type(opcode - ILOAD)),
+        (ISTORE..=ASTORE, Operand::Local(index)) => {
+            index.saturating_add(wide_type(opcode - ISTORE))
+        }
+        (_, Operand::Local(index)) | (_, Operand::Iinc(index, _)) => index.saturating_add(1),
+        _ => 0,
+    }
+}
+
+/// Operands that refer to labels, resolved once all instruction offsets are known.
+#[derive(Debug)]
+enum PendingOperand {
+    Resolved(Operand),
+    Branch(String),
+    TableSwitch(i32, i32, Vec<String>, String), // low, high, targets, default
+    LookupSwitch(Vec<(i32, String)>, String),   // (key, target) pairs, default
+}
+
+#[derive(Debug)]
+struct PendingInstruction {
+    line: usize,
+    opcode: u8,
+    wide: bool,
+    operand: PendingOperand,
+}
+
+impl PendingInstruction {
+    /// The instruction with placeholder branch targets, enough to compute its size.
+    fn layout(&self, offset: u32) -> Instruction {
+        let operand = match &self.operand {
+            PendingOperand::Resolved(operand) => operand.clone(),
+            PendingOperand::Branch(_) => Operand::Branch(offset as i32),
+            PendingOperand::TableSwitch(low, high, targets, _) => {
+                Operand::TableSwitch(0, *low, *high, vec![0; targets.len()])
+            }
+            PendingOperand::LookupSwitch(pairs, _) => {
+                Operand::LookupSwitch(0, pairs.iter().map(|(key, _)| (*key, 0)).collect())
+            }
+        };
+        Instruction {
+            offset,
+            opcode: self.opcode,
+            wide: self.wide,
+            operand,
+        }
+    }
+}
+
+/// A `tableswitch` or `lookupswitch` whose case lines are still being read.
+struct PendingSwitch {
+    instruction: PendingInstruction,
+    low: i32,
+    targets: Vec<String>,
+    pairs: Vec<(i32, String)>,
+}
+
+struct Catch {
+    line: usize,
+    catch_type: u16,
+    from: String,
+    to: String,
+    using: String,
+}
+
+struct MethodBuilder {
+    line: usize,
+    access_flags: AccessFlag,
+    name: String,
+    descriptor: String,
+    max_stack: Option<u16>,
+    max_locals: Option<u16>,
+    instructions: Vec<PendingInstruction>,
+    labels: HashMap<String, usize>, // label => index of the instruction that follows it
+    catches: Vec<Catch>,
+    lines: Vec<(usize, u16)>, // instruction index, source line number
+    throws: Vec<u16>,
+    signature: Option<u16>,
+    switch: Option<PendingSwitch>,
+}
+
+struct Assembler {
+    pool: ConstantPool,
+    major: u16,
+    minor: u16,
+    access_flags: AccessFlag,
+    this_class: Option<u16>,
+    super_class: Option<String>,
+    interfaces: Vec<u16>,
+    fields: Vec<Member>,
+    methods: Vec<Member>,
+    attributes: Vec<AttributeInfo>,
+    method: Option<MethodBuilder>,
+    branch_line: Option<usize>, // first branch or handler, those need a StackMapTable frame
+}
+
+fn pool_error(line: usize) -> impl Fn(ErrorKind) -> AsmError {
+    move |kind| AsmError::new(line, kind.to_string())
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Assembler {
+            pool: ConstantPool::new(),
+            major: DEFAULT_MAJOR,
+            minor: 0,
+            access_flags: AccessFlag::Class(0),
+            this_class: None,
+            super_class: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+            method: None,
+            branch_line: None,
+        }
+    }
+
+    fn attribute(
+        &mut self,
+        line: usize,
+        name: &str,
+        attribute: Attribute,
+    ) -> Result<AttributeInfo, AsmError> {
+        let name_index = self.pool.add_utf8(name).map_err(pool_error(line))?;
+        Ok(AttributeInfo {
+            name_index,
+            attribute,
+        })
+    }
+
+    /// Leading flag names of a directive, e.g. `public static` of `.method public static ...`.
+    fn flags(tokens: &mut Tokens, mut flags: AccessFlag) -> AccessFlag {
+        while let Some(mask) = tokens.peek_word().and_then(|word| flags.mask(word)) {
+            flags = flags.with(mask);
+            tokens.items.pop_front();
+        }
+        flags
+    }
+
+    fn line(&mut self, mut tokens: Tokens) -> Result<(), AsmError> {
+        if self.method.as_ref().is_some_and(|m| m.switch.is_some()) {
+            return self.switch_case(tokens);
+        }
+        let first = match tokens.peek_word() {
+            Some(word) => word.to_string(),
+            None => return tokens.finish(),
+        };
+        if first.starts_with('.') {
+            tokens.items.pop_front();
+            return self.directive(&first, tokens);
+        }
+        let method = match self.method.as_mut() {
+            Some(method) => method,
+            None => return Err(tokens.error(format!("{} outside of a method", first))),
+        };
+        if let Some(label) = first.strip_suffix(':').filter(|label| !label.is_empty()) {
+            if method.labels.contains_key(label) {
+                return Err(tokens.error(format!("label {} defined twice", label)));
+            }
+            method
+                .labels
+                .insert(label.to_string(), method.instructions.len());
+            tokens.items.pop_front();
+            if tokens.items.is_empty() {
+                return Ok(());
+            }
+        }
+        self.instruction(tokens)
+    }
+
+    fn directive(&mut self, directive: &str, mut tokens: Tokens) -> Result<(), AsmError> {
+        let line = tokens.line;
+        let in_method = self.method.is_some();
+        match directive {
+            ".class" | ".interface" if !in_method => {
+                if self.this_class.is_some() {
+                    return Err(tokens.error("class declared twice".to_string()));
+                }
+                let mut flags = Self::flags(&mut tokens, AccessFlag::Class(0));
+                if directive == ".interface" {
+                    flags = flags.with(ACC_INTERFACE | ACC_ABSTRACT);
+                } else if !flags.contains(ACC_INTERFACE) {
+                    flags = flags.with(ACC_SUPER);
+                }
+                let name = tokens.word("class name")?;
+                self.access_flags = flags;
+                self.this_class = Some(self.pool.add_class(&name).map_err(pool_error(line))?);
+            }
+            ".super" if !in_method => self.super_class = Some(tokens.word("class name")?),
+            ".implements" if !in_method => {
+                let name = tokens.word("interface name")?;
+                let index = self.pool.add_class(&name).map_err(pool_error(line))?;
+                self.interfaces.push(index);
+            }
+            ".version" if !in_method => {
+                self.major = tokens.number("major version")?;
+                if !tokens.items.is_empty() {
+                    self.minor = tokens.number("minor version")?;
+                }
+            }
+            ".source" if !in_method => {
+                let name = tokens.string("source file")?;
+                let index = self.pool.add_utf8(&name).map_err(pool_error(line))?;
+                let attribute =
+                    self.attribute(line, ATTR_SOURCE_FILE, Attribute::SourceFile(index))?;
+                self.attributes.push(attribute);
+            }
+            ".signature" => {
+                let value = tokens.string("signature")?;
+                let index = self.pool.add_utf8(&value).map_err(pool_error(line))?;
+                match self.method.as_mut() {
+                    Some(method) => method.signature = Some(index),
+                    None => {
+                        let attribute =
+                            self.attribute(line, ATTR_SIGNATURE, Attribute::Signature(index))?;
+                        self.attributes.push(attribute);
+                    }
+                }
+            }
+            ".field" if !in_method => self.field(&mut tokens)?,
+            ".method" if !in_method => {
+                let access_flags = Self::flags(&mut tokens, AccessFlag::Method(0));
+                let word = tokens.word("method name and descriptor")?;
+                let (name, descriptor) = word
+                    .find('(')
+                    .map(|pos| word.split_at(pos))
+                    .filter(|(name, _)| !name.is_empty())
+                    .ok_or_else(|| {
+                        tokens.error(format!("expected name(descriptor), found {}", word))
+                    })?;
+                signature::parse_method_descriptor(descriptor).map_err(|e| {
+                    tokens.error(format!("bad method descriptor {}: {}", descriptor, e))
+                })?;
+                self.method = Some(MethodBuilder {
+                    line,
+                    access_flags,
+                    name: name.to_string(),
+                    descriptor: descriptor.to_string(),
+                    max_stack: None,
+                    max_locals: None,
+                    instructions: Vec::new(),
+                    labels: HashMap::new(),
+                    catches: Vec::new(),
+                    lines: Vec::new(),
+                    throws: Vec::new(),
+                    signature: None,
+                    switch: None,
+                });
+            }
+            ".limit" if in_method => {
+                let what = tokens.word("stack or locals")?;
+                let value = tokens.number::<u16>(&what)?;
+                let method = self.method.as_mut().expect("in method");
+                match what.as_str() {
+                    "stack" => method.max_stack = Some(value),
+                    "locals" => method.max_locals = Some(value),
+                    _ => return Err(tokens.error(format!("unknown limit {}", what))),
+                }
+            }
+            ".throws" if in_method => {
+                let name = tokens.word("exception class")?;
+                let index = self.pool.add_class(&name).map_err(pool_error(line))?;
+                self.method.as_mut().expect("in method").throws.push(index);
+            }
+            ".catch" if in_method => {
+                let name = tokens.word("exception class")?;
+                let catch_type = if name == "all" {
+                    0
+                } else {
+                    self.pool.add_class(&name).map_err(pool_error(line))?
+                };
+                tokens.keyword("from")?;
+                let from = tokens.word("label")?;
+                tokens.keyword("to")?;
+                let to = tokens.word("label")?;
+                tokens.keyword("using")?;
+                let using = tokens.word("label")?;
+                self.method
+                    .as_mut()
+                    .expect("in method")
+                    .catches
+                    .push(Catch {
+                        line,
+                        catch_type,
+                        from,
+                        to,
+                        using,
+                    });
+            }
+            ".line" if in_method => {
+                let number = tokens.number("line number")?;
+                let method = self.method.as_mut().expect("in method");
+                method.lines.push((method.instructions.len(), number));
+            }
+            ".end" if in_method => {
+                tokens.keyword("method")?;
+                let method = self.method.take().expect("in method");
+                let member = self.finish_method(method)?;
+                self.methods.push(member);
+            }
+            _ if in_method => {
+                return Err(tokens.error(format!("{} is not allowed in a method", directive)))
+            }
+            _ => return Err(tokens.error(format!("unknown directive {}", directive))),
+        }
+        tokens.finish()
+    }
+
+    fn field(&mut self, tokens: &mut Tokens) -> Result<(), AsmError> {
+        let line = tokens.line;
+        let access_flags = Self::flags(tokens, AccessFlag::Field(0));
+        let name = tokens.word("field name")?;
+        let descriptor = tokens.word("field descriptor")?;
+        let field_type = signature::parse_field_descriptor(&descriptor)
+            .map_err(|e| tokens.error(format!("bad field descriptor {}: {}", descriptor, e)))?;
+        let mut attributes = Vec::<AttributeInfo>::new();
+        if !tokens.items.is_empty() {
+            tokens.keyword("=")?;
+            let value = tokens.next("constant value")?;
+            let item = match (&field_type, &value) {
+                (JavaType::Class(class), Token::Str(value))
+                    if class.internal_name() == "java/lang/String" =>
+                {
+                    ConstantPoolItem::String(self.pool.add_utf8(value).map_err(pool_error(line))?)
+                }
+                (JavaType::Base(base), Token::Word(word)) => {
+                    let bad = || tokens.error(format!("bad {} constant {}", descriptor, word));
+                    match base {
+                        BaseType::Long => ConstantPoolItem::Long(
+                            parse_integer(word.trim_end_matches(['l', 'L'])).ok_or_else(bad)?,
+                        ),
+                        BaseType::Float => {
+                            ConstantPoolItem::Float(parse_float(word).ok_or_else(bad)? as f32)
+                        }
+                        BaseType::Double => {
+                            ConstantPoolItem::Double(parse_float(word).ok_or_else(bad)?)
+                        }
+                        BaseType::Boolean if word == "true" || word == "false" => {
+                            ConstantPoolItem::Integer((word == "true") as i32)
+                        }
+                        _ => ConstantPoolItem::Integer(
+                            parse_integer(word)
+                                .and_then(|value| i32::try_from(value).ok())
+                                .ok_or_else(bad)?,
+                        ),
+                    }
+                }
+                _ => {
+                    return Err(tokens.error(format!(
+                        "fields of type {} can not have a constant value",
+                        descriptor
+                    )))
+                }
+            };
+            let index = self.pool.add(item).map_err(pool_error(line))?;
+            attributes.push(self.attribute(
+                line,
+                ATTR_CONSTANT_VALUE,
+                Attribute::ConstantValue(index),
+            )?);
+        }
+        let name_index = self.pool.add_utf8(&name).map_err(pool_error(line))?;
+        let descriptor_index = self.pool.add_utf8(&descriptor).map_err(pool_error(line))?;
+        self.fields.push(Member {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes,
+        });
+        Ok(())
+    }
+
+    fn constant(&mut self, tokens: &mut Tokens, opcode: u8) -> Result<u16, AsmError> {
+        let line = tokens.line;
+        let token = tokens.next("constant")?;
+        let item = match (opcode, &token) {
+            (LDC2_W, Token::Word(word)) => {
+                let long = word
+                    .strip_suffix(['l', 'L'])
+                    .or_else(|| {
+                        (!word.contains(['.', 'e', 'E', 'N', 'I'])).then_some(word.as_str())
+                    })
+                    .and_then(parse_integer);
+                match long {
+                    Some(value) => ConstantPoolItem::Long(value),
+                    None => ConstantPoolItem::Double(
+                        parse_float(word)
+                            .ok_or_else(|| tokens.error(format!("bad constant {}", word)))?,
+                    ),
+                }
+            }
+            (_, Token::Word(word)) => match parse_integer(word) {
+                Some(value) => ConstantPoolItem::Integer(
+                    i32::try_from(value)
+                        .map_err(|_| tokens.error(format!("int constant {} out of range", word)))?,
+                ),
+                None => ConstantPoolItem::Float(
+                    parse_float(word)
+                        .ok_or_else(|| tokens.error(format!("bad constant {}", word)))?
+                        as f32,
+                ),
+            },
+            (LDC2_W, Token::Str(_)) => {
+                return Err(tokens.error("ldc2_w needs a long or double constant".to_string()))
+            }
+            (_, Token::Str(value)) => {
+                let value = self.pool.add_utf8(value).map_err(pool_error(line))?;
+                ConstantPoolItem::String(value)
+            }
+        };
+        self.pool.add(item).map_err(pool_error(line))
+    }
+
+    fn member_ref(&mut self, tokens: &mut Tokens, opcode: u8) -> Result<u16, AsmError> {
+        let line = tokens.line;
+        let word = tokens.word("member reference")?;
+        let bad = |tokens: &Tokens| tokens.error(format!("bad member reference {}", word));
+        if (GETSTATIC..=PUTFIELD).contains(&opcode) {
+            let (class, name) = split_member(&word).ok_or_else(|| bad(tokens))?;
+            let descriptor = tokens.word("field descriptor")?;
+            signature::parse_field_descriptor(&descriptor)
+                .map_err(|e| tokens.error(format!("bad field descriptor {}: {}", descriptor, e)))?;
+            return self
+                .pool
+                .add_field_ref(class, name, &descriptor)
+                .map_err(pool_error(line));
+        }
+        let pos = word.find('(').ok_or_else(|| bad(tokens))?;
+        let (class, name) = split_member(&word[..pos]).ok_or_else(|| bad(tokens))?;
+        let descriptor = &word[pos..];
+        signature::parse_method_descriptor(descriptor)
+            .map_err(|e| tokens.error(format!("bad method descriptor {}: {}", descriptor, e)))?;
+        if opcode == INVOKEINTERFACE {
+            self.pool.add_interface_method_ref(class, name, descriptor)
+        } else {
+            self.pool.add_method_ref(class, name, descriptor)
+        }
+        .map_err(pool_error(line))
+    }
+
+    fn instruction(&mut self, mut tokens: Tokens) -> Result<(), AsmError> {
+        let line = tokens.line;
+        let name = tokens.word("instruction")?;
+        let opcode = match from_mnemonic(&name) {
+            Some(WIDE) => {
+                return Err(tokens.error("wide is added automatically when needed".to_string()))
+            }
+            Some(opcode) => opcode,
+            None => return Err(tokens.error(format!("unknown instruction {}", name))),
+        };
+        let mut wide = false;
+        let operand = match opcode {
+            BIPUSH => Operand::Byte(tokens.number("byte")?),
+            SIPUSH => Operand::Short(tokens.number("short")?),
+            LDC | LDC_W | LDC2_W => {
+                let index = self.constant(&mut tokens, opcode)?;
+                let opcode = if opcode == LDC && index > 0xFF {
+                    LDC_W
+                } else {
+                    opcode
+                };
+                return self.push(
+                    tokens,
+                    opcode,
+                    false,
+                    PendingOperand::Resolved(Operand::Constant(index)),
+                );
+            }
+            ILOAD..=ALOAD | ISTORE..=ASTORE | RET => {
+                let index: u16 = tokens.number("local variable index")?;
+                wide = index > 0xFF;
+                Operand::Local(index)
+            }
+            IINC => {
+                let index: u16 = tokens.number("local variable index")?;
+                let value: i16 = tokens.number("increment")?;
+                wide = index > 0xFF || i8::try_from(value).is_err();
+                Operand::Iinc(index, value)
+            }
+            IFEQ..=JSR | IFNULL | IFNONNULL | GOTO_W | JSR_W => {
+                let label = tokens.word("label")?;
+                return self.push(tokens, opcode, false, PendingOperand::Branch(label));
+            }
+            TABLESWITCH | LOOKUPSWITCH => {
+                let low = if opcode == TABLESWITCH {
+                    tokens.number("low")?
+                } else {
+                    0
+                };
+                if opcode == TABLESWITCH && !tokens.items.is_empty() {
+                    // The optional high value is implied by the number of targets.
+                    tokens.number::<i32>("high")?;
+                }
+                tokens.finish()?;
+                let method = self.method.as_mut().expect("in method");
+                method.switch = Some(PendingSwitch {
+                    instruction: PendingInstruction {
+                        line,
+                        opcode,
+                        wide: false,
+                        operand: PendingOperand::Resolved(Operand::None),
+                    },
+                    low,
+                    targets: Vec::new(),
+                    pairs: Vec::new(),
+                });
+                return Ok(());
+            }
+            GETSTATIC..=INVOKESTATIC => Operand::Constant(self.member_ref(&mut tokens, opcode)?),
+            INVOKEINTERFACE => {
+                let index = self.member_ref(&mut tokens, opcode)?;
+                let count = if tokens.items.is_empty() {
+                    let descriptor = match self.pool.get(index) {
+                        Ok(ConstantPoolItem::InterfaceMethod(_, nat)) => {
+                            match self.pool.get(*nat) {
+                                Ok(ConstantPoolItem::NameAndType(_, desc)) => {
+                                    self.pool.resolve(*desc)
+                                }
+                                _ => String::new(),
+                            }
+                        }
+                        _ => String::new(),
+                    };
+                    let types = signature::parse_method_descriptor(&descriptor)
+                        .map_err(pool_error(line))?;
+                    let count = 1 + types
+                        .parameters
+                        .iter()
+                        .map(slots)
+                        .map(u32::from)
+                        .sum::<u32>();
+                    u8::try_from(count).map_err(|_| {
+                        tokens.error(format!("invokeinterface of {} argument slots", count))
+                    })?
+                } else {
+                    tokens.number("argument count")?
+                };
+                Operand::InvokeInterface(index, count)
+            }
+            INVOKEDYNAMIC => return Err(tokens.error("invokedynamic is not supported".to_string())),
+            NEW | ANEWARRAY | CHECKCAST | INSTANCEOF => {
+                let name = tokens.word("class name")?;
+                Operand::Constant(self.pool.add_class(&name).map_err(pool_error(line))?)
+            }
+            NEWARRAY => {
+                let name = tokens.word("array type")?;
+                let code = bytecode::array_type_code(&name)
+                    .ok_or_else(|| tokens.error(format!("bad array type {}", name)))?;
+                Operand::ArrayType(code)
+            }
+            MULTIANEWARRAY => {
+                let name = tokens.word("array class")?;
+                let index = self.pool.add_class(&name).map_err(pool_error(line))?;
+                let dimensions: u8 = tokens.number("dimensions")?;
+                let depth = name.bytes().take_while(|b| *b == b'[').count();
+                if dimensions == 0 || dimensions as usize > depth {
+                    let message = format!("bad dimensions {} for array class {}", dimensions, name);
+                    return Err(tokens.error(message));
+                }
+                Operand::MultiANewArray(index, dimensions)
+            }
+            _ => Operand::None,
+        };
+        self.push(tokens, opcode, wide, PendingOperand::Resolved(operand))
+    }
+
+    fn push(
+        &mut self,
+        tokens: Tokens,
+        opcode: u8,
+        wide: bool,
+        operand: PendingOperand,
+    ) -> Result<(), AsmError> {
+        tokens.finish()?;
+        let method = self.method.as_mut().expect("in method");
+        method.instructions.push(PendingInstruction {
+            line: tokens.line,
+            opcode,
+            wide,
+            operand,
+        });
+        Ok(())
+    }
+
+    /// A line inside a switch: a target (`tableswitch`), `key : target` (`lookupswitch`) or the
+    /// closing `default : target`.
+    fn switch_case(&mut self, mut tokens: Tokens) -> Result<(), AsmError> {
+        let method = self.method.as_mut().expect("in method");
+        let switch = method.switch.as_mut().expect("in switch");
+        if tokens.items.is_empty() {
+            return Ok(());
+        }
+        let first = tokens.word("switch case")?;
+        // `key : target`, `key: target` and `key:target` are all accepted.
+        let (key, target) = match first.split_once(':') {
+            Some((key, "")) => (Some(key.to_string()), None),
+            Some((key, target)) => (Some(key.to_string()), Some(target.to_string())),
+            None if tokens.peek_word().is_some_and(|word| word.starts_with(':')) => {
+                let word = tokens.word(":")?;
+                let target = Some(word[1..].to_string()).filter(|target| !target.is_empty());
+                (Some(first), target)
+            }
+            None => (None, Some(first)),
+        };
+        let target = match target {
+            Some(target) => target,
+            None => tokens.word("label")?,
+        };
+        tokens.finish()?;
+        let is_table = switch.instruction.opcode == TABLESWITCH;
+        match key.as_deref() {
+            Some("default") => {
+                let mut switch = method.switch.take().expect("in switch");
+                switch.instruction.operand = if is_table {
+                    if switch.targets.is_empty() {
+                        return Err(tokens.error("tableswitch without targets".to_string()));
+                    }
+                    let high = i32::try_from(switch.targets.len() - 1)
+                        .ok()
+                        .and_then(|count| switch.low.checked_add(count))
+                        .ok_or_else(|| {
+                            tokens.error(format!(
+                                "tableswitch from {} with {} targets exceeds the int range",
+                                switch.low,
+                                switch.targets.len()
+                            ))
+                        })?;
+                    PendingOperand::TableSwitch(switch.low, high, switch.targets, target)
+                } else {
+                    PendingOperand::LookupSwitch(switch.pairs, target)
+                };
+                method.instructions.push(switch.instruction);
+            }
+            None if is_table => switch.targets.push(target),
+            Some(key) if !is_table => {
+                let key = parse_integer(key)
+                    .and_then(|key| i32::try_from(key).ok())
+                    .ok_or_else(|| tokens.error(format!("bad switch key {}", key)))?;
+                if switch.pairs.iter().any(|(existing, _)| *existing == key) {
+                    return Err(tokens.error(format!("duplicate switch key {}", key)));
+                }
+                switch.pairs.push((key, target));
+            }
+            _ if is_table => return Err(tokens.error("expected a label or default".to_string())),
+            _ => return Err(tokens.error("expected key : label or default".to_string())),
+        }
+        Ok(())
+    }
+
+    fn finish_method(&mut self, method: MethodBuilder) -> Result<Member, AsmError> {
+        let line = method.line;
+        let mut attributes = Vec::<AttributeInfo>::new();
+        let has_body = !method.access_flags.contains(ACC_ABSTRACT)
+            && !method.access_flags.contains(ACC_NATIVE);
+        if has_body {
+            let code = self.finish_code(&method)?;
+            attributes.push(self.attribute(line, ATTR_CODE, Attribute::Code(code))?);
+        } else if !method.instructions.is_empty() {
+            return Err(AsmError::new(
+                line,
+                "abstract and native methods have no code".to_string(),
+            ));
+        }
+        if !method.throws.is_empty() {
+            attributes.push(self.attribute(
+                line,
+                ATTR_EXCEPTIONS,
+                Attribute::Exceptions(method.throws),
+            )?);
+        }
+        if let Some(index) = method.signature {
+            attributes.push(self.attribute(line, ATTR_SIGNATURE, Attribute::Signature(index))?);
+        }
+        Ok(Member {
+            access_flags: method.access_flags,
+            name_index: self.pool.add_utf8(&method.name).map_err(pool_error(line))?,
+            descriptor_index: self
+                .pool
+                .add_utf8(&method.descriptor)
+                .map_err(pool_error(line))?,
+            attributes,
+        })
+    }
+
+    fn finish_code(&mut self, method: &MethodBuilder) -> Result<Code, AsmError> {
+        let line = method.line;
+        if method.instructions.is_empty() {
+            return Err(AsmError::new(
+                line,
+                format!("method {} has no code", method.name),
+            ));
+        }
+        if self.branch_line.is_none() {
+            self.branch_line = method
+                .instructions
+                .iter()
+                .find(|item| !matches!(item.operand, PendingOperand::Resolved(_)))
+                .map(|item| item.line)
+                .or(method.catches.first().map(|catch| catch.line));
+        }
+        let mut offsets = Vec::<u32>::with_capacity(method.instructions.len() + 1);
+        let mut offset = 0u32;
+        for instruction in method.instructions.iter() {
+            offsets.push(offset);
+            offset += instruction.layout(offset).size();
+        }
+        offsets.push(offset);
+        let label = |line: usize, name: &str| -> Result<u32, AsmError> {
+            method
+                .labels
+                .get(name)
+                .map(|index| offsets[*index])
+                .ok_or_else(|| AsmError::new(line, format!("undefined label {}", name)))
+        };
+        let mut instructions = Vec::<Instruction>::with_capacity(method.instructions.len());
+        for (pending, offset) in method.instructions.iter().zip(offsets.iter()) {
+            let target = |name: &str| label(pending.line, name).map(|offset| offset as i32);
+            let mut instruction = pending.layout(*offset);
+            instruction.operand = match &pending.operand {
+                PendingOperand::Resolved(operand) => operand.clone(),
+                PendingOperand::Branch(name) => Operand::Branch(target(name)?),
+                PendingOperand::TableSwitch(low, high, targets, default) => Operand::TableSwitch(
+                    target(default)?,
+                    *low,
+                    *high,
+                    targets
+                        .iter()
+                        .map(|name| target(name))
+                        .collect::<Result<_, _>>()?,
+                ),
+                PendingOperand::LookupSwitch(pairs, default) => {
+                    let mut pairs = pairs
+                        .iter()
+                        .map(|(key, name)| Ok((*key, target(name)?)))
+                        .collect::<Result<Vec<_>, AsmError>>()?;
+                    // The JVM requires the keys in increasing order.
+                    pairs.sort_by_key(|(key, _)| *key);
+                    Operand::LookupSwitch(target(default)?, pairs)
+                }
+            };
+            instructions.push(instruction);
+        }
+        let code = bytecode::encode(&instructions).map_err(|e| {
+            let index = instructions
+                .iter()
+                .position(|item| item.offset as u64 == e.offset)
+                .unwrap_or(0);
+            AsmError::new(method.instructions[index].line, e.kind.to_string())
+        })?;
+        let exception_table = method
+            .catches
+            .iter()
+            .map(|catch| {
+                let pc = |name: &str| {
+                    u16::try_from(label(catch.line, name)?)
+                        .map_err(|_| AsmError::new(catch.line, "code too large".to_string()))
+                };
+                Ok(ExceptionHandler {
+                    start_pc: pc(&catch.from)?,
+                    end_pc: pc(&catch.to)?,
+                    handler_pc: pc(&catch.using)?,
+                    catch_type: catch.catch_type,
+                })
+            })
+            .collect::<Result<Vec<_>, AsmError>>()?;
+        let mut attributes = Vec::<AttributeInfo>::new();
+        if !method.lines.is_empty() {
+            let lines = method
+                .lines
+                .iter()
+                .filter(|(index, _)| *index < method.instructions.len())
+                .map(|(index, line_number)| {
+                    let start_pc = u16::try_from(offsets[*index])
+                        .map_err(|_| AsmError::new(line, "code too large".to_string()))?;
+                    Ok(LineNumber {
+                        start_pc,
+                        line_number: *line_number,
+                    })
+                })
+                .collect::<Result<Vec<_>, AsmError>>()?;
+            attributes.push(self.attribute(
+                line,
+                ATTR_LINE_NUMBER_TABLE,
+                Attribute::LineNumberTable(lines),
+            )?);
+        }
+        let max_stack = method.max_stack.ok_or_else(|| {
+            AsmError::new(line, format!("method {} has no .limit stack", method.name))
+        })?;
+        let max_locals = match method.max_locals {
+            Some(max_locals) => max_locals,
+            None => {
+                let types = signature::parse_method_descriptor(&method.descriptor)
+                    .map_err(pool_error(line))?;
+                let this = if method.access_flags.contains(ACC_STATIC) {
+                    0
+                } else {
+                    1
+                };
+                let parameters = this + types.parameters.iter().map(slots).sum::<u16>();
+                instructions
+                    .iter()
+                    .map(|item| locals_used(item.opcode, &item.operand))
+                    .fold(parameters, u16::max)
+            }
+        };
+        Ok(Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        })
+    }
+
+    fn finish(mut self, last_line: usize) -> Result<ClassFile, AsmError> {
+        if let Some(method) = self.method.as_ref() {
+            return Err(AsmError::new(
+                method.line,
+                format!("method {} has no .end method", method.name),
+            ));
+        }
+        let this_class = self
+            .this_class
+            .ok_or_else(|| AsmError::new(last_line, "missing .class directive".to_string()))?;
+        // `.version` may follow the methods, so this is only known at the end.
+        if let Some(line) = self.branch_line.filter(|_| self.major > STACK_MAP_VERSION) {
+            return Err(AsmError::new(
+                line,
+                format!(
+                    "branches need StackMapTable frames in version {}, which are not generated (use .version 50 or lower)",
+                    self.major
+                ),
+            ));
+        }
+        let super_name = self
+            .super_class
+            .clone()
+            .unwrap_or(DEFAULT_SUPER.to_string());
+        let super_class = self
+            .pool
+            .add_class(&super_name)
+            .map_err(pool_error(last_line))?;
+        Ok(ClassFile {
+            header: Header {
+                magic: MAGIC,
+                minor: self.minor,
+                major: self.major,
+            },
+            constant_pool: self.pool,
+            access_flags: self.access_flags,
+            this_class,
+            super_class,
+            interfaces: self.interfaces,
+            fields: self.fields,
+            methods: self.methods,
+            attributes: self.attributes,
+        })
+    }
+}
+
+/// Assembles the source into a class file, `ClassFile::write` serialises it.
+pub fn assemble(source: &str) -> Result<ClassFile, AsmError> {
+    let mut assembler = Assembler::new();
+    let mut last_line = 1;
+    for (i, text) in source.lines().enumerate() {
+        last_line = i + 1;
+        let items = tokenize(text).map_err(|message| AsmError::new(last_line, message))?;
+        assembler.line(Tokens {
+            line: last_line,
+            items: items.into(),
+        })?;
+    }
+    assembler.finish(last_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::assemble;
+    use crate::bytecode::{decode, Operand};
+    use crate::classfile::ClassFile;
+    use crate::opcode::*;
+
+    const HELLO: &str = r#"
+; comments start with a semicolon
+.class public final Hello
+.source "Hello.j"
+.field public static final ANSWER I = 42
+
+.method public static main([Ljava/lang/String;)V
+    .limit stack 2
+    getstatic java/lang/System/out Ljava/io/PrintStream;
+    ldc "hello"
+    invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+    return
+.end method
+"#;
+
+    #[test]
+    fn test_assemble_hello() {
+        let class = assemble(HELLO).unwrap();
+        let class = ClassFile::parse(class.to_bytes().unwrap().as_slice()).unwrap();
+        let pool = &class.constant_pool;
+        assert_eq!(class.header.major, 49);
+        assert_eq!(class.this_class_name(), "Hello");
+        assert_eq!(class.super_class_name().unwrap(), "java/lang/Object");
+        assert_eq!(class.access_flags.names(), vec!["public", "final", "super"]);
+        assert_eq!(class.fields[0].name(pool), "ANSWER");
+        let main = class.find_method("main").unwrap();
+        assert_eq!(main.descriptor(pool), "([Ljava/lang/String;)V");
+        let code = main.code().unwrap();
+        assert_eq!((code.max_stack, code.max_locals), (2, 1));
+        let mnemonics: Vec<&str> = decode(&code.code)
+            .unwrap()
+            .iter()
+            .map(|item| item.mnemonic())
+            .collect();
+        assert_eq!(
+            mnemonics,
+            vec!["getstatic", "ldc", "invokevirtual", "return"]
+        );
+    }
+
+    #[test]
+    fn test_assemble_labels_and_switches() {
+        let source = r#"
+.class Switch
+.version 50
+.method static pick(I)I
+    .limit stack 1
+    iload_0
+    tableswitch 1
+        One
+        Two
+        default : Other
+One:
+    iconst_1
+    ireturn
+Two: iconst_2
+    ireturn
+Other:
+    iload_0
+    lookupswitch
+        100: One
+        -1 : Two
+        default:Other
+.end method
+"#;
+        // Version 50 has no StackMapTable requirement yet, the JVM falls back to type inference.
+        let class = assemble(source).unwrap();
+        assert_eq!(class.header.major, 50);
+        let code = class.find_method("pick").unwrap().code().unwrap();
+        let instructions = decode(&code.code).unwrap();
+        assert_eq!(
+            instructions[1].operand,
+            Operand::TableSwitch(28, 1, 2, vec![24, 26])
+        );
+        assert_eq!(
+            instructions[7].operand,
+            Operand::LookupSwitch(28, vec![(-1, 26), (100, 24)])
+        );
+    }
+
+    #[test]
+    fn test_assemble_picks_wide_forms() {
+        let source = r#"
+.class Wide
+.method static m(J)V
+    .limit stack 2
+    .limit locals 400
+    ldc2_w 5
+    lstore 300
+    iinc 2 1000
+    ldc 1.5
+    goto_w End
+End:
+    return
+.end method
+"#;
+        let class = assemble(source).unwrap();
+        let code = class.find_method("m").unwrap().code().unwrap();
+        let instructions = decode(&code.code).unwrap();
+        assert_eq!(instructions[0].opcode, LDC2_W);
+        assert!(instructions[1].wide);
+        assert_eq!(instructions[1].operand, Operand::Local(300));
+        assert!(instructions[2].wide);
+        assert_eq!(instructions[2].operand, Operand::Iinc(2, 1000));
+        assert_eq!(
+            class.constant_pool.resolve(match instructions[3].operand {
+                Operand::Constant(index) => index,
+                _ => 0,
+            }),
+            "1.5f"
+        );
+        assert_eq!(instructions[4].operand, Operand::Branch(20));
+    }
+
+    #[test]
+    fn test_assemble_computes_max_locals() {
+        let source = ".class A\n.method m(JI)V\n.limit stack 0\ndstore 5\nreturn\n.end method";
+        let class = assemble(source).unwrap();
+        assert_eq!(class.methods[0].code().unwrap().max_locals, 7);
+    }
+
+    #[test]
+    fn test_assemble_errors_carry_line_numbers() {
+        let cases = [
+            (
+                ".class A\n.method m()V\n.limit stack 1\ngoto Nowhere\n.end method",
+                "line 4: undefined label Nowhere",
+            ),
+            (
+                ".class A\n.method m()V\n.limit stack 1\nfrobnicate\n.end method",
+                "line 4: unknown instruction frobnicate",
+            ),
+            (
+                ".class A\n.method m()V\nreturn\n.end method",
+                "line 2: method m has no .limit stack",
+            ),
+            (".method m()V", "line 1: method m has no .end method"),
+            (".super B", "line 1: missing .class directive"),
+            (
+                ".class A\n.field x I = \"text\"",
+                "line 2: fields of type I can not have a constant value",
+            ),
+            (
+                ".class A\n.method m()V\n.limit stack 1\nbipush 300\n.end method",
+                "line 4: bad byte 300",
+            ),
+            (
+                ".class A\n.method m()V\n.limit stack 0\nL: goto L\n.end method\n.version 52",
+                "line 4: branches need StackMapTable frames in version 52, which are not generated (use .version 50 or lower)",
+            ),
+            (
+                ".class A\n.version 51\n.method m()V\n.limit stack 1\nL:\nreturn\n.catch all from L to L using L\n.end method",
+                "line 7: branches need StackMapTable frames in version 51, which are not generated (use .version 50 or lower)",
+            ),
+            (
+                ".class A\n.method m()V\n.limit stack 1\ntableswitch 2147483647\nL\nL\ndefault : L\nL: return\n.end method",
+                "line 7: tableswitch from 2147483647 with 2 targets exceeds the int range",
+            ),
+            (
+                ".class A\n.method m()V\n.limit stack 3\nmultianewarray [I 3\n.end method",
+                "line 4: bad dimensions 3 for array class [I",
+            ),
+            (
+                ".class A\n.method m()V\n.limit stack 1\nmultianewarray [[I 0\n.end method",
+                "line 4: bad dimensions 0 for array class [[I",
+            ),
+            (
+                ".class A\n.method m()V\n.limit stack 1\ninvokedynamic x\n.end method",
+                "line 4: invokedynamic is not supported",
+            ),
+        ];
+        for (source, message) in cases {
+            assert_eq!(assemble(source).unwrap_err().to_string(), message);
+        }
+        let source = format!(
+            ".class A\n.method m()V\n.limit stack 1\ninvokeinterface I/m({})V\n.end method",
+            "J".repeat(128)
+        );
+        assert_eq!(
+            assemble(&source).unwrap_err().to_string(),
+            "line 4: invokeinterface of 257 argument slots"
+        );
+        let source = format!(
+            ".class A\n.method m()V\n.limit stack 0\n{}.line 7\nreturn\n.end method",
+            "nop\n".repeat(70_000)
+        );
+        assert_eq!(
+            assemble(&source).unwrap_err().to_string(),
+            "line 2: code too large"
+        );
+    }
+}
@@ -0,0 +1,1245 @@
+use std::borrow::Cow;
+use std::io::{self, Read, Write};
+
+use crate::annotation::{self, Annotation, ElementValue, TypeAnnotation};
+use crate::error::{Context, ErrorKind, ParseError};
+use crate::flag::AccessFlag;
+use crate::pool::ConstantPool;
+use crate::reader::ClassReader;
+use crate::writer::ClassWriter;
+
+pub const ATTR_CODE: &str = "Code";
+pub const ATTR_CONSTANT_VALUE: &str = "ConstantValue";
+pub const ATTR_EXCEPTIONS: &str = "Exceptions";
+pub const ATTR_INNER_CLASSES: &str = "InnerClasses";
+pub const ATTR_ENCLOSING_METHOD: &str = "EnclosingMethod";
+pub const ATTR_SIGNATURE: &str = "Signature";
+pub const ATTR_SOURCE_FILE: &str = "SourceFile";
+pub const ATTR_LINE_NUMBER_TABLE: &str = "LineNumberTable";
+pub const ATTR_LOCAL_VARIABLE_TABLE: &str = "LocalVariableTable";
+pub const ATTR_STACK_MAP_TABLE: &str = "StackMapTable";
+pub const ATTR_BOOTSTRAP_METHODS: &str = "BootstrapMethods";
+pub const ATTR_NEST_HOST: &str = "NestHost";
+pub const ATTR_NEST_MEMBERS: &str = "NestMembers";
+pub const ATTR_RECORD: &str = "Record";
+pub const ATTR_PERMITTED_SUBCLASSES: &str = "PermittedSubclasses";
+pub const ATTR_RUNTIME_VISIBLE_ANNOTATIONS: &str = "RuntimeVisibleAnnotations";
+pub const ATTR_RUNTIME_INVISIBLE_ANNOTATIONS: &str = "RuntimeInvisibleAnnotations";
+pub const ATTR_RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS: &str = "RuntimeVisibleParameterAnnotations";
+pub const ATTR_RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS: &str =
+    "RuntimeInvisibleParameterAnnotations";
+pub const ATTR_RUNTIME_VISIBLE_TYPE_ANNOTATIONS: &str = "RuntimeVisibleTypeAnnotations";
+pub const ATTR_RUNTIME_INVISIBLE_TYPE_ANNOTATIONS: &str = "RuntimeInvisibleTypeAnnotations";
+pub const ATTR_ANNOTATION_DEFAULT: &str = "AnnotationDefault";
+pub const ATTR_MODULE: &str = "Module";
+pub const ATTR_MODULE_PACKAGES: &str = "ModulePackages";
+pub const ATTR_MODULE_MAIN_CLASS: &str = "ModuleMainClass";
+
+const ITEM_TOP: u8 = 0;
+const ITEM_INTEGER: u8 = 1;
+const ITEM_FLOAT: u8 = 2;
+const ITEM_DOUBLE: u8 = 3;
+const ITEM_LONG: u8 = 4;
+const ITEM_NULL: u8 = 5;
+const ITEM_UNINITIALIZED_THIS: u8 = 6;
+const ITEM_OBJECT: u8 = 7;
+const ITEM_UNINITIALIZED: u8 = 8;
+
+/// An `attribute_info` structure: the pool index of the attribute name and its decoded body.
+#[derive(Debug, Clone)]
+pub struct AttributeInfo {
+    pub name_index: u16,
+    pub attribute: Attribute,
+}
+
+#[derive(Debug, Clone)]
+pub enum Attribute {
+    Code(Code),
+    ConstantValue(u16),               // constantValueIndex
+    Exceptions(Vec<u16>),             // exceptionIndexTable
+    InnerClasses(Vec<InnerClass>),    // classes
+    EnclosingMethod(u16, u16),        // classIndex, methodIndex
+    Signature(u16),                   // signatureIndex
+    SourceFile(u16),                  // sourceFileIndex
+    LineNumberTable(Vec<LineNumber>), // lineNumberTable
+    LocalVariableTable(Vec<LocalVariable>),
+    StackMapTable(Vec<StackMapFrame>),
+    BootstrapMethods(Vec<BootstrapMethod>),
+    NestHost(u16),         // hostClassIndex
+    NestMembers(Vec<u16>), // classes
+    Record(Vec<RecordComponent>),
+    PermittedSubclasses(Vec<u16>), // classes
+    RuntimeVisibleAnnotations(Vec<Annotation>),
+    RuntimeInvisibleAnnotations(Vec<Annotation>),
+    RuntimeVisibleParameterAnnotations(Vec<Vec<Annotation>>), // annotations per parameter
+    RuntimeInvisibleParameterAnnotations(Vec<Vec<Annotation>>), // annotations per parameter
+    RuntimeVisibleTypeAnnotations(Vec<TypeAnnotation>),
+    RuntimeInvisibleTypeAnnotations(Vec<TypeAnnotation>),
+    AnnotationDefault(ElementValue),
+    Module(Module),
+    ModulePackages(Vec<u16>), // packages
+    ModuleMainClass(u16),     // mainClassIndex
+    Unknown(String, Vec<u8>), // name, raw info bytes
+}
+
+#[derive(Debug, Clone)]
+pub struct Code {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: Vec<u8>,
+    pub exception_table: Vec<ExceptionHandler>,
+    pub attributes: Vec<AttributeInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExceptionHandler {
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    pub catch_type: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct InnerClass {
+    pub inner_class_info_index: u16,
+    pub outer_class_info_index: u16,
+    pub inner_name_index: u16,
+    pub inner_class_access_flags: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct LineNumber {
+    pub start_pc: u16,
+    pub line_number: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalVariable {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct BootstrapMethod {
+    pub method_ref: u16,
+    pub arguments: Vec<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordComponent {
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<AttributeInfo>,
+}
+
+/// The directives of a `module-info` class.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name_index: u16, // Module entry
+    pub flags: u16,
+    pub version_index: u16, // 0 if the module has no version
+    pub requires: Vec<Requires>,
+    pub exports: Vec<Exports>,
+    pub opens: Vec<Exports>,
+    pub uses: Vec<u16>, // service interfaces
+    pub provides: Vec<Provides>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Requires {
+    pub module_index: u16,
+    pub flags: u16,
+    pub version_index: u16, // 0 if the version was not recorded
+}
+
+/// An `exports` or `opens` directive, `to` is empty when it is unqualified.
+#[derive(Debug, Clone)]
+pub struct Exports {
+    pub package_index: u16,
+    pub flags: u16,
+    pub to: Vec<u16>, // modules
+}
+
+#[derive(Debug, Clone)]
+pub struct Provides {
+    pub service_index: u16,
+    pub with: Vec<u16>, // implementation classes
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Double,
+    Long,
+    Null,
+    UninitializedThis,
+    Object(u16),        // classIndex
+    Uninitialized(u16), // offset of the `new` instruction
+}
+
+#[derive(Debug, Clone)]
+pub enum StackMapFrame {
+    Same(u8),                                                // frameType (0-63)
+    SameLocals1StackItem(u8, VerificationType),              // frameType (64-127), stack
+    SameLocals1StackItemExtended(u16, VerificationType),     // offsetDelta, stack
+    Chop(u8, u16),                                           // frameType (248-250), offsetDelta
+    SameExtended(u16),                                       // offsetDelta
+    Append(u16, Vec<VerificationType>),                      // offsetDelta, locals
+    Full(u16, Vec<VerificationType>, Vec<VerificationType>), // offsetDelta, locals, stack
+}
+
+impl StackMapFrame {
+    pub fn offset_delta(&self) -> u16 {
+        match self {
+            Self::Same(frame_type) => *frame_type as u16,
+            Self::SameLocals1StackItem(frame_type, _) => (*frame_type - 64) as u16,
+            Self::SameLocals1StackItemExtended(delta, _)
+            | Self::Chop(_, delta)
+            | Self::SameExtended(delta)
+            | Self::Append(delta, _)
+            | Self::Full(delta, _, _) => *delta,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Same(_) => "same",
+            Self::SameLocals1StackItem(_, _) => "same_locals_1_stack_item",
+            Self::SameLocals1StackItemExtended(_, _) => "same_locals_1_stack_item_extended",
+            Self::Chop(_, _) => "chop",
+            Self::SameExtended(_) => "same_extended",
+            Self::Append(_, _) => "append",
+            Self::Full(_, _, _) => "full",
+        }
+    }
+}
+
+impl VerificationType {
+    fn parse<R: Read>(rdr: &mut ClassReader<R>) -> Result<Self, ParseError> {
+        let tag = rdr.read_u8()?;
+        let item = match tag {
+            ITEM_TOP => Self::Top,
+            ITEM_INTEGER => Self::Integer,
+            ITEM_FLOAT => Self::Float,
+            ITEM_DOUBLE => Self::Double,
+            ITEM_LONG => Self::Long,
+            ITEM_NULL => Self::Null,
+            ITEM_UNINITIALIZED_THIS => Self::UninitializedThis,
+            ITEM_OBJECT => Self::Object(rdr.read_u16()?),
+            ITEM_UNINITIALIZED => Self::Uninitialized(rdr.read_u16()?),
+            _ => {
+                return Err(ParseError::new(
+                    rdr.offset() - 1,
+                    ErrorKind::Malformed(format!("unknown verification type tag {}", tag)),
+                ))
+            }
+        };
+        Ok(item)
+    }
+
+    fn write<W: Write>(&self, wtr: &mut ClassWriter<W>) -> io::Result<()> {
+        match self {
+            Self::Top => wtr.write_u8(ITEM_TOP),
+            Self::Integer => wtr.write_u8(ITEM_INTEGER),
+            Self::Float => wtr.write_u8(ITEM_FLOAT),
+            Self::Double => wtr.write_u8(ITEM_DOUBLE),
+            Self::Long => wtr.write_u8(ITEM_LONG),
+            Self::Null => wtr.write_u8(ITEM_NULL),
+            Self::UninitializedThis => wtr.write_u8(ITEM_UNINITIALIZED_THIS),
+            Self::Object(index) => {
+                wtr.write_u8(ITEM_OBJECT)?;
+                wtr.write_u16(*index)
+            }
+            Self::Uninitialized(offset) => {
+                wtr.write_u8(ITEM_UNINITIALIZED)?;
+                wtr.write_u16(*offset)
+            }
+        }
+    }
+
+    pub fn resolve(&self, pool: &ConstantPool) -> String {
+        match self {
+            Self::Top => "top".to_string(),
+            Self::Integer => "int".to_string(),
+            Self::Float => "float".to_string(),
+            Self::Double => "double".to_string(),
+            Self::Long => "long".to_string(),
+            Self::Null => "null".to_string(),
+            Self::UninitializedThis => "uninitialized_this".to_string(),
+            Self::Object(index) => pool.resolve(*index),
+            Self::Uninitialized(offset) => format!("uninitialized({})", offset),
+        }
+    }
+}
+
+/// Reads a `u16` count followed by that many entries.
+fn parse_table<R, T, F>(rdr: &mut ClassReader<R>, mut parse: F) -> Result<Vec<T>, ParseError>
+where
+    R: Read,
+    F: FnMut(&mut ClassReader<R>) -> Result<T, ParseError>,
+{
+    let count = rdr.read_u16()?;
+    (0..count).map(|_| parse(rdr)).collect()
+}
+
+fn parse_verification_types<R: Read>(
+    rdr: &mut ClassReader<R>,
+    count: usize,
+) -> Result<Vec<VerificationType>, ParseError> {
+    (0..count).map(|_| VerificationType::parse(rdr)).collect()
+}
+
+fn parse_stack_map_frame<R: Read>(rdr: &mut ClassReader<R>) -> Result<StackMapFrame, ParseError> {
+    let frame_type = rdr.read_u8()?;
+    let frame = match frame_type {
+        0..=63 => StackMapFrame::Same(frame_type),
+        64..=127 => StackMapFrame::SameLocals1StackItem(frame_type, VerificationType::parse(rdr)?),
+        247 => {
+            let delta = rdr.read_u16()?;
+            StackMapFrame::SameLocals1StackItemExtended(delta, VerificationType::parse(rdr)?)
+        }
+        248..=250 => StackMapFrame::Chop(frame_type, rdr.read_u16()?),
+        251 => StackMapFrame::SameExtended(rdr.read_u16()?),
+        252..=254 => {
+            let delta = rdr.read_u16()?;
+            let locals = parse_verification_types(rdr, (frame_type - 251) as usize)?;
+            StackMapFrame::Append(delta, locals)
+        }
+        255 => {
+            let delta = rdr.read_u16()?;
+            let locals_count = rdr.read_u16()?;
+            let locals = parse_verification_types(rdr, locals_count as usize)?;
+            let stack_count = rdr.read_u16()?;
+            let stack = parse_verification_types(rdr, stack_count as usize)?;
+            StackMapFrame::Full(delta, locals, stack)
+        }
+        _ => {
+            return Err(ParseError::new(
+                rdr.offset() - 1,
+                ErrorKind::Malformed(format!("reserved stack map frame type {}", frame_type)),
+            ))
+        }
+    };
+    Ok(frame)
+}
+
+/// Resolves attribute names while decoding, implemented by the parsed pool and by `ClassView`.
+pub(crate) trait AttributeNames {
+    fn attribute_name(&self, index: u16) -> Result<Cow<'_, str>, ErrorKind>;
+}
+
+impl AttributeNames for ConstantPool {
+    fn attribute_name(&self, index: u16) -> Result<Cow<'_, str>, ErrorKind> {
+        self.get_utf8(index).map(Cow::Borrowed)
+    }
+}
+
+fn parse_code<R: Read, P: AttributeNames>(
+    rdr: &mut ClassReader<R>,
+    pool: &P,
+) -> Result<Code, ParseError> {
+    let max_stack = rdr.read_u16()?;
+    let max_locals = rdr.read_u16()?;
+    let code_length = rdr.read_u32()?;
+    let code = rdr.read_n(code_length as usize).context("code")?;
+    let exception_table = parse_table(rdr, |rdr| {
+        Ok(ExceptionHandler {
+            start_pc: rdr.read_u16()?,
+            end_pc: rdr.read_u16()?,
+            handler_pc: rdr.read_u16()?,
+            catch_type: rdr.read_u16()?,
+        })
+    })
+    .context("exception table")?;
+    let attributes = parse_attributes(rdr, pool)?;
+    Ok(Code {
+        max_stack,
+        max_locals,
+        code,
+        exception_table,
+        attributes,
+    })
+}
+
+fn parse_exports<R: Read>(rdr: &mut ClassReader<R>) -> Result<Exports, ParseError> {
+    Ok(Exports {
+        package_index: rdr.read_u16()?,
+        flags: rdr.read_u16()?,
+        to: rdr.read_u16_table()?,
+    })
+}
+
+fn parse_module<R: Read>(rdr: &mut ClassReader<R>) -> Result<Module, ParseError> {
+    Ok(Module {
+        name_index: rdr.read_u16()?,
+        flags: rdr.read_u16()?,
+        version_index: rdr.read_u16()?,
+        requires: parse_table(rdr, |rdr| {
+            Ok(Requires {
+                module_index: rdr.read_u16()?,
+                flags: rdr.read_u16()?,
+                version_index: rdr.read_u16()?,
+            })
+        })
+        .context("requires")?,
+        exports: parse_table(rdr, parse_exports).context("exports")?,
+        opens: parse_table(rdr, parse_exports).context("opens")?,
+        uses: rdr.read_u16_table().context("uses")?,
+        provides: parse_table(rdr, |rdr| {
+            Ok(Provides {
+                service_index: rdr.read_u16()?,
+                with: rdr.read_u16_table()?,
+            })
+        })
+        .context("provides")?,
+    })
+}
+
+/// Reads the body of a known attribute, `None` for names this crate does not decode.
+fn parse_attribute_body<R: Read, P: AttributeNames>(
+    rdr: &mut ClassReader<R>,
+    name: &str,
+    pool: &P,
+) -> Result<Option<Attribute>, ParseError> {
+    let attribute = match name {
+        ATTR_CODE => Attribute::Code(parse_code(rdr, pool)?),
+        ATTR_CONSTANT_VALUE => Attribute::ConstantValue(rdr.read_u16()?),
+        ATTR_EXCEPTIONS => Attribute::Exceptions(rdr.read_u16_table()?),
+        ATTR_INNER_CLASSES => Attribute::InnerClasses(parse_table(rdr, |rdr| {
+            Ok(InnerClass {
+                inner_class_info_index: rdr.read_u16()?,
+                outer_class_info_index: rdr.read_u16()?,
+                inner_name_index: rdr.read_u16()?,
+                inner_class_access_flags: rdr.read_u16()?,
+            })
+        })?),
+        ATTR_ENCLOSING_METHOD => {
+            let class_index = rdr.read_u16()?;
+            let method_index = rdr.read_u16()?;
+            Attribute::EnclosingMethod(class_index, method_index)
+        }
+        ATTR_SIGNATURE => Attribute::Signature(rdr.read_u16()?),
+        ATTR_SOURCE_FILE => Attribute::SourceFile(rdr.read_u16()?),
+        ATTR_LINE_NUMBER_TABLE => Attribute::LineNumberTable(parse_table(rdr, |rdr| {
+            Ok(LineNumber {
+                start_pc: rdr.read_u16()?,
+                line_number: rdr.read_u16()?,
+            })
+        })?),
+        ATTR_LOCAL_VARIABLE_TABLE => Attribute::LocalVariableTable(parse_table(rdr, |rdr| {
+            Ok(LocalVariable {
+                start_pc: rdr.read_u16()?,
+                length: rdr.read_u16()?,
+                name_index: rdr.read_u16()?,
+                descriptor_index: rdr.read_u16()?,
+                index: rdr.read_u16()?,
+            })
+        })?),
+        ATTR_STACK_MAP_TABLE => Attribute::StackMapTable(parse_table(rdr, parse_stack_map_frame)?),
+        ATTR_BOOTSTRAP_METHODS => Attribute::BootstrapMethods(parse_table(rdr, |rdr| {
+            Ok(BootstrapMethod {
+                method_ref: rdr.read_u16()?,
+                arguments: rdr.read_u16_table()?,
+            })
+        })?),
+        ATTR_NEST_HOST => Attribute::NestHost(rdr.read_u16()?),
+        ATTR_NEST_MEMBERS => Attribute::NestMembers(rdr.read_u16_table()?),
+        ATTR_RECORD => Attribute::Record(parse_table(rdr, |rdr| {
+            Ok(RecordComponent {
+                name_index: rdr.read_u16()?,
+                descriptor_index: rdr.read_u16()?,
+                attributes: parse_attributes(rdr, pool)?,
+            })
+        })?),
+        ATTR_PERMITTED_SUBCLASSES => Attribute::PermittedSubclasses(rdr.read_u16_table()?),
+        ATTR_RUNTIME_VISIBLE_ANNOTATIONS => {
+            Attribute::RuntimeVisibleAnnotations(annotation::parse_annotations(rdr)?)
+        }
+        ATTR_RUNTIME_INVISIBLE_ANNOTATIONS => {
+            Attribute::RuntimeInvisibleAnnotations(annotation::parse_annotations(rdr)?)
+        }
+        ATTR_RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS => {
+            Attribute::RuntimeVisibleParameterAnnotations(annotation::parse_parameter_annotations(
+                rdr,
+            )?)
+        }
+        ATTR_RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS => {
+            Attribute::RuntimeInvisibleParameterAnnotations(
+                annotation::parse_parameter_annotations(rdr)?,
+            )
+        }
+        ATTR_RUNTIME_VISIBLE_TYPE_ANNOTATIONS => {
+            Attribute::RuntimeVisibleTypeAnnotations(annotation::parse_type_annotations(rdr)?)
+        }
+        ATTR_RUNTIME_INVISIBLE_TYPE_ANNOTATIONS => {
+            Attribute::RuntimeInvisibleTypeAnnotations(annotation::parse_type_annotations(rdr)?)
+        }
+        ATTR_ANNOTATION_DEFAULT => {
+            Attribute::AnnotationDefault(annotation::parse_element_value(rdr)?)
+        }
+        ATTR_MODULE => Attribute::Module(parse_module(rdr)?),
+        ATTR_MODULE_PACKAGES => Attribute::ModulePackages(rdr.read_u16_table()?),
+        ATTR_MODULE_MAIN_CLASS => Attribute::ModuleMainClass(rdr.read_u16()?),
+        _ => return Ok(None),
+    };
+    Ok(Some(attribute))
+}
+
+/// Decodes the body of an attribute that starts at `start` in the class file, `None` if this
+/// crate does not know the attribute and the caller keeps it as raw bytes.
+pub(crate) fn decode_attribute<P: AttributeNames>(
+    name: &str,
+    body: &[u8],
+    start: u64,
+    pool: &P,
+) -> Result<Option<Attribute>, ParseError> {
+    let mut body_rdr = ClassReader::with_offset(body, start);
+    let Some(attribute) = parse_attribute_body(&mut body_rdr, name, pool)
+        .with_context(|| format!("attribute {}", name))?
+    else {
+        return Ok(None);
+    };
+    if body_rdr.offset() != start + body.len() as u64 {
+        let kind = ErrorKind::Malformed(format!(
+            "attribute length {} does not match its content",
+            body.len()
+        ));
+        return Err(body_rdr.error(kind)).with_context(|| format!("attribute {}", name));
+    }
+    Ok(Some(attribute))
+}
+
+fn parse_attribute<R: Read, P: AttributeNames>(
+    rdr: &mut ClassReader<R>,
+    pool: &P,
+) -> Result<AttributeInfo, ParseError> {
+    let name_offset = rdr.offset();
+    let name_index = rdr.read_u16()?;
+    let name = pool
+        .attribute_name(name_index)
+        .map_err(|kind| ParseError::new(name_offset, kind))?;
+    let length = rdr.read_u32()?;
+    let start = rdr.offset();
+    // The body is always consumed in full, so decoding a known attribute can never
+    // read past its declared length into the next structure.
+    let body = rdr
+        .read_n(length as usize)
+        .with_context(|| format!("attribute {}", name))?;
+    let attribute = match decode_attribute(&name, &body, start, pool)? {
+        Some(attribute) => attribute,
+        None => Attribute::Unknown(name.into_owned(), body),
+    };
+    Ok(AttributeInfo {
+        name_index,
+        attribute,
+    })
+}
+
+pub(crate) fn parse_attributes<R: Read, P: AttributeNames>(
+    rdr: &mut ClassReader<R>,
+    pool: &P,
+) -> Result<Vec<AttributeInfo>, ParseError> {
+    let count = rdr.read_u16().context("attributes count")?;
+    (0..count).map(|_| parse_attribute(rdr, pool)).collect()
+}
+
+fn write_verification_types<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    items: &[VerificationType],
+) -> io::Result<()> {
+    items.iter().try_for_each(|item| item.write(wtr))
+}
+
+fn write_stack_map_frame<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    frame: &StackMapFrame,
+) -> io::Result<()> {
+    match frame {
+        StackMapFrame::Same(frame_type) => wtr.write_u8(*frame_type),
+        StackMapFrame::SameLocals1StackItem(frame_type, item) => {
+            wtr.write_u8(*frame_type)?;
+            item.write(wtr)
+        }
+        StackMapFrame::SameLocals1StackItemExtended(delta, item) => {
+            wtr.write_u8(247)?;
+            wtr.write_u16(*delta)?;
+            item.write(wtr)
+        }
+        StackMapFrame::Chop(frame_type, delta) => {
+            wtr.write_u8(*frame_type)?;
+            wtr.write_u16(*delta)
+        }
+        StackMapFrame::SameExtended(delta) => {
+            wtr.write_u8(251)?;
+            wtr.write_u16(*delta)
+        }
+        StackMapFrame::Append(delta, locals) => {
+            if locals.is_empty() || locals.len() > 3 {
+                let msg = format!("append frame with {} locals", locals.len());
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+            wtr.write_u8(251 + locals.len() as u8)?;
+            wtr.write_u16(*delta)?;
+            write_verification_types(wtr, locals)
+        }
+        StackMapFrame::Full(delta, locals, stack) => {
+            wtr.write_u8(255)?;
+            wtr.write_u16(*delta)?;
+            wtr.write_count("stack map frame locals", locals.len())?;
+            write_verification_types(wtr, locals)?;
+            wtr.write_count("stack map frame stack", stack.len())?;
+            write_verification_types(wtr, stack)
+        }
+    }
+}
+
+fn write_code<W: Write>(wtr: &mut ClassWriter<W>, code: &Code) -> io::Result<()> {
+    wtr.write_u16(code.max_stack)?;
+    wtr.write_u16(code.max_locals)?;
+    wtr.write_length_prefixed("code length", &code.code)?;
+    wtr.write_count("exception table length", code.exception_table.len())?;
+    for handler in code.exception_table.iter() {
+        wtr.write_u16(handler.start_pc)?;
+        wtr.write_u16(handler.end_pc)?;
+        wtr.write_u16(handler.handler_pc)?;
+        wtr.write_u16(handler.catch_type)?;
+    }
+    write_attributes(wtr, &code.attributes)
+}
+
+fn write_exports<W: Write>(wtr: &mut ClassWriter<W>, items: &[Exports]) -> io::Result<()> {
+    wtr.write_count("exports", items.len())?;
+    items.iter().try_for_each(|item| {
+        wtr.write_u16(item.package_index)?;
+        wtr.write_u16(item.flags)?;
+        wtr.write_u16_table("exports to", &item.to)
+    })
+}
+
+fn write_module<W: Write>(wtr: &mut ClassWriter<W>, module: &Module) -> io::Result<()> {
+    wtr.write_u16(module.name_index)?;
+    wtr.write_u16(module.flags)?;
+    wtr.write_u16(module.version_index)?;
+    wtr.write_count("requires", module.requires.len())?;
+    module.requires.iter().try_for_each(|item| {
+        wtr.write_u16(item.module_index)?;
+        wtr.write_u16(item.flags)?;
+        wtr.write_u16(item.version_index)
+    })?;
+    write_exports(wtr, &module.exports)?;
+    write_exports(wtr, &module.opens)?;
+    wtr.write_u16_table("uses", &module.uses)?;
+    wtr.write_count("provides", module.provides.len())?;
+    module.provides.iter().try_for_each(|item| {
+        wtr.write_u16(item.service_index)?;
+        wtr.write_u16_table("provides with", &item.with)
+    })
+}
+
+fn write_attribute_body<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    attribute: &Attribute,
+) -> io::Result<()> {
+    match attribute {
+        Attribute::Code(code) => write_code(wtr, code),
+        Attribute::ConstantValue(index)
+        | Attribute::Signature(index)
+        | Attribute::SourceFile(index)
+        | Attribute::NestHost(index)
+        | Attribute::ModuleMainClass(index) => wtr.write_u16(*index),
+        Attribute::Exceptions(classes)
+        | Attribute::NestMembers(classes)
+        | Attribute::PermittedSubclasses(classes) => wtr.write_u16_table("classes", classes),
+        Attribute::ModulePackages(packages) => wtr.write_u16_table("packages", packages),
+        Attribute::Module(module) => write_module(wtr, module),
+        Attribute::InnerClasses(classes) => {
+            wtr.write_count("inner classes", classes.len())?;
+            classes.iter().try_for_each(|c| {
+                wtr.write_u16(c.inner_class_info_index)?;
+                wtr.write_u16(c.outer_class_info_index)?;
+                wtr.write_u16(c.inner_name_index)?;
+                wtr.write_u16(c.inner_class_access_flags)
+            })
+        }
+        Attribute::EnclosingMethod(class_index, method_index) => {
+            wtr.write_u16(*class_index)?;
+            wtr.write_u16(*method_index)
+        }
+        Attribute::LineNumberTable(lines) => {
+            wtr.write_count("line number table", lines.len())?;
+            lines.iter().try_for_each(|l| {
+                wtr.write_u16(l.start_pc)?;
+                wtr.write_u16(l.line_number)
+            })
+        }
+        Attribute::LocalVariableTable(vars) => {
+            wtr.write_count("local variable table", vars.len())?;
+            vars.iter().try_for_each(|v| {
+                wtr.write_u16(v.start_pc)?;
+                wtr.write_u16(v.length)?;
+                wtr.write_u16(v.name_index)?;
+                wtr.write_u16(v.descriptor_index)?;
+                wtr.write_u16(v.index)
+            })
+        }
+        Attribute::StackMapTable(frames) => {
+            wtr.write_count("stack map frames", frames.len())?;
+            frames
+                .iter()
+                .try_for_each(|frame| write_stack_map_frame(wtr, frame))
+        }
+        Attribute::BootstrapMethods(methods) => {
+            wtr.write_count("bootstrap methods", methods.len())?;
+            methods.iter().try_for_each(|m| {
+                wtr.write_u16(m.method_ref)?;
+                wtr.write_u16_table("bootstrap arguments", &m.arguments)
+            })
+        }
+        Attribute::Record(components) => {
+            wtr.write_count("record components", components.len())?;
+            components.iter().try_for_each(|c| {
+                wtr.write_u16(c.name_index)?;
+                wtr.write_u16(c.descriptor_index)?;
+                write_attributes(wtr, &c.attributes)
+            })
+        }
+        Attribute::RuntimeVisibleAnnotations(annotations)
+        | Attribute::RuntimeInvisibleAnnotations(annotations) => {
+            annotation::write_annotations(wtr, annotations)
+        }
+        Attribute::RuntimeVisibleParameterAnnotations(parameters)
+        | Attribute::RuntimeInvisibleParameterAnnotations(parameters) => {
+            annotation::write_parameter_annotations(wtr, parameters)
+        }
+        Attribute::RuntimeVisibleTypeAnnotations(annotations)
+        | Attribute::RuntimeInvisibleTypeAnnotations(annotations) => {
+            annotation::write_type_annotations(wtr, annotations)
+        }
+        Attribute::AnnotationDefault(value) => annotation::write_element_value(wtr, value),
+        Attribute::Unknown(_, bytes) => wtr.write_bytes(bytes),
+    }
+}
+
+fn write_attribute<W: Write>(wtr: &mut ClassWriter<W>, item: &AttributeInfo) -> io::Result<()> {
+    // The length precedes the body, so the body is serialised first.
+    let mut body = ClassWriter::new(Vec::<u8>::new());
+    write_attribute_body(&mut body, &item.attribute)?;
+    wtr.write_u16(item.name_index)?;
+    wtr.write_length_prefixed("attribute length", &body.into_inner())
+}
+
+pub(crate) fn write_attributes<W: Write>(
+    wtr: &mut ClassWriter<W>,
+    attributes: &[AttributeInfo],
+) -> io::Result<()> {
+    wtr.write_count("attributes count", attributes.len())?;
+    attributes
+        .iter()
+        .try_for_each(|item| write_attribute(wtr, item))
+}
+
+fn resolve_classes(pool: &ConstantPool, classes: &[u16]) -> String {
+    let names: Vec<String> = classes.iter().map(|index| pool.resolve(*index)).collect();
+    names.join(", ")
+}
+
+/// `java.base@17.0.2`, the version is left out if it was not recorded.
+fn module_version(pool: &ConstantPool, name_index: u16, version_index: u16) -> String {
+    if version_index == 0 {
+        pool.resolve(name_index)
+    } else {
+        format!(
+            "{}@{}",
+            pool.resolve(name_index),
+            pool.resolve(version_index)
+        )
+    }
+}
+
+fn with_flags(flags: AccessFlag, rest: String) -> String {
+    if flags.value() == 0 {
+        rest
+    } else {
+        format!("{} {}", flags, rest)
+    }
+}
+
+/// One line per directive, e.g. `requires transitive java.sql@17` or
+/// `exports com/foo/spi to com.bar`.
+fn module_directives(pool: &ConstantPool, module: &Module) -> Vec<String> {
+    let mut acc = Vec::<String>::new();
+    for item in module.requires.iter() {
+        let target = module_version(pool, item.module_index, item.version_index);
+        acc.push(format!(
+            "requires {}",
+            with_flags(AccessFlag::Requires(item.flags), target)
+        ));
+    }
+    for (keyword, items) in [("exports", &module.exports), ("opens", &module.opens)] {
+        for item in items.iter() {
+            let mut target = pool.resolve(item.package_index);
+            if !item.to.is_empty() {
+                target.push_str(&format!(" to {}", resolve_classes(pool, &item.to)));
+            }
+            acc.push(format!(
+                "{} {}",
+                keyword,
+                with_flags(AccessFlag::Exports(item.flags), target)
+            ));
+        }
+    }
+    for index in module.uses.iter() {
+        acc.push(format!("uses {}", pool.resolve(*index)));
+    }
+    for item in module.provides.iter() {
+        acc.push(format!(
+            "provides {} with {}",
+            pool.resolve(item.service_index),
+            resolve_classes(pool, &item.with)
+        ));
+    }
+    acc
+}
+
+impl Attribute {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Code(_) => ATTR_CODE,
+            Self::ConstantValue(_) => ATTR_CONSTANT_VALUE,
+            Self::Exceptions(_) => ATTR_EXCEPTIONS,
+            Self::InnerClasses(_) => ATTR_INNER_CLASSES,
+            Self::EnclosingMethod(_, _) => ATTR_ENCLOSING_METHOD,
+            Self::Signature(_) => ATTR_SIGNATURE,
+            Self::SourceFile(_) => ATTR_SOURCE_FILE,
+            Self::LineNumberTable(_) => ATTR_LINE_NUMBER_TABLE,
+            Self::LocalVariableTable(_) => ATTR_LOCAL_VARIABLE_TABLE,
+            Self::StackMapTable(_) => ATTR_STACK_MAP_TABLE,
+            Self::BootstrapMethods(_) => ATTR_BOOTSTRAP_METHODS,
+            Self::NestHost(_) => ATTR_NEST_HOST,
+            Self::NestMembers(_) => ATTR_NEST_MEMBERS,
+            Self::Record(_) => ATTR_RECORD,
+            Self::PermittedSubclasses(_) => ATTR_PERMITTED_SUBCLASSES,
+            Self::RuntimeVisibleAnnotations(_) => ATTR_RUNTIME_VISIBLE_ANNOTATIONS,
+            Self::RuntimeInvisibleAnnotations(_) => ATTR_RUNTIME_INVISIBLE_ANNOTATIONS,
+            Self::RuntimeVisibleParameterAnnotations(_) => {
+                ATTR_RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS
+            }
+            Self::RuntimeInvisibleParameterAnnotations(_) => {
+                ATTR_RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS
+            }
+            Self::RuntimeVisibleTypeAnnotations(_) => ATTR_RUNTIME_VISIBLE_TYPE_ANNOTATIONS,
+            Self::RuntimeInvisibleTypeAnnotations(_) => ATTR_RUNTIME_INVISIBLE_TYPE_ANNOTATIONS,
+            Self::AnnotationDefault(_) => ATTR_ANNOTATION_DEFAULT,
+            Self::Module(_) => ATTR_MODULE,
+            Self::ModulePackages(_) => ATTR_MODULE_PACKAGES,
+            Self::ModuleMainClass(_) => ATTR_MODULE_MAIN_CLASS,
+            Self::Unknown(name, _) => name,
+        }
+    }
+
+    /// One line summary of the attribute with pool references resolved.
+    pub fn resolve(&self, pool: &ConstantPool) -> String {
+        match self {
+            Self::Code(code) => format!(
+                "MaxStack: {}, MaxLocals: {}, CodeLength: {}, ExceptionTable: {}",
+                code.max_stack,
+                code.max_locals,
+                code.code.len(),
+                code.exception_table.len()
+            ),
+            Self::ConstantValue(index)
+            | Self::Signature(index)
+            | Self::SourceFile(index)
+            | Self::NestHost(index)
+            | Self::ModuleMainClass(index) => pool.resolve(*index),
+            Self::Exceptions(classes)
+            | Self::NestMembers(classes)
+            | Self::PermittedSubclasses(classes)
+            | Self::ModulePackages(classes) => resolve_classes(pool, classes),
+            Self::Module(module) => {
+                let mut acc = module_version(pool, module.name_index, module.version_index);
+                let flags = AccessFlag::Module(module.flags);
+                if flags.value() != 0 {
+                    acc.push_str(&format!(" {}", flags));
+                }
+                acc
+            }
+            Self::InnerClasses(classes) => format!("Classes: {}", classes.len()),
+            Self::EnclosingMethod(class_index, method_index) => {
+                if *method_index == 0 {
+                    pool.resolve(*class_index)
+                } else {
+                    format!(
+                        "{}.{}",
+                        pool.resolve(*class_index),
+                        pool.resolve(*method_index)
+                    )
+                }
+            }
+            Self::LineNumberTable(lines) => format!("Lines: {}", lines.len()),
+            Self::LocalVariableTable(vars) => format!("Variables: {}", vars.len()),
+            Self::StackMapTable(frames) => format!("Frames: {}", frames.len()),
+            Self::BootstrapMethods(methods) => format!("Methods: {}", methods.len()),
+            Self::Record(components) => format!("Components: {}", components.len()),
+            Self::RuntimeVisibleAnnotations(annotations)
+            | Self::RuntimeInvisibleAnnotations(annotations) => {
+                format!("Annotations: {}", annotations.len())
+            }
+            Self::RuntimeVisibleParameterAnnotations(parameters)
+            | Self::RuntimeInvisibleParameterAnnotations(parameters) => {
+                format!("Parameters: {}", parameters.len())
+            }
+            Self::RuntimeVisibleTypeAnnotations(annotations)
+            | Self::RuntimeInvisibleTypeAnnotations(annotations) => {
+                format!("Annotations: {}", annotations.len())
+            }
+            Self::AnnotationDefault(value) => value.to_java(pool),
+            Self::Unknown(_, bytes) => format!("Length: {}", bytes.len()),
+        }
+    }
+
+    /// Detail lines of the attribute body (one entry per table row), if the attribute has any.
+    pub fn details(&self, pool: &ConstantPool) -> Vec<String> {
+        match self {
+            Self::Code(code) => {
+                let mut acc: Vec<String> = code
+                    .exception_table
+                    .iter()
+                    .map(|h| {
+                        let catch_type = if h.catch_type == 0 {
+                            "any".to_string()
+                        } else {
+                            pool.resolve(h.catch_type)
+                        };
+                        format!(
+                            "Exception: {} - {} => {} {}",
+                            h.start_pc, h.end_pc, h.handler_pc, catch_type
+                        )
+                    })
+                    .collect();
+                for attr in code.attributes.iter() {
+                    acc.push(format!(
+                        "{} {}",
+                        attr.attribute.name(),
+                        attr.attribute.resolve(pool)
+                    ));
+                    for line in attr.attribute.details(pool) {
+                        acc.push(format!("  {}", line));
+                    }
+                }
+                acc
+            }
+            Self::InnerClasses(classes) => classes
+                .iter()
+                .map(|c| {
+                    let outer = if c.outer_class_info_index == 0 {
+                        "-".to_string()
+                    } else {
+                        pool.resolve(c.outer_class_info_index)
+                    };
+                    let name = if c.inner_name_index == 0 {
+                        "<anonymous>".to_string()
+                    } else {
+                        pool.resolve(c.inner_name_index)
+                    };
+                    format!(
+                        "{} => Outer: {}, Name: {}, Flags: 0x{:04X}",
+                        pool.resolve(c.inner_class_info_index),
+                        outer,
+                        name,
+                        c.inner_class_access_flags
+                    )
+                })
+                .collect(),
+            Self::LineNumberTable(lines) => lines
+                .iter()
+                .map(|l| format!("line {}: {}", l.line_number, l.start_pc))
+                .collect(),
+            Self::LocalVariableTable(vars) => vars
+                .iter()
+                .map(|v| {
+                    format!(
+                        "{} {} {} {} {}",
+                        v.start_pc,
+                        v.length,
+                        v.index,
+                        pool.resolve(v.name_index),
+                        pool.resolve(v.descriptor_index)
+                    )
+                })
+                .collect(),
+            Self::StackMapTable(frames) => frames
+                .iter()
+                .map(|frame| {
+                    let types = |items: &[VerificationType]| -> String {
+                        let acc: Vec<String> = items.iter().map(|t| t.resolve(pool)).collect();
+                        acc.join(", ")
+                    };
+                    match frame {
+                        StackMapFrame::SameLocals1StackItem(_, item)
+                        | StackMapFrame::SameLocals1StackItemExtended(_, item) => format!(
+                            "{} +{} stack=[{}]",
+                            frame.name(),
+                            frame.offset_delta(),
+                            item.resolve(pool)
+                        ),
+                        StackMapFrame::Chop(frame_type, _) => format!(
+                            "{} +{} k={}",
+                            frame.name(),
+                            frame.offset_delta(),
+                            251 - frame_type
+                        ),
+                        StackMapFrame::Append(_, locals) => format!(
+                            "{} +{} locals=[{}]",
+                            frame.name(),
+                            frame.offset_delta(),
+                            types(locals)
+                        ),
+                        StackMapFrame::Full(_, locals, stack) => format!(
+                            "{} +{} locals=[{}] stack=[{}]",
+                            frame.name(),
+                            frame.offset_delta(),
+                            types(locals),
+                            types(stack)
+                        ),
+                        _ => format!("{} +{}", frame.name(), frame.offset_delta()),
+                    }
+                })
+                .collect(),
+            Self::BootstrapMethods(methods) => methods
+                .iter()
+                .enumerate()
+                .map(|(i, m)| {
+                    let args: Vec<String> = m.arguments.iter().map(|a| format!("#{}", a)).collect();
+                    format!(
+                        "{}: #{} {} ({})",
+                        i,
+                        m.method_ref,
+                        pool.resolve(m.method_ref),
+                        args.join(", ")
+                    )
+                })
+                .collect(),
+            Self::Record(components) => {
+                let mut acc = Vec::<String>::new();
+                for c in components.iter() {
+                    acc.push(format!(
+                        "{} {}",
+                        pool.resolve(c.name_index),
+                        pool.resolve(c.descriptor_index)
+                    ));
+                    for attr in c.attributes.iter() {
+                        acc.push(format!(
+                            "  {} {}",
+                            attr.attribute.name(),
+                            attr.attribute.resolve(pool)
+                        ));
+                    }
+                }
+                acc
+            }
+            Self::RuntimeVisibleAnnotations(annotations)
+            | Self::RuntimeInvisibleAnnotations(annotations) => annotations
+                .iter()
+                .map(|annotation| annotation.to_java(pool))
+                .collect(),
+            Self::Module(module) => module_directives(pool, module),
+            Self::RuntimeVisibleParameterAnnotations(parameters)
+            | Self::RuntimeInvisibleParameterAnnotations(parameters) => parameters
+                .iter()
+                .enumerate()
+                .flat_map(|(i, annotations)| {
+                    annotations.iter().map(move |annotation| {
+                        format!("parameter {}: {}", i, annotation.to_java(pool))
+                    })
+                })
+                .collect(),
+            Self::RuntimeVisibleTypeAnnotations(annotations)
+            | Self::RuntimeInvisibleTypeAnnotations(annotations) => annotations
+                .iter()
+                .map(|annotation| annotation.resolve(pool))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_attributes, write_attributes, Attribute, AttributeInfo, StackMapFrame,
+        VerificationType,
+    };
+    use crate::error::{ErrorKind, ParseError};
+    use crate::pool::ConstantPool;
+    use crate::reader::ClassReader;
+    use crate::writer::ClassWriter;
+
+    fn pool() -> ConstantPool {
+        let mut pool = ConstantPool::new();
+        for name in [
+            "Code",
+            "LineNumberTable",
+            "StackMapTable",
+            "SourceFile",
+            "InnerClasses",
+            "ConstantValue",
+            "Custom",
+        ] {
+            pool.add_utf8(name).unwrap();
+        }
+        pool
+    }
+
+    fn attribute(name_index: u16, body: &[u8]) -> Vec<u8> {
+        let mut acc = name_index.to_be_bytes().to_vec();
+        acc.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        acc.extend_from_slice(body);
+        acc
+    }
+
+    fn attributes(items: &[Vec<u8>]) -> Vec<u8> {
+        let mut acc = (items.len() as u16).to_be_bytes().to_vec();
+        items.iter().for_each(|item| acc.extend_from_slice(item));
+        acc
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Vec<AttributeInfo>, ParseError> {
+        parse_attributes(&mut ClassReader::new(bytes), &pool())
+    }
+
+    fn write(items: &[AttributeInfo]) -> Vec<u8> {
+        let mut wtr = ClassWriter::new(Vec::<u8>::new());
+        write_attributes(&mut wtr, items).unwrap();
+        wtr.into_inner()
+    }
+
+    #[test]
+    fn test_decode_code_with_nested_tables() {
+        #[rustfmt::skip]
+        let stack_map = [
+            0x00, 0x04,                                     // 4 frames
+            0x03,                                           // same
+            0x42, 0x01,                                     // same_locals_1_stack_item int
+            0xFC, 0x00, 0x05, 0x07, 0x00, 0x04,             // append #4
+            0xFF, 0x00, 0x01, 0x00, 0x01, 0x04, 0x00, 0x00, // full [long] []
+        ];
+        let line_numbers = [0x00, 0x01, 0x00, 0x00, 0x00, 0x07];
+        #[rustfmt::skip]
+        let mut code = vec![
+            0x00, 0x02, 0x00, 0x01,             // max stack, max locals
+            0x00, 0x00, 0x00, 0x02, 0x03, 0xAC, // iconst_0, ireturn
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+        ];
+        code.extend(attributes(&[
+            attribute(2, &line_numbers),
+            attribute(3, &stack_map),
+        ]));
+        let bytes = attributes(&[attribute(1, &code)]);
+        let items = parse(&bytes).unwrap();
+        let Attribute::Code(code) = &items[0].attribute else {
+            panic!("expected Code, got {:?}", items[0].attribute);
+        };
+        assert_eq!((code.max_stack, code.max_locals), (2, 1));
+        assert_eq!(code.code, [0x03, 0xAC]);
+        assert_eq!(code.exception_table[0].handler_pc, 1);
+        assert!(matches!(
+            &code.attributes[0].attribute,
+            Attribute::LineNumberTable(lines) if lines[0].line_number == 7
+        ));
+        let Attribute::StackMapTable(frames) = &code.attributes[1].attribute else {
+            panic!("expected StackMapTable");
+        };
+        let frames: Vec<(&str, u16)> = frames
+            .iter()
+            .map(|frame| (frame.name(), frame.offset_delta()))
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                ("same", 3),
+                ("same_locals_1_stack_item", 2),
+                ("append", 5),
+                ("full", 1)
+            ]
+        );
+        assert_eq!(write(&items), bytes);
+    }
+
+    #[test]
+    fn test_decode_class_attributes() {
+        let inner = [0x00, 0x01, 0x00, 0x05, 0x00, 0x00, 0x00, 0x04, 0x00, 0x19];
+        let bytes = attributes(&[
+            attribute(4, &[0x00, 0x02]),
+            attribute(5, &inner),
+            attribute(7, &[0x01, 0x02, 0x03]),
+        ]);
+        let items = parse(&bytes).unwrap();
+        assert!(matches!(items[0].attribute, Attribute::SourceFile(2)));
+        assert!(matches!(
+            &items[1].attribute,
+            Attribute::InnerClasses(classes)
+                if classes[0].inner_class_info_index == 5
+                    && classes[0].inner_class_access_flags == 0x0019
+        ));
+        assert!(matches!(
+            &items[2].attribute,
+            Attribute::Unknown(name, body) if name == "Custom" && body == &[1, 2, 3]
+        ));
+        assert_eq!(items[2].name_index, 7);
+        assert_eq!(write(&items), bytes);
+
+        #[rustfmt::skip]
+        let frame = [
+            0x00, 0x01,                   // 1 frame
+            0xFF, 0x00, 0x00, 0x00, 0x00, // full, no locals
+            0x00, 0x01, 0x08, 0x00, 0x03, // stack [uninitialized(3)]
+        ];
+        let bytes = attributes(&[attribute(3, &frame), attribute(3, &[0x00, 0x00])]);
+        assert!(matches!(
+            &parse(&bytes).unwrap()[0].attribute,
+            Attribute::StackMapTable(frames)
+                if matches!(&frames[0], StackMapFrame::Full(0, locals, stack)
+                    if locals.is_empty() && stack == &[VerificationType::Uninitialized(3)])
+        ));
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_bodies() {
+        // Longer than the ConstantValue it holds.
+        let e = parse(&attributes(&[attribute(6, &[0x00, 0x01, 0x00])])).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "attribute length 3 does not match its content at offset 10 (in attribute ConstantValue)"
+        );
+
+        // Shorter than a SourceFile index.
+        let e = parse(&attributes(&[attribute(4, &[0x00])])).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Truncated);
+        assert_eq!(e.context, ["attribute SourceFile"]);
+
+        // Declared length runs past the end of the data.
+        let bytes = [0x00, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x02];
+        let e = parse(&bytes).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Truncated);
+        assert_eq!(e.context, ["attribute SourceFile"]);
+
+        let e = parse(&attributes(&[attribute(3, &[0x00, 0x01, 0xC8])])).unwrap_err();
+        assert_eq!(
+            e.kind,
+            ErrorKind::Malformed("reserved stack map frame type 200".to_string())
+        );
+        let e = parse(&attributes(&[attribute(3, &[0x00, 0x01, 0x40, 0x09])])).unwrap_err();
+        assert_eq!(
+            e.kind,
+            ErrorKind::Malformed("unknown verification type tag 9".to_string())
+        );
+        let e = parse(&attributes(&[attribute(9, &[])])).unwrap_err();
+        assert_eq!((e.offset, e.kind), (2, ErrorKind::InvalidPoolIndex(9)));
+    }
+}
@@ -0,0 +1,648 @@
+use crate::attribute::Code;
+use crate::error::{ErrorKind, ParseError};
+use crate::opcode::*;
+use crate::pool::{ConstantPool, ConstantPoolItem};
+
+const ARRAY_TYPES: [(&str, u8); 8] = [
+    ("boolean", 4),
+    ("char", 5),
+    ("float", 6),
+    ("double", 7),
+    ("byte", 8),
+    ("short", 9),
+    ("int", 10),
+    ("long", 11),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    None,
+    Byte(i8),                             // bipush
+    Short(i16),                           // sipush
+    Local(u16),                           // local variable index
+    Constant(u16),                        // constant pool index
+    Branch(i32),                          // absolute target offset
+    Iinc(u16, i16),                       // local variable index, increment
+    ArrayType(u8),                        // newarray element type
+    InvokeInterface(u16, u8),             // constant pool index, count
+    InvokeDynamic(u16),                   // constant pool index
+    MultiANewArray(u16, u8),              // constant pool index, dimensions
+    TableSwitch(i32, i32, i32, Vec<i32>), // default, low, high, absolute targets
+    LookupSwitch(i32, Vec<(i32, i32)>),   // default, (match, absolute target) pairs
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub offset: u32,
+    pub opcode: u8,
+    pub wide: bool,
+    pub operand: Operand,
+}
+
+struct CodeReader<'a> {
+    code: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CodeReader<'a> {
+    fn error(&self, offset: u32, kind: ErrorKind) -> ParseError {
+        let mut e = ParseError::new(offset as u64, kind);
+        e.context.push("code".to_string());
+        e
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ParseError> {
+        if self.pos + n > self.code.len() {
+            return Err(self.error(self.pos as u32, ErrorKind::Truncated));
+        }
+        let ret = &self.code[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(ret)
+    }
+
+    /// Checks that `count` entries of `size` bytes fit in the rest of the code before a switch
+    /// table is allocated, the counts come straight from the class file.
+    fn check_table(&self, offset: u32, count: i64, size: i64) -> Result<usize, ParseError> {
+        if count * size > (self.code.len() - self.pos) as i64 {
+            let kind = ErrorKind::Malformed(format!("switch with {} entries overruns code", count));
+            return Err(self.error(offset, kind));
+        }
+        Ok(count as usize)
+    }
+
+    fn u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i8(&mut self) -> Result<i8, ParseError> {
+        Ok(self.u8()? as i8)
+    }
+
+    fn u16(&mut self) -> Result<u16, ParseError> {
+        let buf = self.take(2)?;
+        Ok(u16::from_be_bytes([buf[0], buf[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, ParseError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn i32(&mut self) -> Result<i32, ParseError> {
+        let buf = self.take(4)?;
+        Ok(i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]))
+    }
+}
+
+fn decode_one(rdr: &mut CodeReader) -> Result<Instruction, ParseError> {
+    let offset = rdr.pos as u32;
+    let mut opcode = rdr.u8()?;
+    let wide = opcode == WIDE;
+    if wide {
+        opcode = rdr.u8()?;
+    }
+    let base = offset as i32;
+    let operand = match opcode {
+        BIPUSH => Operand::Byte(rdr.i8()?),
+        SIPUSH => Operand::Short(rdr.i16()?),
+        LDC => Operand::Constant(rdr.u8()? as u16),
+        LDC_W | LDC2_W => Operand::Constant(rdr.u16()?),
+        ILOAD | LLOAD | FLOAD | DLOAD | ALOAD | ISTORE | LSTORE | FSTORE | DSTORE | ASTORE
+        | RET => {
+            if wide {
+                Operand::Local(rdr.u16()?)
+            } else {
+                Operand::Local(rdr.u8()? as u16)
+            }
+        }
+        IINC => {
+            if wide {
+                Operand::Iinc(rdr.u16()?, rdr.i16()?)
+            } else {
+                Operand::Iinc(rdr.u8()? as u16, rdr.i8()? as i16)
+            }
+        }
+        IFEQ..=JSR | IFNULL | IFNONNULL => Operand::Branch(base + rdr.i16()? as i32),
+        GOTO_W | JSR_W => Operand::Branch(base + rdr.i32()?),
+        TABLESWITCH | LOOKUPSWITCH => {
+            // The operands start at the next offset that is a multiple of four.
+            let padding = (4 - rdr.pos % 4) % 4;
+            rdr.take(padding)?;
+            let default = base + rdr.i32()?;
+            if opcode == TABLESWITCH {
+                let low = rdr.i32()?;
+                let high = rdr.i32()?;
+                if high < low {
+                    let kind =
+                        ErrorKind::Malformed("tableswitch high is lower than low".to_string());
+                    return Err(rdr.error(offset, kind));
+                }
+                let count = rdr.check_table(offset, high as i64 - low as i64 + 1, 4)?;
+                let mut targets = Vec::with_capacity(count);
+                for _ in low..=high {
+                    targets.push(base + rdr.i32()?);
+                }
+                Operand::TableSwitch(default, low, high, targets)
+            } else {
+                let count = rdr.i32()?;
+                if count < 0 {
+                    let kind = ErrorKind::Malformed("lookupswitch has negative npairs".to_string());
+                    return Err(rdr.error(offset, kind));
+                }
+                let mut pairs = Vec::with_capacity(rdr.check_table(offset, count as i64, 8)?);
+                for _ in 0..count {
+                    let key = rdr.i32()?;
+                    pairs.push((key, base + rdr.i32()?));
+                }
+                Operand::LookupSwitch(default, pairs)
+            }
+        }
+        GETSTATIC..=INVOKESTATIC | NEW | ANEWARRAY | CHECKCAST | INSTANCEOF => {
+            Operand::Constant(rdr.u16()?)
+        }
+        INVOKEINTERFACE => {
+            let index = rdr.u16()?;
+            let count = rdr.u8()?;
+            rdr.u8()?;
+            Operand::InvokeInterface(index, count)
+        }
+        INVOKEDYNAMIC => {
+            let index = rdr.u16()?;
+            rdr.u16()?;
+            Operand::InvokeDynamic(index)
+        }
+        NEWARRAY => Operand::ArrayType(rdr.u8()?),
+        MULTIANEWARRAY => Operand::MultiANewArray(rdr.u16()?, rdr.u8()?),
+        _ if mnemonic(opcode).is_some() && opcode != WIDE => Operand::None,
+        _ => return Err(rdr.error(offset, ErrorKind::InvalidOpcode(opcode))),
+    };
+    if wide && !matches!(operand, Operand::Local(_) | Operand::Iinc(_, _)) {
+        let kind = ErrorKind::Malformed(format!(
+            "wide can not modify {}",
+            mnemonic(opcode).unwrap_or("?")
+        ));
+        return Err(rdr.error(offset, kind));
+    }
+    Ok(Instruction {
+        offset,
+        opcode,
+        wide,
+        operand,
+    })
+}
+
+/// Decodes the whole instruction stream of a Code attribute.
+pub fn decode(code: &[u8]) -> Result<Vec<Instruction>, ParseError> {
+    let mut rdr = CodeReader { code, pos: 0 };
+    let mut acc = Vec::<Instruction>::new();
+    while rdr.pos < code.len() {
+        acc.push(decode_one(&mut rdr)?);
+    }
+    Ok(acc)
+}
+
+fn encode_error(offset: u32, msg: String) -> ParseError {
+    let mut e = ParseError::new(offset as u64, ErrorKind::Malformed(msg));
+    e.context.push("code".to_string());
+    e
+}
+
+fn encode_one(acc: &mut Vec<u8>, instruction: &Instruction) -> Result<(), ParseError> {
+    let offset = instruction.offset;
+    let base = offset as i64;
+    let opcode = instruction.opcode;
+    let name = mnemonic(opcode).unwrap_or("?");
+    let out_of_range = |what: &str| encode_error(offset, format!("{} {} out of range", name, what));
+    let branch16 = |target: i32| {
+        i16::try_from(target as i64 - base).map_err(|_| out_of_range("branch target"))
+    };
+    let branch32 = |target: i32| {
+        i32::try_from(target as i64 - base).map_err(|_| out_of_range("branch target"))
+    };
+    if instruction.wide {
+        acc.push(WIDE);
+    }
+    acc.push(opcode);
+    match &instruction.operand {
+        Operand::None => {}
+        Operand::Byte(value) => acc.push(*value as u8),
+        Operand::Short(value) => acc.extend_from_slice(&value.to_be_bytes()),
+        Operand::Constant(index) if opcode == LDC => {
+            acc.push(u8::try_from(*index).map_err(|_| out_of_range("constant index"))?)
+        }
+        Operand::Constant(index) => acc.extend_from_slice(&index.to_be_bytes()),
+        Operand::Local(index) if instruction.wide => acc.extend_from_slice(&index.to_be_bytes()),
+        Operand::Local(index) => {
+            acc.push(u8::try_from(*index).map_err(|_| out_of_range("local index"))?)
+        }
+        Operand::Iinc(index, value) if instruction.wide => {
+            acc.extend_from_slice(&index.to_be_bytes());
+            acc.extend_from_slice(&value.to_be_bytes());
+        }
+        Operand::Iinc(index, value) => {
+            acc.push(u8::try_from(*index).map_err(|_| out_of_range("local index"))?);
+            acc.push(i8::try_from(*value).map_err(|_| out_of_range("increment"))? as u8);
+        }
+        Operand::Branch(target) if opcode == GOTO_W || opcode == JSR_W => {
+            acc.extend_from_slice(&branch32(*target)?.to_be_bytes())
+        }
+        Operand::Branch(target) => acc.extend_from_slice(&branch16(*target)?.to_be_bytes()),
+        Operand::ArrayType(atype) => acc.push(*atype),
+        Operand::InvokeInterface(index, count) => {
+            acc.extend_from_slice(&index.to_be_bytes());
+            acc.extend_from_slice(&[*count, 0]);
+        }
+        Operand::InvokeDynamic(index) => {
+            acc.extend_from_slice(&index.to_be_bytes());
+            acc.extend_from_slice(&[0, 0]);
+        }
+        Operand::MultiANewArray(index, dimensions) => {
+            acc.extend_from_slice(&index.to_be_bytes());
+            acc.push(*dimensions);
+        }
+        Operand::TableSwitch(default, low, high, targets) => {
+            acc.resize(acc.len() + (4 - acc.len() % 4) % 4, 0);
+            acc.extend_from_slice(&branch32(*default)?.to_be_bytes());
+            acc.extend_from_slice(&low.to_be_bytes());
+            acc.extend_from_slice(&high.to_be_bytes());
+            if *high as i64 - *low as i64 + 1 != targets.len() as i64 {
+                let msg = "tableswitch has the wrong number of targets".to_string();
+                return Err(encode_error(offset, msg));
+            }
+            for target in targets.iter() {
+                acc.extend_from_slice(&branch32(*target)?.to_be_bytes());
+            }
+        }
+        Operand::LookupSwitch(default, pairs) => {
+            acc.resize(acc.len() + (4 - acc.len() % 4) % 4, 0);
+            acc.extend_from_slice(&branch32(*default)?.to_be_bytes());
+            acc.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+            for (key, target) in pairs.iter() {
+                acc.extend_from_slice(&key.to_be_bytes());
+                acc.extend_from_slice(&branch32(*target)?.to_be_bytes());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Encodes an instruction stream, the inverse of `decode`. Every instruction must start at
+/// its `offset`, use `Instruction::size` to lay out the stream.
+pub fn encode(instructions: &[Instruction]) -> Result<Vec<u8>, ParseError> {
+    let mut acc = Vec::<u8>::new();
+    for instruction in instructions.iter() {
+        if acc.len() != instruction.offset as usize {
+            let msg = format!("instruction expected at offset {}", acc.len());
+            return Err(encode_error(instruction.offset, msg));
+        }
+        encode_one(&mut acc, instruction)?;
+    }
+    Ok(acc)
+}
+
+/// `newarray` element type code of a primitive type name, e.g. 10 for `int`.
+pub fn array_type_code(name: &str) -> Option<u8> {
+    ARRAY_TYPES
+        .iter()
+        .find(|(item, _)| *item == name)
+        .map(|(_, code)| *code)
+}
+
+fn member_name(name: String) -> String {
+    if name.starts_with('<') {
+        format!("\"{}\"", name)
+    } else {
+        name
+    }
+}
+
+fn describe_member(
+    pool: &ConstantPool,
+    class_index: u16,
+    nat_index: u16,
+    this_class: &str,
+) -> String {
+    let class = pool.resolve(class_index);
+    let (name, desc) = match pool.get(nat_index) {
+        Ok(ConstantPoolItem::NameAndType(name, desc)) => (pool.resolve(*name), pool.resolve(*desc)),
+        _ => return pool.resolve(nat_index),
+    };
+    if class == this_class {
+        format!("{}:{}", member_name(name), desc)
+    } else {
+        format!("{}.{}:{}", class, member_name(name), desc)
+    }
+}
+
+/// Describes a constant pool entry the way `javap -c` comments instruction operands.
+pub fn describe_constant(pool: &ConstantPool, index: u16, this_class: &str) -> String {
+    let item = match pool.get(index) {
+        Ok(item) => item,
+        Err(_) => return pool.resolve(index),
+    };
+    match item {
+        ConstantPoolItem::Field(class, nat) => {
+            format!("Field {}", describe_member(pool, *class, *nat, this_class))
+        }
+        ConstantPoolItem::Method(class, nat) => {
+            format!("Method {}", describe_member(pool, *class, *nat, this_class))
+        }
+        ConstantPoolItem::InterfaceMethod(class, nat) => format!(
+            "InterfaceMethod {}",
+            describe_member(pool, *class, *nat, this_class)
+        ),
+        ConstantPoolItem::Class(name) => format!("class {}", pool.resolve(*name)),
+        ConstantPoolItem::String(value) => format!("String {}", pool.resolve(*value)),
+        ConstantPoolItem::Integer(_) => format!("int {}", pool.resolve(index)),
+        ConstantPoolItem::Float(_) => format!("float {}", pool.resolve(index)),
+        ConstantPoolItem::Long(_) => format!("long {}", pool.resolve(index)),
+        ConstantPoolItem::Double(_) => format!("double {}", pool.resolve(index)),
+        ConstantPoolItem::MethodType(desc) => format!("MethodType {}", pool.resolve(*desc)),
+        ConstantPoolItem::MethodHandle(_, _) => format!("MethodHandle {}", pool.resolve(index)),
+        ConstantPoolItem::Dynamic(_, _) => format!("Dynamic {}", pool.resolve(index)),
+        ConstantPoolItem::InvokeDynamic(_, _) => format!("InvokeDynamic {}", pool.resolve(index)),
+        item => format!("{}", item),
+    }
+}
+
+pub(crate) fn array_type_name(atype: u8) -> String {
+    match ARRAY_TYPES.iter().find(|(_, code)| *code == atype) {
+        Some((name, _)) => name.to_string(),
+        None => format!("unknown({})", atype),
+    }
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        mnemonic(self.opcode).unwrap_or("unknown")
+    }
+
+    /// Encoded length in bytes, switch padding depends on `offset`.
+    pub fn size(&self) -> u32 {
+        let operands = match &self.operand {
+            Operand::None => 0,
+            Operand::Byte(_) | Operand::ArrayType(_) => 1,
+            Operand::Short(_) => 2,
+            Operand::Constant(_) if self.opcode == LDC => 1,
+            Operand::Constant(_) => 2,
+            Operand::Local(_) if self.wide => 2,
+            Operand::Local(_) => 1,
+            Operand::Iinc(_, _) if self.wide => 4,
+            Operand::Iinc(_, _) => 2,
+            Operand::Branch(_) if self.opcode == GOTO_W || self.opcode == JSR_W => 4,
+            Operand::Branch(_) => 2,
+            Operand::MultiANewArray(_, _) => 3,
+            Operand::InvokeInterface(_, _) | Operand::InvokeDynamic(_) => 4,
+            Operand::TableSwitch(_, _, _, targets) => {
+                self.switch_padding() + 12 + 4 * targets.len() as u32
+            }
+            Operand::LookupSwitch(_, pairs) => self.switch_padding() + 8 + 8 * pairs.len() as u32,
+        };
+        let prefix = if self.wide { 2 } else { 1 };
+        prefix + operands
+    }
+
+    fn switch_padding(&self) -> u32 {
+        (4 - (self.offset + 1) % 4) % 4
+    }
+
+    /// Formats the instruction as one or more `javap -c` style lines.
+    pub fn format(&self, pool: &ConstantPool, this_class: &str) -> Vec<String> {
+        let name = if self.wide {
+            format!("wide {}", self.mnemonic())
+        } else {
+            self.mnemonic().to_string()
+        };
+        let head = format!("{:>6}: {:<13}", self.offset, name);
+        let with_comment = |operand: String, index: u16| {
+            vec![format!(
+                "{} {:<18} // {}",
+                head,
+                operand,
+                describe_constant(pool, index, this_class)
+            )]
+        };
+        match &self.operand {
+            Operand::None => vec![head.trim_end().to_string()],
+            Operand::Byte(value) => vec![format!("{} {}", head, value)],
+            Operand::Short(value) => vec![format!("{} {}", head, value)],
+            Operand::Local(index) => vec![format!("{} {}", head, index)],
+            Operand::Branch(target) => vec![format!("{} {}", head, target)],
+            Operand::Iinc(index, value) => vec![format!("{} {}, {}", head, index, value)],
+            Operand::ArrayType(atype) => vec![format!("{} {}", head, array_type_name(*atype))],
+            Operand::Constant(index) => with_comment(format!("#{}", index), *index),
+            Operand::InvokeDynamic(index) => with_comment(format!("#{},  0", index), *index),
+            Operand::InvokeInterface(index, count) => {
+                with_comment(format!("#{},  {}", index, count), *index)
+            }
+            Operand::MultiANewArray(index, dims) => {
+                with_comment(format!("#{},  {}", index, dims), *index)
+            }
+            Operand::TableSwitch(default, low, high, targets) => {
+                let mut acc = vec![format!("{} {{ // {} to {}", head, low, high)];
+                for (key, target) in (*low..=*high).zip(targets.iter()) {
+                    acc.push(format!("{:>24}: {}", key, target));
+                }
+                acc.push(format!("{:>24}: {}", "default", default));
+                acc.push(format!("{:>9}", "}"));
+                acc
+            }
+            Operand::LookupSwitch(default, pairs) => {
+                let mut acc = vec![format!("{} {{ // {}", head, pairs.len())];
+                for (key, target) in pairs.iter() {
+                    acc.push(format!("{:>24}: {}", key, target));
+                }
+                acc.push(format!("{:>24}: {}", "default", default));
+                acc.push(format!("{:>9}", "}"));
+                acc
+            }
+        }
+    }
+}
+
+/// The disassembled instruction stream and exception table of a Code attribute, as printed by
+/// `print_code`.
+pub fn code_lines(code: &Code, pool: &ConstantPool, this_class: &str) -> Vec<String> {
+    let mut acc = vec![
+        "    Code:".to_string(),
+        format!(
+            "      stack={}, locals={}, code_length={}",
+            code.max_stack,
+            code.max_locals,
+            code.code.len()
+        ),
+    ];
+    match decode(&code.code) {
+        Ok(instructions) => {
+            for instruction in instructions.iter() {
+                for line in instruction.format(pool, this_class) {
+                    acc.push(format!("    {}", line));
+                }
+            }
+        }
+        Err(e) => acc.push(format!("      ERROR: {}", e)),
+    }
+    if !code.exception_table.is_empty() {
+        acc.push("      Exception table:".to_string());
+        acc.push("         from    to  target type".to_string());
+        for handler in code.exception_table.iter() {
+            let catch_type = if handler.catch_type == 0 {
+                "any".to_string()
+            } else {
+                format!("Class {}", pool.resolve(handler.catch_type))
+            };
+            acc.push(format!(
+                "         {:>5} {:>5} {:>5}   {}",
+                handler.start_pc, handler.end_pc, handler.handler_pc, catch_type
+            ));
+        }
+    }
+    acc
+}
+
+/// Prints the disassembled instruction stream and exception table of a Code attribute.
+pub fn print_code(code: &Code, pool: &ConstantPool, this_class: &str) {
+    for line in code_lines(code, pool, this_class) {
+        println!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, Operand};
+    use crate::opcode::*;
+
+    #[test]
+    fn test_decode_simple_instructions() {
+        let code = [
+            ALOAD_0, BIPUSH, 0xFF, SIPUSH, 0x01, 0x00, GOTO, 0xFF, 0xFA, RETURN,
+        ];
+        let instructions = decode(&code).unwrap();
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(instructions[0].operand, Operand::None);
+        assert_eq!(instructions[1].operand, Operand::Byte(-1));
+        assert_eq!(instructions[2].operand, Operand::Short(256));
+        assert_eq!(instructions[3].offset, 6);
+        assert_eq!(instructions[3].operand, Operand::Branch(0));
+        assert_eq!(instructions[4].mnemonic(), "return");
+    }
+
+    #[test]
+    fn test_decode_tableswitch_padding() {
+        #[rustfmt::skip]
+        let code = [
+            ICONST_0,
+            TABLESWITCH, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x1B, // default
+            0x00, 0x00, 0x00, 0x01, // low
+            0x00, 0x00, 0x00, 0x02, // high
+            0x00, 0x00, 0x00, 0x17,
+            0x00, 0x00, 0x00, 0x19,
+            RETURN, RETURN, RETURN, RETURN,
+        ];
+        let instructions = decode(&code).unwrap();
+        assert_eq!(
+            instructions[1].operand,
+            Operand::TableSwitch(28, 1, 2, vec![24, 26])
+        );
+        assert_eq!(instructions[2].offset, 24);
+    }
+
+    #[test]
+    fn test_decode_lookupswitch() {
+        #[rustfmt::skip]
+        let code = [
+            LOOKUPSWITCH, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x14, // default
+            0x00, 0x00, 0x00, 0x01, // npairs
+            0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x15,
+            RETURN, RETURN,
+        ];
+        let instructions = decode(&code).unwrap();
+        assert_eq!(
+            instructions[0].operand,
+            Operand::LookupSwitch(20, vec![(-1, 21)])
+        );
+    }
+
+    #[test]
+    fn test_decode_switch_overrunning_code() {
+        #[rustfmt::skip]
+        let code = [
+            TABLESWITCH, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x14, // default
+            0x80, 0x00, 0x00, 0x00, // low
+            0x7F, 0xFF, 0xFF, 0xFF, // high
+        ];
+        let e = decode(&code).unwrap_err();
+        assert_eq!(e.offset, 0);
+        assert!(e
+            .to_string()
+            .contains("switch with 4294967296 entries overruns code"));
+
+        #[rustfmt::skip]
+        let code = [
+            ICONST_0, LOOKUPSWITCH, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x14, // default
+            0x00, 0x00, 0x00, 0x02, // npairs
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x15,
+        ];
+        assert!(decode(&code).is_err());
+    }
+
+    #[test]
+    fn test_decode_wide_prefix() {
+        let code = [
+            WIDE, ILOAD, 0x01, 0x2C, WIDE, IINC, 0x01, 0x2C, 0xFF, 0xFE, RETURN,
+        ];
+        let instructions = decode(&code).unwrap();
+        assert!(instructions[0].wide);
+        assert_eq!(instructions[0].opcode, ILOAD);
+        assert_eq!(instructions[0].operand, Operand::Local(300));
+        assert_eq!(instructions[1].offset, 4);
+        assert_eq!(instructions[1].operand, Operand::Iinc(300, -2));
+        assert_eq!(instructions[2].offset, 10);
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_and_invalid_code() {
+        assert!(decode(&[SIPUSH, 0x01]).is_err());
+        assert!(decode(&[WIDE, IADD]).is_err());
+        assert!(decode(&[0xFE]).is_err());
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        #[rustfmt::skip]
+        let code = [
+            ICONST_0,
+            TABLESWITCH, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x1B,
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x02,
+            0x00, 0x00, 0x00, 0x17,
+            0x00, 0x00, 0x00, 0x19,
+            WIDE, IINC, 0x01, 0x2C, 0xFF, 0xFE,
+            LDC, 0x07, GOTO, 0xFF, 0xE6, RETURN,
+        ];
+        let instructions = decode(&code).unwrap();
+        for instruction in instructions.iter() {
+            let next = instruction.offset + instruction.size();
+            assert!(next as usize == code.len() || instructions.iter().any(|i| i.offset == next));
+        }
+        assert_eq!(encode(&instructions).unwrap(), code);
+    }
+
+    #[test]
+    fn test_encode_rejects_operands_out_of_range() {
+        let mut instructions = decode(&[GOTO, 0x00, 0x00]).unwrap();
+        instructions[0].operand = Operand::Branch(40000);
+        let e = encode(&instructions).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "goto branch target out of range at offset 0 (in code)"
+        );
+        let mut instructions = decode(&[ILOAD, 0x01]).unwrap();
+        instructions[0].operand = Operand::Local(256);
+        assert!(encode(&instructions).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+use std::io::{self, Read, Write};
+
+use crate::attribute::{parse_attributes, write_attributes, Attribute, AttributeInfo, Code};
+use crate::error::{Context, ErrorKind, ParseError};
+use crate::flag::AccessFlag;
+use crate::pool::ConstantPool;
+use crate::reader::ClassReader;
+use crate::writer::ClassWriter;
+
+pub const MAGIC: u32 = 0xCAFEBABE;
+/// Minor version of class files that use preview features of their release (Java 12 and later).
+pub const PREVIEW_MINOR: u16 = 0xFFFF;
+
+/// The Java release that introduced a class file major version, e.g. `Java 21` for 65 or
+/// `Java 1.4` for 48. `None` for versions before 45.
+pub fn release(major: u16) -> Option<String> {
+    match major {
+        0..=44 => None,
+        45..=48 => Some(format!("Java 1.{}", major - 44)),
+        major => Some(format!("Java {}", major - 44)),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Header {
+    pub magic: u32,
+    pub minor: u16,
+    pub major: u16,
+}
+
+impl Header {
+    pub(crate) fn parse<R: Read>(rdr: &mut ClassReader<R>) -> Result<Self, ParseError> {
+        let magic = rdr.read_u32()?;
+        if magic != MAGIC {
+            return Err(ParseError::new(0, ErrorKind::BadMagic(magic)));
+        }
+        let minor = rdr.read_u16()?;
+        let major = rdr.read_u16()?;
+        Ok(Header {
+            magic,
+            minor,
+            major,
+        })
+    }
+
+    fn write<W: Write>(&self, wtr: &mut ClassWriter<W>) -> io::Result<()> {
+        wtr.write_u32(self.magic)?;
+        wtr.write_u16(self.minor)?;
+        wtr.write_u16(self.major)
+    }
+
+    pub fn is_preview(&self) -> bool {
+        self.minor == PREVIEW_MINOR
+    }
+
+    /// `Java 21`, `Java 17 (preview)` or `unknown`.
+    pub fn release_name(&self) -> String {
+        match release(self.major) {
+            Some(release) if self.is_preview() => format!("{} (preview)", release),
+            Some(release) => release,
+            None => "unknown".to_string(),
+        }
+    }
+
+    pub fn print(&self) {
+        println!("INFO: Header");
+        println!(
+            "    Magic= 0x{:X}, Major= {}, Minor= {}, Release= {}",
+            self.magic,
+            self.major,
+            self.minor,
+            self.release_name()
+        );
+    }
+}
+
+fn read_utf8_index<R: Read>(
+    rdr: &mut ClassReader<R>,
+    pool: &ConstantPool,
+) -> Result<u16, ParseError> {
+    let offset = rdr.offset();
+    let index = rdr.read_u16()?;
+    match pool.get_utf8(index) {
+        Ok(_) => Ok(index),
+        Err(kind) => Err(ParseError::new(offset, kind)),
+    }
+}
+
+fn read_optional_class_index<R: Read>(
+    rdr: &mut ClassReader<R>,
+    pool: &ConstantPool,
+) -> Result<Option<u16>, ParseError> {
+    let offset = rdr.offset();
+    let index = rdr.read_u16()?;
+    if index == 0 {
+        return Ok(None);
+    }
+    match pool.get_class_name(index) {
+        Ok(_) => Ok(Some(index)),
+        Err(kind) => Err(ParseError::new(offset, kind)),
+    }
+}
+
+fn read_class_index<R: Read>(
+    rdr: &mut ClassReader<R>,
+    pool: &ConstantPool,
+) -> Result<u16, ParseError> {
+    let offset = rdr.offset();
+    match read_optional_class_index(rdr, pool)? {
+        Some(index) => Ok(index),
+        None => Err(ParseError::new(offset, ErrorKind::InvalidPoolIndex(0))),
+    }
+}
+
+fn find_signature(attributes: &[AttributeInfo], pool: &ConstantPool) -> Option<String> {
+    attributes.iter().find_map(|item| match &item.attribute {
+        Attribute::Signature(index) => Some(pool.resolve(*index)),
+        _ => None,
+    })
+}
+
+/// A `field_info` or `method_info` structure, the access flag level tells them apart.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub access_flags: AccessFlag,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<AttributeInfo>,
+}
+
+pub type Field = Member;
+pub type Method = Member;
+
+impl Member {
+    fn parse<R: Read>(
+        rdr: &mut ClassReader<R>,
+        pool: &ConstantPool,
+        access_flags: AccessFlag,
+    ) -> Result<Self, ParseError> {
+        let name_index = read_utf8_index(rdr, pool).context("name")?;
+        let descriptor_index = read_utf8_index(rdr, pool).context("descriptor")?;
+        let attributes = parse_attributes(rdr, pool)?;
+        Ok(Member {
+            access_flags,
+            name_index,
+            descriptor_index,
+            attributes,
+        })
+    }
+
+    fn write<W: Write>(&self, wtr: &mut ClassWriter<W>) -> io::Result<()> {
+        wtr.write_u16(self.access_flags.value())?;
+        wtr.write_u16(self.name_index)?;
+        wtr.write_u16(self.descriptor_index)?;
+        write_attributes(wtr, &self.attributes)
+    }
+
+    pub fn name(&self, pool: &ConstantPool) -> String {
+        pool.resolve(self.name_index)
+    }
+
+    pub fn descriptor(&self, pool: &ConstantPool) -> String {
+        pool.resolve(self.descriptor_index)
+    }
+
+    /// The generic signature, if the member has a Signature attribute.
+    pub fn signature(&self, pool: &ConstantPool) -> Option<String> {
+        find_signature(&self.attributes, pool)
+    }
+
+    pub fn code(&self) -> Option<&Code> {
+        self.attributes
+            .iter()
+            .find_map(|item| match &item.attribute {
+                Attribute::Code(code) => Some(code),
+                _ => None,
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassFile {
+    pub header: Header,
+    pub constant_pool: ConstantPool,
+    pub access_flags: AccessFlag,
+    pub this_class: u16,
+    pub super_class: u16, // zero for java/lang/Object
+    pub interfaces: Vec<u16>,
+    pub fields: Vec<Field>,
+    pub methods: Vec<Method>,
+    pub attributes: Vec<AttributeInfo>,
+}
+
+impl ClassFile {
+    pub fn parse<R: Read>(rdr: R) -> Result<Self, ParseError> {
+        let rdr = &mut ClassReader::new(rdr);
+        let header = Header::parse(rdr).context("header")?;
+        let constant_pool = ConstantPool::parse(rdr)?;
+        let pool = &constant_pool;
+        let access_flags = AccessFlag::parse_class_level(rdr).context("access flags")?;
+        let this_class = read_class_index(rdr, pool).context("this class")?;
+        let super_class = read_optional_class_index(rdr, pool)
+            .context("super class")?
+            .unwrap_or(0);
+        let interfaces_count = rdr.read_u16().context("interfaces")?;
+        let interfaces = (0..interfaces_count)
+            .map(|i| read_class_index(rdr, pool).with_context(|| format!("interface #{}", i)))
+            .collect::<Result<_, _>>()?;
+        let fields_count = rdr.read_u16().context("fields")?;
+        let fields = (0..fields_count)
+            .map(|i| {
+                AccessFlag::parse_field_level(rdr)
+                    .and_then(|flags| Member::parse(rdr, pool, flags))
+                    .with_context(|| format!("field #{}", i))
+            })
+            .collect::<Result<_, _>>()?;
+        let methods_count = rdr.read_u16().context("methods")?;
+        let methods = (0..methods_count)
+            .map(|i| {
+                AccessFlag::parse_method_level(rdr)
+                    .and_then(|flags| Member::parse(rdr, pool, flags))
+                    .with_context(|| format!("method #{}", i))
+            })
+            .collect::<Result<_, _>>()?;
+        let attributes = parse_attributes(rdr, pool).context("class attributes")?;
+        Ok(ClassFile {
+            header,
+            constant_pool,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+
+    /// Serialises the class file. Writing an unmodified parse result reproduces the input
+    /// byte for byte, attribute lengths and table counts are recomputed from the model.
+    pub fn write<W: Write>(&self, wtr: W) -> io::Result<()> {
+        let wtr = &mut ClassWriter::new(wtr);
+        self.header.write(wtr)?;
+        self.constant_pool.write(wtr)?;
+        wtr.write_u16(self.access_flags.value())?;
+        wtr.write_u16(self.this_class)?;
+        wtr.write_u16(self.super_class)?;
+        wtr.write_u16_table("interfaces count", &self.interfaces)?;
+        wtr.write_count("fields count", self.fields.len())?;
+        self.fields.iter().try_for_each(|field| field.write(wtr))?;
+        wtr.write_count("methods count", self.methods.len())?;
+        self.methods
+            .iter()
+            .try_for_each(|method| method.write(wtr))?;
+        write_attributes(wtr, &self.attributes)
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut acc = Vec::<u8>::new();
+        self.write(&mut acc)?;
+        Ok(acc)
+    }
+
+    pub fn this_class_name(&self) -> String {
+        self.constant_pool.resolve(self.this_class)
+    }
+
+    pub fn super_class_name(&self) -> Option<String> {
+        if self.super_class == 0 {
+            None
+        } else {
+            Some(self.constant_pool.resolve(self.super_class))
+        }
+    }
+
+    pub fn interface_names(&self) -> Vec<String> {
+        self.interfaces
+            .iter()
+            .map(|index| self.constant_pool.resolve(*index))
+            .collect()
+    }
+
+    /// The generic signature, if the class has a Signature attribute.
+    pub fn signature(&self) -> Option<String> {
+        find_signature(&self.attributes, &self.constant_pool)
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<&Method> {
+        self.methods
+            .iter()
+            .find(|method| method.name(&self.constant_pool) == name)
+    }
+
+    pub fn find_field(&self, name: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| field.name(&self.constant_pool) == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::ClassFile;
+    use crate::error::ErrorKind;
+    use crate::flag::AccessFlag;
+
+    fn utf8(value: &str) -> Vec<u8> {
+        let mut acc = vec![1];
+        acc.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        acc.extend_from_slice(value.as_bytes());
+        acc
+    }
+
+    fn class_bytes(pool_count: u16, pool: &[u8], this_class: u16, super_class: u16) -> Vec<u8> {
+        let mut acc = vec![0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x34];
+        acc.extend_from_slice(&pool_count.to_be_bytes());
+        acc.extend_from_slice(pool);
+        acc.extend_from_slice(&[0x00, 0x21]);
+        acc.extend_from_slice(&this_class.to_be_bytes());
+        acc.extend_from_slice(&super_class.to_be_bytes());
+        acc.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        acc
+    }
+
+    fn minimal_pool() -> Vec<u8> {
+        let mut pool = utf8("A");
+        pool.extend_from_slice(&[7, 0x00, 0x01]);
+        pool.extend(utf8("java/lang/Object"));
+        pool.extend_from_slice(&[7, 0x00, 0x03]);
+        pool
+    }
+
+    #[test]
+    fn test_parse_minimal_class() {
+        let bytes = class_bytes(5, &minimal_pool(), 2, 4);
+        let class = ClassFile::parse(bytes.as_slice()).unwrap();
+        assert_eq!(class.header.major, 52);
+        assert_eq!(class.this_class_name(), "A");
+        assert_eq!(
+            class.super_class_name(),
+            Some("java/lang/Object".to_string())
+        );
+        assert!(class.methods.is_empty());
+    }
+
+    #[test]
+    fn test_parse_bad_magic() {
+        let mut bytes = class_bytes(5, &minimal_pool(), 2, 4);
+        bytes[0] = 0xCB;
+        let e = ClassFile::parse(bytes.as_slice()).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::BadMagic(0xCBFEBABE));
+        assert_eq!(e.offset, 0);
+    }
+
+    #[test]
+    fn test_parse_truncated_file() {
+        let bytes = class_bytes(5, &minimal_pool(), 2, 4);
+        let e = ClassFile::parse(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Truncated);
+        assert_eq!(e.offset, bytes.len() as u64 - 2);
+        assert_eq!(e.context, vec!["class attributes", "attributes count"]);
+    }
+
+    #[test]
+    fn test_parse_invalid_pool_index() {
+        let bytes = class_bytes(5, &minimal_pool(), 9, 4);
+        let e = ClassFile::parse(bytes.as_slice()).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidPoolIndex(9));
+        assert_eq!(e.context, vec!["this class"]);
+
+        let bytes = class_bytes(5, &minimal_pool(), 0, 4);
+        let e = ClassFile::parse(bytes.as_slice()).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidPoolIndex(0));
+    }
+
+    #[test]
+    fn test_parse_wrong_pool_entry_kind() {
+        let bytes = class_bytes(5, &minimal_pool(), 1, 4);
+        let e = ClassFile::parse(bytes.as_slice()).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::WrongPoolEntry(1, "Class", "UTF8"));
+
+        let mut pool = minimal_pool();
+        pool[6] = 0x02; // Class #2 refers to itself instead of an UTF8 entry
+        let e = ClassFile::parse(class_bytes(5, &pool, 2, 4).as_slice()).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::WrongPoolEntry(2, "UTF8", "Class"));
+        assert_eq!(e.context, vec!["constant pool entry #2"]);
+    }
+
+    #[test]
+    fn test_parse_unknown_pool_tag() {
+        let mut pool = minimal_pool();
+        pool[4] = 42;
+        let e = ClassFile::parse(class_bytes(5, &pool, 2, 4).as_slice()).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::UnknownPoolTag(42));
+        assert_eq!(e.offset, 14);
+    }
+
+    #[test]
+    fn test_write_minimal_class_round_trip() {
+        let bytes = class_bytes(5, &minimal_pool(), 2, 4);
+        let class = ClassFile::parse(bytes.as_slice()).unwrap();
+        assert_eq!(class.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_write_samples_round_trip() {
+        // javac output with lambdas, switches, stack maps, records, nested classes, Long and
+        // Double constants and non-ASCII strings.
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples");
+        let mut count = 0;
+        for entry in fs::read_dir(samples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "class") {
+                continue;
+            }
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::parse(bytes.as_slice()).unwrap();
+            assert_eq!(class.to_bytes().unwrap(), bytes, "{}", path.display());
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_write_modified_class() {
+        let bytes = class_bytes(5, &minimal_pool(), 2, 4);
+        let mut class = ClassFile::parse(bytes.as_slice()).unwrap();
+        class.access_flags = AccessFlag::Class(0x0031);
+        class.header.major = 61;
+        let written = class.to_bytes().unwrap();
+        assert_eq!(written.len(), bytes.len());
+        let reparsed = ClassFile::parse(written.as_slice()).unwrap();
+        assert_eq!(
+            reparsed.access_flags.names(),
+            vec!["public", "final", "super"]
+        );
+        assert_eq!(reparsed.header.major, 61);
+        assert_eq!(reparsed.this_class_name(), "A");
+    }
+}
@@ -0,0 +1,269 @@
+// Walking class path elements: single class files, JAR/ZIP archives and directory trees
+// containing either. Every class found is parsed and handed to a visitor together with where
+// it came from, failures are reported the same way so one bad class does not stop a walk.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::archive::{Archive, ZIP_MAGIC};
+use crate::classfile::ClassFile;
+use crate::error::{ErrorKind, ParseError};
+use crate::signature;
+
+/// A class found on the class path.
+pub struct ClassSource {
+    pub location: String, // file path, or `app.jar!com/foo/Bar.class` for archive entries
+    pub size: u64,        // size of the class file in bytes (uncompressed)
+}
+
+fn io_error(e: std::io::Error) -> ParseError {
+    ParseError::new(0, ErrorKind::Io(e.to_string()))
+}
+
+fn is_archive_name(name: &str) -> bool {
+    name.ends_with(".jar") || name.ends_with(".zip")
+}
+
+fn is_archive_file(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    match File::open(path) {
+        Ok(mut file) => file.read_exact(&mut magic).is_ok() && magic == ZIP_MAGIC,
+        Err(_) => false,
+    }
+}
+
+fn visit_archive<F>(path: &Path, visit: &mut F)
+where
+    F: FnMut(ClassSource, Result<ClassFile, ParseError>),
+{
+    let location = path.display().to_string();
+    let archive = File::open(path)
+        .map_err(io_error)
+        .and_then(|file| Archive::open(BufReader::new(file)));
+    let mut archive = match archive {
+        Ok(archive) => archive,
+        Err(e) => {
+            let source = ClassSource { location, size: 0 };
+            return visit(source, Err(e));
+        }
+    };
+    let sizes: Vec<u64> = archive
+        .entries()
+        .iter()
+        .filter(|entry| entry.is_class())
+        .map(|entry| entry.size as u64)
+        .collect();
+    for ((name, class), size) in archive.classes().zip(sizes) {
+        let source = ClassSource {
+            location: format!("{}!{}", location, name),
+            size,
+        };
+        visit(source, class);
+    }
+}
+
+fn visit_class<F>(path: &Path, visit: &mut F)
+where
+    F: FnMut(ClassSource, Result<ClassFile, ParseError>),
+{
+    let location = path.display().to_string();
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let class = File::open(path)
+        .map_err(io_error)
+        .and_then(|file| ClassFile::parse(BufReader::new(file)));
+    visit(ClassSource { location, size }, class);
+}
+
+/// `visited` holds the canonical paths of the directories walked so far, so symlinks back up
+/// the tree are only entered once.
+fn visit_dir<F>(path: &Path, visited: &mut HashSet<PathBuf>, visit: &mut F)
+where
+    F: FnMut(ClassSource, Result<ClassFile, ParseError>),
+{
+    match fs::canonicalize(path) {
+        Ok(canonical) => {
+            if !visited.insert(canonical) {
+                return;
+            }
+        }
+        Err(e) => {
+            let location = path.display().to_string();
+            return visit(ClassSource { location, size: 0 }, Err(io_error(e)));
+        }
+    }
+    let entries = fs::read_dir(path).and_then(|entries| entries.collect::<Result<Vec<_>, _>>());
+    let mut entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            let location = path.display().to_string();
+            return visit(ClassSource { location, size: 0 }, Err(io_error(e)));
+        }
+    };
+    // Sorted, so walking the same tree always produces the same order.
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            visit_dir(&path, visited, visit);
+        } else if name.ends_with(".class") {
+            visit_class(&path, visit);
+        } else if is_archive_name(&name) {
+            visit_archive(&path, visit);
+        }
+    }
+}
+
+/// Visits every class reachable from `path`: a class file, an archive or a directory tree.
+pub fn walk<F>(path: &Path, visit: &mut F)
+where
+    F: FnMut(ClassSource, Result<ClassFile, ParseError>),
+{
+    if path.is_dir() {
+        visit_dir(path, &mut HashSet::new(), visit);
+    } else if is_archive_file(path) {
+        visit_archive(path, visit);
+    } else {
+        visit_class(path, visit);
+    }
+}
+
+/// One row of a class path summary.
+#[derive(Debug, Serialize)]
+pub struct ClassSummary {
+    pub location: String,
+    pub class: String,
+    pub major: u16,
+    pub minor: u16,
+    pub access_flags: Vec<&'static str>,
+    pub fields: usize,
+    pub methods: usize,
+    pub size: u64,
+}
+
+impl ClassSummary {
+    pub fn new(source: ClassSource, class: &ClassFile) -> Self {
+        ClassSummary {
+            location: source.location,
+            class: signature::java_name(&class.this_class_name()),
+            major: class.header.major,
+            minor: class.header.minor,
+            access_flags: class.access_flags.names(),
+            fields: class.fields.len(),
+            methods: class.methods.len(),
+            size: source.size,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SummaryTotals {
+    pub classes: usize,
+    pub failures: usize,
+    pub fields: usize,
+    pub methods: usize,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummaryFailure {
+    pub location: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub classes: Vec<ClassSummary>,
+    pub failures: Vec<SummaryFailure>,
+    pub totals: SummaryTotals,
+}
+
+impl Summary {
+    /// Walks all paths and summarises every class found.
+    pub fn collect<P: AsRef<Path>>(paths: &[P]) -> Self {
+        let mut summary = Summary::default();
+        for path in paths.iter() {
+            walk(path.as_ref(), &mut |source, class| match class {
+                Ok(class) => summary.add(ClassSummary::new(source, &class)),
+                Err(e) => summary.failures.push(SummaryFailure {
+                    location: source.location,
+                    error: e.to_string(),
+                }),
+            });
+        }
+        summary.totals.failures = summary.failures.len();
+        summary
+    }
+
+    fn add(&mut self, row: ClassSummary) {
+        self.totals.classes += 1;
+        self.totals.fields += row.fields;
+        self.totals.methods += row.methods;
+        self.totals.size += row.size;
+        self.classes.push(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::Summary;
+    use crate::testdata::{zip, MINIMAL_CLASS};
+
+    #[test]
+    fn test_summary_walks_directories_and_archives() {
+        let root = std::env::temp_dir().join(format!("jinspect-classpath-{}", std::process::id()));
+        fs::create_dir_all(root.join("classes/com/foo")).unwrap();
+        fs::write(root.join("classes/com/foo/A.class"), MINIMAL_CLASS).unwrap();
+        fs::write(
+            root.join("classes/com/foo/Broken.class"),
+            &MINIMAL_CLASS[..20],
+        )
+        .unwrap();
+        fs::write(root.join("classes/notes.txt"), b"not a class").unwrap();
+        let jar = zip(&[
+            ("A.class", &MINIMAL_CLASS, true),
+            ("B.class", &MINIMAL_CLASS, false),
+        ]);
+        fs::write(root.join("lib.jar"), jar).unwrap();
+
+        let summary = Summary::collect(&[root.join("classes"), root.join("lib.jar")]);
+        fs::remove_dir_all(&root).unwrap();
+
+        let locations: Vec<&str> = summary
+            .classes
+            .iter()
+            .map(|row| row.location.rsplit(['/', '!']).next().unwrap())
+            .collect();
+        assert_eq!(locations, vec!["A.class", "A.class", "B.class"]);
+        assert!(summary.classes[2].location.ends_with("lib.jar!B.class"));
+        assert_eq!(summary.classes[0].class, "A");
+        assert_eq!(summary.classes[0].size, MINIMAL_CLASS.len() as u64);
+        assert_eq!(summary.totals.classes, 3);
+        assert_eq!(summary.totals.size, 3 * MINIMAL_CLASS.len() as u64);
+        assert_eq!(summary.totals.failures, 1);
+        assert!(summary.failures[0].location.ends_with("Broken.class"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_summary_survives_symlink_loops() {
+        let root = std::env::temp_dir().join(format!("jinspect-symlinks-{}", std::process::id()));
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/A.class"), MINIMAL_CLASS).unwrap();
+        std::os::unix::fs::symlink(root.join("a"), root.join("a/b/up")).unwrap();
+        std::os::unix::fs::symlink(root.join("a/b"), root.join("b")).unwrap();
+
+        let summary = Summary::collect(std::slice::from_ref(&root));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(summary.totals.classes, 1);
+        assert_eq!(summary.totals.failures, 0);
+        assert!(summary.classes[0].location.ends_with("a/b/A.class"));
+    }
+}
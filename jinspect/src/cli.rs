@@ -1,3 +1,5 @@
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
 use clap::{Arg, ArgAction, Command};
 
 const VERBOSE_ALL: u8 = 0;
@@ -11,31 +13,93 @@ const VERBOSE_ATTRIBUTES: u8 = 64;
 
 pub struct VerboseMode(u8);
 
+pub struct Options {
+    pub file_path: String,
+    pub verbose: VerboseMode,
+    pub view: View,
+    pub format: Format,
+    pub list: bool,
+}
+
+pub enum Action {
+    Inspect(Options),
+    Summary(Vec<String>, Format),     // class path elements
+    Assemble(String, Option<String>), // source, output class file
+    Verify(Vec<String>, Format),      // class path elements
+    Run(String, Vec<String>),         // class file, arguments of main
+    Diff(String, String, Format),     // old and new class file
+    Compat(String, String, Format),   // old and new library
+    Dependencies(DependencyOptions),
+    Explore(String), // class file
+}
+
+pub struct DependencyOptions {
+    pub paths: Vec<String>,
+    pub packages: bool,
+    pub external: bool,
+    pub dot: bool,
+    pub format: Format,
+}
+
+pub enum View {
+    Info,
+    Disassemble,
+    Signature,
+    Source,
+}
+
+impl View {
+    fn build(val: &str) -> Self {
+        match val {
+            "disassemble" => Self::Disassemble,
+            "signature" => Self::Signature,
+            "source" => Self::Source,
+            _ => Self::Info,
+        }
+    }
+}
+
+pub enum Format {
+    Text,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    fn build(val: &str) -> Self {
+        match val {
+            "json" => Self::Json,
+            "yaml" => Self::Yaml,
+            _ => Self::Text,
+        }
+    }
+}
+
 impl VerboseMode {
     fn build(vals: &Vec<&String>) -> Self {
         let mut ret: u8 = VERBOSE_ALL;
 
         if vals.contains(&&"header".to_string()) {
-            ret = ret | VERBOSE_HEADER;
+            ret |= VERBOSE_HEADER;
         }
 
         if vals.contains(&&"clazz".to_string()) {
-            ret = ret | VERBOSE_CLASS;
+            ret |= VERBOSE_CLASS;
         }
         if vals.contains(&&"pool".to_string()) {
-            ret = ret | VERBOSE_POOL;
+            ret |= VERBOSE_POOL;
         }
         if vals.contains(&&"interface".to_string()) {
-            ret = ret | VERBOSE_INTERFACES;
+            ret |= VERBOSE_INTERFACES;
         }
         if vals.contains(&&"method".to_string()) {
-            ret = ret | VERBOSE_METHODS;
+            ret |= VERBOSE_METHODS;
         }
         if vals.contains(&&"field".to_string()) {
-            ret = ret | VERBOSE_FIELDS;
+            ret |= VERBOSE_FIELDS;
         }
         if vals.contains(&&"attribute".to_string()) {
-            ret = ret | VERBOSE_ATTRIBUTES;
+            ret |= VERBOSE_ATTRIBUTES;
         }
         Self(ret)
     }
@@ -72,16 +136,161 @@ impl VerboseMode {
     }
 }
 
-pub fn parse_cli_args() -> (String, VerboseMode) {
-    let matches = Command::new("jinspect")
+pub fn parse_cli_args() -> Action {
+    let mut command = Command::new("jinspect")
         .version("0.1.0")
         .about("inspects java class files")
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            Command::new("summary")
+                .about("Summarises every class in directory trees, jars and class files")
+                .arg(
+                    Arg::new("paths")
+                        .required(true)
+                        .num_args(1..)
+                        .help("Directories, JAR/ZIP archives or class files"),
+                ),
+        )
+        .subcommand(
+            Command::new("asm")
+                .about("Assembles a class file from Jasmin-like source")
+                .long_about(
+                    "Assembles a class file from Jasmin-like source. StackMapTable frames are not \
+                     generated, so branches and exception handlers need .version 50 or lower, \
+                     and invokedynamic is not supported.",
+                )
+                .arg(
+                    Arg::new("source")
+                        .required(true)
+                        .help("Assembler source file"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Class file to write, defaults to the source path with .class"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Type-checks the bytecode of every method against its StackMapTable")
+                .arg(
+                    Arg::new("paths")
+                        .required(true)
+                        .num_args(1..)
+                        .help("Directories, JAR/ZIP archives or class files"),
+                ),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Runs static void main of a class with the bytecode interpreter")
+                .arg(
+                    Arg::new("class")
+                        .required(true)
+                        .help("Class file or archive entry (app.jar!com/foo/Bar.class)"),
+                )
+                .arg(
+                    Arg::new("args")
+                        .num_args(0..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .help("Arguments passed to main"),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Compares two versions of a class: members, constants and bytecode")
+                .arg(
+                    Arg::new("old")
+                        .required(true)
+                        .help("Old class file or archive entry"),
+                )
+                .arg(
+                    Arg::new("new")
+                        .required(true)
+                        .help("New class file or archive entry"),
+                ),
+        )
+        .subcommand(
+            Command::new("compat")
+                .about("Reports API changes that break clients of an older library version")
+                .arg(
+                    Arg::new("old")
+                        .required(true)
+                        .help("Old version: JAR/ZIP archive, directory or class file"),
+                )
+                .arg(
+                    Arg::new("new")
+                        .required(true)
+                        .help("New version: JAR/ZIP archive, directory or class file"),
+                ),
+        )
+        .subcommand(
+            Command::new("deps")
+                .about("Builds the class and package dependency graph and reports package cycles")
+                .arg(
+                    Arg::new("paths")
+                        .required(true)
+                        .num_args(1..)
+                        .help("Directories, JAR/ZIP archives or class files"),
+                )
+                .arg(
+                    Arg::new("packages")
+                        .long("packages")
+                        .action(ArgAction::SetTrue)
+                        .help("Print package instead of class dependencies"),
+                )
+                .arg(
+                    Arg::new("external")
+                        .long("external")
+                        .action(ArgAction::SetTrue)
+                        .help("Keep dependencies on classes outside the given paths"),
+                )
+                .arg(
+                    Arg::new("dot")
+                        .long("dot")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("format")
+                        .help("Print the graph in Graphviz DOT format"),
+                ),
+        )
+        .subcommand(
+            Command::new("explore")
+                .about("Explores a class interactively: pool, members, code and cross references")
+                .arg(
+                    Arg::new("class")
+                        .required(true)
+                        .help("Class file or archive entry (app.jar!com/foo/Bar.class)"),
+                ),
+        )
         .arg(
             Arg::new("file")
                 .short('f')
                 .long("file")
                 .default_missing_value("samples/App.class")
-                .default_value("samples/App.class"),
+                .default_value("samples/App.class")
+                .help("Class file, JAR/ZIP archive or archive entry (app.jar!com/foo/Bar.class)"),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .action(ArgAction::SetTrue)
+                .help("List the classes of a JAR/ZIP archive"),
+        )
+        .arg(
+            Arg::new("view")
+                .long("view")
+                .value_parser(["info", "disassemble", "signature", "source"])
+                .default_value("info")
+                .help("Choose how the class file is presented"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(["text", "json", "yaml"])
+                .default_value("text")
+                .global(true)
+                .help("Output format, json and yaml serialise the class file or the subcommand's report"),
         )
         .arg(
             Arg::new("verbose")
@@ -103,13 +312,89 @@ pub fn parse_cli_args() -> (String, VerboseMode) {
                 .default_missing_value("all")
                 .value_delimiter(',')
                 .help("Print all information of class file"),
-        )
-        .get_matches();
+        );
+    let matches = command.get_matches_mut();
+    let format = matches.get_one::<String>("format").expect("defaulted");
+    // `--format` is global so it can follow the subcommand, these subcommands have no structured
+    // output and must not silently print text.
+    if let Some((name @ ("asm" | "run" | "explore"), sub)) = matches.subcommand() {
+        if sub.value_source("format") == Some(ValueSource::CommandLine) {
+            let message = format!("--format can not be used with {}", name);
+            let sub_command = command.find_subcommand_mut(name).expect("matched");
+            sub_command
+                .error(ErrorKind::ArgumentConflict, message)
+                .exit();
+        }
+    }
+    if let Some(("summary", sub)) = matches.subcommand() {
+        let paths = sub
+            .get_many::<String>("paths")
+            .expect("required")
+            .cloned()
+            .collect();
+        return Action::Summary(paths, Format::build(format));
+    }
+    if let Some(("asm", sub)) = matches.subcommand() {
+        let source = sub.get_one::<String>("source").expect("required");
+        let output = sub.get_one::<String>("output").cloned();
+        return Action::Assemble(source.to_string(), output);
+    }
+    if let Some(("verify", sub)) = matches.subcommand() {
+        let paths = sub
+            .get_many::<String>("paths")
+            .expect("required")
+            .cloned()
+            .collect();
+        return Action::Verify(paths, Format::build(format));
+    }
+    if let Some(("run", sub)) = matches.subcommand() {
+        let class = sub.get_one::<String>("class").expect("required");
+        let args = sub
+            .get_many::<String>("args")
+            .map(|args| args.cloned().collect())
+            .unwrap_or_default();
+        return Action::Run(class.to_string(), args);
+    }
+    if let Some(("diff", sub)) = matches.subcommand() {
+        let old = sub.get_one::<String>("old").expect("required");
+        let new = sub.get_one::<String>("new").expect("required");
+        return Action::Diff(old.to_string(), new.to_string(), Format::build(format));
+    }
+    if let Some(("compat", sub)) = matches.subcommand() {
+        let old = sub.get_one::<String>("old").expect("required");
+        let new = sub.get_one::<String>("new").expect("required");
+        return Action::Compat(old.to_string(), new.to_string(), Format::build(format));
+    }
+    if let Some(("deps", sub)) = matches.subcommand() {
+        let paths = sub
+            .get_many::<String>("paths")
+            .expect("required")
+            .cloned()
+            .collect();
+        return Action::Dependencies(DependencyOptions {
+            paths,
+            packages: sub.get_flag("packages"),
+            external: sub.get_flag("external"),
+            dot: sub.get_flag("dot"),
+            format: Format::build(format),
+        });
+    }
+    if let Some(("explore", sub)) = matches.subcommand() {
+        let class = sub.get_one::<String>("class").expect("required");
+        return Action::Explore(class.to_string());
+    }
     let file_path = matches.get_one::<String>("file").expect("required");
+    let view = matches.get_one::<String>("view").expect("defaulted");
     let verbose_mode = matches
         .get_many::<String>("verbose")
         .unwrap()
         .collect::<Vec<_>>();
 
-    (file_path.to_string(), VerboseMode::build(&verbose_mode))
+    Action::Inspect(Options {
+        file_path: file_path.to_string(),
+        verbose: VerboseMode::build(&verbose_mode),
+        view: View::build(view),
+        format: Format::build(format),
+        list: matches.get_flag("list"),
+    })
 }
@@ -0,0 +1,864 @@
+// Compatibility check between two versions of a library. Only the API is compared: public and
+// protected members of public classes. Supertypes are resolved within each library, so a method
+// moved up into a superclass of the same library is not reported as removed.
+//
+// Severities follow the JLS chapter 13 notion of binary compatibility: `Error` changes make
+// existing client class files fail to link or run, `Warning` changes break recompiling clients or
+// change behaviour (e.g. inlined constants), `Info` changes are compatible additions.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::attribute::Attribute;
+use crate::bytecode::describe_constant;
+use crate::classfile::{ClassFile, Field, Method};
+use crate::flag::{AccessFlag, ACC_INTERFACE};
+use crate::signature;
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_PRIVATE: u16 = 0x0002;
+const ACC_PROTECTED: u16 = 0x0004;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_BRIDGE: u16 = 0x0040;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_SYNTHETIC: u16 = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,    // compatible
+    Warning, // source incompatible or changes behaviour
+    Error,   // binary incompatible
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Info => "INFO",
+            Self::Warning => "WARNING",
+            Self::Error => "ERROR",
+        };
+        f.pad(name)
+    }
+}
+
+/// One API change, `kind` is a stable identifier such as `method-removed`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Problem {
+    pub severity: Severity,
+    pub kind: &'static str,
+    pub class: String,
+    pub member: Option<String>, // field name, or method name and descriptor
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<7} {}", self.severity, self.class)?;
+        if let Some(member) = &self.member {
+            write!(f, ".{}", member)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CompatTotals {
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CompatReport {
+    pub problems: Vec<Problem>,
+    pub totals: CompatTotals,
+}
+
+impl CompatReport {
+    /// Whether existing clients keep linking, i.e. no `Error` was found.
+    pub fn is_binary_compatible(&self) -> bool {
+        self.totals.errors == 0
+    }
+}
+
+/// Classes of one library version by internal name.
+#[derive(Debug, Default)]
+pub struct Library {
+    classes: HashMap<String, ClassFile>,
+    unreadable: Vec<(String, String)>, // location and parse error of class files that failed
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, class: ClassFile) {
+        self.classes.insert(class.this_class_name(), class);
+    }
+
+    /// Records a class file that could not be parsed, it is reported as `class-unreadable`.
+    pub fn add_unreadable(&mut self, location: &str, error: String) {
+        self.unreadable.push((location.to_string(), error));
+    }
+
+    /// Whether a class file that failed to parse is named after the class.
+    fn is_unreadable(&self, name: &str) -> bool {
+        self.unreadable
+            .iter()
+            .any(|(location, _)| is_location_of(location, name))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ClassFile> {
+        self.classes.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// All superclasses and interfaces, types outside the library are included but not expanded.
+    fn supertypes(&self, class: &ClassFile) -> BTreeSet<String> {
+        let mut acc = BTreeSet::<String>::new();
+        let mut pending: Vec<String> = direct_supertypes(class);
+        while let Some(name) = pending.pop() {
+            if let Some(class) = self.classes.get(&name) {
+                pending.extend(
+                    direct_supertypes(class)
+                        .into_iter()
+                        .filter(|n| !acc.contains(n)),
+                );
+            }
+            acc.insert(name);
+        }
+        acc
+    }
+
+    /// Finds a method of the class, or an inherited accessible one of its supertypes within the
+    /// library.
+    fn find_method<'a>(
+        &'a self,
+        class: &'a ClassFile,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<(&'a ClassFile, &'a Method)> {
+        let supertypes = self.supertypes(class);
+        std::iter::once(class)
+            .chain(supertypes.iter().filter_map(|name| self.classes.get(name)))
+            .find_map(|owner| {
+                let pool = &owner.constant_pool;
+                owner
+                    .methods
+                    .iter()
+                    .find(|m| {
+                        (std::ptr::eq(owner, class) || is_api(&m.access_flags))
+                            && m.name(pool) == name
+                            && m.descriptor(pool) == descriptor
+                    })
+                    .map(|method| (owner, method))
+            })
+    }
+
+    fn find_field<'a>(
+        &'a self,
+        class: &'a ClassFile,
+        name: &str,
+    ) -> Option<(&'a ClassFile, &'a Field)> {
+        let supertypes = self.supertypes(class);
+        std::iter::once(class)
+            .chain(supertypes.iter().filter_map(|name| self.classes.get(name)))
+            .find_map(|owner| {
+                owner
+                    .find_field(name)
+                    .filter(|field| std::ptr::eq(owner, class) || is_api(&field.access_flags))
+                    .map(|field| (owner, field))
+            })
+    }
+}
+
+fn direct_supertypes(class: &ClassFile) -> Vec<String> {
+    class
+        .super_class_name()
+        .into_iter()
+        .chain(class.interface_names())
+        .collect()
+}
+
+/// Public and protected members are part of the API.
+fn is_api(flags: &AccessFlag) -> bool {
+    flags.contains(ACC_PUBLIC) || flags.contains(ACC_PROTECTED)
+}
+
+fn is_generated(flags: &AccessFlag) -> bool {
+    flags.contains(ACC_SYNTHETIC) || flags.contains(ACC_BRIDGE)
+}
+
+/// private < package < protected < public
+fn visibility(flags: &AccessFlag) -> (u8, &'static str) {
+    if flags.contains(ACC_PUBLIC) {
+        (3, "public")
+    } else if flags.contains(ACC_PROTECTED) {
+        (2, "protected")
+    } else if flags.contains(ACC_PRIVATE) {
+        (0, "private")
+    } else {
+        (1, "package-private")
+    }
+}
+
+fn is_api_class(class: &ClassFile) -> bool {
+    // Anonymous and local classes, e.g. `Foo$1`, can not be referenced from source.
+    let name = class.this_class_name();
+    let simple = name.rsplit('$').next().unwrap_or(&name);
+    class.access_flags.contains(ACC_PUBLIC)
+        && !class.access_flags.contains(ACC_SYNTHETIC)
+        && !(name.contains('$') && simple.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Whether a class file at `location`, e.g. `lib/p/Api.class` or `app.jar!p/Api.class`, is
+/// named after the class.
+fn is_location_of(location: &str, name: &str) -> bool {
+    location.strip_suffix(".class").is_some_and(|stem| {
+        stem.strip_suffix(name)
+            .is_some_and(|prefix| prefix.is_empty() || prefix.ends_with(['/', '\\', '!']))
+    })
+}
+
+fn kind_name(class: &ClassFile) -> &'static str {
+    if class.access_flags.contains(ACC_INTERFACE) {
+        "interface"
+    } else {
+        "class"
+    }
+}
+
+fn return_type(descriptor: &str) -> &str {
+    descriptor.rsplit(')').next().unwrap_or(descriptor)
+}
+
+fn parameters(descriptor: &str) -> &str {
+    descriptor.split(')').next().unwrap_or(descriptor)
+}
+
+fn java_type(descriptor: &str) -> String {
+    signature::parse_field_descriptor(descriptor)
+        .map(|item| item.to_string())
+        .unwrap_or_else(|_| descriptor.to_string())
+}
+
+fn exceptions(class: &ClassFile, method: &Method) -> BTreeSet<String> {
+    method
+        .attributes
+        .iter()
+        .find_map(|item| match &item.attribute {
+            Attribute::Exceptions(classes) => Some(
+                classes
+                    .iter()
+                    .map(|index| class.constant_pool.resolve(*index))
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn constant_value(class: &ClassFile, field: &Field) -> Option<String> {
+    field
+        .attributes
+        .iter()
+        .find_map(|item| match item.attribute {
+            Attribute::ConstantValue(index) => Some(describe_constant(
+                &class.constant_pool,
+                index,
+                &class.this_class_name(),
+            )),
+            _ => None,
+        })
+}
+
+struct Checker<'a> {
+    old: &'a Library,
+    new: &'a Library,
+    report: CompatReport,
+}
+
+impl<'a> Checker<'a> {
+    fn report(
+        &mut self,
+        severity: Severity,
+        kind: &'static str,
+        class: &str,
+        member: Option<String>,
+        message: String,
+    ) {
+        match severity {
+            Severity::Error => self.report.totals.errors += 1,
+            Severity::Warning => self.report.totals.warnings += 1,
+            Severity::Info => self.report.totals.infos += 1,
+        }
+        self.report.problems.push(Problem {
+            severity,
+            kind,
+            class: class.to_string(),
+            member,
+            message,
+        });
+    }
+
+    fn check_class(&mut self, old: &ClassFile, new: &ClassFile) {
+        let name = old.this_class_name();
+        let (old_flags, new_flags) = (&old.access_flags, &new.access_flags);
+        if !new_flags.contains(ACC_PUBLIC) {
+            self.report(
+                Severity::Error,
+                "class-less-accessible",
+                &name,
+                None,
+                "class is no longer public".to_string(),
+            );
+            return;
+        }
+        if kind_name(old) != kind_name(new) {
+            let message = format!("{} changed to {}", kind_name(old), kind_name(new));
+            self.report(Severity::Error, "class-kind-changed", &name, None, message);
+            return;
+        }
+        let is_interface = old_flags.contains(ACC_INTERFACE);
+        if !is_interface && !old_flags.contains(ACC_FINAL) && new_flags.contains(ACC_FINAL) {
+            self.report(
+                Severity::Error,
+                "class-now-final",
+                &name,
+                None,
+                "class is now final".to_string(),
+            );
+        }
+        if !is_interface && !old_flags.contains(ACC_ABSTRACT) && new_flags.contains(ACC_ABSTRACT) {
+            self.report(
+                Severity::Error,
+                "class-now-abstract",
+                &name,
+                None,
+                "class is now abstract".to_string(),
+            );
+        }
+        let old_supertypes = self.old.supertypes(old);
+        let new_supertypes = self.new.supertypes(new);
+        for removed in old_supertypes.difference(&new_supertypes) {
+            let message = format!("{} is no longer a supertype", removed);
+            self.report(Severity::Error, "supertype-removed", &name, None, message);
+        }
+        for added in new_supertypes.difference(&old_supertypes) {
+            let message = format!("{} is a new supertype", added);
+            self.report(Severity::Info, "supertype-added", &name, None, message);
+        }
+        self.check_fields(old, new);
+        self.check_methods(old, new);
+    }
+
+    fn check_fields(&mut self, old: &ClassFile, new: &ClassFile) {
+        let class = old.this_class_name();
+        let (old_pool, new_pool) = (&old.constant_pool, &new.constant_pool);
+        for field in old.fields.iter() {
+            if !is_api(&field.access_flags) || is_generated(&field.access_flags) {
+                continue;
+            }
+            let name = field.name(old_pool);
+            let member = Some(name.clone());
+            let (owner, other) = match self.new.find_field(new, &name) {
+                Some(found) => found,
+                None => {
+                    self.report(
+                        Severity::Error,
+                        "field-removed",
+                        &class,
+                        member,
+                        "field removed".to_string(),
+                    );
+                    continue;
+                }
+            };
+            let (old_type, new_type) = (
+                field.descriptor(old_pool),
+                other.descriptor(&owner.constant_pool),
+            );
+            if old_type != new_type {
+                let message = format!(
+                    "type changed from {} to {}",
+                    java_type(&old_type),
+                    java_type(&new_type)
+                );
+                self.report(
+                    Severity::Error,
+                    "field-type-changed",
+                    &class,
+                    member.clone(),
+                    message,
+                );
+            }
+            self.check_access(
+                &class,
+                &member,
+                &field.access_flags,
+                &other.access_flags,
+                "field",
+            );
+            let (old_flags, new_flags) = (&field.access_flags, &other.access_flags);
+            if !old_flags.contains(ACC_FINAL) && new_flags.contains(ACC_FINAL) {
+                self.report(
+                    Severity::Error,
+                    "field-now-final",
+                    &class,
+                    member.clone(),
+                    "field is now final".to_string(),
+                );
+            }
+            let (old_value, new_value) = (constant_value(old, field), constant_value(owner, other));
+            if old_value != new_value {
+                let describe = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+                let message = format!(
+                    "constant value changed from {} to {}, clients compiled against the old value keep it",
+                    describe(old_value),
+                    describe(new_value)
+                );
+                self.report(
+                    Severity::Warning,
+                    "field-constant-changed",
+                    &class,
+                    member,
+                    message,
+                );
+            }
+        }
+        for field in new.fields.iter() {
+            let name = field.name(new_pool);
+            let is_new = old
+                .find_field(&name)
+                .is_none_or(|field| !is_api(&field.access_flags));
+            if is_api(&field.access_flags) && !is_generated(&field.access_flags) && is_new {
+                self.report(
+                    Severity::Info,
+                    "field-added",
+                    &class,
+                    Some(name),
+                    "field added".to_string(),
+                );
+            }
+        }
+    }
+
+    fn check_methods(&mut self, old: &ClassFile, new: &ClassFile) {
+        let class = old.this_class_name();
+        let (old_pool, new_pool) = (&old.constant_pool, &new.constant_pool);
+        let is_interface = old.access_flags.contains(ACC_INTERFACE);
+        let can_subclass = !new.access_flags.contains(ACC_FINAL);
+        for method in old.methods.iter() {
+            if !is_api(&method.access_flags) || is_generated(&method.access_flags) {
+                continue;
+            }
+            let (name, descriptor) = (method.name(old_pool), method.descriptor(old_pool));
+            let member = Some(format!("{}{}", name, descriptor));
+            let other = match self.new.find_method(new, &name, &descriptor) {
+                Some((owner, other)) => (owner, other),
+                None => {
+                    self.report_missing_method(new, &class, &name, &descriptor);
+                    continue;
+                }
+            };
+            let (owner, other) = other;
+            self.check_access(
+                &class,
+                &member,
+                &method.access_flags,
+                &other.access_flags,
+                "method",
+            );
+            let (old_flags, new_flags) = (&method.access_flags, &other.access_flags);
+            if old_flags.contains(ACC_STATIC) != new_flags.contains(ACC_STATIC) {
+                let message = if new_flags.contains(ACC_STATIC) {
+                    "method is now static"
+                } else {
+                    "method is no longer static"
+                };
+                self.report(
+                    Severity::Error,
+                    "method-static-changed",
+                    &class,
+                    member.clone(),
+                    message.to_string(),
+                );
+            }
+            let overridable = can_subclass && !old_flags.contains(ACC_STATIC) && name != "<init>";
+            if overridable && !old_flags.contains(ACC_FINAL) && new_flags.contains(ACC_FINAL) {
+                self.report(
+                    Severity::Error,
+                    "method-now-final",
+                    &class,
+                    member.clone(),
+                    "method is now final".to_string(),
+                );
+            }
+            if overridable && !old_flags.contains(ACC_ABSTRACT) && new_flags.contains(ACC_ABSTRACT)
+            {
+                self.report(
+                    Severity::Error,
+                    "method-now-abstract",
+                    &class,
+                    member.clone(),
+                    "method is now abstract".to_string(),
+                );
+            }
+            let (old_throws, new_throws) = (exceptions(old, method), exceptions(owner, other));
+            for added in new_throws.difference(&old_throws) {
+                let message = format!("now throws {}", added);
+                self.report(
+                    Severity::Warning,
+                    "method-exception-added",
+                    &class,
+                    member.clone(),
+                    message,
+                );
+            }
+        }
+        for method in new.methods.iter() {
+            if !is_api(&method.access_flags) || is_generated(&method.access_flags) {
+                continue;
+            }
+            let (name, descriptor) = (method.name(new_pool), method.descriptor(new_pool));
+            let existed = self.old.find_method(old, &name, &descriptor).is_some();
+            // Methods whose return type changed are already reported.
+            let retyped = old.methods.iter().any(|method| {
+                is_api(&method.access_flags)
+                    && method.name(old_pool) == name
+                    && parameters(&method.descriptor(old_pool)) == parameters(&descriptor)
+            });
+            if existed || retyped {
+                continue;
+            }
+            let member = Some(format!("{}{}", name, descriptor));
+            let is_abstract = method.access_flags.contains(ACC_ABSTRACT);
+            if is_abstract && can_subclass {
+                let what = if is_interface { "interface" } else { "class" };
+                let message = format!(
+                    "abstract method added, implementations of the {} must add it",
+                    what
+                );
+                self.report(
+                    Severity::Warning,
+                    "method-abstract-added",
+                    &class,
+                    member,
+                    message,
+                );
+            } else {
+                self.report(
+                    Severity::Info,
+                    "method-added",
+                    &class,
+                    member,
+                    "method added".to_string(),
+                );
+            }
+        }
+    }
+
+    /// A method with the same name and parameters but another return type is a return type
+    /// change, anything else a removal.
+    fn report_missing_method(
+        &mut self,
+        new: &ClassFile,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) {
+        let pool = &new.constant_pool;
+        let member = Some(format!("{}{}", name, descriptor));
+        let changed = new.methods.iter().find(|method| {
+            let other = method.descriptor(pool);
+            method.name(pool) == name
+                && parameters(&other) == parameters(descriptor)
+                && is_api(&method.access_flags)
+        });
+        match changed {
+            Some(method) => {
+                let other = method.descriptor(pool);
+                let message = format!(
+                    "return type changed from {} to {}",
+                    java_type(return_type(descriptor)),
+                    java_type(return_type(&other))
+                );
+                self.report(
+                    Severity::Error,
+                    "method-return-type-changed",
+                    class,
+                    member,
+                    message,
+                );
+            }
+            None => self.report(
+                Severity::Error,
+                "method-removed",
+                class,
+                member,
+                "method removed".to_string(),
+            ),
+        }
+    }
+
+    fn check_access(
+        &mut self,
+        class: &str,
+        member: &Option<String>,
+        old: &AccessFlag,
+        new: &AccessFlag,
+        what: &str,
+    ) {
+        let ((old_level, old_name), (new_level, new_name)) = (visibility(old), visibility(new));
+        if new_level < old_level {
+            let message = format!(
+                "{} visibility narrowed from {} to {}",
+                what, old_name, new_name
+            );
+            self.report(
+                Severity::Error,
+                "visibility-narrowed",
+                class,
+                member.clone(),
+                message,
+            );
+        }
+    }
+}
+
+/// Compares the API of two versions of a library, problems are sorted by class name.
+pub fn check(old: &Library, new: &Library) -> CompatReport {
+    let mut checker = Checker {
+        old,
+        new,
+        report: CompatReport::default(),
+    };
+    // A class can not be compared when either version fails to parse.
+    for (library, version) in [(old, "old"), (new, "new")] {
+        for (location, error) in library.unreadable.iter() {
+            checker.report(
+                Severity::Error,
+                "class-unreadable",
+                location,
+                None,
+                format!("could not parse {} class file: {}", version, error),
+            );
+        }
+    }
+    let mut names: Vec<&String> = old.classes.keys().collect();
+    names.sort();
+    for name in names {
+        let class = &old.classes[name];
+        if !is_api_class(class) {
+            continue;
+        }
+        match new.classes.get(name) {
+            Some(other) => checker.check_class(class, other),
+            None if new.is_unreadable(name) => {}
+            None => checker.report(
+                Severity::Error,
+                "class-removed",
+                name,
+                None,
+                format!("{} removed", kind_name(class)),
+            ),
+        }
+    }
+    let mut names: Vec<&String> = new.classes.keys().collect();
+    names.sort();
+    for name in names {
+        let class = &new.classes[name];
+        let existed = old.classes.get(name).is_some_and(is_api_class);
+        if is_api_class(class) && !existed && !old.is_unreadable(name) {
+            checker.report(
+                Severity::Info,
+                "class-added",
+                name,
+                None,
+                format!("{} added", kind_name(class)),
+            );
+        }
+    }
+    let mut report = checker.report;
+    report.problems.sort_by(|a, b| a.class.cmp(&b.class));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Library, Severity};
+    use crate::assembler::assemble;
+
+    fn library(sources: &[&str]) -> Library {
+        let mut acc = Library::new();
+        for source in sources {
+            acc.add(assemble(source).unwrap());
+        }
+        acc
+    }
+
+    const OLD_BASE: &str = r#"
+.class public p/Base
+.method public shared()V
+    .limit stack 0
+    return
+.end method
+"#;
+
+    const OLD_API: &str = r#"
+.class public p/Api
+.super p/Base
+.implements java/lang/Runnable
+.field public count I
+.method public run()V
+    .limit stack 0
+    return
+.end method
+.method public size()I
+    .limit stack 1
+    iconst_0
+    ireturn
+.end method
+.method public moved()V
+    .limit stack 0
+    return
+.end method
+.method public close()V
+    .limit stack 0
+    return
+.end method
+"#;
+
+    const OLD_SHAPE: &str = r#"
+.interface public abstract p/Shape
+.method public abstract area()D
+.end method
+"#;
+
+    const NEW_BASE: &str = r#"
+.class public p/Base
+.method public shared()V
+    .limit stack 0
+    return
+.end method
+.method public moved()V
+    .limit stack 0
+    return
+.end method
+"#;
+
+    const NEW_API: &str = r#"
+.class public final p/Api
+.super p/Base
+.field protected count I
+.method public size()J
+    .limit stack 2
+    lconst_0
+    lreturn
+.end method
+.method public run()V
+    .limit stack 0
+    return
+.end method
+"#;
+
+    const NEW_SHAPE: &str = r#"
+.interface public abstract p/Shape
+.method public abstract area()D
+.end method
+.method public abstract perimeter()D
+.end method
+"#;
+
+    #[test]
+    fn test_check_reports_incompatible_changes() {
+        let old = library(&[OLD_BASE, OLD_API, OLD_SHAPE]);
+        let new = library(&[NEW_BASE, NEW_API, NEW_SHAPE]);
+        let report = check(&old, &new);
+        let lines: Vec<String> = report
+            .problems
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "ERROR   p/Api: class is now final",
+                "ERROR   p/Api: java/lang/Runnable is no longer a supertype",
+                "ERROR   p/Api.count: field visibility narrowed from public to protected",
+                "ERROR   p/Api.size()I: return type changed from int to long",
+                "ERROR   p/Api.close()V: method removed",
+                "INFO    p/Base.moved()V: method added",
+                "WARNING p/Shape.perimeter()D: abstract method added, implementations of the interface must add it",
+            ]
+        );
+        assert_eq!(report.problems[4].kind, "method-removed");
+        assert_eq!(report.problems[6].severity, Severity::Warning);
+        assert_eq!(
+            (
+                report.totals.errors,
+                report.totals.warnings,
+                report.totals.infos
+            ),
+            (5, 1, 1)
+        );
+        assert!(!report.is_binary_compatible());
+    }
+
+    #[test]
+    fn test_check_classes_added_and_removed() {
+        let old = library(&[OLD_BASE, OLD_SHAPE]);
+        let new = library(&[OLD_BASE, OLD_API]);
+        let report = check(&old, &new);
+        let lines: Vec<String> = report
+            .problems
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "INFO    p/Api: class added",
+                "ERROR   p/Shape: interface removed"
+            ]
+        );
+        assert!(check(&new, &new).problems.is_empty());
+    }
+
+    #[test]
+    fn test_check_reports_unreadable_classes() {
+        const GONE: &str = ".class public p/Gone";
+        let mut old = library(&[OLD_BASE, OLD_SHAPE]);
+        old.add_unreadable("old/p/Gone.class", "unexpected end of file".to_string());
+        let mut new = library(&[OLD_BASE, GONE]);
+        new.add_unreadable("new.jar!p/Shape.class", "bad magic".to_string());
+        let report = check(&old, &new);
+        let lines: Vec<String> = report
+            .problems
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        // The unreadable new p/Shape is not also reported as removed, nor p/Gone as added.
+        assert_eq!(
+            lines,
+            vec![
+                "ERROR   new.jar!p/Shape.class: could not parse new class file: bad magic",
+                "ERROR   old/p/Gone.class: could not parse old class file: unexpected end of file",
+            ]
+        );
+        assert_eq!(report.problems[0].kind, "class-unreadable");
+        assert!(!report.is_binary_compatible());
+    }
+}
@@ -0,0 +1,308 @@
+// Java-source-like declarations of classes, fields and methods. Generic Signature attributes
+// are preferred over descriptors, members whose types can not be parsed fall back to the raw
+// descriptor.
+
+use crate::attribute::{Attribute, AttributeInfo};
+use crate::classfile::{ClassFile, Field, Method};
+use crate::flag::{AccessFlag, ACC_ANNOTATION, ACC_ENUM, ACC_INTERFACE, ACC_VARARGS};
+use crate::pool::ConstantPool;
+use crate::signature::{self, JavaType, MethodSignature};
+
+/// Declaration annotations of a class, field or method in source form, e.g.
+/// `@java.lang.Deprecated(since="9")`. A bare `Deprecated` attribute counts as `@Deprecated`.
+pub fn annotations(attributes: &[AttributeInfo], pool: &ConstantPool) -> Vec<String> {
+    let mut acc = Vec::<String>::new();
+    let mut deprecated = false;
+    for item in attributes.iter() {
+        match &item.attribute {
+            Attribute::RuntimeVisibleAnnotations(items)
+            | Attribute::RuntimeInvisibleAnnotations(items) => {
+                for annotation in items.iter() {
+                    deprecated |= annotation.type_name(pool) == "java/lang/Deprecated";
+                    acc.push(annotation.to_java(pool));
+                }
+            }
+            _ => {}
+        }
+    }
+    let has_attribute = attributes
+        .iter()
+        .any(|item| matches!(&item.attribute, Attribute::Unknown(name, _) if name == "Deprecated"));
+    if has_attribute && !deprecated {
+        acc.push("@java.lang.Deprecated".to_string());
+    }
+    acc
+}
+
+// Annotations per parameter. The attributes may leave out synthetic leading parameters such
+// as the outer instance of an inner class, so they are aligned with the last parameters.
+fn parameter_annotations(method: &Method, pool: &ConstantPool, count: usize) -> Vec<String> {
+    let mut acc = vec![String::new(); count];
+    for item in method.attributes.iter() {
+        let parameters = match &item.attribute {
+            Attribute::RuntimeVisibleParameterAnnotations(parameters)
+            | Attribute::RuntimeInvisibleParameterAnnotations(parameters) => parameters,
+            _ => continue,
+        };
+        let skip = count.saturating_sub(parameters.len());
+        for (slot, annotations) in acc.iter_mut().skip(skip).zip(parameters.iter()) {
+            for annotation in annotations.iter() {
+                slot.push_str(&annotation.to_java(pool));
+                slot.push(' ');
+            }
+        }
+    }
+    acc
+}
+
+// Source modifiers in the order the JLS recommends, flags such as `synthetic` have no keyword.
+const CLASS_MODIFIERS: [&str; 6] = [
+    "public",
+    "protected",
+    "private",
+    "abstract",
+    "static",
+    "final",
+];
+const FIELD_MODIFIERS: [&str; 7] = [
+    "public",
+    "protected",
+    "private",
+    "static",
+    "final",
+    "transient",
+    "volatile",
+];
+const METHOD_MODIFIERS: [&str; 9] = [
+    "public",
+    "protected",
+    "private",
+    "abstract",
+    "static",
+    "final",
+    "synchronized",
+    "native",
+    "strict",
+];
+
+fn modifiers(flags: &AccessFlag, keywords: &[&str], skip: &[&str]) -> Vec<&'static str> {
+    let names = flags.names();
+    keywords
+        .iter()
+        .filter(|keyword| !skip.contains(keyword))
+        .filter_map(|keyword| names.iter().find(|name| *name == keyword).copied())
+        .map(|name| if name == "strict" { "strictfp" } else { name })
+        .collect()
+}
+
+fn join_types(items: &[JavaType]) -> String {
+    let acc: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    acc.join(", ")
+}
+
+fn with_modifiers(modifiers: Vec<&str>, rest: String) -> String {
+    if modifiers.is_empty() {
+        rest
+    } else {
+        format!("{} {}", modifiers.join(" "), rest)
+    }
+}
+
+// The keyword of a class and the modifiers it implies, nested interfaces, enums and records are
+// implicitly static.
+fn class_kind(flags: &AccessFlag, is_record: bool) -> (&'static str, &'static [&'static str]) {
+    if flags.contains(ACC_ANNOTATION) {
+        ("@interface", &["abstract", "static"])
+    } else if flags.contains(ACC_INTERFACE) {
+        ("interface", &["abstract", "static"])
+    } else if flags.contains(ACC_ENUM) {
+        ("enum", &["final", "static"])
+    } else if is_record {
+        ("record", &["final", "static"])
+    } else {
+        ("class", &[])
+    }
+}
+
+/// Whether the class is declared with `record`: it has a Record attribute or extends
+/// `java.lang.Record`.
+pub fn is_record(class: &ClassFile) -> bool {
+    class
+        .attributes
+        .iter()
+        .any(|item| matches!(item.attribute, Attribute::Record(_)))
+        || class.super_class_name().as_deref() == Some("java/lang/Record")
+}
+
+/// e.g. `public final class com.foo.Bar<T> extends com.foo.Base implements java.lang.Runnable`
+pub fn class_declaration(class: &ClassFile) -> String {
+    nested_class_declaration(class, &class.access_flags)
+}
+
+/// Same as [`class_declaration`] with the modifiers of an InnerClasses entry, which unlike the
+/// class access flags record `private`, `protected` and `static`.
+pub fn nested_class_declaration(class: &ClassFile, flags: &AccessFlag) -> String {
+    let pool = &class.constant_pool;
+    let (kind, skip) = class_kind(flags, is_record(class));
+    let generic = class
+        .signature()
+        .and_then(|value| signature::parse_class_signature(&value).ok());
+    let (type_parameters, superclass, interfaces) = match generic {
+        Some(generic) => (
+            signature::type_parameters_to_string(&generic.type_parameters),
+            Some(generic.superclass.to_string()),
+            generic
+                .interfaces
+                .iter()
+                .map(|item| item.to_string())
+                .collect(),
+        ),
+        None => (
+            String::new(),
+            class
+                .super_class_name()
+                .map(|name| signature::java_name(&name)),
+            class
+                .interface_names()
+                .iter()
+                .map(|name| signature::java_name(name))
+                .collect::<Vec<String>>(),
+        ),
+    };
+    let mut acc = format!(
+        "{} {}{}",
+        kind,
+        signature::java_name(&pool.resolve(class.this_class)),
+        type_parameters
+    );
+    if let Some(superclass) = superclass {
+        // Supertypes implied by the kind of class are left out, as in source code.
+        let implied = match kind {
+            "class" => superclass == "java.lang.Object",
+            "enum" => superclass.starts_with("java.lang.Enum"),
+            "record" => superclass == "java.lang.Record",
+            _ => true,
+        };
+        if !implied {
+            acc.push_str(&format!(" extends {}", superclass));
+        }
+    }
+    let interfaces: Vec<String> = interfaces
+        .into_iter()
+        .filter(|name| !(kind == "@interface" && name == "java.lang.annotation.Annotation"))
+        .collect();
+    if !interfaces.is_empty() {
+        let keyword = if flags.contains(ACC_INTERFACE) {
+            "extends"
+        } else {
+            "implements"
+        };
+        acc.push_str(&format!(" {} {}", keyword, interfaces.join(", ")));
+    }
+    with_modifiers(modifiers(flags, &CLASS_MODIFIERS, skip), acc)
+}
+
+/// e.g. `public static class Inner`, as declared in the enclosing class. InnerClasses flags do not
+/// tell records from final classes, see [`is_record`].
+pub fn inner_class_declaration(flags: &AccessFlag, name: &str, is_record: bool) -> String {
+    let (kind, skip) = class_kind(flags, is_record);
+    with_modifiers(
+        modifiers(flags, &CLASS_MODIFIERS, skip),
+        format!("{} {}", kind, name),
+    )
+}
+
+/// e.g. `private static final java.util.List<java.lang.String> names`
+pub fn field_declaration(class: &ClassFile, field: &Field) -> String {
+    let pool = &class.constant_pool;
+    let descriptor = field.descriptor(pool);
+    let field_type = field
+        .signature(pool)
+        .and_then(|value| signature::parse_field_signature(&value).ok())
+        .or_else(|| signature::parse_field_descriptor(&descriptor).ok())
+        .map(|item| item.to_string())
+        .unwrap_or(descriptor);
+    with_modifiers(
+        modifiers(&field.access_flags, &FIELD_MODIFIERS, &[]),
+        format!("{} {}", field_type, field.name(pool)),
+    )
+}
+
+fn method_types(class: &ClassFile, method: &Method) -> Option<MethodSignature> {
+    let pool = &class.constant_pool;
+    let generic = method
+        .signature(pool)
+        .and_then(|value| signature::parse_method_signature(&value).ok());
+    let mut ret = match generic {
+        Some(generic) => generic,
+        None => signature::parse_method_descriptor(&method.descriptor(pool)).ok()?,
+    };
+    if ret.throws.is_empty() {
+        ret.throws = method
+            .attributes
+            .iter()
+            .find_map(|item| match &item.attribute {
+                Attribute::Exceptions(classes) => Some(
+                    classes
+                        .iter()
+                        .filter_map(|index| {
+                            let name = format!("L{};", pool.resolve(*index));
+                            signature::parse_field_descriptor(&name).ok()
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default();
+    }
+    Some(ret)
+}
+
+/// e.g. `public static void main(java.lang.String[])`, constructors are named after the class
+/// and static initializers are rendered as `static {}`.
+pub fn method_declaration(class: &ClassFile, method: &Method) -> String {
+    let pool = &class.constant_pool;
+    let name = method.name(pool);
+    if name == "<clinit>" {
+        return "static {}".to_string();
+    }
+    let flags = &method.access_flags;
+    let types = match method_types(class, method) {
+        Some(types) => types,
+        None => {
+            return with_modifiers(
+                modifiers(flags, &METHOD_MODIFIERS, &[]),
+                format!("{} {}", name, method.descriptor(pool)),
+            )
+        }
+    };
+    let mut parameters: Vec<String> = types.parameters.iter().map(|p| p.to_string()).collect();
+    if flags.contains(ACC_VARARGS) {
+        if let (Some(last), Some(JavaType::Array(component))) =
+            (parameters.last_mut(), types.parameters.last())
+        {
+            *last = format!("{}...", component);
+        }
+    }
+    let annotations = parameter_annotations(method, pool, parameters.len());
+    for (parameter, annotations) in parameters.iter_mut().zip(annotations) {
+        parameter.insert_str(0, &annotations);
+    }
+    let head = if name == "<init>" {
+        let this_name = pool.resolve(class.this_class);
+        let simple = this_name.rsplit('/').next().unwrap_or(&this_name);
+        simple.to_string()
+    } else {
+        format!("{} {}", types.return_type, name)
+    };
+    let type_parameters = signature::type_parameters_to_string(&types.type_parameters);
+    let mut acc = if type_parameters.is_empty() {
+        format!("{}({})", head, parameters.join(", "))
+    } else {
+        format!("{} {}({})", type_parameters, head, parameters.join(", "))
+    };
+    if !types.throws.is_empty() {
+        acc.push_str(&format!(" throws {}", join_types(&types.throws)));
+    }
+    with_modifiers(modifiers(flags, &METHOD_MODIFIERS, &[]), acc)
+}
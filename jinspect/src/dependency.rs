@@ -0,0 +1,254 @@
+// Class and package dependency graphs. A class depends on every class its constant pool names:
+// Class entries, the owners of field and method references and the types in descriptors of
+// references, method types and its own members. Package cycles are the strongly connected
+// components of the package graph.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::classfile::ClassFile;
+use crate::pool::ConstantPoolItem;
+use crate::signature;
+
+/// Adds the classes named in a field or method descriptor, e.g. `(Ljava/util/List;[I)V`.
+fn descriptor_classes(descriptor: &str, acc: &mut BTreeSet<String>) {
+    let mut rest = descriptor;
+    while let Some(start) = rest.find('L') {
+        let Some(end) = rest[start..].find(';') else {
+            return;
+        };
+        acc.insert(rest[start + 1..start + end].to_string());
+        rest = &rest[start + end + 1..];
+    }
+}
+
+/// Classes referenced by a class, without itself.
+pub fn class_dependencies(class: &ClassFile) -> BTreeSet<String> {
+    let pool = &class.constant_pool;
+    let mut acc = BTreeSet::<String>::new();
+    for (_, item) in pool.iter() {
+        match item {
+            ConstantPoolItem::Class(name) => match pool.get_utf8(*name) {
+                Ok(name) if name.starts_with('[') => descriptor_classes(name, &mut acc),
+                Ok(name) => {
+                    acc.insert(name.to_string());
+                }
+                Err(_) => {}
+            },
+            ConstantPoolItem::NameAndType(_, descriptor)
+            | ConstantPoolItem::MethodType(descriptor) => {
+                if let Ok(descriptor) = pool.get_utf8(*descriptor) {
+                    descriptor_classes(descriptor, &mut acc);
+                }
+            }
+            _ => {}
+        }
+    }
+    for member in class.fields.iter().chain(class.methods.iter()) {
+        descriptor_classes(&member.descriptor(pool), &mut acc);
+    }
+    acc.remove(&class.this_class_name());
+    acc
+}
+
+/// `java/util/Map$Entry` is in `java/util`, classes of the default package in ``.
+pub fn package_of(class: &str) -> &str {
+    class.rsplit_once('/').map_or("", |(package, _)| package)
+}
+
+/// Dependencies by class and by package, keyed by internal names.
+#[derive(Debug, Default, Serialize)]
+pub struct DependencyGraph {
+    pub classes: BTreeMap<String, BTreeSet<String>>,
+    pub packages: BTreeMap<String, BTreeSet<String>>,
+    pub package_cycles: Vec<Vec<String>>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph, dependencies on classes outside `classes` are dropped unless `external`.
+    pub fn build(classes: &[ClassFile], external: bool) -> Self {
+        let names: BTreeSet<String> = classes
+            .iter()
+            .map(|class| class.this_class_name())
+            .collect();
+        let mut graph = DependencyGraph::default();
+        for class in classes.iter() {
+            let name = class.this_class_name();
+            let mut dependencies = class_dependencies(class);
+            if !external {
+                dependencies.retain(|item| names.contains(item));
+            }
+            let package = package_of(&name).to_string();
+            let edges = graph.packages.entry(package.clone()).or_default();
+            edges.extend(
+                dependencies
+                    .iter()
+                    .map(|item| package_of(item).to_string())
+                    .filter(|item| *item != package),
+            );
+            graph.classes.entry(name).or_default().extend(dependencies);
+        }
+        // Packages only depended upon are nodes too.
+        let targets: Vec<String> = graph.packages.values().flatten().cloned().collect();
+        for target in targets {
+            graph.packages.entry(target).or_default();
+        }
+        graph.package_cycles = strongly_connected(&graph.packages)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect();
+        graph
+    }
+
+    /// Graphviz rendering of the class or package graph, nodes are Java names.
+    pub fn to_dot(&self, packages: bool) -> String {
+        let edges = if packages {
+            &self.packages
+        } else {
+            &self.classes
+        };
+        let label = |name: &str| match name {
+            "" => "(default)".to_string(),
+            name => signature::java_name(name),
+        };
+        let in_cycle: BTreeSet<&String> = self.package_cycles.iter().flatten().collect();
+        let mut acc = String::from("digraph dependencies {\n    node [shape=box];\n");
+        for (from, targets) in edges.iter() {
+            if packages && in_cycle.contains(from) {
+                let _ = writeln!(acc, "    \"{}\" [color=red];", label(from));
+            } else if targets.is_empty() {
+                let _ = writeln!(acc, "    \"{}\";", label(from));
+            }
+            for to in targets.iter() {
+                let _ = writeln!(acc, "    \"{}\" -> \"{}\";", label(from), label(to));
+            }
+        }
+        acc.push_str("}\n");
+        acc
+    }
+}
+
+/// Tarjan's algorithm, components are listed in discovery order with sorted members.
+fn strongly_connected(graph: &BTreeMap<String, BTreeSet<String>>) -> Vec<Vec<String>> {
+    struct State<'a> {
+        graph: &'a BTreeMap<String, BTreeSet<String>>,
+        index: BTreeMap<&'a str, usize>,
+        low: BTreeMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        components: Vec<Vec<String>>,
+    }
+
+    fn visit<'a>(state: &mut State<'a>, node: &'a str) {
+        let index = state.index.len();
+        state.index.insert(node, index);
+        state.low.insert(node, index);
+        state.stack.push(node);
+        state.on_stack.insert(node);
+        for next in state.graph.get(node).into_iter().flatten() {
+            let next = next.as_str();
+            if !state.index.contains_key(next) {
+                visit(state, next);
+                let low = state.low[node].min(state.low[next]);
+                state.low.insert(node, low);
+            } else if state.on_stack.contains(next) {
+                let low = state.low[node].min(state.index[next]);
+                state.low.insert(node, low);
+            }
+        }
+        if state.low[node] == state.index[node] {
+            let mut component = Vec::<String>::new();
+            while let Some(item) = state.stack.pop() {
+                state.on_stack.remove(item);
+                component.push(item.to_string());
+                if item == node {
+                    break;
+                }
+            }
+            component.sort();
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        graph,
+        index: BTreeMap::new(),
+        low: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+    for node in graph.keys() {
+        if !state.index.contains_key(node.as_str()) {
+            visit(&mut state, node);
+        }
+    }
+    state.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{class_dependencies, package_of, DependencyGraph};
+    use crate::assembler::assemble;
+
+    const ORDER: &str = r#"
+.class public shop/Order
+.field private items [Lshop/model/Item;
+.method public total(Lshop/model/Item;)J
+    .limit stack 2
+    .limit locals 2
+    aload_1
+    getfield shop/model/Item/price Lshop/money/Amount;
+    invokevirtual shop/money/Amount/cents()J
+    lreturn
+.end method
+"#;
+
+    const ITEM: &str = r#"
+.class public shop/model/Item
+.field public price Lshop/money/Amount;
+.field public order Lshop/Order;
+"#;
+
+    const AMOUNT: &str = r#"
+.class public shop/money/Amount
+.method public cents()J
+    .limit stack 2
+    lconst_0
+    lreturn
+.end method
+"#;
+
+    #[test]
+    fn test_class_dependencies() {
+        let order = assemble(ORDER).unwrap();
+        let acc: Vec<String> = class_dependencies(&order).into_iter().collect();
+        assert_eq!(
+            acc,
+            ["java/lang/Object", "shop/model/Item", "shop/money/Amount"]
+        );
+        assert_eq!(package_of("java/util/Map$Entry"), "java/util");
+        assert_eq!(package_of("Main"), "");
+    }
+
+    #[test]
+    fn test_package_cycles() {
+        let classes: Vec<_> = [ORDER, ITEM, AMOUNT]
+            .iter()
+            .map(|source| assemble(source).unwrap())
+            .collect();
+        let graph = DependencyGraph::build(&classes, false);
+        assert_eq!(graph.classes["shop/Order"].len(), 2);
+        assert_eq!(graph.classes["shop/model/Item"].len(), 2);
+        assert!(graph.classes["shop/money/Amount"].is_empty());
+        assert_eq!(graph.package_cycles, [["shop", "shop/model"]]);
+        let dot = graph.to_dot(true);
+        assert!(dot.contains("\"shop.model\" -> \"shop.money\";"));
+        assert!(dot.contains("\"shop\" [color=red];"));
+
+        let graph = DependencyGraph::build(&classes, true);
+        assert!(graph.packages.contains_key("java/lang"));
+    }
+}
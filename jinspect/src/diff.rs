@@ -0,0 +1,683 @@
+// Semantic comparison of two class files. Members are matched by name (and descriptor for
+// methods), constants and instruction operands are compared by value rather than by constant
+// pool index, so recompiling a class with a reordered pool shows no differences.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::attribute::{Attribute, Code};
+use crate::bytecode::{self, array_type_name, describe_constant, Operand};
+use crate::classfile::{ClassFile, Field, Method};
+use crate::declaration;
+use crate::opcode::LDC_W;
+
+/// Common prefix and suffix are skipped, code differences with more instructions in between are
+/// summarised without a line diff.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One difference. Added and removed members carry their Java declaration in `new`/`old`,
+/// code changes list the differing instructions in `lines`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub subject: String,        // `class`, `field count` or `method run()V`
+    pub property: &'static str, // e.g. `access flags`, `declaration` for added/removed members
+    pub old: Option<String>,
+    pub new: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<String>, // `- ...` and `+ ...` instructions
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let none = String::from("(none)");
+        match self.kind {
+            ChangeKind::Added => write!(
+                f,
+                "+ {}: {}",
+                self.subject,
+                self.new.as_ref().unwrap_or(&none)
+            )?,
+            ChangeKind::Removed => write!(
+                f,
+                "- {}: {}",
+                self.subject,
+                self.old.as_ref().unwrap_or(&none)
+            )?,
+            ChangeKind::Changed => write!(
+                f,
+                "~ {} {}: {} -> {}",
+                self.subject,
+                self.property,
+                self.old.as_ref().unwrap_or(&none),
+                self.new.as_ref().unwrap_or(&none)
+            )?,
+        }
+        for line in self.lines.iter() {
+            write!(f, "\n    {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Differences between two versions of a class, empty if they are equivalent.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClassDiff {
+    pub class: String,
+    pub changes: Vec<Change>,
+}
+
+impl ClassDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+struct Differ<'a> {
+    old: &'a ClassFile,
+    new: &'a ClassFile,
+    changes: Vec<Change>,
+}
+
+impl<'a> Differ<'a> {
+    fn push(
+        &mut self,
+        kind: ChangeKind,
+        subject: &str,
+        property: &'static str,
+        old: Option<String>,
+        new: Option<String>,
+    ) {
+        self.changes.push(Change {
+            kind,
+            subject: subject.to_string(),
+            property,
+            old,
+            new,
+            lines: Vec::new(),
+        });
+    }
+
+    /// Records a change if the property differs, `None` means the property is absent.
+    fn compare(
+        &mut self,
+        subject: &str,
+        property: &'static str,
+        old: Option<String>,
+        new: Option<String>,
+    ) {
+        if old != new {
+            self.push(ChangeKind::Changed, subject, property, old, new);
+        }
+    }
+
+    fn compare_class(&mut self) {
+        let (old, new) = (self.old, self.new);
+        let version = |class: &ClassFile| format!("{}.{}", class.header.major, class.header.minor);
+        let flags = |class: &ClassFile| class.access_flags.names().join(" ");
+        self.compare(
+            "class",
+            "name",
+            Some(old.this_class_name()),
+            Some(new.this_class_name()),
+        );
+        self.compare("class", "version", Some(version(old)), Some(version(new)));
+        self.compare("class", "access flags", Some(flags(old)), Some(flags(new)));
+        self.compare(
+            "class",
+            "super class",
+            old.super_class_name(),
+            new.super_class_name(),
+        );
+        let interfaces = |class: &ClassFile| {
+            Some(class.interface_names().join(", ")).filter(|names| !names.is_empty())
+        };
+        self.compare("class", "interfaces", interfaces(old), interfaces(new));
+        self.compare("class", "signature", old.signature(), new.signature());
+    }
+
+    fn compare_fields(&mut self) {
+        let (old, new) = (self.old, self.new);
+        let old_pool = &old.constant_pool;
+        let new_pool = &new.constant_pool;
+        for field in old.fields.iter() {
+            let name = field.name(old_pool);
+            let subject = format!("field {}", name);
+            match new.find_field(&name) {
+                Some(other) => {
+                    let flags = |field: &Field| field.access_flags.names().join(" ");
+                    self.compare(
+                        &subject,
+                        "access flags",
+                        Some(flags(field)),
+                        Some(flags(other)),
+                    );
+                    self.compare(
+                        &subject,
+                        "descriptor",
+                        Some(field.descriptor(old_pool)),
+                        Some(other.descriptor(new_pool)),
+                    );
+                    self.compare(
+                        &subject,
+                        "signature",
+                        field.signature(old_pool),
+                        other.signature(new_pool),
+                    );
+                    self.compare(
+                        &subject,
+                        "constant value",
+                        constant_value(old, field),
+                        constant_value(new, other),
+                    );
+                }
+                None => {
+                    let declaration = declaration::field_declaration(old, field);
+                    self.push(
+                        ChangeKind::Removed,
+                        &subject,
+                        "declaration",
+                        Some(declaration),
+                        None,
+                    );
+                }
+            }
+        }
+        for field in new.fields.iter() {
+            let name = field.name(new_pool);
+            if old.find_field(&name).is_none() {
+                let declaration = declaration::field_declaration(new, field);
+                self.push(
+                    ChangeKind::Added,
+                    &format!("field {}", name),
+                    "declaration",
+                    None,
+                    Some(declaration),
+                );
+            }
+        }
+    }
+
+    fn compare_methods(&mut self) {
+        let (old, new) = (self.old, self.new);
+        let key = |class: &ClassFile, method: &Method| {
+            let pool = &class.constant_pool;
+            (method.name(pool), method.descriptor(pool))
+        };
+        let old_keys: Vec<(String, String)> = old.methods.iter().map(|m| key(old, m)).collect();
+        let new_keys: Vec<(String, String)> = new.methods.iter().map(|m| key(new, m)).collect();
+        let mut removed: Vec<usize> = (0..old_keys.len())
+            .filter(|i| !new_keys.contains(&old_keys[*i]))
+            .collect();
+        let mut added: Vec<usize> = (0..new_keys.len())
+            .filter(|i| !old_keys.contains(&new_keys[*i]))
+            .collect();
+        // A method that is the only one of its name on both sides changed its descriptor.
+        let count = |keys: &[(String, String)], indexes: &[usize], name: &str| {
+            indexes.iter().filter(|i| keys[**i].0 == name).count()
+        };
+        let renamed: Vec<(usize, usize)> = removed
+            .iter()
+            .filter_map(|i| {
+                let name = &old_keys[*i].0;
+                let j = added.iter().find(|j| new_keys[**j].0 == *name)?;
+                let unique =
+                    count(&old_keys, &removed, name) == 1 && count(&new_keys, &added, name) == 1;
+                unique.then_some((*i, *j))
+            })
+            .collect();
+        removed.retain(|i| !renamed.iter().any(|(old, _)| old == i));
+        added.retain(|j| !renamed.iter().any(|(_, new)| new == j));
+        for (i, method) in old.methods.iter().enumerate() {
+            if let Some(j) = new_keys.iter().position(|key| *key == old_keys[i]) {
+                self.compare_method(method, &new.methods[j]);
+            } else if let Some((_, j)) = renamed.iter().find(|(old, _)| *old == i) {
+                let (name, descriptor) = &old_keys[i];
+                let subject = format!("method {}{}", name, descriptor);
+                self.compare(
+                    &subject,
+                    "descriptor",
+                    Some(descriptor.clone()),
+                    Some(new_keys[*j].1.clone()),
+                );
+                self.compare_method(method, &new.methods[*j]);
+            }
+        }
+        for i in removed {
+            let (name, descriptor) = &old_keys[i];
+            let declaration = declaration::method_declaration(old, &old.methods[i]);
+            self.push(
+                ChangeKind::Removed,
+                &format!("method {}{}", name, descriptor),
+                "declaration",
+                Some(declaration),
+                None,
+            );
+        }
+        for j in added {
+            let (name, descriptor) = &new_keys[j];
+            let declaration = declaration::method_declaration(new, &new.methods[j]);
+            self.push(
+                ChangeKind::Added,
+                &format!("method {}{}", name, descriptor),
+                "declaration",
+                None,
+                Some(declaration),
+            );
+        }
+    }
+
+    fn compare_method(&mut self, method: &Method, other: &Method) {
+        let (old, new) = (self.old, self.new);
+        let subject = format!(
+            "method {}{}",
+            other.name(&new.constant_pool),
+            other.descriptor(&new.constant_pool)
+        );
+        let flags = |method: &Method| method.access_flags.names().join(" ");
+        self.compare(
+            &subject,
+            "access flags",
+            Some(flags(method)),
+            Some(flags(other)),
+        );
+        self.compare(
+            &subject,
+            "signature",
+            method.signature(&old.constant_pool),
+            other.signature(&new.constant_pool),
+        );
+        self.compare(
+            &subject,
+            "exceptions",
+            exceptions(old, method),
+            exceptions(new, other),
+        );
+        let old_code = method.code().map(|code| code_lines(old, code));
+        let new_code = other.code().map(|code| code_lines(new, code));
+        match (old_code, new_code) {
+            (Some(old_lines), Some(new_lines)) if old_lines != new_lines => {
+                let count = |lines: &[String]| format!("{} instructions", lines.len());
+                self.changes.push(Change {
+                    kind: ChangeKind::Changed,
+                    subject,
+                    property: "code",
+                    old: Some(count(&old_lines)),
+                    new: Some(count(&new_lines)),
+                    lines: diff_lines(&old_lines, &new_lines),
+                });
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                let has_code = |code: Option<&Code>| {
+                    Some(if code.is_some() { "present" } else { "absent" }.to_string())
+                };
+                self.compare(
+                    &subject,
+                    "code",
+                    has_code(method.code()),
+                    has_code(other.code()),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+fn constant_value(class: &ClassFile, field: &Field) -> Option<String> {
+    field
+        .attributes
+        .iter()
+        .find_map(|item| match item.attribute {
+            Attribute::ConstantValue(index) => Some(describe_constant(
+                &class.constant_pool,
+                index,
+                &class.this_class_name(),
+            )),
+            _ => None,
+        })
+}
+
+fn exceptions(class: &ClassFile, method: &Method) -> Option<String> {
+    method
+        .attributes
+        .iter()
+        .find_map(|item| match &item.attribute {
+            Attribute::Exceptions(classes) => {
+                let names: Vec<String> = classes
+                    .iter()
+                    .map(|index| class.constant_pool.resolve(*index))
+                    .collect();
+                Some(names.join(", "))
+            }
+            _ => None,
+        })
+}
+
+/// Instructions with pool references resolved and branch targets as instruction numbers, so
+/// only changes in behaviour show up. Exception handlers follow the instructions.
+fn code_lines(class: &ClassFile, code: &Code) -> Vec<String> {
+    let pool = &class.constant_pool;
+    let this_class = class.this_class_name();
+    let instructions = match bytecode::decode(&code.code) {
+        Ok(instructions) => instructions,
+        Err(e) => return vec![format!("invalid code: {}", e)],
+    };
+    let labels: HashMap<u32, usize> = instructions
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.offset, i))
+        .collect();
+    let label = |offset: i32| match labels.get(&(offset as u32)) {
+        Some(index) => format!("L{}", index),
+        None => format!("@{}", offset),
+    };
+    let mut acc: Vec<String> = instructions
+        .iter()
+        .map(|instruction| {
+            let name = if instruction.wide {
+                format!("wide {}", instruction.mnemonic())
+            } else if instruction.opcode == LDC_W {
+                "ldc".to_string()
+            } else {
+                instruction.mnemonic().to_string()
+            };
+            let operand = match &instruction.operand {
+                Operand::None => String::new(),
+                Operand::Byte(value) => value.to_string(),
+                Operand::Short(value) => value.to_string(),
+                Operand::Local(index) => index.to_string(),
+                Operand::Branch(target) => label(*target),
+                Operand::Iinc(index, value) => format!("{}, {}", index, value),
+                Operand::ArrayType(atype) => array_type_name(*atype),
+                Operand::Constant(index)
+                | Operand::InvokeDynamic(index)
+                | Operand::InvokeInterface(index, _) => {
+                    describe_constant(pool, *index, &this_class)
+                }
+                Operand::MultiANewArray(index, dimensions) => {
+                    format!(
+                        "{}, {}",
+                        describe_constant(pool, *index, &this_class),
+                        dimensions
+                    )
+                }
+                Operand::TableSwitch(default, low, _, targets) => {
+                    let cases: Vec<String> = targets
+                        .iter()
+                        .enumerate()
+                        .map(|(i, target)| {
+                            format!("{}: {}", *low as i64 + i as i64, label(*target))
+                        })
+                        .collect();
+                    format!("{{ {}, default: {} }}", cases.join(", "), label(*default))
+                }
+                Operand::LookupSwitch(default, pairs) => {
+                    let cases: Vec<String> = pairs
+                        .iter()
+                        .map(|(key, target)| format!("{}: {}", key, label(*target)))
+                        .collect();
+                    format!("{{ {}, default: {} }}", cases.join(", "), label(*default))
+                }
+            };
+            format!("{} {}", name, operand).trim_end().to_string()
+        })
+        .collect();
+    for handler in code.exception_table.iter() {
+        let catch_type = match handler.catch_type {
+            0 => "any".to_string(),
+            index => pool.resolve(index),
+        };
+        acc.push(format!(
+            "catch {} {} to {} using {}",
+            catch_type,
+            label(handler.start_pc as i32),
+            label(handler.end_pc as i32),
+            label(handler.handler_pc as i32)
+        ));
+    }
+    acc
+}
+
+/// Line diff of the changed middle part, `-` lines are only in `old`, `+` lines only in `new`.
+fn diff_lines(old: &[String], new: &[String]) -> Vec<String> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+    let (n, m) = (old.len(), new.len());
+    if n * m > MAX_DIFF_CELLS {
+        return vec![format!("{} instructions differ", n.max(m))];
+    }
+    // Longest common subsequence table, lcs[i][j] is the length for old[i..] and new[j..].
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut acc = Vec::<String>::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            acc.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            acc.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    acc
+}
+
+/// Compares two versions of a class.
+pub fn diff(old: &ClassFile, new: &ClassFile) -> ClassDiff {
+    let mut differ = Differ {
+        old,
+        new,
+        changes: Vec::new(),
+    };
+    differ.compare_class();
+    differ.compare_fields();
+    differ.compare_methods();
+    ClassDiff {
+        class: new.this_class_name(),
+        changes: differ.changes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Change, ChangeKind};
+    use crate::assembler::assemble;
+
+    const OLD: &str = r#"
+.class public Shapes
+.field public static final SIDES I = 4
+.field private static cache Ljava/lang/Object;
+
+.method public static area(I)I
+    .limit stack 2
+    iload_0
+    iload_0
+    imul
+    ireturn
+.end method
+
+.method public static describe()Ljava/lang/String;
+    .limit stack 1
+    ldc "square"
+    areturn
+.end method
+"#;
+
+    const NEW: &str = r#"
+.class public final Shapes
+.version 52
+.field public static final SIDES I = 5
+.field public static count I
+
+.method public static area(J)J
+    .limit stack 4
+    lload_0
+    lload_0
+    lmul
+    lreturn
+.end method
+
+.method public static describe()Ljava/lang/String;
+    .limit stack 1
+    ldc "pentagon"
+    areturn
+.end method
+"#;
+
+    fn change(
+        kind: ChangeKind,
+        subject: &str,
+        property: &'static str,
+        old: Option<&str>,
+        new: Option<&str>,
+    ) -> Change {
+        Change {
+            kind,
+            subject: subject.to_string(),
+            property,
+            old: old.map(String::from),
+            new: new.map(String::from),
+            lines: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff_ignores_pool_layout() {
+        // Same class with the constant pool entries added in a different order.
+        let reordered = OLD.replace(
+            ".method public static area",
+            ".method public static unused()V\n.limit stack 1\nldc \"square\"\npop\nreturn\n.end method\n.method public static area",
+        );
+        let old = assemble(OLD).unwrap();
+        let new = assemble(&reordered).unwrap();
+        let result = diff(&old, &new);
+        assert_eq!(result.changes.len(), 1, "{:?}", result.changes);
+        assert_eq!(result.changes[0].subject, "method unused()V");
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_diff_reports_member_changes() {
+        let result = diff(&assemble(OLD).unwrap(), &assemble(NEW).unwrap());
+        let mut area = change(
+            ChangeKind::Changed,
+            "method area(J)J",
+            "code",
+            Some("4 instructions"),
+            Some("4 instructions"),
+        );
+        area.lines = [
+            "- iload_0",
+            "- iload_0",
+            "- imul",
+            "- ireturn",
+            "+ lload_0",
+            "+ lload_0",
+            "+ lmul",
+            "+ lreturn",
+        ]
+        .map(String::from)
+        .to_vec();
+        let mut describe = change(
+            ChangeKind::Changed,
+            "method describe()Ljava/lang/String;",
+            "code",
+            Some("2 instructions"),
+            Some("2 instructions"),
+        );
+        describe.lines = vec![
+            "- ldc String square".to_string(),
+            "+ ldc String pentagon".to_string(),
+        ];
+        assert_eq!(
+            result.changes,
+            vec![
+                change(
+                    ChangeKind::Changed,
+                    "class",
+                    "version",
+                    Some("49.0"),
+                    Some("52.0")
+                ),
+                change(
+                    ChangeKind::Changed,
+                    "class",
+                    "access flags",
+                    Some("public super"),
+                    Some("public final super")
+                ),
+                change(
+                    ChangeKind::Changed,
+                    "field SIDES",
+                    "constant value",
+                    Some("int 4"),
+                    Some("int 5")
+                ),
+                change(
+                    ChangeKind::Removed,
+                    "field cache",
+                    "declaration",
+                    Some("private static java.lang.Object cache"),
+                    None
+                ),
+                change(
+                    ChangeKind::Added,
+                    "field count",
+                    "declaration",
+                    None,
+                    Some("public static int count")
+                ),
+                change(
+                    ChangeKind::Changed,
+                    "method area(I)I",
+                    "descriptor",
+                    Some("(I)I"),
+                    Some("(J)J")
+                ),
+                area,
+                describe,
+            ]
+        );
+        assert_eq!(
+            result.changes[1].to_string(),
+            "~ class access flags: public super -> public final super"
+        );
+        assert_eq!(
+            result.changes[7].to_string(),
+            "~ method describe()Ljava/lang/String; code: 2 instructions -> 2 instructions\n    - ldc String square\n    + ldc String pentagon"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    Io(String),
+    Truncated,
+    BadMagic(u32),
+    UnknownPoolTag(u8),
+    InvalidPoolIndex(u16),
+    WrongPoolEntry(u16, &'static str, &'static str), // index, expected, found
+    BadUtf8,
+    InvalidOpcode(u8),
+    Malformed(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(msg) => write!(f, "i/o error: {}", msg),
+            Self::Truncated => write!(f, "unexpected end of class file"),
+            Self::BadMagic(magic) => write!(f, "bad magic 0x{:08X}, expected 0xCAFEBABE", magic),
+            Self::UnknownPoolTag(tag) => write!(f, "unknown constant pool tag {}", tag),
+            Self::InvalidPoolIndex(index) => write!(f, "invalid constant pool index #{}", index),
+            Self::WrongPoolEntry(index, expected, found) => write!(
+                f,
+                "constant pool entry #{} is {}, expected {}",
+                index, found, expected
+            ),
+            Self::BadUtf8 => write!(f, "malformed modified UTF-8 string"),
+            Self::InvalidOpcode(opcode) => write!(f, "invalid opcode 0x{:02x}", opcode),
+            Self::Malformed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Error raised while parsing a class file: where it happened, inside which structure and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub offset: u64,
+    pub context: Vec<String>, // outermost structure first
+    pub kind: ErrorKind,
+}
+
+impl ParseError {
+    pub fn new(offset: u64, kind: ErrorKind) -> Self {
+        ParseError {
+            offset,
+            context: Vec::new(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)?;
+        if !self.context.is_empty() {
+            write!(f, " (in {})", self.context.join(" > "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {}
+
+/// Annotates a parse result with the structure being parsed.
+pub(crate) trait Context<T> {
+    fn context(self, structure: &str) -> Result<T, ParseError>;
+    fn with_context<F: FnOnce() -> String>(self, structure: F) -> Result<T, ParseError>;
+}
+
+impl<T> Context<T> for Result<T, ParseError> {
+    fn context(self, structure: &str) -> Result<T, ParseError> {
+        self.with_context(|| structure.to_string())
+    }
+
+    fn with_context<F: FnOnce() -> String>(self, structure: F) -> Result<T, ParseError> {
+        self.map_err(|mut e| {
+            e.context.insert(0, structure());
+            e
+        })
+    }
+}
@@ -0,0 +1,463 @@
+// Commands of the interactive `explore` mode. Every command renders to text so the same code
+// serves the prompt and the tests, the line editor and its history live in the binary.
+
+use std::fmt::Write;
+
+use crate::attribute::AttributeInfo;
+use crate::bytecode::{self, Operand};
+use crate::classfile::{ClassFile, Member};
+use crate::declaration;
+use crate::feature;
+use crate::pool::{ConstantPool, ConstantPoolItem};
+
+/// Command names with their arguments and a short description, as listed by `help`.
+pub const COMMANDS: [(&str, &str, &str); 11] = [
+    ("help", "", "list the commands"),
+    ("info", "", "version, flags, super class and member counts"),
+    (
+        "pool",
+        "[index]",
+        "constant pool, or one entry and the code using it",
+    ),
+    ("fields", "", "field declarations"),
+    ("methods", "", "method declarations"),
+    (
+        "field",
+        "<name>",
+        "flags, descriptor and attributes of a field",
+    ),
+    (
+        "method",
+        "<name>",
+        "flags, descriptor and attributes of a method",
+    ),
+    ("code", "<name>", "disassembled code of a method"),
+    (
+        "xref",
+        "<class>[.member]",
+        "code and members that refer to a class or member",
+    ),
+    (
+        "attrs",
+        "[member]",
+        "attributes of the class or of a member",
+    ),
+    ("quit", "", "leave the explorer"),
+];
+
+pub struct Explorer {
+    class: ClassFile,
+    this_class: String,
+}
+
+/// `java.io.PrintStream`, `java/io/PrintStream.println` or `Main.run` as (internal class name,
+/// member). Dotted names are classes if `is_class` knows them, the last part is a member otherwise.
+fn split_target<F: Fn(&str) -> bool>(target: &str, is_class: F) -> (String, Option<&str>) {
+    let class = target.replace('.', "/");
+    if is_class(&class) {
+        return (class, None);
+    }
+    let start = target.rfind('/').map_or(0, |slash| slash + 1);
+    let split = if target.contains('/') {
+        target[start..].find('.').map(|dot| start + dot)
+    } else {
+        target.rfind('.')
+    };
+    match split {
+        Some(dot) => (target[..dot].replace('.', "/"), Some(&target[dot + 1..])),
+        None => (class, None),
+    }
+}
+
+fn mentions(descriptor: &str, class: &str) -> bool {
+    descriptor.contains(&format!("L{};", class))
+}
+
+fn attribute_lines(items: &[AttributeInfo], pool: &ConstantPool, acc: &mut Vec<String>) {
+    for (i, item) in items.iter().enumerate() {
+        acc.push(format!(
+            "{:02} {} => {}",
+            i,
+            item.attribute.name(),
+            item.attribute.resolve(pool)
+        ));
+        for line in item.attribute.details(pool) {
+            acc.push(format!("     {}", line));
+        }
+    }
+}
+
+impl Explorer {
+    pub fn new(class: ClassFile) -> Self {
+        let this_class = class.this_class_name();
+        Explorer { class, this_class }
+    }
+
+    pub fn class(&self) -> &ClassFile {
+        &self.class
+    }
+
+    /// Runs one command line and returns its output, errors are messages for the prompt.
+    pub fn execute(&self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(String::new());
+        };
+        let argument = words.next();
+        let lines = match (command, argument) {
+            ("help", _) => self.help(),
+            ("info", _) => self.info(),
+            ("pool", None) => self.pool(),
+            ("pool", Some(index)) => {
+                let index = index
+                    .trim_start_matches('#')
+                    .parse::<u16>()
+                    .map_err(|_| format!("not a constant pool index: {}", index))?;
+                self.pool_entry(index)?
+            }
+            ("fields", _) => self.declarations(&self.class.fields),
+            ("methods", _) => self.declarations(&self.class.methods),
+            ("field", Some(name)) => self.members(&self.class.fields, "field", name)?,
+            ("method", Some(name)) => self.members(&self.class.methods, "method", name)?,
+            ("code", Some(name)) => self.code(name)?,
+            ("xref", Some(target)) => self.xref(target),
+            ("attrs", None) => {
+                let mut acc = Vec::new();
+                attribute_lines(&self.class.attributes, &self.class.constant_pool, &mut acc);
+                acc
+            }
+            ("attrs", Some(name)) => self.member_attributes(name)?,
+            ("field" | "method" | "code" | "xref", None) => {
+                return Err(format!("{} needs an argument, see help", command))
+            }
+            _ => return Err(format!("unknown command: {}, see help", command)),
+        };
+        let mut acc = String::new();
+        for line in lines {
+            let _ = writeln!(acc, "{}", line);
+        }
+        Ok(acc)
+    }
+
+    /// Completion candidates for the last word of `line` and the position where that word starts.
+    /// The first word completes to commands, the argument to member or class names.
+    pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let prefix = &line[start..];
+        let candidates: Vec<String> = match line[..start].split_whitespace().next() {
+            None => COMMANDS
+                .iter()
+                .map(|(name, _, _)| name.to_string())
+                .collect(),
+            Some(_) if line[..start].split_whitespace().count() > 1 => Vec::new(),
+            Some("method" | "code") => self.names(&self.class.methods),
+            Some("field") => self.names(&self.class.fields),
+            Some("attrs") => {
+                let mut acc = self.names(&self.class.fields);
+                acc.extend(self.names(&self.class.methods));
+                acc
+            }
+            Some("xref") => {
+                let pool = &self.class.constant_pool;
+                pool.iter()
+                    .filter_map(|(index, item)| match item {
+                        ConstantPoolItem::Class(_) => pool.get_class_name(index).ok(),
+                        _ => None,
+                    })
+                    .map(str::to_string)
+                    .collect()
+            }
+            Some(_) => Vec::new(),
+        };
+        let mut candidates: Vec<String> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        (start, candidates)
+    }
+
+    fn names(&self, members: &[Member]) -> Vec<String> {
+        let pool = &self.class.constant_pool;
+        members.iter().map(|member| member.name(pool)).collect()
+    }
+
+    fn find<'a>(&self, members: &'a [Member], name: &str) -> Vec<&'a Member> {
+        let pool = &self.class.constant_pool;
+        members
+            .iter()
+            .filter(|member| member.name(pool) == name)
+            .collect()
+    }
+
+    fn help(&self) -> Vec<String> {
+        COMMANDS
+            .iter()
+            .map(|(name, arguments, about)| {
+                format!("{:<24} {}", format!("{} {}", name, arguments), about)
+            })
+            .collect()
+    }
+
+    fn info(&self) -> Vec<String> {
+        let class = &self.class;
+        let mut acc = vec![declaration::class_declaration(class)];
+        let mut version = format!(
+            "version {}.{} ({})",
+            class.header.major,
+            class.header.minor,
+            class.header.release_name()
+        );
+        let features: Vec<&str> = feature::detect(class)
+            .iter()
+            .map(|item| item.feature.name())
+            .collect();
+        if !features.is_empty() {
+            version.push_str(&format!(", features: {}", features.join(", ")));
+        }
+        acc.push(version);
+        acc.push(format!(
+            "{} pool entries, {} fields, {} methods, {} attributes",
+            class.constant_pool.len(),
+            class.fields.len(),
+            class.methods.len(),
+            class.attributes.len()
+        ));
+        acc
+    }
+
+    fn pool(&self) -> Vec<String> {
+        let pool = &self.class.constant_pool;
+        pool.iter()
+            .map(|(index, item)| format!("#{:<4} {} = {}", index, item, item.resolve(pool)))
+            .collect()
+    }
+
+    fn pool_entry(&self, index: u16) -> Result<Vec<String>, String> {
+        let pool = &self.class.constant_pool;
+        let item = pool.get(index).map_err(|e| e.to_string())?;
+        let mut acc = vec![
+            format!("#{} {}", index, item),
+            format!("    {}", item.resolve(pool)),
+        ];
+        self.code_references(|operand| operand == index, &mut acc);
+        Ok(acc)
+    }
+
+    fn declarations(&self, members: &[Member]) -> Vec<String> {
+        members
+            .iter()
+            .enumerate()
+            .map(|(i, member)| format!("{:02} {}", i, self.declaration(member)))
+            .collect()
+    }
+
+    fn declaration(&self, member: &Member) -> String {
+        if self
+            .class
+            .fields
+            .iter()
+            .any(|field| std::ptr::eq(field, member))
+        {
+            declaration::field_declaration(&self.class, member)
+        } else {
+            declaration::method_declaration(&self.class, member)
+        }
+    }
+
+    fn members(&self, members: &[Member], kind: &str, name: &str) -> Result<Vec<String>, String> {
+        let pool = &self.class.constant_pool;
+        let found = self.find(members, name);
+        if found.is_empty() {
+            return Err(format!("no {} named {}", kind, name));
+        }
+        let mut acc = Vec::new();
+        for member in found {
+            acc.push(self.declaration(member));
+            acc.push(format!("  descriptor: {}", member.descriptor(pool)));
+            acc.push(format!("  flags: {}", member.access_flags));
+            if let Some(signature) = member.signature(pool) {
+                acc.push(format!("  signature: {}", signature));
+            }
+            let mut attributes = Vec::new();
+            attribute_lines(&member.attributes, pool, &mut attributes);
+            acc.extend(attributes.into_iter().map(|line| format!("  {}", line)));
+        }
+        Ok(acc)
+    }
+
+    fn member_attributes(&self, name: &str) -> Result<Vec<String>, String> {
+        let pool = &self.class.constant_pool;
+        let mut found = self.find(&self.class.fields, name);
+        found.extend(self.find(&self.class.methods, name));
+        if found.is_empty() {
+            return Err(format!("no field or method named {}", name));
+        }
+        let mut acc = Vec::new();
+        for member in found {
+            acc.push(format!("{}{}:", member.name(pool), member.descriptor(pool)));
+            attribute_lines(&member.attributes, pool, &mut acc);
+        }
+        Ok(acc)
+    }
+
+    fn code(&self, name: &str) -> Result<Vec<String>, String> {
+        let pool = &self.class.constant_pool;
+        let found = self.find(&self.class.methods, name);
+        if found.is_empty() {
+            return Err(format!("no method named {}", name));
+        }
+        let mut acc = Vec::new();
+        for method in found {
+            acc.push(format!("{}{}", method.name(pool), method.descriptor(pool)));
+            match method.code() {
+                Some(code) => acc.extend(bytecode::code_lines(code, pool, &self.this_class)),
+                None => acc.push("    no code".to_string()),
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Appends the instructions whose pool operand matches, prefixed with their method.
+    fn code_references<F: Fn(u16) -> bool>(&self, matches: F, acc: &mut Vec<String>) {
+        let pool = &self.class.constant_pool;
+        for method in self.class.methods.iter() {
+            let Some(code) = method.code() else {
+                continue;
+            };
+            let Ok(instructions) = bytecode::decode(&code.code) else {
+                continue;
+            };
+            for instruction in instructions.iter() {
+                let index = match instruction.operand {
+                    Operand::Constant(index)
+                    | Operand::InvokeDynamic(index)
+                    | Operand::InvokeInterface(index, _)
+                    | Operand::MultiANewArray(index, _) => index,
+                    _ => continue,
+                };
+                if matches(index) {
+                    acc.push(format!(
+                        "{}{} {:>5}: {} {}",
+                        method.name(pool),
+                        method.descriptor(pool),
+                        instruction.offset,
+                        instruction.mnemonic(),
+                        bytecode::describe_constant(pool, index, &self.this_class)
+                    ));
+                }
+            }
+        }
+    }
+
+    fn xref(&self, target: &str) -> Vec<String> {
+        let pool = &self.class.constant_pool;
+        let (class, member) = split_target(target, |name| {
+            name == self.this_class
+                || pool
+                    .iter()
+                    .any(|(index, _)| pool.get_class_name(index).is_ok_and(|item| item == name))
+        });
+        let refers = |index: u16| match pool.get(index) {
+            Ok(ConstantPoolItem::Class(_)) => {
+                let name = pool.get_class_name(index).unwrap_or_default();
+                member.is_none() && (name == class || mentions(name, &class))
+            }
+            Ok(
+                ConstantPoolItem::Field(owner, nat)
+                | ConstantPoolItem::Method(owner, nat)
+                | ConstantPoolItem::InterfaceMethod(owner, nat),
+            ) => {
+                let owner = pool.get_class_name(*owner).unwrap_or_default();
+                let (name, descriptor) = pool.get_name_and_type(*nat).unwrap_or_default();
+                match member {
+                    Some(member) => owner == class && name == member,
+                    None => owner == class || mentions(descriptor, &class),
+                }
+            }
+            _ => false,
+        };
+        let mut acc = Vec::new();
+        if member.is_none() {
+            for member in self.class.fields.iter().chain(self.class.methods.iter()) {
+                if mentions(&member.descriptor(pool), &class) {
+                    acc.push(format!("declared {}", self.declaration(member)));
+                }
+            }
+        }
+        self.code_references(refers, &mut acc);
+        acc.push(format!("{} references", acc.len()));
+        acc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{split_target, Explorer};
+    use crate::classfile::ClassFile;
+
+    fn calc() -> Explorer {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/Calc.class");
+        Explorer::new(ClassFile::parse(fs::read(path).unwrap().as_slice()).unwrap())
+    }
+
+    #[test]
+    fn test_explorer_commands() {
+        let explorer = calc();
+        let output = explorer.execute("method fib").unwrap();
+        assert!(output.starts_with("static int fib(int)\n  descriptor: (I)I\n"));
+        assert!(output.contains("Code => "));
+
+        let output = explorer.execute("code divide").unwrap();
+        assert!(output.contains("idiv"));
+        assert!(output.contains("Class java/lang/ArithmeticException"));
+
+        let output = explorer.execute("xref java.io.PrintStream").unwrap();
+        assert!(output.contains("main([Ljava/lang/String;)V"));
+        assert!(output.contains("getstatic Field java/lang/System.out:Ljava/io/PrintStream;"));
+        let output = explorer.execute("xref Calc.fib").unwrap();
+        assert!(output.starts_with("fib(I)I    20: invokestatic Method fib:(I)I\n"));
+        assert!(output.ends_with("\n3 references\n"));
+
+        let output = explorer.execute("pool 1").unwrap();
+        assert!(output.starts_with("#1 Method => ClassIndex: 2, NatIndex: 3\n"));
+        assert!(output.contains("<init>()V     1: invokespecial Method java/lang/Object"));
+        assert!(explorer
+            .execute("attrs")
+            .unwrap()
+            .contains("01 InnerClasses => Classes: 1"));
+        assert_eq!(
+            explorer.execute("method nope").unwrap_err(),
+            "no method named nope"
+        );
+        assert!(explorer.execute("frobnicate").is_err());
+        let known = |name: &str| name == "a/B";
+        assert_eq!(split_target("a/B.c", known), ("a/B".to_string(), Some("c")));
+        assert_eq!(split_target("a.B", known), ("a/B".to_string(), None));
+        assert_eq!(split_target("a.B.c", known), ("a/B".to_string(), Some("c")));
+    }
+
+    #[test]
+    fn test_explorer_completion() {
+        let explorer = calc();
+        assert_eq!(
+            explorer.complete("me"),
+            (0, vec!["method".to_string(), "methods".to_string()])
+        );
+        assert_eq!(
+            explorer.complete("code f"),
+            (5, vec!["factorial".to_string(), "fib".to_string()])
+        );
+        assert_eq!(
+            explorer.complete("field s"),
+            (6, vec!["squares".to_string()])
+        );
+        let (_, classes) = explorer.complete("xref java/io/");
+        assert_eq!(classes, ["java/io/PrintStream"]);
+        assert!(explorer.complete("pool 1").1.is_empty());
+    }
+}
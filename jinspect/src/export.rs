@@ -0,0 +1,1148 @@
+// Serialisable view of a parsed class file, used by the `--format json|yaml` output modes.
+// Pool references are resolved to their values so consumers do not have to walk the pool,
+// the raw indexes are kept next to them. The layout is documented in the README and only
+// changes together with `SCHEMA_VERSION`.
+
+use serde::Serialize;
+
+use crate::annotation::{Annotation, ElementValue, TypeAnnotation};
+use crate::attribute::{
+    Attribute, AttributeInfo, Code, Exports, Module, StackMapFrame, VerificationType,
+};
+use crate::bytecode::{self, Instruction, Operand};
+use crate::classfile::{ClassFile, Member};
+use crate::feature;
+use crate::flag::AccessFlag;
+use crate::literal;
+use crate::pool::{self, ConstantPool, ConstantPoolItem};
+
+pub const SCHEMA_VERSION: u32 = 4;
+
+#[derive(Debug, Serialize)]
+pub struct ClassDocument {
+    pub schema_version: u32,
+    pub version: VersionDocument,
+    pub access_flags: Vec<&'static str>,
+    pub this_class: String,
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub constant_pool: Vec<PoolEntryDocument>,
+    pub fields: Vec<MemberDocument>,
+    pub methods: Vec<MemberDocument>,
+    pub attributes: Vec<AttributeDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VersionDocument {
+    pub major: u16,
+    pub minor: u16,
+    pub release: String,
+    pub features: Vec<FeatureDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureDocument {
+    pub name: &'static str,
+    pub since: u16, // major version
+    pub allowed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolEntryDocument {
+    pub index: u16,
+    #[serde(flatten)]
+    pub entry: PoolValueDocument,
+}
+
+/// One constant pool entry, `kind` uses the JVMS tag names.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum PoolValueDocument {
+    Utf8 {
+        value: String,
+    },
+    Integer {
+        value: i32,
+    },
+    // Floating point values are written the way Java prints them, so NaN and the
+    // infinities survive formats that have no notation for them.
+    Float {
+        value: String,
+    },
+    Long {
+        value: i64,
+    },
+    Double {
+        value: String,
+    },
+    Class {
+        name_index: u16,
+        name: String,
+    },
+    String {
+        string_index: u16,
+        value: String,
+    },
+    Fieldref(MemberRefDocument),
+    Methodref(MemberRefDocument),
+    InterfaceMethodref(MemberRefDocument),
+    NameAndType {
+        name_index: u16,
+        descriptor_index: u16,
+        name: String,
+        descriptor: String,
+    },
+    MethodHandle {
+        reference_kind: &'static str,
+        reference_index: u16,
+        reference: String,
+    },
+    MethodType {
+        descriptor_index: u16,
+        descriptor: String,
+    },
+    Dynamic(DynamicDocument),
+    InvokeDynamic(DynamicDocument),
+    Module {
+        name_index: u16,
+        name: String,
+    },
+    Package {
+        name_index: u16,
+        name: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberRefDocument {
+    pub class_index: u16,
+    pub name_and_type_index: u16,
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DynamicDocument {
+    pub bootstrap_method_attr_index: u16,
+    pub name_and_type_index: u16,
+    pub name: String,
+    pub descriptor: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberDocument {
+    pub access_flags: Vec<&'static str>,
+    pub name: String,
+    pub descriptor: String,
+    pub attributes: Vec<AttributeDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttributeDocument {
+    pub name: String,
+    #[serde(flatten)]
+    pub body: AttributeBodyDocument,
+}
+
+/// Decoded attribute body, its fields are merged into the enclosing `AttributeDocument`.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum AttributeBodyDocument {
+    Code(CodeDocument),
+    // ConstantValue, Signature, SourceFile, NestHost and ModuleMainClass
+    Value {
+        index: u16,
+        value: String,
+    },
+    // Exceptions, NestMembers and PermittedSubclasses
+    Classes {
+        classes: Vec<String>,
+    },
+    InnerClasses {
+        classes: Vec<InnerClassDocument>,
+    },
+    EnclosingMethod {
+        class: String,
+        method: Option<String>,
+    },
+    LineNumberTable {
+        lines: Vec<LineNumberDocument>,
+    },
+    LocalVariableTable {
+        variables: Vec<LocalVariableDocument>,
+    },
+    StackMapTable {
+        frames: Vec<FrameDocument>,
+    },
+    BootstrapMethods {
+        methods: Vec<BootstrapMethodDocument>,
+    },
+    Record {
+        components: Vec<RecordComponentDocument>,
+    },
+    // Runtime(In)VisibleAnnotations
+    Annotations {
+        annotations: Vec<AnnotationDocument>,
+    },
+    // Runtime(In)VisibleParameterAnnotations
+    ParameterAnnotations {
+        parameters: Vec<Vec<AnnotationDocument>>,
+    },
+    // Runtime(In)VisibleTypeAnnotations
+    TypeAnnotations {
+        type_annotations: Vec<TypeAnnotationDocument>,
+    },
+    AnnotationDefault {
+        default_value: ElementValueDocument,
+    },
+    Module(ModuleDocument),
+    ModulePackages {
+        packages: Vec<String>,
+    },
+    Unknown {
+        length: usize,
+        bytes: String, // lower case hex
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct CodeDocument {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code_length: usize,
+    pub instructions: Vec<InstructionDocument>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decode_error: Option<String>,
+    pub exception_table: Vec<ExceptionHandlerDocument>,
+    pub attributes: Vec<AttributeDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstructionDocument {
+    pub offset: u32,
+    pub mnemonic: &'static str,
+    #[serde(skip_serializing_if = "is_false")]
+    pub wide: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub operands: Vec<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub switch: Option<SwitchDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SwitchDocument {
+    pub default: i32,
+    pub cases: Vec<SwitchCaseDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SwitchCaseDocument {
+    pub key: i32,
+    pub target: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExceptionHandlerDocument {
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    pub catch_type: Option<String>, // None catches everything
+}
+
+#[derive(Debug, Serialize)]
+pub struct InnerClassDocument {
+    pub inner_class: String,
+    pub outer_class: Option<String>,
+    pub inner_name: Option<String>,
+    pub access_flags: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LineNumberDocument {
+    pub start_pc: u16,
+    pub line_number: u16,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LocalVariableDocument {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+    pub name: String,
+    pub descriptor: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FrameDocument {
+    pub frame_type: &'static str,
+    pub offset_delta: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chopped: Option<u8>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub locals: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stack: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BootstrapMethodDocument {
+    pub method_ref: String,
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordComponentDocument {
+    pub name: String,
+    pub descriptor: String,
+    pub attributes: Vec<AttributeDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleDocument {
+    pub module: String,
+    pub module_flags: Vec<&'static str>,
+    pub module_version: Option<String>,
+    pub requires: Vec<RequiresDocument>,
+    pub exports: Vec<ExportsDocument>,
+    pub opens: Vec<ExportsDocument>,
+    pub uses: Vec<String>,
+    pub provides: Vec<ProvidesDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequiresDocument {
+    pub module: String,
+    pub flags: Vec<&'static str>,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportsDocument {
+    pub package: String,
+    pub flags: Vec<&'static str>,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProvidesDocument {
+    pub service: String,
+    pub with: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnnotationDocument {
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub elements: Vec<ElementPairDocument>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ElementPairDocument {
+    pub name: String,
+    pub value: ElementValueDocument,
+}
+
+/// An element value, `kind` is `const`, `enum`, `class`, `annotation` or `array`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ElementValueDocument {
+    Const {
+        tag: String,
+        value: String, // source form, e.g. `'a'` or `10L`
+    },
+    Enum {
+        #[serde(rename = "type")]
+        type_name: String,
+        constant: String,
+    },
+    Class {
+        descriptor: String,
+    },
+    Annotation(AnnotationDocument),
+    Array {
+        values: Vec<ElementValueDocument>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct TypeAnnotationDocument {
+    pub target: String,
+    pub location: Vec<String>,
+    pub annotation: AnnotationDocument,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn optional_resolve(pool: &ConstantPool, index: u16) -> Option<String> {
+    if index == 0 {
+        None
+    } else {
+        Some(pool.resolve(index))
+    }
+}
+
+fn name_and_type(pool: &ConstantPool, index: u16) -> (String, String) {
+    match pool.get(index) {
+        Ok(ConstantPoolItem::NameAndType(name, desc)) => (pool.resolve(*name), pool.resolve(*desc)),
+        _ => (pool.resolve(index), String::new()),
+    }
+}
+
+fn member_ref(pool: &ConstantPool, class_index: u16, nat_index: u16) -> MemberRefDocument {
+    let (name, descriptor) = name_and_type(pool, nat_index);
+    MemberRefDocument {
+        class_index,
+        name_and_type_index: nat_index,
+        class: pool.resolve(class_index),
+        name,
+        descriptor,
+    }
+}
+
+fn dynamic(pool: &ConstantPool, bsm_index: u16, nat_index: u16) -> DynamicDocument {
+    let (name, descriptor) = name_and_type(pool, nat_index);
+    DynamicDocument {
+        bootstrap_method_attr_index: bsm_index,
+        name_and_type_index: nat_index,
+        name,
+        descriptor,
+    }
+}
+
+fn pool_value(pool: &ConstantPool, item: &ConstantPoolItem) -> Option<PoolValueDocument> {
+    let value = match item {
+        ConstantPoolItem::Utf8(value) => PoolValueDocument::Utf8 {
+            value: value.clone(),
+        },
+        ConstantPoolItem::Integer(value) => PoolValueDocument::Integer { value: *value },
+        ConstantPoolItem::Float(value) => PoolValueDocument::Float {
+            value: literal::float_to_string(*value),
+        },
+        ConstantPoolItem::Long(value) => PoolValueDocument::Long { value: *value },
+        ConstantPoolItem::Double(value) => PoolValueDocument::Double {
+            value: literal::double_to_string(*value),
+        },
+        ConstantPoolItem::Class(index) => PoolValueDocument::Class {
+            name_index: *index,
+            name: pool.resolve(*index),
+        },
+        ConstantPoolItem::String(index) => PoolValueDocument::String {
+            string_index: *index,
+            value: pool.resolve(*index),
+        },
+        ConstantPoolItem::Field(class, nat) => {
+            PoolValueDocument::Fieldref(member_ref(pool, *class, *nat))
+        }
+        ConstantPoolItem::Method(class, nat) => {
+            PoolValueDocument::Methodref(member_ref(pool, *class, *nat))
+        }
+        ConstantPoolItem::InterfaceMethod(class, nat) => {
+            PoolValueDocument::InterfaceMethodref(member_ref(pool, *class, *nat))
+        }
+        ConstantPoolItem::NameAndType(name, desc) => PoolValueDocument::NameAndType {
+            name_index: *name,
+            descriptor_index: *desc,
+            name: pool.resolve(*name),
+            descriptor: pool.resolve(*desc),
+        },
+        ConstantPoolItem::MethodHandle(kind, index) => PoolValueDocument::MethodHandle {
+            reference_kind: pool::reference_kind_name(*kind),
+            reference_index: *index,
+            reference: pool.resolve(*index),
+        },
+        ConstantPoolItem::MethodType(index) => PoolValueDocument::MethodType {
+            descriptor_index: *index,
+            descriptor: pool.resolve(*index),
+        },
+        ConstantPoolItem::Dynamic(bsm, nat) => {
+            PoolValueDocument::Dynamic(dynamic(pool, *bsm, *nat))
+        }
+        ConstantPoolItem::InvokeDynamic(bsm, nat) => {
+            PoolValueDocument::InvokeDynamic(dynamic(pool, *bsm, *nat))
+        }
+        ConstantPoolItem::Module(index) => PoolValueDocument::Module {
+            name_index: *index,
+            name: pool.resolve(*index),
+        },
+        ConstantPoolItem::Package(index) => PoolValueDocument::Package {
+            name_index: *index,
+            name: pool.resolve(*index),
+        },
+        ConstantPoolItem::Unusable => return None,
+    };
+    Some(value)
+}
+
+fn instruction(pool: &ConstantPool, this_class: &str, item: &Instruction) -> InstructionDocument {
+    let constant = |index: u16| Some(bytecode::describe_constant(pool, index, this_class));
+    let (operands, constant, switch) = match &item.operand {
+        Operand::None => (vec![], None, None),
+        Operand::Byte(value) => (vec![*value as i64], None, None),
+        Operand::Short(value) => (vec![*value as i64], None, None),
+        Operand::Local(index) => (vec![*index as i64], None, None),
+        Operand::Branch(target) => (vec![*target as i64], None, None),
+        Operand::Iinc(index, value) => (vec![*index as i64, *value as i64], None, None),
+        Operand::ArrayType(atype) => (vec![*atype as i64], None, None),
+        Operand::Constant(index) | Operand::InvokeDynamic(index) => {
+            (vec![*index as i64], constant(*index), None)
+        }
+        Operand::InvokeInterface(index, extra) | Operand::MultiANewArray(index, extra) => {
+            (vec![*index as i64, *extra as i64], constant(*index), None)
+        }
+        Operand::TableSwitch(default, low, high, targets) => {
+            let cases = (*low..=*high)
+                .zip(targets.iter())
+                .map(|(key, target)| SwitchCaseDocument {
+                    key,
+                    target: *target,
+                })
+                .collect();
+            let switch = SwitchDocument {
+                default: *default,
+                cases,
+            };
+            (vec![], None, Some(switch))
+        }
+        Operand::LookupSwitch(default, pairs) => {
+            let cases = pairs
+                .iter()
+                .map(|(key, target)| SwitchCaseDocument {
+                    key: *key,
+                    target: *target,
+                })
+                .collect();
+            let switch = SwitchDocument {
+                default: *default,
+                cases,
+            };
+            (vec![], None, Some(switch))
+        }
+    };
+    InstructionDocument {
+        offset: item.offset,
+        mnemonic: item.mnemonic(),
+        wide: item.wide,
+        operands,
+        constant,
+        switch,
+    }
+}
+
+fn code(pool: &ConstantPool, this_class: &str, code: &Code) -> CodeDocument {
+    let (instructions, decode_error) = match bytecode::decode(&code.code) {
+        Ok(items) => (
+            items
+                .iter()
+                .map(|item| instruction(pool, this_class, item))
+                .collect(),
+            None,
+        ),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+    let exception_table = code
+        .exception_table
+        .iter()
+        .map(|h| ExceptionHandlerDocument {
+            start_pc: h.start_pc,
+            end_pc: h.end_pc,
+            handler_pc: h.handler_pc,
+            catch_type: optional_resolve(pool, h.catch_type),
+        })
+        .collect();
+    CodeDocument {
+        max_stack: code.max_stack,
+        max_locals: code.max_locals,
+        code_length: code.code.len(),
+        instructions,
+        decode_error,
+        exception_table,
+        attributes: attributes(pool, this_class, &code.attributes),
+    }
+}
+
+fn types(pool: &ConstantPool, items: &[VerificationType]) -> Vec<String> {
+    items.iter().map(|t| t.resolve(pool)).collect()
+}
+
+fn frame(pool: &ConstantPool, item: &StackMapFrame) -> FrameDocument {
+    let (chopped, locals, stack) = match item {
+        StackMapFrame::Same(_) | StackMapFrame::SameExtended(_) => (None, vec![], vec![]),
+        StackMapFrame::SameLocals1StackItem(_, stack)
+        | StackMapFrame::SameLocals1StackItemExtended(_, stack) => {
+            (None, vec![], vec![stack.resolve(pool)])
+        }
+        StackMapFrame::Chop(frame_type, _) => (Some(251 - frame_type), vec![], vec![]),
+        StackMapFrame::Append(_, locals) => (None, types(pool, locals), vec![]),
+        StackMapFrame::Full(_, locals, stack) => (None, types(pool, locals), types(pool, stack)),
+    };
+    FrameDocument {
+        frame_type: item.name(),
+        offset_delta: item.offset_delta(),
+        chopped,
+        locals,
+        stack,
+    }
+}
+
+fn annotation(pool: &ConstantPool, item: &Annotation) -> AnnotationDocument {
+    AnnotationDocument {
+        type_name: item.type_name(pool),
+        elements: item
+            .elements
+            .iter()
+            .map(|pair| ElementPairDocument {
+                name: pool.resolve(pair.name_index),
+                value: element_value(pool, &pair.value),
+            })
+            .collect(),
+    }
+}
+
+fn element_value(pool: &ConstantPool, item: &ElementValue) -> ElementValueDocument {
+    match item {
+        ElementValue::Const(tag, _) => ElementValueDocument::Const {
+            tag: (*tag as char).to_string(),
+            value: item.to_java(pool),
+        },
+        ElementValue::Enum(type_index, name_index) => ElementValueDocument::Enum {
+            type_name: pool.resolve(*type_index),
+            constant: pool.resolve(*name_index),
+        },
+        ElementValue::Class(index) => ElementValueDocument::Class {
+            descriptor: pool.resolve(*index),
+        },
+        ElementValue::Annotation(item) => ElementValueDocument::Annotation(annotation(pool, item)),
+        ElementValue::Array(values) => ElementValueDocument::Array {
+            values: values
+                .iter()
+                .map(|value| element_value(pool, value))
+                .collect(),
+        },
+    }
+}
+
+fn type_annotation(pool: &ConstantPool, item: &TypeAnnotation) -> TypeAnnotationDocument {
+    TypeAnnotationDocument {
+        target: item.target_to_string(),
+        location: item.location(),
+        annotation: annotation(pool, &item.annotation),
+    }
+}
+
+fn resolve_all(pool: &ConstantPool, items: &[u16]) -> Vec<String> {
+    items.iter().map(|index| pool.resolve(*index)).collect()
+}
+
+fn exports(pool: &ConstantPool, items: &[Exports]) -> Vec<ExportsDocument> {
+    items
+        .iter()
+        .map(|item| ExportsDocument {
+            package: pool.resolve(item.package_index),
+            flags: AccessFlag::Exports(item.flags).names(),
+            to: resolve_all(pool, &item.to),
+        })
+        .collect()
+}
+
+fn module(pool: &ConstantPool, item: &Module) -> ModuleDocument {
+    ModuleDocument {
+        module: pool.resolve(item.name_index),
+        module_flags: AccessFlag::Module(item.flags).names(),
+        module_version: optional_resolve(pool, item.version_index),
+        requires: item
+            .requires
+            .iter()
+            .map(|r| RequiresDocument {
+                module: pool.resolve(r.module_index),
+                flags: AccessFlag::Requires(r.flags).names(),
+                version: optional_resolve(pool, r.version_index),
+            })
+            .collect(),
+        exports: exports(pool, &item.exports),
+        opens: exports(pool, &item.opens),
+        uses: resolve_all(pool, &item.uses),
+        provides: item
+            .provides
+            .iter()
+            .map(|p| ProvidesDocument {
+                service: pool.resolve(p.service_index),
+                with: resolve_all(pool, &p.with),
+            })
+            .collect(),
+    }
+}
+
+fn classes(pool: &ConstantPool, items: &[u16]) -> AttributeBodyDocument {
+    AttributeBodyDocument::Classes {
+        classes: resolve_all(pool, items),
+    }
+}
+
+fn attribute_body(
+    pool: &ConstantPool,
+    this_class: &str,
+    item: &Attribute,
+) -> AttributeBodyDocument {
+    match item {
+        Attribute::Code(body) => AttributeBodyDocument::Code(code(pool, this_class, body)),
+        Attribute::ConstantValue(index)
+        | Attribute::Signature(index)
+        | Attribute::SourceFile(index)
+        | Attribute::NestHost(index)
+        | Attribute::ModuleMainClass(index) => AttributeBodyDocument::Value {
+            index: *index,
+            value: pool.resolve(*index),
+        },
+        Attribute::Exceptions(items)
+        | Attribute::NestMembers(items)
+        | Attribute::PermittedSubclasses(items) => classes(pool, items),
+        Attribute::InnerClasses(items) => AttributeBodyDocument::InnerClasses {
+            classes: items
+                .iter()
+                .map(|c| InnerClassDocument {
+                    inner_class: pool.resolve(c.inner_class_info_index),
+                    outer_class: optional_resolve(pool, c.outer_class_info_index),
+                    inner_name: optional_resolve(pool, c.inner_name_index),
+                    access_flags: AccessFlag::InnerClass(c.inner_class_access_flags).names(),
+                })
+                .collect(),
+        },
+        Attribute::EnclosingMethod(class_index, method_index) => {
+            AttributeBodyDocument::EnclosingMethod {
+                class: pool.resolve(*class_index),
+                method: optional_resolve(pool, *method_index),
+            }
+        }
+        Attribute::LineNumberTable(lines) => AttributeBodyDocument::LineNumberTable {
+            lines: lines
+                .iter()
+                .map(|l| LineNumberDocument {
+                    start_pc: l.start_pc,
+                    line_number: l.line_number,
+                })
+                .collect(),
+        },
+        Attribute::LocalVariableTable(vars) => AttributeBodyDocument::LocalVariableTable {
+            variables: vars
+                .iter()
+                .map(|v| LocalVariableDocument {
+                    start_pc: v.start_pc,
+                    length: v.length,
+                    index: v.index,
+                    name: pool.resolve(v.name_index),
+                    descriptor: pool.resolve(v.descriptor_index),
+                })
+                .collect(),
+        },
+        Attribute::StackMapTable(frames) => AttributeBodyDocument::StackMapTable {
+            frames: frames.iter().map(|f| frame(pool, f)).collect(),
+        },
+        Attribute::BootstrapMethods(methods) => AttributeBodyDocument::BootstrapMethods {
+            methods: methods
+                .iter()
+                .map(|m| BootstrapMethodDocument {
+                    method_ref: pool.resolve(m.method_ref),
+                    arguments: m.arguments.iter().map(|a| pool.resolve(*a)).collect(),
+                })
+                .collect(),
+        },
+        Attribute::Record(components) => AttributeBodyDocument::Record {
+            components: components
+                .iter()
+                .map(|c| RecordComponentDocument {
+                    name: pool.resolve(c.name_index),
+                    descriptor: pool.resolve(c.descriptor_index),
+                    attributes: attributes(pool, this_class, &c.attributes),
+                })
+                .collect(),
+        },
+        Attribute::RuntimeVisibleAnnotations(items)
+        | Attribute::RuntimeInvisibleAnnotations(items) => AttributeBodyDocument::Annotations {
+            annotations: items.iter().map(|item| annotation(pool, item)).collect(),
+        },
+        Attribute::RuntimeVisibleParameterAnnotations(parameters)
+        | Attribute::RuntimeInvisibleParameterAnnotations(parameters) => {
+            AttributeBodyDocument::ParameterAnnotations {
+                parameters: parameters
+                    .iter()
+                    .map(|items| items.iter().map(|item| annotation(pool, item)).collect())
+                    .collect(),
+            }
+        }
+        Attribute::RuntimeVisibleTypeAnnotations(items)
+        | Attribute::RuntimeInvisibleTypeAnnotations(items) => {
+            AttributeBodyDocument::TypeAnnotations {
+                type_annotations: items
+                    .iter()
+                    .map(|item| type_annotation(pool, item))
+                    .collect(),
+            }
+        }
+        Attribute::AnnotationDefault(value) => AttributeBodyDocument::AnnotationDefault {
+            default_value: element_value(pool, value),
+        },
+        Attribute::Module(item) => AttributeBodyDocument::Module(module(pool, item)),
+        Attribute::ModulePackages(items) => AttributeBodyDocument::ModulePackages {
+            packages: resolve_all(pool, items),
+        },
+        Attribute::Unknown(_, bytes) => AttributeBodyDocument::Unknown {
+            length: bytes.len(),
+            bytes: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        },
+    }
+}
+
+fn attributes(
+    pool: &ConstantPool,
+    this_class: &str,
+    items: &[AttributeInfo],
+) -> Vec<AttributeDocument> {
+    items
+        .iter()
+        .map(|item| AttributeDocument {
+            name: item.attribute.name().to_string(),
+            body: attribute_body(pool, this_class, &item.attribute),
+        })
+        .collect()
+}
+
+fn member(pool: &ConstantPool, this_class: &str, item: &Member) -> MemberDocument {
+    MemberDocument {
+        access_flags: item.access_flags.names(),
+        name: item.name(pool),
+        descriptor: item.descriptor(pool),
+        attributes: attributes(pool, this_class, &item.attributes),
+    }
+}
+
+impl ClassDocument {
+    pub fn new(class: &ClassFile) -> Self {
+        let pool = &class.constant_pool;
+        let this_class = class.this_class_name();
+        let constant_pool = pool
+            .iter()
+            .filter_map(|(index, item)| {
+                pool_value(pool, item).map(|entry| PoolEntryDocument { index, entry })
+            })
+            .collect();
+        ClassDocument {
+            schema_version: SCHEMA_VERSION,
+            version: VersionDocument {
+                major: class.header.major,
+                minor: class.header.minor,
+                release: class.header.release_name(),
+                features: feature::detect(class)
+                    .into_iter()
+                    .map(|item| FeatureDocument {
+                        name: item.feature.name(),
+                        since: item.since,
+                        allowed: item.allowed,
+                    })
+                    .collect(),
+            },
+            access_flags: class.access_flags.names(),
+            super_class: class.super_class_name(),
+            interfaces: class.interface_names(),
+            constant_pool,
+            fields: class
+                .fields
+                .iter()
+                .map(|f| member(pool, &this_class, f))
+                .collect(),
+            methods: class
+                .methods
+                .iter()
+                .map(|m| member(pool, &this_class, m))
+                .collect(),
+            attributes: attributes(pool, &this_class, &class.attributes),
+            this_class,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use serde_json::{json, Value};
+
+    use super::ClassDocument;
+    use crate::assembler::assemble;
+    use crate::attribute::{Attribute, AttributeInfo, Exports, Module, Requires};
+    use crate::flag::{AccessFlag, ACC_MODULE};
+    use crate::pool::ConstantPoolItem;
+    use crate::testdata::MINIMAL_CLASS;
+    use crate::ClassFile;
+
+    fn export(class: &ClassFile) -> Value {
+        let document = ClassDocument::new(class);
+        let value = serde_json::to_value(&document).unwrap();
+        // Both formats serialise the same document.
+        let yaml: Value = serde_yaml::from_str(&serde_yaml::to_string(&document).unwrap()).unwrap();
+        assert_eq!(yaml, value);
+        value
+    }
+
+    fn sample(name: &str) -> Value {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("samples")
+            .join(name);
+        export(&ClassFile::parse(fs::read(path).unwrap().as_slice()).unwrap())
+    }
+
+    fn member<'a>(members: &'a Value, name: &str) -> &'a Value {
+        members
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|member| member["name"] == name)
+            .unwrap()
+    }
+
+    fn keys(value: &Value) -> Vec<&str> {
+        value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    #[test]
+    fn test_document_schema() {
+        let class = ClassFile::parse(MINIMAL_CLASS.as_slice()).unwrap();
+        let value = export(&class);
+        assert_eq!(value["schema_version"], 4);
+        assert_eq!(value["version"]["major"], 52);
+        assert_eq!(value["version"]["release"], "Java 8");
+        assert_eq!(value["access_flags"], json!(["public", "super"]));
+        assert_eq!(value["this_class"], "A");
+        assert_eq!(value["super_class"], "java/lang/Object");
+        assert_eq!(
+            value["constant_pool"][1],
+            json!({"index": 2, "kind": "Class", "name_index": 1, "name": "A"})
+        );
+        assert_eq!(value["constant_pool"].as_array().unwrap().len(), 4);
+        assert!(value["methods"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_document_code_section() {
+        let value = sample("Calc.class");
+        assert_eq!(
+            keys(&value),
+            [
+                "access_flags",
+                "attributes",
+                "constant_pool",
+                "fields",
+                "interfaces",
+                "methods",
+                "schema_version",
+                "super_class",
+                "this_class",
+                "version"
+            ]
+        );
+        assert_eq!(
+            value["version"]["features"],
+            json!([{"name": "invokedynamic", "since": 51, "allowed": true}])
+        );
+        let divide = member(&value["methods"], "divide");
+        assert_eq!(divide["access_flags"], json!(["static"]));
+        let code = &divide["attributes"][0];
+        assert_eq!(
+            keys(code),
+            [
+                "attributes",
+                "code_length",
+                "exception_table",
+                "instructions",
+                "max_locals",
+                "max_stack",
+                "name"
+            ]
+        );
+        assert_eq!(code["name"], "Code");
+        assert_eq!(
+            (&code["max_stack"], &code["code_length"]),
+            (&json!(2), &json!(22))
+        );
+        assert_eq!(
+            code["instructions"][2],
+            json!({"offset": 2, "mnemonic": "idiv"})
+        );
+        assert_eq!(
+            code["instructions"][5],
+            json!({
+                "offset": 5,
+                "mnemonic": "getstatic",
+                "operands": [29],
+                "constant": "Field java/lang/System.out:Ljava/io/PrintStream;"
+            })
+        );
+        assert_eq!(
+            code["exception_table"],
+            json!([{
+                "start_pc": 0,
+                "end_pc": 3,
+                "handler_pc": 4,
+                "catch_type": "java/lang/ArithmeticException"
+            }])
+        );
+        assert_eq!(
+            code["attributes"],
+            json!([{
+                "name": "StackMapTable",
+                "frames": [{
+                    "frame_type": "same_locals_1_stack_item",
+                    "offset_delta": 4,
+                    "stack": ["java/lang/ArithmeticException"]
+                }]
+            }])
+        );
+
+        let value = sample("Sample.class");
+        let code = &member(&value["methods"], "sw")["attributes"][0];
+        assert_eq!(
+            code["instructions"][1],
+            json!({
+                "offset": 1,
+                "mnemonic": "lookupswitch",
+                "switch": {
+                    "default": 57,
+                    "cases": [
+                        {"key": 1, "target": 44},
+                        {"key": 2, "target": 47},
+                        {"key": 3, "target": 50},
+                        {"key": 100, "target": 53}
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_document_attribute_and_annotation_sections() {
+        let value = sample("Sample.class");
+        let sw = member(&value["methods"], "sw");
+        assert_eq!(
+            member(&sw["attributes"], "Exceptions"),
+            &json!({"name": "Exceptions", "classes": ["java/io/IOException"]})
+        );
+        assert_eq!(
+            member(&sw["attributes"], "Deprecated"),
+            &json!({"name": "Deprecated", "length": 0, "bytes": ""})
+        );
+        assert_eq!(
+            member(&sw["attributes"], "RuntimeVisibleAnnotations"),
+            &json!({
+                "name": "RuntimeVisibleAnnotations",
+                "annotations": [{"type": "java/lang/Deprecated", "elements": []}]
+            })
+        );
+        let inner = &member(&value["attributes"], "InnerClasses")["classes"];
+        assert_eq!(
+            inner[1],
+            json!({
+                "inner_class": "Sample$Inner",
+                "outer_class": "Sample",
+                "inner_name": "Inner",
+                "access_flags": []
+            })
+        );
+
+        let value = sample("Sample$Point.class");
+        assert_eq!(
+            member(&value["attributes"], "NestHost"),
+            &json!({"name": "NestHost", "index": 43, "value": "Sample"})
+        );
+        assert_eq!(
+            member(&value["attributes"], "Record")["components"],
+            json!([
+                {"name": "x", "descriptor": "I", "attributes": []},
+                {"name": "y", "descriptor": "I", "attributes": []}
+            ])
+        );
+        let features: Vec<&Value> = value["version"]["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|feature| &feature["name"])
+            .collect();
+        assert_eq!(features, ["invokedynamic", "nestmates", "records"]);
+    }
+
+    #[test]
+    fn test_document_module_section() {
+        let mut class = assemble(".class module-info\n.version 53\n").unwrap();
+        class.access_flags = AccessFlag::Class(ACC_MODULE);
+        let pool = &mut class.constant_pool;
+        let mut module_entry = |name: &str| {
+            let name = pool.add_utf8(name).unwrap();
+            pool.add(ConstantPoolItem::Module(name)).unwrap()
+        };
+        let name_index = module_entry("com.acme");
+        let base = module_entry("java.base");
+        let package = pool.add_utf8("com/acme/api").unwrap();
+        let package = pool.add(ConstantPoolItem::Package(package)).unwrap();
+        let module = Module {
+            name_index,
+            flags: 0,
+            version_index: pool.add_utf8("1.0").unwrap(),
+            requires: vec![Requires {
+                module_index: base,
+                flags: 0x8000,
+                version_index: 0,
+            }],
+            exports: vec![Exports {
+                package_index: package,
+                flags: 0,
+                to: vec![],
+            }],
+            opens: vec![],
+            uses: vec![],
+            provides: vec![],
+        };
+        for (name, attribute) in [
+            ("Module", Attribute::Module(module)),
+            ("ModulePackages", Attribute::ModulePackages(vec![package])),
+        ] {
+            let name_index = pool.add_utf8(name).unwrap();
+            class.attributes.push(AttributeInfo {
+                name_index,
+                attribute,
+            });
+        }
+
+        let value = export(&class);
+        assert_eq!(value["access_flags"], json!(["module"]));
+        assert_eq!(
+            value["version"]["features"],
+            json!([{"name": "modules", "since": 53, "allowed": true}])
+        );
+        assert_eq!(
+            value["attributes"],
+            json!([
+                {
+                    "name": "Module",
+                    "module": "com.acme",
+                    "module_flags": [],
+                    "module_version": "1.0",
+                    "requires": [{"module": "java.base", "flags": ["mandated"], "version": null}],
+                    "exports": [{"package": "com/acme/api", "flags": [], "to": []}],
+                    "opens": [],
+                    "uses": [],
+                    "provides": []
+                },
+                {"name": "ModulePackages", "packages": ["com/acme/api"]}
+            ])
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// Version-gated class file features. Each feature is recognised by the pool entries, attributes or
+// flags it needs and checked against the class file version: a JVM rejects pool entries it does
+// not know for the version and silently ignores attributes that are newer than the class.
+
+use crate::attribute::Attribute;
+use crate::classfile::{ClassFile, Header};
+use crate::flag::ACC_MODULE;
+use crate::pool::ConstantPoolItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Feature {
+    InvokeDynamic,   // InvokeDynamic, MethodHandle and MethodType entries
+    Modules,         // Module attribute or ACC_MODULE
+    NestMates,       // NestHost and NestMembers attributes
+    ConstantDynamic, // Dynamic entries (condy)
+    Records,         // Record attribute
+    SealedClasses,   // PermittedSubclasses attribute
+}
+
+impl Feature {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InvokeDynamic => "invokedynamic",
+            Self::Modules => "modules",
+            Self::NestMates => "nestmates",
+            Self::ConstantDynamic => "condy",
+            Self::Records => "records",
+            Self::SealedClasses => "sealed",
+        }
+    }
+
+    /// First major version where the feature is final.
+    pub fn since(&self) -> u16 {
+        match self {
+            Self::InvokeDynamic => 51,
+            Self::Modules => 53,
+            Self::NestMates | Self::ConstantDynamic => 55,
+            Self::Records => 60,
+            Self::SealedClasses => 61,
+        }
+    }
+
+    /// First major version where the feature could be used as a preview.
+    fn preview_since(&self) -> Option<u16> {
+        match self {
+            Self::Records => Some(58),
+            Self::SealedClasses => Some(59),
+            _ => None,
+        }
+    }
+
+    /// Whether a class file with `header` may use the feature.
+    pub fn allowed(&self, header: &Header) -> bool {
+        header.major >= self.since()
+            || (header.is_preview()
+                && self
+                    .preview_since()
+                    .is_some_and(|since| header.major >= since))
+    }
+}
+
+/// A feature used by a class and whether its version allows it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureUse {
+    pub feature: Feature,
+    pub since: u16,
+    pub allowed: bool,
+}
+
+/// The version-gated features a class uses, in the order of the releases that introduced them.
+pub fn detect(class: &ClassFile) -> Vec<FeatureUse> {
+    let mut found = Vec::<Feature>::new();
+    for (_, item) in class.constant_pool.iter() {
+        match item {
+            ConstantPoolItem::InvokeDynamic(..)
+            | ConstantPoolItem::MethodHandle(..)
+            | ConstantPoolItem::MethodType(..) => found.push(Feature::InvokeDynamic),
+            ConstantPoolItem::Dynamic(..) => found.push(Feature::ConstantDynamic),
+            _ => {}
+        }
+    }
+    if class.access_flags.contains(ACC_MODULE) {
+        found.push(Feature::Modules);
+    }
+    for item in class.attributes.iter() {
+        match item.attribute {
+            Attribute::Module(_) => found.push(Feature::Modules),
+            Attribute::NestHost(_) | Attribute::NestMembers(_) => found.push(Feature::NestMates),
+            Attribute::Record(_) => found.push(Feature::Records),
+            Attribute::PermittedSubclasses(_) => found.push(Feature::SealedClasses),
+            _ => {}
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+        .into_iter()
+        .map(|feature| FeatureUse {
+            feature,
+            since: feature.since(),
+            allowed: feature.allowed(&class.header),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{detect, Feature};
+    use crate::assembler::assemble;
+    use crate::classfile::{ClassFile, Header, MAGIC, PREVIEW_MINOR};
+
+    fn header(major: u16, minor: u16) -> Header {
+        Header {
+            magic: MAGIC,
+            minor,
+            major,
+        }
+    }
+
+    #[test]
+    fn test_release_names() {
+        assert_eq!(header(65, 0).release_name(), "Java 21");
+        assert_eq!(
+            header(61, PREVIEW_MINOR).release_name(),
+            "Java 17 (preview)"
+        );
+        assert_eq!(header(52, 0).release_name(), "Java 8");
+        assert_eq!(header(49, 0).release_name(), "Java 5");
+        assert_eq!(header(48, 0).release_name(), "Java 1.4");
+        assert_eq!(header(45, 3).release_name(), "Java 1.1");
+        assert_eq!(header(44, 0).release_name(), "unknown");
+
+        assert!(Feature::Records.allowed(&header(60, 0)));
+        assert!(Feature::Records.allowed(&header(58, PREVIEW_MINOR)));
+        assert!(!Feature::Records.allowed(&header(59, 0)));
+        assert!(!Feature::NestMates.allowed(&header(54, PREVIEW_MINOR)));
+    }
+
+    #[test]
+    fn test_detect_features() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/Sample$Point.class");
+        let mut class = ClassFile::parse(fs::read(path).unwrap().as_slice()).unwrap();
+        let features: Vec<(Feature, bool)> = detect(&class)
+            .iter()
+            .map(|item| (item.feature, item.allowed))
+            .collect();
+        assert_eq!(
+            features,
+            [
+                (Feature::InvokeDynamic, true),
+                (Feature::NestMates, true),
+                (Feature::Records, true)
+            ]
+        );
+
+        class.header.major = 55;
+        let features: Vec<bool> = detect(&class).iter().map(|item| item.allowed).collect();
+        assert_eq!(features, [true, true, false]);
+
+        let class = assemble(".class public A\n").unwrap();
+        assert!(detect(&class).is_empty());
+    }
+}
@@ -1,10 +1,17 @@
 use core::slice::Iter;
-use std::fs::File;
-use std::io::BufReader;
+use std::fmt;
+use std::io::Read;
 
-use crate::reader;
+use crate::error::ParseError;
+use crate::reader::ClassReader;
 
-const ACC_CLASS: [(&str, u16); 8] = [
+pub const ACC_VARARGS: u16 = 0x0080;
+pub const ACC_INTERFACE: u16 = 0x0200;
+pub const ACC_ANNOTATION: u16 = 0x2000;
+pub const ACC_ENUM: u16 = 0x4000;
+pub const ACC_MODULE: u16 = 0x8000;
+
+const ACC_CLASS: [(&str, u16); 9] = [
     ("public", 0x0001),
     ("final", 0x0010),
     ("super", 0x0020),
@@ -13,6 +20,7 @@ const ACC_CLASS: [(&str, u16); 8] = [
     ("synthetic", 0x1000),
     ("annotation", 0x2000),
     ("enum", 0x4000),
+    ("module", 0x8000),
 ];
 
 const ACC_FIELD: [(&str, u16); 9] = [
@@ -42,51 +50,138 @@ const ACC_METHOD: [(&str, u16); 12] = [
     ("synthetic", 0x1000),
 ];
 
+const ACC_INNER_CLASS: [(&str, u16); 10] = [
+    ("public", 0x0001),
+    ("private", 0x0002),
+    ("protected", 0x0004),
+    ("static", 0x0008),
+    ("final", 0x0010),
+    ("interface", 0x0200),
+    ("abstract", 0x0400),
+    ("synthetic", 0x1000),
+    ("annotation", 0x2000),
+    ("enum", 0x4000),
+];
+
+const ACC_MODULE_FLAGS: [(&str, u16); 3] = [
+    ("open", 0x0020),
+    ("synthetic", 0x1000),
+    ("mandated", 0x8000),
+];
+
+const ACC_REQUIRES: [(&str, u16); 4] = [
+    ("transitive", 0x0020),
+    ("static", 0x0040),
+    ("synthetic", 0x1000),
+    ("mandated", 0x8000),
+];
+
+const ACC_EXPORTS: [(&str, u16); 2] = [("synthetic", 0x1000), ("mandated", 0x8000)];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AccessFlag {
-    ClassLevel(u16),
-    FieldLevel(u16),
-    MethodLevel(u16),
+    Class(u16),
+    Field(u16),
+    Method(u16),
+    InnerClass(u16), // inner_class_access_flags of an InnerClasses entry
+    Module(u16),     // module_flags of a Module attribute
+    Requires(u16),   // requires_flags of a requires directive
+    Exports(u16),    // flags of an exports or opens directive
 }
 
 impl AccessFlag {
-    fn parse(reader: &mut BufReader<File>) -> u16 {
-        reader::read_u16(reader)
+    pub(crate) fn parse_class_level<R: Read>(
+        reader: &mut ClassReader<R>,
+    ) -> Result<Self, ParseError> {
+        Ok(Self::Class(reader.read_u16()?))
+    }
+
+    pub(crate) fn parse_field_level<R: Read>(
+        reader: &mut ClassReader<R>,
+    ) -> Result<Self, ParseError> {
+        Ok(Self::Field(reader.read_u16()?))
+    }
+
+    pub(crate) fn parse_method_level<R: Read>(
+        reader: &mut ClassReader<R>,
+    ) -> Result<Self, ParseError> {
+        Ok(Self::Method(reader.read_u16()?))
     }
 
-    pub fn parse_class_level(reader: &mut BufReader<File>) -> Self {
-        Self::ClassLevel(Self::parse(reader))
+    pub fn value(&self) -> u16 {
+        match self {
+            Self::Class(item)
+            | Self::Field(item)
+            | Self::Method(item)
+            | Self::InnerClass(item)
+            | Self::Module(item)
+            | Self::Requires(item)
+            | Self::Exports(item) => *item,
+        }
     }
 
-    pub fn parse_field_level(reader: &mut BufReader<File>) -> Self {
-        Self::FieldLevel(Self::parse(reader))
+    pub fn contains(&self, mask: u16) -> bool {
+        self.value() & mask == mask
     }
 
-    pub fn parse_method_level(reader: &mut BufReader<File>) -> Self {
-        Self::MethodLevel(Self::parse(reader))
+    fn table(&self) -> Iter<'static, (&'static str, u16)> {
+        match self {
+            Self::Class(_) => ACC_CLASS.iter(),
+            Self::Field(_) => ACC_FIELD.iter(),
+            Self::Method(_) => ACC_METHOD.iter(),
+            Self::InnerClass(_) => ACC_INNER_CLASS.iter(),
+            Self::Module(_) => ACC_MODULE_FLAGS.iter(),
+            Self::Requires(_) => ACC_REQUIRES.iter(),
+            Self::Exports(_) => ACC_EXPORTS.iter(),
+        }
     }
 
-    fn collect_to_string(&self, item: u16, coll: Iter<(&str, u16)>) -> String {
-        let ret: Vec<String> = coll
+    /// Names of the flags that are set, in the order the JVMS lists them.
+    pub fn names(&self) -> Vec<&'static str> {
+        let item = self.value();
+        self.table()
             .filter(|(_, mask)| item & *mask == *mask)
-            .map(|(caption, _)| caption.to_string())
-            .collect();
-        ret.join(" ")
+            .map(|(caption, _)| *caption)
+            .collect()
+    }
+
+    /// Mask of a flag name at this level, e.g. `static` is 0x0008 for fields.
+    pub fn mask(&self, name: &str) -> Option<u16> {
+        self.table()
+            .find(|(caption, _)| *caption == name)
+            .map(|(_, mask)| *mask)
     }
 
-    pub fn to_string(&self) -> String {
+    /// The same level with the given flag set.
+    pub fn with(&self, mask: u16) -> Self {
+        let item = self.value() | mask;
         match self {
-            Self::ClassLevel(item) => self.collect_to_string(*item, ACC_CLASS.iter()),
-            Self::FieldLevel(item) => self.collect_to_string(*item, ACC_FIELD.iter()),
-            Self::MethodLevel(item) => self.collect_to_string(*item, ACC_METHOD.iter()),
+            Self::Class(_) => Self::Class(item),
+            Self::Field(_) => Self::Field(item),
+            Self::Method(_) => Self::Method(item),
+            Self::InnerClass(_) => Self::InnerClass(item),
+            Self::Module(_) => Self::Module(item),
+            Self::Requires(_) => Self::Requires(item),
+            Self::Exports(_) => Self::Exports(item),
         }
     }
 
     pub fn print(&self) {
         let level = match self {
-            Self::ClassLevel(_) => "Class",
-            Self::FieldLevel(_) => "Field",
-            Self::MethodLevel(_) => "Method",
+            Self::Class(_) => "Class",
+            Self::Field(_) => "Field",
+            Self::Method(_) => "Method",
+            Self::InnerClass(_) => "InnerClass",
+            Self::Module(_) => "Module",
+            Self::Requires(_) => "Requires",
+            Self::Exports(_) => "Exports",
         };
-        println!("INFO: AccessFlag= {} => {}", level, self.to_string())
+        println!("INFO: AccessFlag= {} => {}", level, self)
+    }
+}
+
+impl fmt::Display for AccessFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names().join(" "))
     }
 }
@@ -0,0 +1,1733 @@
+// Interpreter for a subset of JVM bytecode, enough to run `static void main` of a single class:
+// int/long/float/double arithmetic, locals, control flow, arrays, static fields and static methods
+// of the same class. `System.out`/`System.err` printing and string concatenation are stubbed,
+// their output is collected so generated bytecode can be tested deterministically.
+//
+// Objects are not supported, `new`, instance fields and calls into other classes stop the
+// interpreter with a `RuntimeError`. Arithmetic and array exceptions are raised as `Throwable`
+// values and can be caught by the method's exception handlers.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::attribute::Attribute;
+use crate::bytecode::{self, Instruction, Operand};
+use crate::classfile::ClassFile;
+use crate::literal::{double_to_string, float_to_string};
+use crate::opcode::*;
+use crate::pool::ConstantPoolItem;
+use crate::signature::{self, JavaType};
+
+const ACC_STATIC: u16 = 0x0008;
+
+/// Steps executed before `run_main` gives up, see `Interpreter::with_step_limit`.
+pub const DEFAULT_STEP_LIMIT: u64 = 100_000_000;
+
+/// Array elements allocated before a `java/lang/OutOfMemoryError` is thrown, see
+/// `Interpreter::with_array_limit`.
+pub const DEFAULT_ARRAY_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Nested invocations before a `java/lang/StackOverflowError` is thrown.
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Out,
+    Err,
+}
+
+#[derive(Debug)]
+pub struct Array {
+    pub id: usize,         // allocation number, printed in place of the identity hash code
+    pub component: String, // component type descriptor, e.g. `I` or `Ljava/lang/String;`
+    pub values: Vec<Value>,
+}
+
+/// An exception raised by the interpreter, e.g. `java/lang/ArithmeticException: / by zero`.
+#[derive(Debug)]
+pub struct Throwable {
+    pub class: String,
+    pub message: Option<String>,
+}
+
+impl fmt::Display for Throwable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", signature::java_name(&self.class))?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl Throwable {
+    /// Whether a handler for `class` catches this exception.
+    fn is_instance_of(&self, class: &str) -> bool {
+        let mut current = self.class.as_str();
+        loop {
+            if current == class {
+                return true;
+            }
+            current = match current {
+                "java/lang/ArrayIndexOutOfBoundsException" => "java/lang/IndexOutOfBoundsException",
+                "java/lang/OutOfMemoryError" | "java/lang/StackOverflowError" => {
+                    "java/lang/VirtualMachineError"
+                }
+                "java/lang/VirtualMachineError" => "java/lang/Error",
+                "java/lang/Error" | "java/lang/Exception" => "java/lang/Throwable",
+                "java/lang/RuntimeException" => "java/lang/Exception",
+                "java/lang/Throwable" => return false,
+                _ => "java/lang/RuntimeException",
+            };
+        }
+    }
+}
+
+/// A value on the operand stack or in a local variable.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i32), // also boolean, byte, char and short
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Null,
+    String(Rc<str>),
+    Array(Rc<RefCell<Array>>),
+    Throwable(Rc<Throwable>),
+    PrintStream(Stream),
+}
+
+impl fmt::Display for Value {
+    /// Same output as Java's `String.valueOf`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Long(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", float_to_string(*value)),
+            Self::Double(value) => write!(f, "{}", double_to_string(*value)),
+            Self::Null => write!(f, "null"),
+            Self::String(value) => write!(f, "{}", value),
+            Self::Array(array) => {
+                let array = array.borrow();
+                let name = format!("[{}", array.component).replace('/', ".");
+                write!(f, "{}@{:x}", name, array.id)
+            }
+            Self::Throwable(throwable) => write!(f, "{}", throwable),
+            Self::PrintStream(_) => write!(f, "java.io.PrintStream"),
+        }
+    }
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Int(_) => "int",
+            Self::Long(_) => "long",
+            Self::Float(_) => "float",
+            Self::Double(_) => "double",
+            Self::Null => "null",
+            Self::String(_) => "java/lang/String",
+            Self::Array(_) => "array",
+            Self::Throwable(_) => "java/lang/Throwable",
+            Self::PrintStream(_) => "java/io/PrintStream",
+        }
+    }
+
+    fn is_category2(&self) -> bool {
+        matches!(self, Self::Long(_) | Self::Double(_))
+    }
+
+    fn is_reference(&self) -> bool {
+        !matches!(
+            self,
+            Self::Int(_) | Self::Long(_) | Self::Float(_) | Self::Double(_)
+        )
+    }
+
+    /// Default value of a field or array element with the given descriptor.
+    fn zero(descriptor: &str) -> Self {
+        match descriptor.as_bytes().first() {
+            Some(b'J') => Self::Long(0),
+            Some(b'F') => Self::Float(0.0),
+            Some(b'D') => Self::Double(0.0),
+            Some(b'L' | b'[') => Self::Null,
+            _ => Self::Int(0),
+        }
+    }
+
+    /// Reference equality, as `if_acmpeq` compares.
+    fn same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null) => true,
+            (Self::String(a), Self::String(b)) => Rc::ptr_eq(a, b),
+            (Self::Array(a), Self::Array(b)) => Rc::ptr_eq(a, b),
+            (Self::Throwable(a), Self::Throwable(b)) => Rc::ptr_eq(a, b),
+            (Self::PrintStream(a), Self::PrintStream(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Java's string conversion of a value passed as `descriptor`, e.g. an `Int` passed as
+    /// `C` is a character and as `Z` is `true` or `false`.
+    fn to_java_string(&self, descriptor: &str) -> String {
+        match (descriptor, self) {
+            ("Z", Self::Int(value)) => (*value != 0).to_string(),
+            ("C", Self::Int(value)) => char::from_u32(*value as u16 as u32)
+                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                .to_string(),
+            ("[C", Self::Array(array)) => array
+                .borrow()
+                .values
+                .iter()
+                .map(|item| item.to_java_string("C"))
+                .collect(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Failure to run a method, `offset` is `None` for errors about the method as a whole.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub method: String, // name and descriptor, e.g. `main([Ljava/lang/String;)V`
+    pub offset: Option<u32>,
+    pub message: String,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} at offset {}: {}", self.method, offset, self.message),
+            None => write!(f, "{}: {}", self.method, self.message),
+        }
+    }
+}
+
+/// Why a method did not complete normally.
+enum Abrupt {
+    Error(String),         // not yet attributed to a method and offset
+    Located(RuntimeError), // raised by a callee
+    Throw(Rc<Throwable>, Option<(String, u32)>), // exception, method and offset it was thrown at
+}
+
+impl From<String> for Abrupt {
+    fn from(message: String) -> Self {
+        Abrupt::Error(message)
+    }
+}
+
+fn throw(class: &str, message: impl Into<Option<String>>) -> Abrupt {
+    let throwable = Throwable {
+        class: class.to_string(),
+        message: message.into(),
+    };
+    Abrupt::Throw(Rc::new(throwable), None)
+}
+
+/// How execution continues after an instruction.
+enum Next {
+    Continue,
+    Jump(i32),
+    Call(usize, Vec<Value>), // static method of the class by index, and its arguments
+    Return(Option<Value>),
+}
+
+struct Frame {
+    locals: Vec<Option<Value>>, // `None` for unset slots and the second slot of longs and doubles
+    stack: Vec<Value>,
+    slots: usize, // stack depth with longs and doubles counted twice, at most `max_stack`
+    max_stack: usize,
+}
+
+impl Frame {
+    fn push(&mut self, value: Value) -> Result<(), String> {
+        let width = if value.is_category2() { 2 } else { 1 };
+        if self.slots + width > self.max_stack {
+            return Err(format!(
+                "operand stack exceeds max_stack {}",
+                self.max_stack
+            ));
+        }
+        self.slots += width;
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value, String> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| "operand stack underflow".to_string())?;
+        self.slots -= if value.is_category2() { 2 } else { 1 };
+        Ok(value)
+    }
+
+    fn clear(&mut self) {
+        self.stack.clear();
+        self.slots = 0;
+    }
+
+    fn mismatch(expected: &str, found: &Value) -> String {
+        format!(
+            "expected {} on the operand stack, found {}",
+            expected,
+            found.type_name()
+        )
+    }
+
+    fn pop_int(&mut self) -> Result<i32, String> {
+        match self.pop()? {
+            Value::Int(value) => Ok(value),
+            found => Err(Self::mismatch("int", &found)),
+        }
+    }
+
+    fn pop_long(&mut self) -> Result<i64, String> {
+        match self.pop()? {
+            Value::Long(value) => Ok(value),
+            found => Err(Self::mismatch("long", &found)),
+        }
+    }
+
+    fn pop_float(&mut self) -> Result<f32, String> {
+        match self.pop()? {
+            Value::Float(value) => Ok(value),
+            found => Err(Self::mismatch("float", &found)),
+        }
+    }
+
+    fn pop_double(&mut self) -> Result<f64, String> {
+        match self.pop()? {
+            Value::Double(value) => Ok(value),
+            found => Err(Self::mismatch("double", &found)),
+        }
+    }
+
+    fn pop_reference(&mut self) -> Result<Value, String> {
+        let found = self.pop()?;
+        if found.is_reference() {
+            Ok(found)
+        } else {
+            Err(Self::mismatch("a reference", &found))
+        }
+    }
+
+    fn pop_array(&mut self) -> Result<Rc<RefCell<Array>>, Abrupt> {
+        match self.pop()? {
+            Value::Array(array) => Ok(array),
+            Value::Null => Err(throw("java/lang/NullPointerException", None)),
+            found => Err(Self::mismatch("an array", &found).into()),
+        }
+    }
+
+    fn pop_category1(&mut self) -> Result<Value, String> {
+        let found = self.pop()?;
+        if found.is_category2() {
+            Err(Self::mismatch("a category 1 value", &found))
+        } else {
+            Ok(found)
+        }
+    }
+
+    fn push_all(&mut self, values: &[&Value]) -> Result<(), String> {
+        for value in values {
+            self.push((*value).clone())?;
+        }
+        Ok(())
+    }
+
+    fn load(&self, index: u16) -> Result<Value, String> {
+        self.locals
+            .get(index as usize)
+            .cloned()
+            .flatten()
+            .ok_or_else(|| format!("local variable {} is not set", index))
+    }
+
+    fn store(&mut self, index: u16, value: Value) -> Result<(), String> {
+        let index = index as usize;
+        let width = if value.is_category2() { 2 } else { 1 };
+        if index + width > self.locals.len() {
+            return Err(format!("local variable {} exceeds max_locals", index));
+        }
+        if index > 0
+            && self.locals[index - 1]
+                .as_ref()
+                .is_some_and(Value::is_category2)
+        {
+            self.locals[index - 1] = None;
+        }
+        if width == 2 {
+            self.locals[index + 1] = None;
+        }
+        self.locals[index] = Some(value);
+        Ok(())
+    }
+}
+
+/// Decoded code of a method, shared by all its invocations.
+struct Prepared {
+    instructions: Vec<Instruction>,
+    starts: HashMap<u32, usize>, // instruction offset => index
+}
+
+/// A method invocation on the interpreter's call stack.
+struct Activation {
+    index: usize,      // method index in the class
+    signature: String, // name and descriptor, for errors
+    prepared: Rc<Prepared>,
+    frame: Frame,
+    pc: usize, // index of the current instruction, an invoke while a callee runs
+}
+
+impl Activation {
+    fn locate(&self, offset: Option<u32>, message: String) -> Abrupt {
+        Abrupt::Located(RuntimeError {
+            method: self.signature.clone(),
+            offset,
+            message,
+        })
+    }
+
+    fn offset(&self) -> u32 {
+        self.prepared.instructions[self.pc].offset
+    }
+}
+
+fn check_index(array: &Array, index: i32) -> Result<usize, Abrupt> {
+    if index < 0 || index as usize >= array.values.len() {
+        let message = format!(
+            "Index {} out of bounds for length {}",
+            index,
+            array.values.len()
+        );
+        return Err(throw("java/lang/ArrayIndexOutOfBoundsException", message));
+    }
+    Ok(index as usize)
+}
+
+fn compare<T: PartialOrd>(a: T, b: T, nan: i32) -> i32 {
+    match a.partial_cmp(&b) {
+        Some(std::cmp::Ordering::Less) => -1,
+        Some(std::cmp::Ordering::Equal) => 0,
+        Some(std::cmp::Ordering::Greater) => 1,
+        None => nan,
+    }
+}
+
+/// Runs static methods of one class.
+pub struct Interpreter<'a> {
+    class: &'a ClassFile,
+    this_class: String,
+    statics: HashMap<String, Value>, // static fields by name
+    strings: HashMap<u16, Rc<str>>,  // interned string constants by pool index
+    prepared: HashMap<usize, Rc<Prepared>>,
+    initialized: bool,
+    stdout: String,
+    stderr: String,
+    steps: u64,
+    step_limit: u64,
+    elements: u64, // array elements allocated by the bytecode
+    element_limit: u64,
+    arrays: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(class: &'a ClassFile) -> Self {
+        let pool = &class.constant_pool;
+        let statics = class
+            .fields
+            .iter()
+            .filter(|field| field.access_flags.contains(ACC_STATIC))
+            .map(|field| {
+                let constant = field
+                    .attributes
+                    .iter()
+                    .find_map(|item| match item.attribute {
+                        Attribute::ConstantValue(index) => pool.get(index).ok(),
+                        _ => None,
+                    });
+                let value = match constant {
+                    Some(ConstantPoolItem::Integer(value)) => Value::Int(*value),
+                    Some(ConstantPoolItem::Long(value)) => Value::Long(*value),
+                    Some(ConstantPoolItem::Float(value)) => Value::Float(*value),
+                    Some(ConstantPoolItem::Double(value)) => Value::Double(*value),
+                    Some(ConstantPoolItem::String(index)) => {
+                        Value::String(Rc::from(pool.resolve(*index)))
+                    }
+                    _ => Value::zero(&field.descriptor(pool)),
+                };
+                (field.name(pool), value)
+            })
+            .collect();
+        Interpreter {
+            class,
+            this_class: class.this_class_name(),
+            statics,
+            strings: HashMap::new(),
+            prepared: HashMap::new(),
+            initialized: false,
+            stdout: String::new(),
+            stderr: String::new(),
+            steps: 0,
+            step_limit: DEFAULT_STEP_LIMIT,
+            elements: 0,
+            element_limit: DEFAULT_ARRAY_LIMIT,
+            arrays: 0,
+        }
+    }
+
+    /// Limits the number of instructions executed, to stop runaway loops.
+    pub fn with_step_limit(mut self, limit: u64) -> Self {
+        self.step_limit = limit;
+        self
+    }
+
+    /// Limits the number of array elements the bytecode allocates over the run, larger
+    /// allocations throw `java/lang/OutOfMemoryError` instead of exhausting the host.
+    pub fn with_array_limit(mut self, limit: u64) -> Self {
+        self.element_limit = limit;
+        self
+    }
+
+    /// Text printed to `System.out` so far.
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    /// Text printed to `System.err` so far.
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+
+    pub fn static_field(&self, name: &str) -> Option<&Value> {
+        self.statics.get(name)
+    }
+
+    /// Creates a new array, e.g. `new_array("I", vec![Value::Int(1)])` for `new int[] {1}`.
+    pub fn new_array(&mut self, component: &str, values: Vec<Value>) -> Value {
+        self.arrays += 1;
+        Value::Array(Rc::new(RefCell::new(Array {
+            id: self.arrays,
+            component: component.to_string(),
+            values,
+        })))
+    }
+
+    /// Runs `public static void main(String[])` with the given arguments.
+    pub fn run_main(&mut self, args: &[&str]) -> Result<(), RuntimeError> {
+        let args = args
+            .iter()
+            .map(|arg| Value::String(Rc::from(*arg)))
+            .collect();
+        let args = self.new_array("Ljava/lang/String;", args);
+        self.invoke_static("main", "([Ljava/lang/String;)V", vec![args])
+            .map(|_| ())
+    }
+
+    /// Runs a static method of the class, the class is initialized by `<clinit>` first.
+    pub fn invoke_static(
+        &mut self,
+        name: &str,
+        descriptor: &str,
+        args: Vec<Value>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let signature = format!("{}{}", name, descriptor);
+        let error = |offset: Option<u32>, message: String| RuntimeError {
+            method: signature.clone(),
+            offset,
+            message,
+        };
+        let index = self
+            .find_static(name, descriptor)
+            .ok_or_else(|| error(None, "no such static method".to_string()))?;
+        self.steps = 0;
+        let result = self.initialize().and_then(|_| self.execute(index, args));
+        match result {
+            Ok(value) => Ok(value),
+            Err(Abrupt::Error(message)) => Err(error(None, message)),
+            Err(Abrupt::Located(e)) => Err(e),
+            Err(Abrupt::Throw(throwable, origin)) => {
+                let (method, offset) = origin.unwrap_or((signature.clone(), 0));
+                Err(RuntimeError {
+                    method,
+                    offset: Some(offset),
+                    message: format!("uncaught exception {}", throwable),
+                })
+            }
+        }
+    }
+
+    fn find_static(&self, name: &str, descriptor: &str) -> Option<usize> {
+        let pool = &self.class.constant_pool;
+        self.class.methods.iter().position(|method| {
+            method.access_flags.contains(ACC_STATIC)
+                && method.name(pool) == name
+                && method.descriptor(pool) == descriptor
+        })
+    }
+
+    fn initialize(&mut self) -> Result<(), Abrupt> {
+        if self.initialized {
+            return Ok(());
+        }
+        self.initialized = true;
+        match self.find_static("<clinit>", "()V") {
+            Some(index) => self.execute(index, Vec::new()).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn prepare(&mut self, index: usize) -> Result<Rc<Prepared>, String> {
+        if let Some(prepared) = self.prepared.get(&index) {
+            return Ok(prepared.clone());
+        }
+        let code = self.class.methods[index]
+            .code()
+            .ok_or_else(|| "method has no Code attribute".to_string())?;
+        let instructions = bytecode::decode(&code.code).map_err(|e| e.to_string())?;
+        let starts = instructions
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (item.offset, i))
+            .collect();
+        let prepared = Rc::new(Prepared {
+            instructions,
+            starts,
+        });
+        self.prepared.insert(index, prepared.clone());
+        Ok(prepared)
+    }
+
+    /// Runs a method, calls to static methods of the class go on `calls` instead of the host stack.
+    fn execute(&mut self, index: usize, args: Vec<Value>) -> Result<Option<Value>, Abrupt> {
+        let mut calls = vec![self.activate(index, args)?];
+        loop {
+            let depth = calls.len();
+            let current = calls.last_mut().expect("an active method");
+            let prepared = current.prepared.clone();
+            let Some(instruction) = prepared.instructions.get(current.pc) else {
+                let message = "execution falls off the end of the code".to_string();
+                return Err(current.locate(None, message));
+            };
+            let offset = instruction.offset;
+            self.steps += 1;
+            if self.steps > self.step_limit {
+                let message = format!("step limit of {} exceeded", self.step_limit);
+                return Err(current.locate(Some(offset), message));
+            }
+            let (throwable, origin) = match self.step(&mut current.frame, instruction) {
+                Ok(Next::Continue) => {
+                    current.pc += 1;
+                    continue;
+                }
+                Ok(Next::Jump(target)) => match prepared.starts.get(&(target as u32)) {
+                    Some(target) => {
+                        current.pc = *target;
+                        continue;
+                    }
+                    None => {
+                        let message = format!("invalid branch target {}", target);
+                        return Err(current.locate(Some(offset), message));
+                    }
+                },
+                Ok(Next::Call(target, args)) => {
+                    if depth < MAX_DEPTH {
+                        let callee = self.activate(target, args)?;
+                        calls.push(callee);
+                        continue;
+                    }
+                    let throwable = Throwable {
+                        class: "java/lang/StackOverflowError".to_string(),
+                        message: None,
+                    };
+                    (Rc::new(throwable), None)
+                }
+                Ok(Next::Return(value)) => {
+                    calls.pop();
+                    let Some(caller) = calls.last_mut() else {
+                        return Ok(value);
+                    };
+                    if let Some(value) = value {
+                        if let Err(e) = caller.frame.push(value) {
+                            return Err(caller.locate(Some(caller.offset()), e));
+                        }
+                    }
+                    caller.pc += 1;
+                    continue;
+                }
+                Err(Abrupt::Error(message)) => return Err(current.locate(Some(offset), message)),
+                Err(Abrupt::Located(e)) => return Err(Abrupt::Located(e)),
+                Err(Abrupt::Throw(throwable, origin)) => (throwable, origin),
+            };
+            // Unwind to the innermost handler, callers see the exception at their invoke.
+            let origin = origin.or_else(|| Some((current.signature.clone(), offset)));
+            loop {
+                let Some(current) = calls.last_mut() else {
+                    return Err(Abrupt::Throw(throwable, origin));
+                };
+                if let Some(handler) = self.find_handler(current, &throwable) {
+                    current.frame.clear();
+                    if let Err(e) = current.frame.push(Value::Throwable(throwable)) {
+                        return Err(current.locate(Some(current.offset()), e));
+                    }
+                    current.pc = handler;
+                    break;
+                }
+                calls.pop();
+            }
+        }
+    }
+
+    /// Prepares a method and a frame with its arguments in the first locals.
+    fn activate(&mut self, index: usize, args: Vec<Value>) -> Result<Activation, Abrupt> {
+        let class = self.class;
+        let pool = &class.constant_pool;
+        let method = &class.methods[index];
+        let signature = format!("{}{}", method.name(pool), method.descriptor(pool));
+        let locate = |message: String| {
+            Abrupt::Located(RuntimeError {
+                method: signature.clone(),
+                offset: None,
+                message,
+            })
+        };
+        let prepared = self.prepare(index).map_err(locate)?;
+        let code = method.code().expect("prepared methods have code");
+        let mut frame = Frame {
+            locals: vec![None; code.max_locals as usize],
+            stack: Vec::new(),
+            slots: 0,
+            max_stack: code.max_stack as usize,
+        };
+        let mut slot = 0;
+        for arg in args {
+            let width = if arg.is_category2() { 2 } else { 1 };
+            frame.store(slot, arg).map_err(locate)?;
+            slot += width;
+        }
+        Ok(Activation {
+            index,
+            signature,
+            prepared,
+            frame,
+            pc: 0,
+        })
+    }
+
+    /// The instruction index of the handler in `activation` that catches `throwable` at the
+    /// current instruction.
+    fn find_handler(&self, activation: &Activation, throwable: &Throwable) -> Option<usize> {
+        let pool = &self.class.constant_pool;
+        let code = self.class.methods[activation.index].code()?;
+        let offset = activation.offset();
+        code.exception_table
+            .iter()
+            .find(|handler| {
+                let covers = handler.start_pc as u32 <= offset && offset < handler.end_pc as u32;
+                covers
+                    && (handler.catch_type == 0
+                        || pool
+                            .get_class_name(handler.catch_type)
+                            .is_ok_and(|name| throwable.is_instance_of(name)))
+            })
+            .and_then(|handler| {
+                activation
+                    .prepared
+                    .starts
+                    .get(&(handler.handler_pc as u32))
+                    .copied()
+            })
+    }
+
+    fn string_constant(&mut self, index: u16) -> Result<Value, String> {
+        if let Some(value) = self.strings.get(&index) {
+            return Ok(Value::String(value.clone()));
+        }
+        let value: Rc<str> = match self.class.constant_pool.get(index) {
+            Ok(ConstantPoolItem::String(utf8)) => Rc::from(self.class.constant_pool.resolve(*utf8)),
+            _ => return Err(format!("#{} is not a String constant", index)),
+        };
+        self.strings.insert(index, value.clone());
+        Ok(Value::String(value))
+    }
+
+    fn constant(&mut self, index: u16) -> Result<Value, String> {
+        match self
+            .class
+            .constant_pool
+            .get(index)
+            .map_err(|e| e.to_string())?
+        {
+            ConstantPoolItem::Integer(value) => Ok(Value::Int(*value)),
+            ConstantPoolItem::Float(value) => Ok(Value::Float(*value)),
+            ConstantPoolItem::Long(value) => Ok(Value::Long(*value)),
+            ConstantPoolItem::Double(value) => Ok(Value::Double(*value)),
+            ConstantPoolItem::String(_) => self.string_constant(index),
+            item => Err(format!("unsupported {} constant", item.kind())),
+        }
+    }
+
+    fn alloc_array(&mut self, descriptor: &str, counts: &[i32]) -> Result<Value, Abrupt> {
+        if let Some(count) = counts.iter().find(|count| **count < 0) {
+            return Err(throw(
+                "java/lang/NegativeArraySizeException",
+                count.to_string(),
+            ));
+        }
+        // Every dimension allocates the product of the counts up to it, checked before any of it.
+        let mut product = 1u64;
+        let mut total = 0u64;
+        for count in counts.iter() {
+            product = product.saturating_mul(*count as u64);
+            total = total.saturating_add(product);
+        }
+        if self.elements.saturating_add(total) > self.element_limit {
+            let message = format!("array limit of {} elements exceeded", self.element_limit);
+            return Err(throw("java/lang/OutOfMemoryError", message));
+        }
+        self.elements += total;
+        Ok(self.fill_array(descriptor, counts))
+    }
+
+    fn fill_array(&mut self, descriptor: &str, counts: &[i32]) -> Value {
+        let component = &descriptor[1..];
+        let values = if counts.len() == 1 {
+            vec![Value::zero(component); counts[0] as usize]
+        } else {
+            (0..counts[0])
+                .map(|_| self.fill_array(component, &counts[1..]))
+                .collect()
+        };
+        self.new_array(component, values)
+    }
+
+    fn field(&mut self, frame: &mut Frame, opcode: u8, index: u16) -> Result<(), String> {
+        let (class, name, descriptor) = self
+            .class
+            .constant_pool
+            .get_member_ref(index)
+            .map_err(|e| e.to_string())?;
+        match (opcode, class) {
+            (GETSTATIC, "java/lang/System") if name == "out" => {
+                frame.push(Value::PrintStream(Stream::Out))?
+            }
+            (GETSTATIC, "java/lang/System") if name == "err" => {
+                frame.push(Value::PrintStream(Stream::Err))?
+            }
+            (GETSTATIC, _) if class == self.this_class => {
+                let value = self
+                    .statics
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("no static field {}", name))?;
+                frame.push(value)?;
+            }
+            (PUTSTATIC, _) if class == self.this_class && self.statics.contains_key(name) => {
+                let value = frame.pop()?;
+                self.statics.insert(name.to_string(), value);
+            }
+            _ => {
+                return Err(format!(
+                    "unsupported {} {}.{}:{}",
+                    mnemonic(opcode).unwrap_or("?"),
+                    class,
+                    name,
+                    descriptor
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn pop_arguments(frame: &mut Frame, parameters: &[JavaType]) -> Result<Vec<Value>, String> {
+        let mut args = Vec::<Value>::with_capacity(parameters.len());
+        for _ in parameters {
+            args.push(frame.pop()?);
+        }
+        args.reverse();
+        Ok(args)
+    }
+
+    fn invoke(&mut self, frame: &mut Frame, instruction: &Instruction) -> Result<Next, Abrupt> {
+        let opcode = instruction.opcode;
+        let index = match instruction.operand {
+            Operand::Constant(index)
+            | Operand::InvokeInterface(index, _)
+            | Operand::InvokeDynamic(index) => index,
+            _ => return Err("missing method reference".to_string().into()),
+        };
+        if opcode == INVOKEDYNAMIC {
+            self.invoke_dynamic(frame, index)?;
+            return Ok(Next::Continue);
+        }
+        let (class, name, descriptor) = self
+            .class
+            .constant_pool
+            .get_member_ref(index)
+            .map_err(|e| e.to_string())?;
+        let types = signature::parse_method_descriptor(descriptor).map_err(|e| e.to_string())?;
+        if opcode == INVOKESTATIC && class == self.this_class {
+            let target = self
+                .find_static(name, descriptor)
+                .ok_or_else(|| format!("no static method {}{}", name, descriptor))?;
+            let args = Self::pop_arguments(frame, &types.parameters)?;
+            return Ok(Next::Call(target, args));
+        }
+        let args = Self::pop_arguments(frame, &types.parameters)?;
+        let describe = |i: usize| args[i].to_java_string(&types.parameters[i].descriptor());
+        match (opcode, class, name, args.len()) {
+            (INVOKESTATIC, "java/lang/String", "valueOf", 1) => {
+                frame.push(Value::String(Rc::from(describe(0))))?;
+                return Ok(Next::Continue);
+            }
+            (INVOKEVIRTUAL, "java/io/PrintStream", "println" | "print", 0 | 1) => {}
+            _ => {
+                let message = format!(
+                    "unsupported {} {}.{}{}",
+                    mnemonic(opcode).unwrap_or("?"),
+                    class,
+                    name,
+                    descriptor
+                );
+                return Err(message.into());
+            }
+        }
+        let stream = match frame.pop()? {
+            Value::PrintStream(stream) => stream,
+            Value::Null => return Err(throw("java/lang/NullPointerException", None)),
+            found => return Err(Frame::mismatch("java/io/PrintStream", &found).into()),
+        };
+        let mut text = if args.is_empty() {
+            String::new()
+        } else {
+            describe(0)
+        };
+        if name == "println" {
+            text.push('\n');
+        }
+        match stream {
+            Stream::Out => self.stdout.push_str(&text),
+            Stream::Err => self.stderr.push_str(&text),
+        }
+        Ok(Next::Continue)
+    }
+
+    /// String concatenation through `StringConcatFactory`, the only supported bootstrap method.
+    fn invoke_dynamic(&mut self, frame: &mut Frame, index: u16) -> Result<(), Abrupt> {
+        let pool = &self.class.constant_pool;
+        let (bootstrap, nat) = match pool.get(index) {
+            Ok(ConstantPoolItem::InvokeDynamic(bootstrap, nat)) => (*bootstrap, *nat),
+            _ => return Err(format!("#{} is not an InvokeDynamic constant", index).into()),
+        };
+        let (_, descriptor) = pool.get_name_and_type(nat).map_err(|e| e.to_string())?;
+        let method = self
+            .class
+            .attributes
+            .iter()
+            .find_map(|item| match &item.attribute {
+                Attribute::BootstrapMethods(methods) => methods.get(bootstrap as usize),
+                _ => None,
+            })
+            .ok_or_else(|| format!("no bootstrap method {}", bootstrap))?;
+        let factory = match pool.get(method.method_ref) {
+            Ok(ConstantPoolItem::MethodHandle(_, reference)) => {
+                pool.get_member_ref(*reference).ok()
+            }
+            _ => None,
+        };
+        let recipe = match factory {
+            Some(("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants", _)) => {
+                let recipe = method.arguments.first().copied().unwrap_or(0);
+                match pool.get(recipe) {
+                    Ok(ConstantPoolItem::String(utf8)) => pool.resolve(*utf8),
+                    _ => {
+                        return Err("makeConcatWithConstants without a recipe"
+                            .to_string()
+                            .into())
+                    }
+                }
+            }
+            Some(("java/lang/invoke/StringConcatFactory", "makeConcat", _)) => String::new(),
+            _ => {
+                let message = format!(
+                    "unsupported invokedynamic {}",
+                    pool.resolve(method.method_ref)
+                );
+                return Err(message.into());
+            }
+        };
+        let types = signature::parse_method_descriptor(descriptor).map_err(|e| e.to_string())?;
+        let args = Self::pop_arguments(frame, &types.parameters)?;
+        let mut args = args
+            .iter()
+            .zip(types.parameters.iter())
+            .map(|(arg, parameter)| arg.to_java_string(&parameter.descriptor()));
+        let mut constants = method.arguments.iter().skip(1);
+        let mut text = String::new();
+        if recipe.is_empty() {
+            text.extend(args);
+        } else {
+            for c in recipe.chars() {
+                match c {
+                    '\u{1}' => text.push_str(&args.next().unwrap_or_default()),
+                    '\u{2}' => {
+                        let constant = constants.next().copied().unwrap_or(0);
+                        let value = self.constant(constant)?;
+                        text.push_str(&value.to_string());
+                    }
+                    c => text.push(c),
+                }
+            }
+        }
+        frame.push(Value::String(Rc::from(text)))?;
+        Ok(())
+    }
+
+    fn step(&mut self, frame: &mut Frame, instruction: &Instruction) -> Result<Next, Abrupt> {
+        let opcode = instruction.opcode;
+        let local = match instruction.operand {
+            Operand::Local(index) | Operand::Iinc(index, _) => index,
+            _ => 0,
+        };
+        let constant = match instruction.operand {
+            Operand::Constant(index) => index,
+            _ => 0,
+        };
+        match opcode {
+            NOP => {}
+            ACONST_NULL => frame.push(Value::Null)?,
+            ICONST_M1..=ICONST_5 => frame.push(Value::Int(opcode as i32 - ICONST_0 as i32))?,
+            LCONST_0 | LCONST_1 => frame.push(Value::Long((opcode - LCONST_0) as i64))?,
+            FCONST_0..=FCONST_2 => frame.push(Value::Float((opcode - FCONST_0) as f32))?,
+            DCONST_0 | DCONST_1 => frame.push(Value::Double((opcode - DCONST_0) as f64))?,
+            BIPUSH | SIPUSH => {
+                let value = match instruction.operand {
+                    Operand::Byte(value) => value as i32,
+                    Operand::Short(value) => value as i32,
+                    _ => 0,
+                };
+                frame.push(Value::Int(value))?;
+            }
+            LDC | LDC_W | LDC2_W => {
+                let value = self.constant(constant)?;
+                frame.push(value)?;
+            }
+            ILOAD..=ALOAD => frame.push(frame.load(local)?)?,
+            ILOAD_0..=ALOAD_3 => frame.push(frame.load(((opcode - ILOAD_0) % 4) as u16)?)?,
+            IALOAD..=SALOAD => {
+                let index = frame.pop_int()?;
+                let array = frame.pop_array()?;
+                let array = array.borrow();
+                let index = check_index(&array, index)?;
+                frame.push(array.values[index].clone())?;
+            }
+            ISTORE..=ASTORE => {
+                let value = frame.pop()?;
+                frame.store(local, value)?;
+            }
+            ISTORE_0..=ASTORE_3 => {
+                let value = frame.pop()?;
+                frame.store(((opcode - ISTORE_0) % 4) as u16, value)?;
+            }
+            IASTORE..=SASTORE => {
+                let value = frame.pop()?;
+                let index = frame.pop_int()?;
+                let array = frame.pop_array()?;
+                let mut array = array.borrow_mut();
+                let index = check_index(&array, index)?;
+                let value = match (opcode, value) {
+                    (BASTORE, Value::Int(value)) if array.component == "Z" => Value::Int(value & 1),
+                    (BASTORE, Value::Int(value)) => Value::Int(value as i8 as i32),
+                    (CASTORE, Value::Int(value)) => Value::Int(value as u16 as i32),
+                    (SASTORE, Value::Int(value)) => Value::Int(value as i16 as i32),
+                    (_, value) => value,
+                };
+                array.values[index] = value;
+            }
+            POP => {
+                frame.pop_category1()?;
+            }
+            POP2 => {
+                if !frame.pop()?.is_category2() {
+                    frame.pop_category1()?;
+                }
+            }
+            DUP => {
+                let v1 = frame.pop_category1()?;
+                frame.push_all(&[&v1, &v1])?;
+            }
+            DUP_X1 => {
+                let v1 = frame.pop_category1()?;
+                let v2 = frame.pop_category1()?;
+                frame.push_all(&[&v1, &v2, &v1])?;
+            }
+            DUP_X2 => {
+                let v1 = frame.pop_category1()?;
+                let v2 = frame.pop()?;
+                if v2.is_category2() {
+                    frame.push_all(&[&v1, &v2, &v1])?;
+                } else {
+                    let v3 = frame.pop_category1()?;
+                    frame.push_all(&[&v1, &v3, &v2, &v1])?;
+                }
+            }
+            DUP2 => {
+                let v1 = frame.pop()?;
+                if v1.is_category2() {
+                    frame.push_all(&[&v1, &v1])?;
+                } else {
+                    let v2 = frame.pop_category1()?;
+                    frame.push_all(&[&v2, &v1, &v2, &v1])?;
+                }
+            }
+            DUP2_X1 => {
+                let v1 = frame.pop()?;
+                if v1.is_category2() {
+                    let v2 = frame.pop_category1()?;
+                    frame.push_all(&[&v1, &v2, &v1])?;
+                } else {
+                    let v2 = frame.pop_category1()?;
+                    let v3 = frame.pop_category1()?;
+                    frame.push_all(&[&v2, &v1, &v3, &v2, &v1])?;
+                }
+            }
+            DUP2_X2 => {
+                let v1 = frame.pop()?;
+                if v1.is_category2() {
+                    let v2 = frame.pop()?;
+                    if v2.is_category2() {
+                        frame.push_all(&[&v1, &v2, &v1])?;
+                    } else {
+                        let v3 = frame.pop_category1()?;
+                        frame.push_all(&[&v1, &v3, &v2, &v1])?;
+                    }
+                } else {
+                    let v2 = frame.pop_category1()?;
+                    let v3 = frame.pop()?;
+                    if v3.is_category2() {
+                        frame.push_all(&[&v2, &v1, &v3, &v2, &v1])?;
+                    } else {
+                        let v4 = frame.pop_category1()?;
+                        frame.push_all(&[&v2, &v1, &v4, &v3, &v2, &v1])?;
+                    }
+                }
+            }
+            SWAP => {
+                let v1 = frame.pop_category1()?;
+                let v2 = frame.pop_category1()?;
+                frame.push_all(&[&v1, &v2])?;
+            }
+            IADD | ISUB | IMUL | IDIV | IREM | ISHL | ISHR | IUSHR | IAND | IOR | IXOR => {
+                let b = frame.pop_int()?;
+                let a = frame.pop_int()?;
+                if b == 0 && (opcode == IDIV || opcode == IREM) {
+                    return Err(throw(
+                        "java/lang/ArithmeticException",
+                        "/ by zero".to_string(),
+                    ));
+                }
+                let value = match opcode {
+                    IADD => a.wrapping_add(b),
+                    ISUB => a.wrapping_sub(b),
+                    IMUL => a.wrapping_mul(b),
+                    IDIV => a.wrapping_div(b),
+                    IREM => a.wrapping_rem(b),
+                    ISHL => a.wrapping_shl(b as u32),
+                    ISHR => a.wrapping_shr(b as u32),
+                    IUSHR => (a as u32).wrapping_shr(b as u32) as i32,
+                    IAND => a & b,
+                    IOR => a | b,
+                    _ => a ^ b,
+                };
+                frame.push(Value::Int(value))?;
+            }
+            LADD | LSUB | LMUL | LDIV | LREM | LAND | LOR | LXOR => {
+                let b = frame.pop_long()?;
+                let a = frame.pop_long()?;
+                if b == 0 && (opcode == LDIV || opcode == LREM) {
+                    return Err(throw(
+                        "java/lang/ArithmeticException",
+                        "/ by zero".to_string(),
+                    ));
+                }
+                let value = match opcode {
+                    LADD => a.wrapping_add(b),
+                    LSUB => a.wrapping_sub(b),
+                    LMUL => a.wrapping_mul(b),
+                    LDIV => a.wrapping_div(b),
+                    LREM => a.wrapping_rem(b),
+                    LAND => a & b,
+                    LOR => a | b,
+                    _ => a ^ b,
+                };
+                frame.push(Value::Long(value))?;
+            }
+            LSHL | LSHR | LUSHR => {
+                let b = frame.pop_int()? as u32;
+                let a = frame.pop_long()?;
+                let value = match opcode {
+                    LSHL => a.wrapping_shl(b),
+                    LSHR => a.wrapping_shr(b),
+                    _ => (a as u64).wrapping_shr(b) as i64,
+                };
+                frame.push(Value::Long(value))?;
+            }
+            FADD | FSUB | FMUL | FDIV | FREM => {
+                let b = frame.pop_float()?;
+                let a = frame.pop_float()?;
+                let value = match opcode {
+                    FADD => a + b,
+                    FSUB => a - b,
+                    FMUL => a * b,
+                    FDIV => a / b,
+                    _ => a % b,
+                };
+                frame.push(Value::Float(value))?;
+            }
+            DADD | DSUB | DMUL | DDIV | DREM => {
+                let b = frame.pop_double()?;
+                let a = frame.pop_double()?;
+                let value = match opcode {
+                    DADD => a + b,
+                    DSUB => a - b,
+                    DMUL => a * b,
+                    DDIV => a / b,
+                    _ => a % b,
+                };
+                frame.push(Value::Double(value))?;
+            }
+            INEG => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Int(value.wrapping_neg()))?;
+            }
+            LNEG => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Long(value.wrapping_neg()))?;
+            }
+            FNEG => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Float(-value))?;
+            }
+            DNEG => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Double(-value))?;
+            }
+            IINC => {
+                let increment = match instruction.operand {
+                    Operand::Iinc(_, increment) => increment as i32,
+                    _ => 0,
+                };
+                let value = match frame.load(local)? {
+                    Value::Int(value) => value.wrapping_add(increment),
+                    found => return Err(format!("iinc of a {} local", found.type_name()).into()),
+                };
+                frame.store(local, Value::Int(value))?;
+            }
+            // Rust's `as` saturates and maps NaN to zero, as the JVM does.
+            I2L => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Long(value as i64))?;
+            }
+            I2F => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Float(value as f32))?;
+            }
+            I2D => {
+                let value = frame.pop_int()?;
+                frame.push(Value::Double(value as f64))?;
+            }
+            L2I => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Int(value as i32))?;
+            }
+            L2F => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Float(value as f32))?;
+            }
+            L2D => {
+                let value = frame.pop_long()?;
+                frame.push(Value::Double(value as f64))?;
+            }
+            F2I => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Int(value as i32))?;
+            }
+            F2L => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Long(value as i64))?;
+            }
+            F2D => {
+                let value = frame.pop_float()?;
+                frame.push(Value::Double(value as f64))?;
+            }
+            D2I => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Int(value as i32))?;
+            }
+            D2L => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Long(value as i64))?;
+            }
+            D2F => {
+                let value = frame.pop_double()?;
+                frame.push(Value::Float(value as f32))?;
+            }
+            I2B | I2C | I2S => {
+                let value = frame.pop_int()?;
+                let value = match opcode {
+                    I2B => value as i8 as i32,
+                    I2C => value as u16 as i32,
+                    _ => value as i16 as i32,
+                };
+                frame.push(Value::Int(value))?;
+            }
+            LCMP => {
+                let b = frame.pop_long()?;
+                let a = frame.pop_long()?;
+                frame.push(Value::Int(compare(a, b, 0)))?;
+            }
+            FCMPL | FCMPG => {
+                let b = frame.pop_float()?;
+                let a = frame.pop_float()?;
+                let nan = if opcode == FCMPL { -1 } else { 1 };
+                frame.push(Value::Int(compare(a, b, nan)))?;
+            }
+            DCMPL | DCMPG => {
+                let b = frame.pop_double()?;
+                let a = frame.pop_double()?;
+                let nan = if opcode == DCMPL { -1 } else { 1 };
+                frame.push(Value::Int(compare(a, b, nan)))?;
+            }
+            IFEQ..=IF_ACMPNE | IFNULL | IFNONNULL | GOTO | GOTO_W => {
+                let taken = match opcode {
+                    IFEQ..=IFLE => {
+                        let value = frame.pop_int()?;
+                        [
+                            value == 0,
+                            value != 0,
+                            value < 0,
+                            value >= 0,
+                            value > 0,
+                            value <= 0,
+                        ][(opcode - IFEQ) as usize]
+                    }
+                    IF_ICMPEQ..=IF_ICMPLE => {
+                        let b = frame.pop_int()?;
+                        let a = frame.pop_int()?;
+                        [a == b, a != b, a < b, a >= b, a > b, a <= b]
+                            [(opcode - IF_ICMPEQ) as usize]
+                    }
+                    IF_ACMPEQ | IF_ACMPNE => {
+                        let b = frame.pop_reference()?;
+                        let a = frame.pop_reference()?;
+                        a.same(&b) == (opcode == IF_ACMPEQ)
+                    }
+                    IFNULL | IFNONNULL => {
+                        let value = frame.pop_reference()?;
+                        matches!(value, Value::Null) == (opcode == IFNULL)
+                    }
+                    _ => true,
+                };
+                if let (true, Operand::Branch(target)) = (taken, &instruction.operand) {
+                    return Ok(Next::Jump(*target));
+                }
+            }
+            TABLESWITCH | LOOKUPSWITCH => {
+                let key = frame.pop_int()?;
+                let target = match &instruction.operand {
+                    Operand::TableSwitch(default, low, high, targets) => {
+                        if (*low..=*high).contains(&key) {
+                            targets[(key - low) as usize]
+                        } else {
+                            *default
+                        }
+                    }
+                    Operand::LookupSwitch(default, pairs) => pairs
+                        .iter()
+                        .find(|(value, _)| *value == key)
+                        .map_or(*default, |(_, target)| *target),
+                    _ => return Err("missing switch targets".to_string().into()),
+                };
+                return Ok(Next::Jump(target));
+            }
+            IRETURN..=ARETURN => return Ok(Next::Return(Some(frame.pop()?))),
+            RETURN => return Ok(Next::Return(None)),
+            GETSTATIC | PUTSTATIC => self.field(frame, opcode, constant)?,
+            INVOKEVIRTUAL..=INVOKEDYNAMIC => return self.invoke(frame, instruction),
+            NEWARRAY => {
+                let count = frame.pop_int()?;
+                let component = match instruction.operand {
+                    Operand::ArrayType(4) => "Z",
+                    Operand::ArrayType(5) => "C",
+                    Operand::ArrayType(6) => "F",
+                    Operand::ArrayType(7) => "D",
+                    Operand::ArrayType(8) => "B",
+                    Operand::ArrayType(9) => "S",
+                    Operand::ArrayType(10) => "I",
+                    Operand::ArrayType(11) => "J",
+                    _ => return Err("invalid newarray element type".to_string().into()),
+                };
+                let array = self.alloc_array(&format!("[{}", component), &[count])?;
+                frame.push(array)?;
+            }
+            ANEWARRAY => {
+                let count = frame.pop_int()?;
+                let class = self
+                    .class
+                    .constant_pool
+                    .get_class_name(constant)
+                    .map_err(|e| e.to_string())?;
+                let descriptor = if class.starts_with('[') {
+                    format!("[{}", class)
+                } else {
+                    format!("[L{};", class)
+                };
+                let array = self.alloc_array(&descriptor, &[count])?;
+                frame.push(array)?;
+            }
+            MULTIANEWARRAY => {
+                let (index, dimensions) = match instruction.operand {
+                    Operand::MultiANewArray(index, dimensions) => (index, dimensions),
+                    _ => (0, 0),
+                };
+                let class = self
+                    .class
+                    .constant_pool
+                    .get_class_name(index)
+                    .map_err(|e| e.to_string())?
+                    .to_string();
+                let depth = class.bytes().take_while(|b| *b == b'[').count();
+                if dimensions == 0 || dimensions as usize > depth {
+                    let message =
+                        format!("multianewarray of {} with {} dimensions", class, dimensions);
+                    return Err(message.into());
+                }
+                let mut counts = Vec::<i32>::with_capacity(dimensions as usize);
+                for _ in 0..dimensions {
+                    counts.push(frame.pop_int()?);
+                }
+                counts.reverse();
+                let array = self.alloc_array(&class, &counts)?;
+                frame.push(array)?;
+            }
+            ARRAYLENGTH => {
+                let array = frame.pop_array()?;
+                let length = array.borrow().values.len() as i32;
+                frame.push(Value::Int(length))?;
+            }
+            ATHROW => {
+                return match frame.pop_reference()? {
+                    Value::Throwable(throwable) => Err(Abrupt::Throw(throwable, None)),
+                    Value::Null => Err(throw("java/lang/NullPointerException", None)),
+                    found => Err(Frame::mismatch("java/lang/Throwable", &found).into()),
+                }
+            }
+            MONITORENTER | MONITOREXIT => {
+                if let Value::Null = frame.pop_reference()? {
+                    return Err(throw("java/lang/NullPointerException", None));
+                }
+            }
+            _ => {
+                let message = format!("unsupported instruction {}", instruction.mnemonic());
+                return Err(message.into());
+            }
+        }
+        Ok(Next::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{Interpreter, RuntimeError, Throwable, Value};
+    use crate::assembler::assemble;
+    use crate::classfile::ClassFile;
+
+    #[test]
+    fn test_run_main_of_compiled_class() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/Calc.class");
+        let class = ClassFile::parse(fs::read(path).unwrap().as_slice()).unwrap();
+        let mut interpreter = Interpreter::new(&class);
+        interpreter.run_main(&["a", "b"]).unwrap();
+        // Same output as `java Calc a b`.
+        let expected = "\
+fib(20) = 6765 in 21891 calls
+20! = 2432902008176640000
+2.6666666666666665
+0.33333334 3.1415927 1.0E10
+tue day1000
+caught java.lang.ArithmeticException: / by zero
+3 -1
+jam
+20 3 4
+-2147483648
+-1 15 4611686018427387904 -56
+false
+2
+";
+        assert_eq!(interpreter.stdout(), expected);
+        assert!(matches!(
+            interpreter.static_field("calls"),
+            Some(Value::Long(21891))
+        ));
+    }
+
+    #[test]
+    fn test_invoke_static_of_assembled_class() {
+        let source = r#"
+.class public Sum
+.method public static sum([I)I
+    .limit stack 3
+    iconst_0
+    istore_1
+    iconst_0
+    istore_2
+Loop:
+    iload_2
+    aload_0
+    arraylength
+    if_icmpge Done
+    iload_1
+    aload_0
+    iload_2
+    iaload
+    iadd
+    istore_1
+    iinc 2 1
+    goto Loop
+Done:
+    getstatic java/lang/System/err Ljava/io/PrintStream;
+    iload_1
+    invokevirtual java/io/PrintStream/println(I)V
+    iload_1
+    ireturn
+.end method
+"#;
+        let class = assemble(source).unwrap();
+        let mut interpreter = Interpreter::new(&class);
+        let values = (1..=4).map(Value::Int).collect();
+        let array = interpreter.new_array("I", values);
+        let result = interpreter.invoke_static("sum", "([I)I", vec![array]);
+        assert!(matches!(result, Ok(Some(Value::Int(10)))));
+        assert_eq!(interpreter.stderr(), "10\n");
+        assert_eq!(interpreter.stdout(), "");
+    }
+
+    #[test]
+    fn test_run_reports_uncaught_exceptions_and_limits() {
+        let source = r#"
+.class public Bad
+.method public static main([Ljava/lang/String;)V
+    .limit stack 2
+    iconst_1
+    iconst_0
+    idiv
+    pop
+    return
+.end method
+
+.method public static spin()V
+    .limit stack 0
+Loop:
+    goto Loop
+.end method
+
+.method public static create()V
+    .limit stack 2
+    new java/lang/Object
+    return
+.end method
+
+.method public static huge()V
+    .limit stack 1
+    ldc 2147483647
+    newarray int
+    pop
+    return
+.end method
+
+.method public static flood()V
+    .limit stack 1
+Loop:
+    iconst_0
+    goto Loop
+.end method
+
+.method public static grid()I
+    .limit stack 2
+    sipush 1000
+    sipush 1000
+    multianewarray [[I 2
+    arraylength
+    ireturn
+.end method
+"#;
+        let class = assemble(source).unwrap();
+        let mut interpreter = Interpreter::new(&class);
+        assert_eq!(
+            interpreter.run_main(&[]),
+            Err(RuntimeError {
+                method: "main([Ljava/lang/String;)V".to_string(),
+                offset: Some(2),
+                message: "uncaught exception java.lang.ArithmeticException: / by zero".to_string(),
+            })
+        );
+        let mut interpreter = Interpreter::new(&class).with_step_limit(1000);
+        let e = interpreter
+            .invoke_static("spin", "()V", vec![])
+            .unwrap_err();
+        assert_eq!(e.message, "step limit of 1000 exceeded");
+        let e = interpreter
+            .invoke_static("create", "()V", vec![])
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "create()V at offset 0: unsupported instruction new"
+        );
+
+        let e = interpreter
+            .invoke_static("flood", "()V", vec![])
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "flood()V at offset 0: operand stack exceeds max_stack 1"
+        );
+
+        let e = interpreter
+            .invoke_static("huge", "()V", vec![])
+            .unwrap_err();
+        assert_eq!(
+            e.message,
+            "uncaught exception java.lang.OutOfMemoryError: array limit of 16777216 elements exceeded"
+        );
+        let mut interpreter = Interpreter::new(&class).with_array_limit(1_001_000);
+        assert!(matches!(
+            interpreter.invoke_static("grid", "()I", vec![]),
+            Ok(Some(Value::Int(1000)))
+        ));
+        let e = interpreter
+            .invoke_static("grid", "()I", vec![])
+            .unwrap_err();
+        assert_eq!(e.offset, Some(6));
+        assert!(e
+            .message
+            .contains("OutOfMemoryError: array limit of 1001000 elements"));
+
+        // The assembler rejects these dimensions, so patch them into the class file.
+        let bytes = class.to_bytes().unwrap();
+        let at = bytes
+            .windows(4)
+            .position(|w| w[0] == 0xc5 && w[3] == 2)
+            .unwrap();
+        for (dimensions, message) in [
+            (3, "multianewarray of [[I with 3 dimensions"),
+            (0, "multianewarray of [[I with 0 dimensions"),
+        ] {
+            let mut patched = bytes.clone();
+            patched[at + 3] = dimensions;
+            let class = ClassFile::parse(patched.as_slice()).unwrap();
+            let mut interpreter = Interpreter::new(&class);
+            let e = interpreter
+                .invoke_static("grid", "()I", vec![])
+                .unwrap_err();
+            assert_eq!((e.offset, e.message.as_str()), (Some(6), message));
+        }
+    }
+
+    #[test]
+    fn test_deep_recursion_throws_stack_overflow_error() {
+        let source = r#"
+.class public Deep
+.method public static down(I)I
+    .limit stack 2
+    iload_0
+    iconst_1
+    iadd
+    invokestatic Deep/down(I)I
+    ireturn
+.end method
+
+.method public static depth(I)I
+    .limit stack 2
+Start:
+    iload_0
+    iconst_1
+    iadd
+    invokestatic Deep/depth(I)I
+    ireturn
+End:
+    pop
+    iload_0
+    ireturn
+.catch java/lang/StackOverflowError from Start to End using End
+.end method
+"#;
+        let class = assemble(source).unwrap();
+        let mut interpreter = Interpreter::new(&class);
+        let e = interpreter
+            .invoke_static("down", "(I)I", vec![Value::Int(0)])
+            .unwrap_err();
+        assert_eq!(
+            e,
+            RuntimeError {
+                method: "down(I)I".to_string(),
+                offset: Some(3),
+                message: "uncaught exception java.lang.StackOverflowError".to_string(),
+            }
+        );
+        // The innermost of the 512 frames catches the error and every frame returns its depth.
+        assert!(matches!(
+            interpreter.invoke_static("depth", "(I)I", vec![Value::Int(0)]),
+            Ok(Some(Value::Int(511)))
+        ));
+    }
+
+    #[test]
+    fn test_virtual_machine_errors_are_caught_as_errors() {
+        let source = r#"
+.class public Oom
+.method public static alloc()I
+    .limit stack 1
+Start:
+    ldc 2147483647
+    newarray int
+    pop
+    iconst_0
+    ireturn
+End:
+    pop
+    iconst_1
+    ireturn
+Vm:
+    pop
+    iconst_2
+    ireturn
+.catch java/lang/RuntimeException from Start to End using End
+.catch java/lang/VirtualMachineError from Start to End using Vm
+.end method
+"#;
+        let class = assemble(source).unwrap();
+        let mut interpreter = Interpreter::new(&class);
+        assert!(matches!(
+            interpreter.invoke_static("alloc", "()I", vec![]),
+            Ok(Some(Value::Int(2)))
+        ));
+        let catches = |class: &str| {
+            Throwable {
+                class: "java/lang/OutOfMemoryError".to_string(),
+                message: None,
+            }
+            .is_instance_of(class)
+        };
+        assert!(catches("java/lang/VirtualMachineError"));
+        assert!(catches("java/lang/Error"));
+        assert!(catches("java/lang/Throwable"));
+        assert!(!catches("java/lang/RuntimeException"));
+        assert!(!catches("java/lang/Exception"));
+    }
+}
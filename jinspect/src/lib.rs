@@ -0,0 +1,32 @@
+pub mod annotation;
+pub mod archive;
+pub mod assembler;
+pub mod attribute;
+pub mod bytecode;
+pub mod classfile;
+pub mod classpath;
+pub mod compat;
+pub mod declaration;
+pub mod dependency;
+pub mod diff;
+pub mod error;
+pub mod explorer;
+pub mod export;
+pub mod feature;
+pub mod flag;
+pub mod interpreter;
+pub mod literal;
+pub mod mutf8;
+pub mod opcode;
+pub mod pool;
+mod reader;
+pub mod signature;
+pub mod source;
+#[cfg(test)]
+mod testdata;
+pub mod verifier;
+pub mod view;
+mod writer;
+
+pub use crate::classfile::{ClassFile, Field, Header, Member, Method};
+pub use crate::error::{ErrorKind, ParseError};
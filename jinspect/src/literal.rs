@@ -0,0 +1,193 @@
+// Formatting of numeric constants the way Java prints them (`Double.toString`, `Float.toString`)
+// and as they appear in `javap` output (`1.5f`, `10l`, `3.14d`).
+
+/// Splits the shortest round-trip representation produced by `{:e}` into its significant
+/// digits and decimal exponent, e.g. `1.25e3` => ("125", 3).
+fn split_exponent(formatted: &str) -> (String, i32) {
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((formatted, "0"));
+    let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+    (digits, exponent.parse().unwrap_or(0))
+}
+
+fn to_java_string(negative: bool, formatted: &str, magnitude: f64) -> String {
+    let sign = if negative { "-" } else { "" };
+    if magnitude == 0.0 {
+        return format!("{}0.0", sign);
+    }
+    let (digits, exponent) = split_exponent(formatted);
+    if (1e-3..1e7).contains(&magnitude) {
+        if exponent >= 0 {
+            let split = exponent as usize + 1;
+            let padded = format!("{:0<width$}", digits, width = split);
+            let (int_part, frac_part) = padded.split_at(split);
+            let frac_part = if frac_part.is_empty() { "0" } else { frac_part };
+            format!("{}{}.{}", sign, int_part, frac_part)
+        } else {
+            let zeros = "0".repeat((-exponent - 1) as usize);
+            format!("{}0.{}{}", sign, zeros, digits)
+        }
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        format!("{}{}.{}E{}", sign, first, rest, exponent)
+    }
+}
+
+/// Same output as Java's `Double.toString`.
+pub fn double_to_string(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    let magnitude = value.abs();
+    to_java_string(
+        value.is_sign_negative(),
+        &format!("{:e}", magnitude),
+        magnitude,
+    )
+}
+
+/// Same output as Java's `Float.toString`.
+pub fn float_to_string(value: f32) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    let magnitude = value.abs();
+    // Format the f32 itself so we get the shortest digits that round-trip as a float.
+    to_java_string(
+        value.is_sign_negative(),
+        &format!("{:e}", magnitude),
+        magnitude as f64,
+    )
+}
+
+pub fn int_literal(value: i32) -> String {
+    format!("{}", value)
+}
+
+pub fn long_literal(value: i64) -> String {
+    format!("{}l", value)
+}
+
+pub fn float_literal(value: f32) -> String {
+    format!("{}f", float_to_string(value))
+}
+
+pub fn double_literal(value: f64) -> String {
+    format!("{}d", double_to_string(value))
+}
+
+fn escape(c: char, quote: char, acc: &mut String) {
+    match c {
+        '\u{8}' => acc.push_str("\\b"),
+        '\t' => acc.push_str("\\t"),
+        '\n' => acc.push_str("\\n"),
+        '\u{c}' => acc.push_str("\\f"),
+        '\r' => acc.push_str("\\r"),
+        '\\' => acc.push_str("\\\\"),
+        c if c == quote => {
+            acc.push('\\');
+            acc.push(c);
+        }
+        c if c.is_control() => {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                acc.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+        c => acc.push(c),
+    }
+}
+
+/// Java source form of a string constant, e.g. `"a\tb"`.
+pub fn string_literal(value: &str) -> String {
+    let mut acc = String::from('"');
+    for c in value.chars() {
+        escape(c, '"', &mut acc);
+    }
+    acc.push('"');
+    acc
+}
+
+/// Java source form of a char constant, unpaired surrogates are written as `\uXXXX`.
+pub fn char_literal(value: u16) -> String {
+    let mut acc = String::from('\'');
+    match char::from_u32(value as u32) {
+        Some(c) => escape(c, '\'', &mut acc),
+        None => acc.push_str(&format!("\\u{:04x}", value)),
+    }
+    acc.push('\'');
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        char_literal, double_literal, double_to_string, float_literal, float_to_string,
+        long_literal, string_literal,
+    };
+
+    #[test]
+    fn test_double_to_string_decimal_range() {
+        assert_eq!(double_to_string(1.0), "1.0");
+        assert_eq!(double_to_string(100.0), "100.0");
+        assert_eq!(double_to_string(2.71), "2.71");
+        assert_eq!(double_to_string(-2.5), "-2.5");
+        assert_eq!(double_to_string(0.001), "0.001");
+        assert_eq!(double_to_string(0.0125), "0.0125");
+        assert_eq!(double_to_string(1234567.0), "1234567.0");
+        assert_eq!(double_to_string(0.1 + 0.2), "0.30000000000000004");
+    }
+
+    #[test]
+    fn test_double_to_string_scientific_range() {
+        assert_eq!(double_to_string(1e7), "1.0E7");
+        assert_eq!(double_to_string(1.25e10), "1.25E10");
+        assert_eq!(double_to_string(1e-4), "1.0E-4");
+        assert_eq!(double_to_string(f64::MAX), "1.7976931348623157E308");
+        assert_eq!(
+            double_to_string(f64::MIN_POSITIVE),
+            "2.2250738585072014E-308"
+        );
+    }
+
+    #[test]
+    fn test_double_to_string_special_values() {
+        assert_eq!(double_to_string(0.0), "0.0");
+        assert_eq!(double_to_string(-0.0), "-0.0");
+        assert_eq!(double_to_string(f64::NAN), "NaN");
+        assert_eq!(double_to_string(f64::INFINITY), "Infinity");
+        assert_eq!(double_to_string(f64::NEG_INFINITY), "-Infinity");
+    }
+
+    #[test]
+    fn test_float_to_string() {
+        assert_eq!(float_to_string(1.5), "1.5");
+        assert_eq!(float_to_string(0.1), "0.1");
+        assert_eq!(float_to_string(3.4028235e38), "3.4028235E38");
+        assert_eq!(float_to_string(1.0e-5), "1.0E-5");
+        assert_eq!(float_to_string(f32::NAN), "NaN");
+    }
+
+    #[test]
+    fn test_javap_literals() {
+        assert_eq!(float_literal(1.5), "1.5f");
+        assert_eq!(float_literal(f32::NEG_INFINITY), "-Infinityf");
+        assert_eq!(double_literal(3.0), "3.0d");
+        assert_eq!(double_literal(f64::NAN), "NaNd");
+        assert_eq!(long_literal(-9), "-9l");
+    }
+
+    #[test]
+    fn test_source_literals() {
+        assert_eq!(string_literal("a\"b\\c\n"), r#""a\"b\\c\n""#);
+        assert_eq!(string_literal("\u{0}é"), r#""\u0000é""#);
+        assert_eq!(char_literal(b'\'' as u16), r"'\''");
+        assert_eq!(char_literal(0xd800), r"'\ud800'");
+    }
+}
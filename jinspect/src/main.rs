@@ -1,239 +1,619 @@
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use jinspect::archive::{Archive, ZIP_MAGIC};
+use jinspect::assembler;
+use jinspect::attribute::AttributeInfo;
+use jinspect::bytecode;
+use jinspect::classfile;
+use jinspect::classpath::{self, Summary};
+use jinspect::compat::{self, Library};
+use jinspect::declaration;
+use jinspect::dependency::DependencyGraph;
+use jinspect::diff;
+use jinspect::export::ClassDocument;
+use jinspect::feature;
+use jinspect::interpreter::Interpreter;
+use jinspect::pool::ConstantPool;
+use jinspect::signature::java_name;
+use jinspect::source;
+use jinspect::verifier::{self, VerifyError};
+use jinspect::{ClassFile, Member};
 
 mod cli;
-mod flag;
-mod pool;
-mod reader;
-use crate::flag::AccessFlag;
-use crate::pool::{ConstantPool, ConstantPoolItem};
-use crate::reader::{read_u16, read_u32};
+mod repl;
+use crate::cli::{Action, DependencyOptions, Format, Options, VerboseMode, View};
+
+fn print_attributes(items: &[AttributeInfo], pool: &ConstantPool, indent: usize) {
+    for (i, item) in items.iter().enumerate() {
+        let attr = &item.attribute;
+        println!(
+            "{:indent$}{:02} {} => {}",
+            "",
+            i,
+            pool.resolve(item.name_index),
+            attr.resolve(pool),
+            indent = indent
+        );
+        for line in attr.details(pool) {
+            println!("{:indent$}{}", "", line, indent = indent + 5);
+        }
+    }
+}
+
+fn print_members(caption: &str, items: &[Member], pool: &ConstantPool) {
+    println!("INFO: {}s= {}", caption, items.len());
+    for (i, item) in items.iter().enumerate() {
+        println!(
+            "    {:02} {} {} {}",
+            i,
+            item.access_flags,
+            item.name(pool),
+            item.descriptor(pool)
+        );
+        println!("      {} Attributes= {}", caption, item.attributes.len());
+        print_attributes(&item.attributes, pool, 8);
+    }
+}
+
+fn print_info(class: &ClassFile, verbose: &VerboseMode) {
+    let pool = &class.constant_pool;
 
-struct Header(u32, u16, u16);
+    if verbose.can_verbose_header() {
+        class.header.print();
+        print_features(class);
+    }
+
+    if verbose.can_verbose_class() {
+        class.access_flags.print();
+        println!("INFO: ThisClass= {}", class.this_class_name());
+        if let Some(super_name) = class.super_class_name() {
+            println!("INFO: SuperClass= {}", super_name);
+        }
+    }
+
+    if verbose.can_verbose_pool() {
+        pool.print();
+    }
+
+    if verbose.can_verbose_interfaces() {
+        let items = class.interface_names();
+        println!("INFO: Interfaces= {}", items.len());
+        for (i, item) in items.iter().enumerate() {
+            println!("    {:02} {} ", i, item);
+        }
+    }
+
+    if verbose.can_verbose_fields() {
+        print_members("Field", &class.fields, pool);
+    }
+
+    if verbose.can_verbose_methods() {
+        print_members("Method", &class.methods, pool);
+    }
 
-impl Header {
-    fn from(rdr: &mut BufReader<File>) -> Self {
-        let magic = read_u32(rdr);
-        let minor = read_u16(rdr);
-        let major = read_u16(rdr);
-        Header(magic, major, minor)
+    if verbose.can_verbose_attributes() {
+        println!("INFO: Attributes= {}", class.attributes.len());
+        print_attributes(&class.attributes, pool, 4);
     }
+}
 
-    fn print(&self) {
-        println!("INFO: Header");
+fn print_features(class: &ClassFile) {
+    let features = feature::detect(class);
+    if features.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = features.iter().map(|item| item.feature.name()).collect();
+    println!("INFO: Features= {}", names.join(", "));
+    for item in features.iter().filter(|item| !item.allowed) {
         println!(
-            "    Magic= 0x{:X}, Major= {}, Minor= {}",
-            self.0, self.1, self.2
+            "WARNING: {} not allowed before {} (major {}), class version is {}",
+            item.feature.name(),
+            classfile::release(item.since).unwrap_or_default(),
+            item.since,
+            class.header.release_name()
         );
     }
 }
 
-fn parse_interfaces(reader: &mut BufReader<File>, pool: &ConstantPool) -> Option<Vec<String>> {
-    let count = read_u16(reader);
-    if count > 0 {
-        let mut acc = Vec::<String>::with_capacity(count as usize);
-        for _ in 0..count {
-            let index = read_u16(reader);
-            acc.push(pool.resolve(index));
+fn print_disassembly(class: &ClassFile) {
+    let pool = &class.constant_pool;
+    let this_name = class.this_class_name();
+    println!("{} class {} {{", class.access_flags, this_name);
+    for method in class.methods.iter() {
+        println!(
+            "  {} {} {}",
+            method.access_flags,
+            method.name(pool),
+            method.descriptor(pool)
+        );
+        if let Some(code) = method.code() {
+            bytecode::print_code(code, pool, &this_name);
         }
-        return Some(acc);
+        println!();
     }
-    None
+    println!("}}");
 }
 
-struct RawAttribute(String, u32);
-fn parse_attributes(
-    reader: &mut BufReader<File>,
-    pool: &ConstantPool,
-) -> Option<Vec<RawAttribute>> {
-    let count = read_u16(reader);
-    if count > 0 {
-        let mut acc = Vec::<RawAttribute>::with_capacity(count as usize);
-        for _i in 0..count {
-            let name_index = read_u16(reader);
-            let name = pool.resolve(name_index);
-            let length = read_u32(reader);
-            // let val = read_n(reader, length as usize);
-            acc.push(RawAttribute(name, length));
+fn print_signatures(class: &ClassFile) {
+    if let Some(source) = source::module_source(class) {
+        print!("{}", source);
+        return;
+    }
+    let pool = &class.constant_pool;
+    for annotation in declaration::annotations(&class.attributes, pool) {
+        println!("{}", annotation);
+    }
+    println!("{} {{", declaration::class_declaration(class));
+    for field in class.fields.iter() {
+        for annotation in declaration::annotations(&field.attributes, pool) {
+            println!("  {}", annotation);
         }
-        Some(acc)
-    } else {
-        None
+        println!("  {};", declaration::field_declaration(class, field));
     }
+    for method in class.methods.iter() {
+        for annotation in declaration::annotations(&method.attributes, pool) {
+            println!("  {}", annotation);
+        }
+        println!("  {};", declaration::method_declaration(class, method));
+    }
+    println!("}}");
+}
+
+fn print_serialized<T: Serialize>(value: &T, format: &Format) -> Result<(), String> {
+    let output = match format {
+        Format::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string())?,
+        Format::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string())?,
+        Format::Text => unreachable!("text output is printed directly"),
+    };
+    println!("{}", output.trim_end());
+    Ok(())
 }
 
-struct Field(AccessFlag, String, String, Option<Vec<RawAttribute>>);
-fn parse_fields(reader: &mut BufReader<File>, pool: &ConstantPool) -> Option<Vec<Field>> {
-    let count = read_u16(reader);
-    if count > 0 {
-        let mut acc = Vec::<Field>::with_capacity(count as usize);
-        for _ in 0..count {
-            let flags = AccessFlag::parse_field_level(reader);
-            let name_index = read_u16(reader);
-            let desc_index = read_u16(reader);
-            let name = pool.resolve(name_index);
-            let desc = pool.resolve(desc_index);
-            let attrs = parse_attributes(reader, pool);
-            acc.push(Field(flags, name, desc, attrs));
+/// Prints a class, `find_class` looks up its member classes by internal name for the source view.
+fn print_class(
+    class: &ClassFile,
+    options: &Options,
+    find_class: &mut dyn FnMut(&str) -> Option<ClassFile>,
+) -> Result<(), String> {
+    match (&options.format, &options.view) {
+        (Format::Text, View::Info) => print_info(class, &options.verbose),
+        (Format::Text, View::Disassemble) => print_disassembly(class),
+        (Format::Text, View::Signature) => print_signatures(class),
+        (Format::Text, View::Source) => {
+            print!("{}", source::class_source_with(class, find_class))
         }
-        Some(acc)
-    } else {
-        None
+        (format, _) => print_serialized(&ClassDocument::new(class), format)
+            .map_err(|e| format!("could not serialise class file: {e}"))?,
     }
+    Ok(())
 }
 
-struct Method(AccessFlag, String, String, Option<Vec<RawAttribute>>);
-fn parse_methods(reader: &mut BufReader<File>, pool: &ConstantPool) -> Option<Vec<Method>> {
-    let count = read_u16(reader);
-    if count > 0 {
-        let mut acc = Vec::<Method>::with_capacity(count as usize);
-        for _ in 0..count {
-            let flags = AccessFlag::parse_method_level(reader);
-            let name_index = read_u16(reader);
-            let desc_index = read_u16(reader);
-            let name = pool.resolve(name_index);
-            let desc = pool.resolve(desc_index);
-            let attrs = parse_attributes(reader, pool);
-            acc.push(Method(flags, name, desc, attrs));
+/// Splits `app.jar!com/foo/Bar.class` into the archive path and the entry name.
+fn split_entry(path: &str) -> Option<(&str, &str)> {
+    match path.split_once('!') {
+        Some((archive, entry)) if Path::new(archive).is_file() => Some((archive, entry)),
+        _ => None,
+    }
+}
+
+fn open_archive(path: &str) -> Result<Archive<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("could not open file: {path}: {e}"))?;
+    Archive::open(BufReader::new(file)).map_err(|e| format!("could not read archive: {path}: {e}"))
+}
+
+fn inspect_archive(path: &str, options: &Options) -> Result<(), String> {
+    let mut archive = open_archive(path)?;
+    if options.list {
+        for name in archive.class_names() {
+            println!("{}", name);
         }
-        Some(acc)
-    } else {
-        None
+        return Ok(());
+    }
+    // Member classes are read through a second handle while the first one is iterated.
+    let mut members = open_archive(path)?;
+    let mut find_class = |name: &str| members.parse_class(&format!("{name}.class")).ok();
+    let mut documents = Vec::<ClassDocument>::new();
+    let mut failures = 0;
+    for (name, class) in archive.classes() {
+        let class = match class {
+            Ok(class) => class,
+            Err(e) => {
+                eprintln!("ERROR: could not parse class file: {path}!{name}: {e}");
+                failures += 1;
+                continue;
+            }
+        };
+        match options.format {
+            Format::Text => {
+                println!("INFO: Entry= {}", name);
+                print_class(&class, options, &mut find_class)?;
+            }
+            _ => documents.push(ClassDocument::new(&class)),
+        }
+    }
+    if !matches!(options.format, Format::Text) {
+        print_serialized(&documents, &options.format)
+            .map_err(|e| format!("could not serialise class files: {e}"))?;
+    }
+    if failures > 0 {
+        return Err(format!("{failures} classes of {path} could not be parsed"));
+    }
+    Ok(())
+}
+
+fn inspect_entry(path: &str, entry: &str, options: &Options) -> Result<(), String> {
+    if options.list {
+        return Err(format!("--list needs a JAR/ZIP archive: {path}!{entry}"));
     }
+    let mut archive = open_archive(path)?;
+    if archive.find(entry).is_none() {
+        return Err(format!("no entry named {entry} in {path}"));
+    }
+    let class = archive
+        .parse_class(entry)
+        .map_err(|e| format!("could not parse class file: {path}!{entry}: {e}"))?;
+    print_class(&class, options, &mut |name| {
+        archive.parse_class(&format!("{name}.class")).ok()
+    })
 }
 
-fn parse_this_class(reader: &mut BufReader<File>, pool: &ConstantPool) -> Option<ConstantPoolItem> {
-    let class_index = read_u16(reader);
-    if let &ConstantPoolItem::Class(_) = pool.get(class_index) {
-        return Some(ConstantPoolItem::Class(class_index));
+fn inspect_file(path: &str, options: &Options) -> Result<(), String> {
+    let mut file = File::open(path).map_err(|e| format!("could not open file: {path}: {e}"))?;
+    let mut magic = [0u8; 4];
+    let is_archive = file.read_exact(&mut magic).is_ok() && magic == ZIP_MAGIC;
+    if is_archive {
+        return inspect_archive(path, options);
+    }
+    if options.list {
+        return Err(format!("--list needs a JAR/ZIP archive: {path}"));
     }
-    None
+    file.rewind()
+        .map_err(|e| format!("could not open file: {path}: {e}"))?;
+    let class = ClassFile::parse(BufReader::new(file))
+        .map_err(|e| format!("could not parse class file: {path}: {e}"))?;
+    // Member classes are looked for next to the class file, e.g. `Outer$Inner.class`.
+    print_class(&class, options, &mut |name| {
+        let simple = name.rsplit('/').next().unwrap_or(name);
+        let file = File::open(Path::new(path).with_file_name(format!("{simple}.class"))).ok()?;
+        ClassFile::parse(BufReader::new(file)).ok()
+    })
 }
 
-fn parse_super_class(
-    reader: &mut BufReader<File>,
-    pool: &ConstantPool,
-) -> Option<ConstantPoolItem> {
-    let class_index = read_u16(reader);
-    if let &ConstantPoolItem::Class(_) = pool.get(class_index) {
-        return Some(ConstantPoolItem::Class(class_index));
+fn print_summary(summary: &Summary) {
+    let rows: Vec<[String; 6]> = summary
+        .classes
+        .iter()
+        .map(|row| {
+            [
+                row.class.clone(),
+                format!("{}.{}", row.major, row.minor),
+                row.access_flags.join(" "),
+                row.fields.to_string(),
+                row.methods.to_string(),
+                row.size.to_string(),
+            ]
+        })
+        .collect();
+    let totals = &summary.totals;
+    let footer = [
+        format!("TOTAL ({} classes)", totals.classes),
+        String::new(),
+        String::new(),
+        totals.fields.to_string(),
+        totals.methods.to_string(),
+        totals.size.to_string(),
+    ];
+    let header = ["CLASS", "VERSION", "FLAGS", "FIELDS", "METHODS", "SIZE"].map(String::from);
+    let mut widths = [0usize; 6];
+    for row in rows.iter().chain([&header, &footer]) {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
     }
-    None
+    let print_row = |row: &[String; 6]| {
+        let line = format!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {:>w3$}  {:>w4$}  {:>w5$}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            row[5],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+            w5 = widths[5]
+        );
+        println!("{}", line.trim_end());
+    };
+    print_row(&header);
+    for row in rows.iter() {
+        print_row(row);
+    }
+    print_row(&footer);
 }
 
-fn main() {
-    let (file_path, verbose) = cli::parse_cli_args();
-    match File::open(&file_path) {
-        Ok(file) => {
-            let mut reader = BufReader::new(file);
+fn summarize(paths: &[String], format: &Format) -> Result<(), String> {
+    let summary = Summary::collect(paths);
+    for failure in summary.failures.iter() {
+        eprintln!(
+            "ERROR: could not parse class file: {}: {}",
+            failure.location, failure.error
+        );
+    }
+    match format {
+        Format::Text => print_summary(&summary),
+        format => print_serialized(&summary, format)
+            .map_err(|e| format!("could not serialise summary: {e}"))?,
+    }
+    if summary.totals.failures > 0 {
+        return Err(format!(
+            "{} classes could not be parsed",
+            summary.totals.failures
+        ));
+    }
+    Ok(())
+}
 
-            let header = Header::from(&mut reader);
-            let constant_pool = ConstantPool::from(&mut reader);
-            let acc_class = AccessFlag::parse_class_level(&mut reader);
-            let this_class = parse_this_class(&mut reader, &constant_pool);
-            let super_class = parse_super_class(&mut reader, &constant_pool);
-            let interfaces = parse_interfaces(&mut reader, &constant_pool);
-            let fields = parse_fields(&mut reader, &constant_pool);
-            let methods = parse_methods(&mut reader, &constant_pool);
-            let attributes = parse_attributes(&mut reader, &constant_pool);
+#[derive(Serialize)]
+struct VerifyReport {
+    location: String,
+    class: String,
+    skipped: bool, // class file predates StackMapTable
+    errors: Vec<VerifyError>,
+}
 
-            if verbose.can_verbose_header() {
-                header.print();
+fn verify(paths: &[String], format: &Format) -> Result<(), String> {
+    let mut reports = Vec::<VerifyReport>::new();
+    let mut failures = 0;
+    for path in paths {
+        classpath::walk(Path::new(path), &mut |source, class| match class {
+            Ok(class) => {
+                let skipped = class.header.major < verifier::STACK_MAP_VERSION;
+                let errors = if skipped {
+                    Vec::new()
+                } else {
+                    verifier::verify_class(&class)
+                };
+                reports.push(VerifyReport {
+                    location: source.location,
+                    class: class.this_class_name(),
+                    skipped,
+                    errors,
+                });
             }
-
-            if verbose.can_verbose_class() {
-                acc_class.print();
-                if let Some(this_item) = this_class {
-                    println!("INFO: ThisClass= {}", this_item.resolve(&constant_pool));
-                    if let Some(super_item) = super_class {
-                        println!("INFO: SuperClass= {}", super_item.resolve(&constant_pool));
-                    }
+            Err(e) => {
+                eprintln!(
+                    "ERROR: could not parse class file: {}: {e}",
+                    source.location
+                );
+                failures += 1;
+            }
+        });
+    }
+    match format {
+        Format::Text => {
+            for report in reports.iter() {
+                if report.skipped {
+                    println!(
+                        "SKIP: {}: class file predates StackMapTable",
+                        report.location
+                    );
+                }
+                if !report.errors.is_empty() {
+                    println!("FAIL: {}", report.location);
+                }
+                for error in report.errors.iter() {
+                    println!("    {}", error);
                 }
             }
+        }
+        format => print_serialized(&reports, format)
+            .map_err(|e| format!("could not serialise verification report: {e}"))?,
+    }
+    let rejected = reports
+        .iter()
+        .filter(|item| !item.errors.is_empty())
+        .count();
+    let skipped = reports.iter().filter(|item| item.skipped).count();
+    if let Format::Text = format {
+        println!(
+            "INFO: Verified {} classes, {} failed, {} skipped",
+            reports.len() - skipped,
+            rejected,
+            skipped
+        );
+    }
+    match (rejected, failures) {
+        (0, 0) => Ok(()),
+        (0, _) => Err(format!("{failures} classes could not be parsed")),
+        _ => Err(format!("{rejected} classes failed verification")),
+    }
+}
+
+fn assemble(source: &str, output: Option<&str>) -> Result<(), String> {
+    let text =
+        std::fs::read_to_string(source).map_err(|e| format!("could not read {source}: {e}"))?;
+    let class = assembler::assemble(&text).map_err(|e| format!("{source}: {e}"))?;
+    let output = match output {
+        Some(output) => output.to_string(),
+        None => Path::new(source)
+            .with_extension("class")
+            .display()
+            .to_string(),
+    };
+    let bytes = class
+        .to_bytes()
+        .map_err(|e| format!("could not serialise class file: {e}"))?;
+    std::fs::write(&output, &bytes).map_err(|e| format!("could not write {output}: {e}"))?;
+    println!("INFO: Wrote {} ({} bytes)", output, bytes.len());
+    Ok(())
+}
+
+fn read_class(path: &str) -> Result<ClassFile, String> {
+    if let Some((archive, entry)) = split_entry(path) {
+        return open_archive(archive)?
+            .parse_class(entry)
+            .map_err(|e| format!("could not parse class file: {path}: {e}"));
+    }
+    let file = File::open(path).map_err(|e| format!("could not open file: {path}: {e}"))?;
+    ClassFile::parse(BufReader::new(file))
+        .map_err(|e| format!("could not parse class file: {path}: {e}"))
+}
+
+fn run(path: &str, args: &[String]) -> Result<(), String> {
+    let class = read_class(path)?;
+    let mut interpreter = Interpreter::new(&class);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = interpreter.run_main(&args);
+    print!("{}", interpreter.stdout());
+    eprint!("{}", interpreter.stderr());
+    result.map_err(|e| e.to_string())
+}
 
-            if verbose.can_verbose_pool() {
-                constant_pool.print();
+fn compare(old: &str, new: &str, format: &Format) -> Result<(), String> {
+    let result = diff::diff(&read_class(old)?, &read_class(new)?);
+    match format {
+        Format::Text => {
+            for change in result.changes.iter() {
+                println!("{}", change);
             }
+            println!("INFO: Differences= {}", result.changes.len());
+        }
+        format => print_serialized(&result, format)
+            .map_err(|e| format!("could not serialise diff: {e}"))?,
+    }
+    Ok(())
+}
 
-            if verbose.can_verbose_interfaces() {
-                match interfaces {
-                    Some(items) => {
-                        println!("INFO: Interfaces= {}", items.capacity());
-                        for (i, item) in items.iter().enumerate() {
-                            println!("    {:02} {} ", i, item);
-                        }
-                    }
-                    None => println!("INFO: Interfaces= 0"),
-                }
+fn load_library(path: &str) -> Result<Library, String> {
+    if !Path::new(path).exists() {
+        return Err(format!("no such file or directory: {path}"));
+    }
+    let mut library = Library::new();
+    classpath::walk(Path::new(path), &mut |source, class| match class {
+        Ok(class) => library.add(class),
+        Err(e) => library.add_unreadable(&source.location, e.to_string()),
+    });
+    Ok(library)
+}
+
+fn check_compat(old: &str, new: &str, format: &Format) -> Result<(), String> {
+    let report = compat::check(&load_library(old)?, &load_library(new)?);
+    match format {
+        Format::Text => {
+            for problem in report.problems.iter() {
+                println!("{}", problem);
             }
+            let totals = &report.totals;
+            println!(
+                "INFO: Errors= {}, Warnings= {}, Infos= {}",
+                totals.errors, totals.warnings, totals.infos
+            );
+        }
+        format => print_serialized(&report, format)
+            .map_err(|e| format!("could not serialise compatibility report: {e}"))?,
+    }
+    if !report.is_binary_compatible() {
+        return Err(format!(
+            "{} binary incompatible changes",
+            report.totals.errors
+        ));
+    }
+    Ok(())
+}
 
-            if verbose.can_verbose_fields() {
-                match fields {
-                    Some(items) => {
-                        println!("INFO: Fields= {}", items.capacity());
-                        for (i, item) in items.iter().enumerate() {
-                            let flag = item.0.to_string();
-                            println!("    {:02} {} {} {}", i, flag, item.1, item.2);
-                            let attributes = &item.3;
-                            match attributes {
-                                Some(items) => {
-                                    println!("      Field Attributes= {}", items.capacity());
-                                    for (i, item) in items.iter().enumerate() {
-                                        let name = item.0.to_string();
-                                        let len = item.1;
-                                        println!("        {:02} {} {}", i, name, len);
-                                    }
-                                }
-
-                                None => println!("      Field Attributes= 0"),
-                            }
-                        }
-                    }
-                    None => println!("INFO: Fields= 0"),
-                }
+fn dependencies(options: &DependencyOptions) -> Result<(), String> {
+    let mut classes = Vec::<ClassFile>::new();
+    let mut failures = 0;
+    for path in options.paths.iter() {
+        if !Path::new(path).exists() {
+            return Err(format!("no such file or directory: {path}"));
+        }
+        classpath::walk(Path::new(path), &mut |source, class| match class {
+            Ok(class) => classes.push(class),
+            Err(e) => {
+                eprintln!(
+                    "ERROR: could not parse class file: {}: {e}",
+                    source.location
+                );
+                failures += 1;
             }
+        });
+    }
+    let graph = DependencyGraph::build(&classes, options.external);
+    if options.dot {
+        print!("{}", graph.to_dot(options.packages));
+    } else {
+        print_dependencies(&graph, options)?;
+    }
+    // The graph misses the dependencies of these classes, so it is printed but not trusted.
+    if failures > 0 {
+        return Err(format!("{failures} classes could not be parsed"));
+    }
+    Ok(())
+}
 
-            if verbose.can_verbose_methods() {
-                match methods {
-                    Some(items) => {
-                        println!("INFO: Methods= {}", items.capacity());
-                        for (i, item) in items.iter().enumerate() {
-                            let flag = item.0.to_string();
-                            println!("    {:02} {} {} {}", i, flag, item.1, item.2);
-                            let attributes = &item.3;
-                            match attributes {
-                                Some(items) => {
-                                    println!("      Method Attributes= {}", items.capacity());
-                                    for (i, item) in items.iter().enumerate() {
-                                        let name = item.0.to_string();
-                                        let len = item.1;
-                                        println!("        {:02} {} {}", i, name, len);
-                                    }
-                                }
-
-                                None => println!("      Method Attributes= 0"),
-                            }
-                        }
-                    }
-                    None => println!("INFO: Methods= 0"),
+fn print_dependencies(graph: &DependencyGraph, options: &DependencyOptions) -> Result<(), String> {
+    match &options.format {
+        Format::Text => {
+            let edges = if options.packages {
+                &graph.packages
+            } else {
+                &graph.classes
+            };
+            for (from, targets) in edges.iter() {
+                for to in targets.iter() {
+                    println!("{} -> {}", java_name(from), java_name(to));
                 }
             }
-            if verbose.can_verbose_attributes() {
-                match attributes {
-                    Some(items) => {
-                        println!("INFO: Attributes= {}", items.capacity());
-                        for (i, item) in items.iter().enumerate() {
-                            let name = item.0.to_string();
-                            let len = item.1;
-                            println!("    {:02} {} {}", i, name, len);
-                        }
-                    }
-                    None => println!("INFO: Attributes= 0"),
-                }
+            for cycle in graph.package_cycles.iter() {
+                let names: Vec<String> = cycle.iter().map(|name| java_name(name)).collect();
+                println!("WARNING: package cycle: {}", names.join(", "));
+            }
+            println!(
+                "INFO: Classes= {}, Packages= {}, Cycles= {}",
+                graph.classes.len(),
+                graph.packages.len(),
+                graph.package_cycles.len()
+            );
+        }
+        format => print_serialized(graph, format)
+            .map_err(|e| format!("could not serialise dependency graph: {e}"))?,
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = match cli::parse_cli_args() {
+        Action::Inspect(options) => {
+            let path = options.file_path.as_str();
+            match split_entry(path) {
+                Some((archive, entry)) => inspect_entry(archive, entry, &options),
+                None => inspect_file(path, &options),
             }
         }
+        Action::Summary(paths, format) => summarize(&paths, &format),
+        Action::Assemble(source, output) => assemble(&source, output.as_deref()),
+        Action::Verify(paths, format) => verify(&paths, &format),
+        Action::Run(path, args) => run(&path, &args),
+        Action::Diff(old, new, format) => compare(&old, &new, &format),
+        Action::Compat(old, new, format) => check_compat(&old, &new, &format),
+        Action::Dependencies(options) => dependencies(&options),
+        Action::Explore(path) => read_class(&path).and_then(repl::explore),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
-            eprintln!("ERROR: could not open file: {file_path}: {e}");
+            eprintln!("ERROR: {e}");
+            ExitCode::FAILURE
         }
     }
 }
@@ -0,0 +1,153 @@
+// Java "modified UTF-8" as used by CONSTANT_Utf8 entries (JVMS 4.4.7). It differs from
+// standard UTF-8 in two ways: NUL is encoded with two bytes (0xC0 0x80) and supplementary
+// characters are encoded as a surrogate pair of three byte sequences instead of four bytes.
+
+const REPLACEMENT: char = '\u{FFFD}';
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Decodes one UTF-16 code unit starting at `pos`, returns it with its encoded length.
+fn decode_unit(bytes: &[u8], pos: usize) -> Result<(u16, usize), usize> {
+    let b0 = bytes[pos];
+    match b0 {
+        0x01..=0x7F => Ok((b0 as u16, 1)),
+        0xC0..=0xDF => match bytes.get(pos + 1) {
+            Some(&b1) if is_continuation(b1) => {
+                Ok((((b0 as u16 & 0x1F) << 6) | (b1 as u16 & 0x3F), 2))
+            }
+            _ => Err(pos),
+        },
+        0xE0..=0xEF => match (bytes.get(pos + 1), bytes.get(pos + 2)) {
+            (Some(&b1), Some(&b2)) if is_continuation(b1) && is_continuation(b2) => Ok((
+                ((b0 as u16 & 0x0F) << 12) | ((b1 as u16 & 0x3F) << 6) | (b2 as u16 & 0x3F),
+                3,
+            )),
+            _ => Err(pos),
+        },
+        // NUL is never encoded as a single byte, 0xF0..=0xFF and lone continuation
+        // bytes never appear in modified UTF-8.
+        _ => Err(pos),
+    }
+}
+
+/// Decodes modified UTF-8 bytes, the error holds the position of the malformed sequence.
+///
+/// Unpaired surrogates are legal in Java strings but can not be represented by a Rust
+/// `String`, they are replaced by U+FFFD.
+pub fn decode(bytes: &[u8]) -> Result<String, usize> {
+    // Plain ASCII (the vast majority of class file strings) is identical in both encodings.
+    if bytes.iter().all(|b| (0x01..0x80).contains(b)) {
+        return Ok(bytes.iter().map(|b| *b as char).collect());
+    }
+    let mut acc = String::with_capacity(bytes.len());
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let (unit, length) = decode_unit(bytes, pos)?;
+        pos += length;
+        match unit {
+            0xD800..=0xDBFF => {
+                if pos < bytes.len() {
+                    if let Ok((low @ 0xDC00..=0xDFFF, low_length)) = decode_unit(bytes, pos) {
+                        let code =
+                            0x10000 + (((unit as u32 - 0xD800) << 10) | (low as u32 - 0xDC00));
+                        acc.push(char::from_u32(code).unwrap_or(REPLACEMENT));
+                        pos += low_length;
+                        continue;
+                    }
+                }
+                acc.push(REPLACEMENT);
+            }
+            0xDC00..=0xDFFF => acc.push(REPLACEMENT),
+            _ => acc.push(char::from_u32(unit as u32).unwrap_or(REPLACEMENT)),
+        }
+    }
+    Ok(acc)
+}
+
+fn encode_unit(acc: &mut Vec<u8>, unit: u16) {
+    match unit {
+        0x0001..=0x007F => acc.push(unit as u8),
+        0x0000 | 0x0080..=0x07FF => {
+            acc.push(0xC0 | (unit >> 6) as u8);
+            acc.push(0x80 | (unit & 0x3F) as u8);
+        }
+        _ => {
+            acc.push(0xE0 | (unit >> 12) as u8);
+            acc.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+            acc.push(0x80 | (unit & 0x3F) as u8);
+        }
+    }
+}
+
+/// Encodes a string the way the JVM stores CONSTANT_Utf8 entries.
+pub fn encode(value: &str) -> Vec<u8> {
+    let mut acc = Vec::<u8>::with_capacity(value.len());
+    let mut units = [0u16; 2];
+    for c in value.chars() {
+        for unit in c.encode_utf16(&mut units).iter() {
+            encode_unit(&mut acc, *unit);
+        }
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn test_mutf8_ascii() {
+        assert_eq!(
+            decode(b"java/lang/Object"),
+            Ok("java/lang/Object".to_string())
+        );
+        assert_eq!(encode("java/lang/Object"), b"java/lang/Object".to_vec());
+        assert_eq!(decode(b""), Ok(String::new()));
+    }
+
+    #[test]
+    fn test_mutf8_embedded_nul() {
+        assert_eq!(decode(&[b'a', 0xC0, 0x80, b'b']), Ok("a\0b".to_string()));
+        assert_eq!(encode("a\0b"), vec![b'a', 0xC0, 0x80, b'b']);
+    }
+
+    #[test]
+    fn test_mutf8_two_and_three_byte_sequences() {
+        assert_eq!(decode(&[0xC3, 0xA9]), Ok("é".to_string()));
+        assert_eq!(decode(&[0xE2, 0x82, 0xAC]), Ok("€".to_string()));
+        assert_eq!(encode("é€"), vec![0xC3, 0xA9, 0xE2, 0x82, 0xAC]);
+    }
+
+    #[test]
+    fn test_mutf8_supplementary_characters_as_surrogate_pairs() {
+        let bytes = [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+        assert_eq!(decode(&bytes), Ok("😀".to_string()));
+        assert_eq!(encode("😀"), bytes.to_vec());
+    }
+
+    #[test]
+    fn test_mutf8_unpaired_surrogate_is_replaced() {
+        assert_eq!(
+            decode(&[0xED, 0xA0, 0xBD, b'x']),
+            Ok("\u{FFFD}x".to_string())
+        );
+        assert_eq!(decode(&[0xED, 0xB8, 0x80]), Ok("\u{FFFD}".to_string()));
+    }
+
+    #[test]
+    fn test_mutf8_rejects_malformed_input() {
+        assert_eq!(decode(&[b'a', 0x00]), Err(1));
+        assert_eq!(decode(&[0xF0, 0x9F, 0x98, 0x80]), Err(0));
+        assert_eq!(decode(&[b'a', b'b', 0xC3]), Err(2));
+        assert_eq!(decode(&[0xE2, 0x82, b'x']), Err(0));
+        assert_eq!(decode(&[0x80]), Err(0));
+    }
+
+    #[test]
+    fn test_mutf8_round_trip() {
+        let value = "rich\0😀é\u{10FFFF}mixed ascii";
+        assert_eq!(decode(&encode(value)), Ok(value.to_string()));
+    }
+}
@@ -1,27 +1,52 @@
+use std::collections::HashMap;
 use std::fmt;
-use std::fs::File;
-use std::io::BufReader;
-
-use crate::reader;
-
-const CONSTANTPOOL_UTF8: u8 = 1;
-const CONSTANTPOOL_INTEGER: u8 = 3;
-const CONSTANTPOOL_FLOAT: u8 = 4;
-const CONSTANTPOOL_LONG: u8 = 5;
-const CONSTANTPOOL_DOUBLE: u8 = 6;
-const CONSTANTPOOL_CLASS: u8 = 7;
-const CONSTANTPOOL_STRING: u8 = 8;
-const CONSTANTPOOL_FIELDREF: u8 = 9;
-const CONSTANTPOOL_METHODREF: u8 = 10;
-const CONSTANTPOOL_INTERFACEMETHODREF: u8 = 11;
-const CONSTANTPOOL_NAMEANDTYPE: u8 = 12;
-const CONSTANTPOOL_METHODHANDLE: u8 = 15;
-const CONSTANTPOOL_METHODTYPE: u8 = 16;
-const CONSTANTPOOL_INVOKEDYNAMIC: u8 = 18;
-
-#[derive(Debug, Clone)]
+use std::io::{self, Read, Write};
+
+use crate::error::{Context, ErrorKind, ParseError};
+use crate::literal;
+use crate::mutf8;
+use crate::reader::ClassReader;
+use crate::writer::ClassWriter;
+
+pub(crate) const CONSTANTPOOL_UTF8: u8 = 1;
+pub(crate) const CONSTANTPOOL_INTEGER: u8 = 3;
+pub(crate) const CONSTANTPOOL_FLOAT: u8 = 4;
+pub(crate) const CONSTANTPOOL_LONG: u8 = 5;
+pub(crate) const CONSTANTPOOL_DOUBLE: u8 = 6;
+pub(crate) const CONSTANTPOOL_CLASS: u8 = 7;
+pub(crate) const CONSTANTPOOL_STRING: u8 = 8;
+pub(crate) const CONSTANTPOOL_FIELDREF: u8 = 9;
+pub(crate) const CONSTANTPOOL_METHODREF: u8 = 10;
+pub(crate) const CONSTANTPOOL_INTERFACEMETHODREF: u8 = 11;
+pub(crate) const CONSTANTPOOL_NAMEANDTYPE: u8 = 12;
+pub(crate) const CONSTANTPOOL_METHODHANDLE: u8 = 15;
+pub(crate) const CONSTANTPOOL_METHODTYPE: u8 = 16;
+pub(crate) const CONSTANTPOOL_DYNAMIC: u8 = 17;
+pub(crate) const CONSTANTPOOL_INVOKEDYNAMIC: u8 = 18;
+pub(crate) const CONSTANTPOOL_MODULE: u8 = 19;
+pub(crate) const CONSTANTPOOL_PACKAGE: u8 = 20;
+
+const REFERENCE_KINDS: [(&str, u8); 9] = [
+    ("REF_getField", 1),
+    ("REF_getStatic", 2),
+    ("REF_putField", 3),
+    ("REF_putStatic", 4),
+    ("REF_invokeVirtual", 5),
+    ("REF_invokeStatic", 6),
+    ("REF_invokeSpecial", 7),
+    ("REF_newInvokeSpecial", 8),
+    ("REF_invokeInterface", 9),
+];
+
+pub fn reference_kind_name(kind: u8) -> &'static str {
+    match REFERENCE_KINDS.iter().find(|(_, item)| *item == kind) {
+        Some((name, _)) => name,
+        None => "REF_unknown",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConstantPoolItem {
-    Unknown(u8),
     Utf8(String),              // bytes/content
     Class(u16),                // nameIndex
     String(u16),               // stringIndex
@@ -31,11 +56,15 @@ pub enum ConstantPoolItem {
     InterfaceMethod(u16, u16), // classIndex, nameAndTypeIndex
     MethodHandle(u8, u16),     // referenceKind, referenceIndex
     MethodType(u16),           // descriptorIndex
+    Dynamic(u16, u16),         // bootstrapMethodAttrIndex, nameAndTypeIndex
     InvokeDynamic(u16, u16),   // bootstrapMethodAttrIndex, nameAndTypeIndex
-    Integer(u32),              // bytes/content
-    Float(u32),                // bytes/content
-    Long(u32, u32),            // high, low bytes
-    Double(u32, u32),          // high, low bytes
+    Module(u16),               // nameIndex
+    Package(u16),              // nameIndex
+    Integer(i32),              // value
+    Float(f32),                // value
+    Long(i64),                 // value
+    Double(f64),               // value
+    Unusable,                  // second slot taken by the preceding Long or Double
 }
 
 impl fmt::Display for ConstantPoolItem {
@@ -62,142 +91,727 @@ impl fmt::Display for ConstantPoolItem {
                 write!(f, "MethodHandle => Kind: {}, RefIndex: {}", kind, index)
             }
             Self::MethodType(index) => write!(f, "MethodType => DescIndex: {}", index),
+            Self::Dynamic(bootstrap_index, nat_index) => write!(
+                f,
+                "Dynamic => BootstrapMethodAttrIndex: {}, NatIndex: {}",
+                bootstrap_index, nat_index
+            ),
             Self::InvokeDynamic(class_index, nat_index) => write!(
                 f,
                 "InvokeDynamic => BootstrapMethodAttrIndex: {}, NatIndex: {}",
                 class_index, nat_index
             ),
-            Self::Integer(index) => write!(f, "Integer => Value: {}", index),
-            Self::Float(index) => write!(f, "Float => Value: {}", index),
-            Self::Long(high, low) => write!(f, "Long => High: {}, Low: {}", high, low),
-            Self::Double(high, low) => write!(f, "Double => High: {}, Low: {}", high, low),
-            Self::Unknown(tag) => write!(f, "Unknown => Tag: {}", tag),
+            Self::Module(index) => write!(f, "Module => NameIndex: {}", index),
+            Self::Package(index) => write!(f, "Package => NameIndex: {}", index),
+            Self::Integer(value) => write!(f, "Integer => Value: {}", literal::int_literal(*value)),
+            Self::Float(value) => write!(f, "Float => Value: {}", literal::float_literal(*value)),
+            Self::Long(value) => write!(f, "Long => Value: {}", literal::long_literal(*value)),
+            Self::Double(value) => {
+                write!(f, "Double => Value: {}", literal::double_literal(*value))
+            }
+            Self::Unusable => write!(f, "Unusable"),
         }
     }
 }
 
 impl ConstantPoolItem {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Utf8(_) => "UTF8",
+            Self::Class(_) => "Class",
+            Self::String(_) => "String",
+            Self::Field(_, _) => "Field",
+            Self::Method(_, _) => "Method",
+            Self::NameAndType(_, _) => "NameAndType",
+            Self::InterfaceMethod(_, _) => "InterfaceMethod",
+            Self::MethodHandle(_, _) => "MethodHandle",
+            Self::MethodType(_) => "MethodType",
+            Self::Dynamic(_, _) => "Dynamic",
+            Self::InvokeDynamic(_, _) => "InvokeDynamic",
+            Self::Module(_) => "Module",
+            Self::Package(_) => "Package",
+            Self::Integer(_) => "Integer",
+            Self::Float(_) => "Float",
+            Self::Long(_) => "Long",
+            Self::Double(_) => "Double",
+            Self::Unusable => "Unusable",
+        }
+    }
+
+    /// Whether both entries encode the same bytes. Unlike `==`, floats compare by their bits so
+    /// that `-0.0` and `0.0` stay apart and a NaN matches itself.
+    pub fn same(&self, other: &ConstantPoolItem) -> bool {
+        match (self, other) {
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::Double(a), Self::Double(b)) => a.to_bits() == b.to_bits(),
+            (a, b) => a == b,
+        }
+    }
+
     pub fn resolve(&self, pool: &ConstantPool) -> String {
         match self {
             Self::Utf8(c) => c.to_string(),
-            Self::Unknown(c) => format!("Unknown({})", c),
-            Self::Integer(c) => format!("{}", c),
-            Self::Float(c) => format!("{}", c),
-            Self::Long(c1, c2) => format!("H:{},L:{}", c1, c2),
-            Self::Double(c1, c2) => format!("H:{},L:{}", c1, c2),
-            Self::Class(index) | Self::String(index) | Self::MethodType(index) => {
-                let item = pool.get(index.clone());
-                item.resolve(pool)
-            }
+            Self::Integer(value) => literal::int_literal(*value),
+            Self::Float(value) => literal::float_literal(*value),
+            Self::Long(value) => literal::long_literal(*value),
+            Self::Double(value) => literal::double_literal(*value),
+            Self::Unusable => "Unusable".to_string(),
+            Self::Class(index)
+            | Self::String(index)
+            | Self::MethodType(index)
+            | Self::Module(index)
+            | Self::Package(index) => pool.resolve(*index),
             Self::NameAndType(name_index, type_index) => {
-                let n = pool.get(name_index.clone());
-                let t = pool.get(type_index.clone());
-                format!("{} {}", n.resolve(pool), t.resolve(pool))
+                format!(
+                    "{}:{}",
+                    pool.resolve(*name_index),
+                    pool.resolve(*type_index)
+                )
+            }
+            Self::Field(class_index, nat_index)
+            | Self::Method(class_index, nat_index)
+            | Self::InterfaceMethod(class_index, nat_index) => {
+                format!(
+                    "{}.{}",
+                    pool.resolve(*class_index),
+                    pool.resolve(*nat_index)
+                )
+            }
+            Self::MethodHandle(kind, index) => {
+                format!("{} {}", reference_kind_name(*kind), pool.resolve(*index))
             }
-            Self::Field(name_index, type_index) => {
-                let n = pool.get(name_index.clone());
-                let t = pool.get(type_index.clone());
-                format!("Field: {} {}", n.resolve(pool), t.resolve(pool))
+            Self::Dynamic(bootstrap_index, nat_index)
+            | Self::InvokeDynamic(bootstrap_index, nat_index) => {
+                format!("#{}:{}", bootstrap_index, pool.resolve(*nat_index))
             }
-            _ => "not implemented yet".to_string(),
         }
     }
 }
 
-pub struct ConstantPool(Vec<ConstantPoolItem>);
+#[derive(Debug, Clone, Default)]
+pub struct ConstantPool {
+    items: Vec<ConstantPoolItem>,
+    // Original bytes of the UTF8 entries that are not in canonical modified UTF-8 (e.g.
+    // unpaired surrogates or overlong sequences), so that writing reproduces them.
+    raw_utf8: HashMap<u16, Vec<u8>>,
+}
 
 impl ConstantPool {
-    pub fn from(rdr: &mut BufReader<File>) -> Self {
-        let constant_pool_count = reader::read_u16(rdr);
+    pub(crate) fn parse<R: Read>(rdr: &mut ClassReader<R>) -> Result<Self, ParseError> {
+        let constant_pool_count = rdr.read_u16().context("constant pool count")?;
         let mut ret = Vec::<ConstantPoolItem>::with_capacity(constant_pool_count as usize);
-        for _ in 1..(constant_pool_count) {
-            let tag = reader::read_u8(rdr);
-            let item = match tag {
-                CONSTANTPOOL_CLASS => {
-                    let index = reader::read_u16(rdr);
-                    ConstantPoolItem::Class(index)
-                }
-                CONSTANTPOOL_METHODREF => {
-                    let class = reader::read_u16(rdr);
-                    let nat = reader::read_u16(rdr);
-                    ConstantPoolItem::Method(class, nat)
-                }
-                CONSTANTPOOL_NAMEANDTYPE => {
-                    let class = reader::read_u16(rdr);
-                    let nat = reader::read_u16(rdr);
-                    ConstantPoolItem::NameAndType(class, nat)
-                }
-                CONSTANTPOOL_UTF8 => {
-                    let length = reader::read_u16(rdr);
-                    let value: String = reader::read_str(rdr, length as usize);
-                    ConstantPoolItem::Utf8(value)
-                }
-                CONSTANTPOOL_FIELDREF => {
-                    let class = reader::read_u16(rdr);
-                    let nat = reader::read_u16(rdr);
-                    ConstantPoolItem::Field(class, nat)
-                }
-                CONSTANTPOOL_INTERFACEMETHODREF => {
-                    let class = reader::read_u16(rdr);
-                    let nat = reader::read_u16(rdr);
-                    ConstantPoolItem::InterfaceMethod(class, nat)
-                }
-                CONSTANTPOOL_STRING => {
-                    let class_index = reader::read_u16(rdr);
-                    ConstantPoolItem::String(class_index)
-                }
-                CONSTANTPOOL_INTEGER => {
-                    let val = reader::read_u32(rdr);
-                    ConstantPoolItem::Integer(val)
-                }
-                CONSTANTPOOL_FLOAT => {
-                    let val = reader::read_u32(rdr);
-                    ConstantPoolItem::Float(val)
-                }
-                CONSTANTPOOL_LONG => {
-                    let high_val = reader::read_u32(rdr);
-                    let low_val = reader::read_u32(rdr);
-                    ConstantPoolItem::Long(high_val, low_val)
-                }
-                CONSTANTPOOL_DOUBLE => {
-                    let high_val = reader::read_u32(rdr);
-                    let low_val = reader::read_u32(rdr);
-                    ConstantPoolItem::Double(high_val, low_val)
-                }
-                CONSTANTPOOL_METHODHANDLE => {
-                    let kind = reader::read_u8(rdr);
-                    let index = reader::read_u16(rdr);
-                    ConstantPoolItem::MethodHandle(kind, index)
-                }
-                CONSTANTPOOL_METHODTYPE => {
-                    let index = reader::read_u16(rdr);
-                    ConstantPoolItem::MethodType(index)
+        let mut offsets = Vec::<(u16, u64)>::with_capacity(constant_pool_count as usize);
+        let mut raw_utf8 = HashMap::<u16, Vec<u8>>::new();
+        let mut index: u16 = 1;
+        while index < constant_pool_count {
+            let offset = rdr.offset();
+            let (item, raw) =
+                Self::parse_item(rdr).with_context(|| format!("constant pool entry #{}", index))?;
+            if let Some(raw) = raw {
+                raw_utf8.insert(index, raw);
+            }
+            // Long and Double constants take two indexes, the second one is not usable (JVMS 4.4.5).
+            let wide = matches!(
+                item,
+                ConstantPoolItem::Long(_) | ConstantPoolItem::Double(_)
+            );
+            if wide && index + 1 >= constant_pool_count {
+                let kind = ErrorKind::Malformed(format!(
+                    "{} constant has no room for its second slot",
+                    item.kind()
+                ));
+                return Err(ParseError::new(offset, kind))
+                    .with_context(|| format!("constant pool entry #{}", index));
+            }
+            offsets.push((index, offset));
+            ret.push(item);
+            index += 1;
+            if wide {
+                ret.push(ConstantPoolItem::Unusable);
+                index += 1;
+            }
+        }
+        let pool = ConstantPool {
+            items: ret,
+            raw_utf8,
+        };
+        for (index, offset) in offsets.into_iter() {
+            pool.validate(index)
+                .map_err(|kind| ParseError::new(offset, kind))
+                .with_context(|| format!("constant pool entry #{}", index))?;
+        }
+        Ok(pool)
+    }
+
+    /// Parses one entry, UTF8 entries that do not survive decoding also return their bytes.
+    pub(crate) fn parse_item<R: Read>(
+        rdr: &mut ClassReader<R>,
+    ) -> Result<(ConstantPoolItem, Option<Vec<u8>>), ParseError> {
+        let tag = rdr.read_u8()?;
+        let mut raw = None;
+        let item = match tag {
+            CONSTANTPOOL_CLASS => {
+                let index = rdr.read_u16()?;
+                ConstantPoolItem::Class(index)
+            }
+            CONSTANTPOOL_METHODREF => {
+                let class = rdr.read_u16()?;
+                let nat = rdr.read_u16()?;
+                ConstantPoolItem::Method(class, nat)
+            }
+            CONSTANTPOOL_NAMEANDTYPE => {
+                let class = rdr.read_u16()?;
+                let nat = rdr.read_u16()?;
+                ConstantPoolItem::NameAndType(class, nat)
+            }
+            CONSTANTPOOL_UTF8 => {
+                let length = rdr.read_u16()?;
+                let (value, bytes) = rdr.read_str(length as usize)?;
+                if mutf8::encode(&value) != bytes {
+                    raw = Some(bytes);
                 }
-                CONSTANTPOOL_INVOKEDYNAMIC => {
-                    let attr_index = reader::read_u16(rdr);
-                    let name_and_type_index = reader::read_u16(rdr);
-                    ConstantPoolItem::InvokeDynamic(attr_index, name_and_type_index)
+                ConstantPoolItem::Utf8(value)
+            }
+            CONSTANTPOOL_FIELDREF => {
+                let class = rdr.read_u16()?;
+                let nat = rdr.read_u16()?;
+                ConstantPoolItem::Field(class, nat)
+            }
+            CONSTANTPOOL_INTERFACEMETHODREF => {
+                let class = rdr.read_u16()?;
+                let nat = rdr.read_u16()?;
+                ConstantPoolItem::InterfaceMethod(class, nat)
+            }
+            CONSTANTPOOL_STRING => {
+                let class_index = rdr.read_u16()?;
+                ConstantPoolItem::String(class_index)
+            }
+            CONSTANTPOOL_INTEGER => {
+                let val = rdr.read_u32()?;
+                ConstantPoolItem::Integer(val as i32)
+            }
+            CONSTANTPOOL_FLOAT => {
+                let val = rdr.read_u32()?;
+                ConstantPoolItem::Float(f32::from_bits(val))
+            }
+            CONSTANTPOOL_LONG => {
+                let high_val = rdr.read_u32()?;
+                let low_val = rdr.read_u32()?;
+                ConstantPoolItem::Long((((high_val as u64) << 32) | low_val as u64) as i64)
+            }
+            CONSTANTPOOL_DOUBLE => {
+                let high_val = rdr.read_u32()?;
+                let low_val = rdr.read_u32()?;
+                ConstantPoolItem::Double(f64::from_bits(((high_val as u64) << 32) | low_val as u64))
+            }
+            CONSTANTPOOL_METHODHANDLE => {
+                let kind = rdr.read_u8()?;
+                let index = rdr.read_u16()?;
+                ConstantPoolItem::MethodHandle(kind, index)
+            }
+            CONSTANTPOOL_METHODTYPE => {
+                let index = rdr.read_u16()?;
+                ConstantPoolItem::MethodType(index)
+            }
+            CONSTANTPOOL_DYNAMIC => {
+                let attr_index = rdr.read_u16()?;
+                let name_and_type_index = rdr.read_u16()?;
+                ConstantPoolItem::Dynamic(attr_index, name_and_type_index)
+            }
+            CONSTANTPOOL_INVOKEDYNAMIC => {
+                let attr_index = rdr.read_u16()?;
+                let name_and_type_index = rdr.read_u16()?;
+                ConstantPoolItem::InvokeDynamic(attr_index, name_and_type_index)
+            }
+            CONSTANTPOOL_MODULE => {
+                let name_index = rdr.read_u16()?;
+                ConstantPoolItem::Module(name_index)
+            }
+            CONSTANTPOOL_PACKAGE => {
+                let name_index = rdr.read_u16()?;
+                ConstantPoolItem::Package(name_index)
+            }
+            _ => {
+                return Err(ParseError::new(
+                    rdr.offset() - 1,
+                    ErrorKind::UnknownPoolTag(tag),
+                ))
+            }
+        };
+        Ok((item, raw))
+    }
+
+    pub(crate) fn write<W: Write>(&self, wtr: &mut ClassWriter<W>) -> io::Result<()> {
+        wtr.write_count("constant pool count", self.items.len() + 1)?;
+        for (index, item) in self.iter() {
+            self.write_item(wtr, index, item)?;
+        }
+        Ok(())
+    }
+
+    fn write_item<W: Write>(
+        &self,
+        wtr: &mut ClassWriter<W>,
+        index: u16,
+        item: &ConstantPoolItem,
+    ) -> io::Result<()> {
+        match item {
+            ConstantPoolItem::Utf8(value) => {
+                wtr.write_u8(CONSTANTPOOL_UTF8)?;
+                match self.raw_utf8.get(&index) {
+                    Some(raw) => {
+                        wtr.write_count("string length", raw.len())?;
+                        wtr.write_bytes(raw)
+                    }
+                    None => wtr.write_str(value),
                 }
-                _ => ConstantPoolItem::Unknown(tag),
-            };
-            ret.push(item);
+            }
+            ConstantPoolItem::Integer(value) => {
+                wtr.write_u8(CONSTANTPOOL_INTEGER)?;
+                wtr.write_u32(*value as u32)
+            }
+            ConstantPoolItem::Float(value) => {
+                wtr.write_u8(CONSTANTPOOL_FLOAT)?;
+                wtr.write_u32(value.to_bits())
+            }
+            ConstantPoolItem::Long(value) => {
+                wtr.write_u8(CONSTANTPOOL_LONG)?;
+                wtr.write_bytes(&value.to_be_bytes())
+            }
+            ConstantPoolItem::Double(value) => {
+                wtr.write_u8(CONSTANTPOOL_DOUBLE)?;
+                wtr.write_bytes(&value.to_bits().to_be_bytes())
+            }
+            ConstantPoolItem::MethodHandle(kind, index) => {
+                wtr.write_u8(CONSTANTPOOL_METHODHANDLE)?;
+                wtr.write_u8(*kind)?;
+                wtr.write_u16(*index)
+            }
+            ConstantPoolItem::Class(index)
+            | ConstantPoolItem::String(index)
+            | ConstantPoolItem::MethodType(index)
+            | ConstantPoolItem::Module(index)
+            | ConstantPoolItem::Package(index) => {
+                let tag = match item {
+                    ConstantPoolItem::Class(_) => CONSTANTPOOL_CLASS,
+                    ConstantPoolItem::String(_) => CONSTANTPOOL_STRING,
+                    ConstantPoolItem::MethodType(_) => CONSTANTPOOL_METHODTYPE,
+                    ConstantPoolItem::Module(_) => CONSTANTPOOL_MODULE,
+                    _ => CONSTANTPOOL_PACKAGE,
+                };
+                wtr.write_u8(tag)?;
+                wtr.write_u16(*index)
+            }
+            ConstantPoolItem::Field(first, second)
+            | ConstantPoolItem::Method(first, second)
+            | ConstantPoolItem::InterfaceMethod(first, second)
+            | ConstantPoolItem::NameAndType(first, second)
+            | ConstantPoolItem::Dynamic(first, second)
+            | ConstantPoolItem::InvokeDynamic(first, second) => {
+                let tag = match item {
+                    ConstantPoolItem::Field(_, _) => CONSTANTPOOL_FIELDREF,
+                    ConstantPoolItem::Method(_, _) => CONSTANTPOOL_METHODREF,
+                    ConstantPoolItem::InterfaceMethod(_, _) => CONSTANTPOOL_INTERFACEMETHODREF,
+                    ConstantPoolItem::NameAndType(_, _) => CONSTANTPOOL_NAMEANDTYPE,
+                    ConstantPoolItem::Dynamic(_, _) => CONSTANTPOOL_DYNAMIC,
+                    _ => CONSTANTPOOL_INVOKEDYNAMIC,
+                };
+                wtr.write_u8(tag)?;
+                wtr.write_u16(*first)?;
+                wtr.write_u16(*second)
+            }
+            // The second slot of a Long or Double has no bytes of its own.
+            ConstantPoolItem::Unusable => Ok(()),
+        }
+    }
+
+    /// Checks that the references of an entry point at entries of the expected kind.
+    fn validate(&self, index: u16) -> Result<(), ErrorKind> {
+        match self.get(index)? {
+            ConstantPoolItem::Class(name) => self.get_utf8(*name).map(|_| ()),
+            ConstantPoolItem::String(value) => self.get_utf8(*value).map(|_| ()),
+            ConstantPoolItem::MethodType(desc) => self.get_utf8(*desc).map(|_| ()),
+            ConstantPoolItem::Module(name) | ConstantPoolItem::Package(name) => {
+                self.get_utf8(*name).map(|_| ())
+            }
+            ConstantPoolItem::NameAndType(name, desc) => {
+                self.get_utf8(*name)?;
+                self.get_utf8(*desc).map(|_| ())
+            }
+            ConstantPoolItem::Field(class, nat)
+            | ConstantPoolItem::Method(class, nat)
+            | ConstantPoolItem::InterfaceMethod(class, nat) => {
+                self.get_class_name(*class)?;
+                self.expect(*nat, "NameAndType", |item| {
+                    matches!(item, ConstantPoolItem::NameAndType(_, _))
+                })
+            }
+            ConstantPoolItem::Dynamic(_, nat) | ConstantPoolItem::InvokeDynamic(_, nat) => self
+                .expect(*nat, "NameAndType", |item| {
+                    matches!(item, ConstantPoolItem::NameAndType(_, _))
+                }),
+            ConstantPoolItem::MethodHandle(kind, _) if !(1..=9).contains(kind) => Err(
+                ErrorKind::Malformed(format!("invalid method handle reference kind {}", kind)),
+            ),
+            ConstantPoolItem::MethodHandle(_, reference) => {
+                self.expect(*reference, "Field, Method or InterfaceMethod", |item| {
+                    matches!(
+                        item,
+                        ConstantPoolItem::Field(_, _)
+                            | ConstantPoolItem::Method(_, _)
+                            | ConstantPoolItem::InterfaceMethod(_, _)
+                    )
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn expect<F: Fn(&ConstantPoolItem) -> bool>(
+        &self,
+        index: u16,
+        expected: &'static str,
+        accept: F,
+    ) -> Result<(), ErrorKind> {
+        let item = self.get(index)?;
+        if accept(item) {
+            Ok(())
+        } else {
+            Err(ErrorKind::WrongPoolEntry(index, expected, item.kind()))
         }
-        ConstantPool(ret)
     }
 
     pub fn print(&self) {
-        println!("INFO: ConstantPool= {:02}", self.0.capacity());
-        for (i, item) in self.0.iter().enumerate() {
-            println!("    {:03} {}", (i + 1), item);
+        println!("INFO: ConstantPool= {:02}", self.items.len() + 1);
+        for (index, item) in self.iter() {
+            println!("    {:03} {}", index, item);
+        }
+    }
+
+    /// Number of slots, including the unusable ones (`constant_pool_count - 1`).
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// An empty pool to build a class file from scratch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry and returns its index, the same entry already in the pool is reused.
+    pub fn add(&mut self, item: ConstantPoolItem) -> Result<u16, ErrorKind> {
+        if let Some((index, _)) = self.iter().find(|(_, existing)| existing.same(&item)) {
+            return Ok(index);
+        }
+        let wide = matches!(
+            item,
+            ConstantPoolItem::Long(_) | ConstantPoolItem::Double(_)
+        );
+        let slots = if wide { 2 } else { 1 };
+        // constant_pool_count is a u16 and one larger than the number of slots.
+        if self.items.len() + slots >= u16::MAX as usize {
+            return Err(ErrorKind::Malformed("constant pool is full".to_string()));
+        }
+        self.items.push(item);
+        let index = self.items.len() as u16;
+        if wide {
+            self.items.push(ConstantPoolItem::Unusable);
+        }
+        Ok(index)
+    }
+
+    pub fn add_utf8(&mut self, value: &str) -> Result<u16, ErrorKind> {
+        self.add(ConstantPoolItem::Utf8(value.to_string()))
+    }
+
+    pub fn add_class(&mut self, name: &str) -> Result<u16, ErrorKind> {
+        let name = self.add_utf8(name)?;
+        self.add(ConstantPoolItem::Class(name))
+    }
+
+    pub fn add_string(&mut self, value: &str) -> Result<u16, ErrorKind> {
+        let value = self.add_utf8(value)?;
+        self.add(ConstantPoolItem::String(value))
+    }
+
+    pub fn add_name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, ErrorKind> {
+        let name = self.add_utf8(name)?;
+        let descriptor = self.add_utf8(descriptor)?;
+        self.add(ConstantPoolItem::NameAndType(name, descriptor))
+    }
+
+    pub fn add_field_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ErrorKind> {
+        let class = self.add_class(class)?;
+        let nat = self.add_name_and_type(name, descriptor)?;
+        self.add(ConstantPoolItem::Field(class, nat))
+    }
+
+    pub fn add_method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ErrorKind> {
+        let class = self.add_class(class)?;
+        let nat = self.add_name_and_type(name, descriptor)?;
+        self.add(ConstantPoolItem::Method(class, nat))
+    }
+
+    pub fn add_interface_method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<u16, ErrorKind> {
+        let class = self.add_class(class)?;
+        let nat = self.add_name_and_type(name, descriptor)?;
+        self.add(ConstantPoolItem::InterfaceMethod(class, nat))
+    }
+
+    /// Entry at a constant pool index as used by the class file (1-based, Long and Double
+    /// taking two slots). Index zero and the unusable slots are invalid.
+    pub fn get(&self, index: u16) -> Result<&ConstantPoolItem, ErrorKind> {
+        if index == 0 {
+            return Err(ErrorKind::InvalidPoolIndex(index));
+        }
+        match self.items.get((index - 1) as usize) {
+            Some(ConstantPoolItem::Unusable) | None => Err(ErrorKind::InvalidPoolIndex(index)),
+            Some(item) => Ok(item),
+        }
+    }
+
+    /// Iterates over the usable entries with their indexes.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &ConstantPoolItem)> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| !matches!(item, ConstantPoolItem::Unusable))
+            .map(|(i, item)| ((i + 1) as u16, item))
+    }
+
+    pub fn get_utf8(&self, index: u16) -> Result<&str, ErrorKind> {
+        match self.get(index)? {
+            ConstantPoolItem::Utf8(value) => Ok(value),
+            item => Err(ErrorKind::WrongPoolEntry(index, "UTF8", item.kind())),
+        }
+    }
+
+    pub fn get_class_name(&self, index: u16) -> Result<&str, ErrorKind> {
+        match self.get(index)? {
+            ConstantPoolItem::Class(name) => self.get_utf8(*name),
+            item => Err(ErrorKind::WrongPoolEntry(index, "Class", item.kind())),
+        }
+    }
+
+    pub fn get_name_and_type(&self, index: u16) -> Result<(&str, &str), ErrorKind> {
+        match self.get(index)? {
+            ConstantPoolItem::NameAndType(name, descriptor) => {
+                Ok((self.get_utf8(*name)?, self.get_utf8(*descriptor)?))
+            }
+            item => Err(ErrorKind::WrongPoolEntry(index, "NameAndType", item.kind())),
         }
     }
 
-    pub fn get(&self, index: u16) -> &ConstantPoolItem {
-        &self.0[(index - 1) as usize]
+    /// Class name, member name and descriptor of a Field, Method or InterfaceMethod entry.
+    pub fn get_member_ref(&self, index: u16) -> Result<(&str, &str, &str), ErrorKind> {
+        match self.get(index)? {
+            ConstantPoolItem::Field(class, nat)
+            | ConstantPoolItem::Method(class, nat)
+            | ConstantPoolItem::InterfaceMethod(class, nat) => {
+                let (name, descriptor) = self.get_name_and_type(*nat)?;
+                Ok((self.get_class_name(*class)?, name, descriptor))
+            }
+            item => Err(ErrorKind::WrongPoolEntry(
+                index,
+                "Field, Method or InterfaceMethod",
+                item.kind(),
+            )),
+        }
     }
 
+    /// Human readable value of an entry, invalid indexes are rendered instead of failing.
     pub fn resolve(&self, index: u16) -> String {
-        let item = self.get(index);
-        item.resolve(self)
+        match self.get(index) {
+            Ok(item) => item.resolve(self),
+            Err(_) => format!("<invalid #{}>", index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstantPool, ConstantPoolItem};
+    use crate::error::ErrorKind;
+    use crate::reader::ClassReader;
+    use crate::writer::ClassWriter;
+
+    fn parse(count: u16, entries: &[u8]) -> Result<ConstantPool, crate::error::ParseError> {
+        let mut bytes = count.to_be_bytes().to_vec();
+        bytes.extend_from_slice(entries);
+        ConstantPool::parse(&mut ClassReader::new(bytes.as_slice()))
+    }
+
+    fn write(pool: &ConstantPool) -> Vec<u8> {
+        let mut wtr = ClassWriter::new(Vec::<u8>::new());
+        pool.write(&mut wtr).unwrap();
+        wtr.into_inner()
+    }
+
+    #[rustfmt::skip]
+    const LONG_DOUBLE_POOL: [u8; 28] = [
+        5, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, // #1 Long, #2 unusable
+        1, 0x00, 0x01, b'A',                               // #3 Utf8 "A"
+        6, 0x40, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // #4 Double, #5 unusable
+        7, 0x00, 0x03,                                     // #6 Class #3
+        8, 0x00, 0x03,                                     // #7 String #3
+    ];
+
+    #[test]
+    fn test_pool_long_and_double_take_two_slots() {
+        let pool = parse(8, &LONG_DOUBLE_POOL).unwrap();
+        assert_eq!(pool.len(), 7);
+        assert!(matches!(
+            pool.get(1),
+            Ok(ConstantPoolItem::Long(0x0000_0001_0000_0002))
+        ));
+        assert!(matches!(pool.get(3), Ok(ConstantPoolItem::Utf8(value)) if value == "A"));
+        assert!(matches!(pool.get(4), Ok(ConstantPoolItem::Double(value)) if *value == 2.5));
+        assert!(matches!(pool.get(6), Ok(ConstantPoolItem::Class(3))));
+        assert_eq!(pool.get_class_name(6), Ok("A"));
+        assert_eq!(pool.resolve(7), "A");
+        assert_eq!(pool.resolve(1), "4294967298l");
+        assert_eq!(pool.resolve(4), "2.5d");
+    }
+
+    #[test]
+    fn test_pool_unusable_slots_are_invalid_indexes() {
+        let pool = parse(8, &LONG_DOUBLE_POOL).unwrap();
+        assert_eq!(pool.get(0).unwrap_err(), ErrorKind::InvalidPoolIndex(0));
+        assert_eq!(pool.get(2).unwrap_err(), ErrorKind::InvalidPoolIndex(2));
+        assert_eq!(pool.get(5).unwrap_err(), ErrorKind::InvalidPoolIndex(5));
+        assert_eq!(pool.get(8).unwrap_err(), ErrorKind::InvalidPoolIndex(8));
+        let indexes: Vec<u16> = pool.iter().map(|(index, _)| index).collect();
+        assert_eq!(indexes, vec![1, 3, 4, 6, 7]);
+    }
+
+    #[test]
+    fn test_pool_reference_to_unusable_slot() {
+        let mut entries = LONG_DOUBLE_POOL.to_vec();
+        entries[24] = 0x02; // Class #2 points at the second slot of the Long
+        let e = parse(8, &entries).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::InvalidPoolIndex(2));
+        assert_eq!(e.context, vec!["constant pool entry #6"]);
+    }
+
+    #[test]
+    fn test_pool_long_without_second_slot() {
+        let e = parse(2, &LONG_DOUBLE_POOL[..9]).unwrap_err();
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+    }
+
+    #[rustfmt::skip]
+    const MODERN_POOL: [u8; 57] = [
+        1, 0x00, 0x01, b'A',                // #1 Utf8 "A"
+        7, 0x00, 0x01,                      // #2 Class A
+        1, 0x00, 0x01, b'm',                // #3 Utf8 "m"
+        1, 0x00, 0x03, b'(', b')', b'V',    // #4 Utf8 "()V"
+        12, 0x00, 0x03, 0x00, 0x04,         // #5 NameAndType m:()V
+        10, 0x00, 0x02, 0x00, 0x05,         // #6 Method A.m:()V
+        15, 6, 0x00, 0x06,                  // #7 MethodHandle REF_invokeStatic #6
+        17, 0x00, 0x00, 0x00, 0x05,         // #8 Dynamic #0:m:()V
+        18, 0x00, 0x01, 0x00, 0x05,         // #9 InvokeDynamic #1:m:()V
+        11, 0x00, 0x02, 0x00, 0x05,         // #10 InterfaceMethod A.m:()V
+        19, 0x00, 0x03,                     // #11 Module m
+        20, 0x00, 0x01,                     // #12 Package A
+        9, 0x00, 0x02, 0x00, 0x05,          // #13 Field A.m:()V
+    ];
+
+    #[test]
+    fn test_pool_modern_tags_and_resolve() {
+        let pool = parse(14, &MODERN_POOL).unwrap();
+        assert_eq!(pool.resolve(5), "m:()V");
+        assert_eq!(pool.resolve(6), "A.m:()V");
+        assert_eq!(pool.resolve(7), "REF_invokeStatic A.m:()V");
+        assert_eq!(pool.resolve(8), "#0:m:()V");
+        assert_eq!(pool.resolve(9), "#1:m:()V");
+        assert_eq!(pool.resolve(10), "A.m:()V");
+        assert_eq!(pool.resolve(11), "m");
+        assert_eq!(pool.resolve(12), "A");
+        assert_eq!(pool.resolve(13), "A.m:()V");
+        assert!(matches!(pool.get(11), Ok(ConstantPoolItem::Module(3))));
+        assert!(matches!(pool.get(12), Ok(ConstantPoolItem::Package(1))));
+    }
+
+    #[test]
+    fn test_pool_invalid_method_handle_kind() {
+        let mut entries = MODERN_POOL.to_vec();
+        entries[28] = 10;
+        let e = parse(14, &entries).unwrap_err();
+        assert!(matches!(e.kind, ErrorKind::Malformed(_)));
+        assert_eq!(e.context, vec!["constant pool entry #7"]);
+    }
+
+    #[test]
+    fn test_pool_write_round_trip() {
+        let pool = parse(8, &LONG_DOUBLE_POOL).unwrap();
+        assert_eq!(write(&pool)[2..], LONG_DOUBLE_POOL);
+        let pool = parse(14, &MODERN_POOL).unwrap();
+        assert_eq!(write(&pool)[..2], [0x00, 0x0E]);
+        assert_eq!(write(&pool)[2..], MODERN_POOL);
+    }
+
+    #[test]
+    fn test_pool_write_keeps_non_canonical_utf8() {
+        #[rustfmt::skip]
+        let entries = [
+            1, 0x00, 0x04, 0xED, 0xA0, 0xBD, b'x', // #1 unpaired high surrogate
+            1, 0x00, 0x02, 0xC1, 0x81,             // #2 overlong "A"
+            1, 0x00, 0x02, 0xC3, 0xA9,             // #3 "é"
+        ];
+        let pool = parse(4, &entries).unwrap();
+        assert_eq!(pool.get_utf8(1), Ok("\u{FFFD}x"));
+        assert_eq!(pool.get_utf8(2), Ok("A"));
+        assert_eq!(write(&pool)[2..], entries);
+    }
+
+    #[test]
+    fn test_pool_add_reuses_equal_entries() {
+        let mut pool = ConstantPool::new();
+        assert_eq!(pool.add_class("A"), Ok(2));
+        assert_eq!(pool.add(ConstantPoolItem::Long(7)), Ok(3));
+        assert_eq!(pool.add_field_ref("A", "x", "J"), Ok(8));
+        assert_eq!(pool.add_class("A"), Ok(2));
+        assert_eq!(pool.add_utf8("x"), Ok(5));
+        assert_eq!(pool.len(), 8);
+        assert_eq!(pool.get(4).unwrap_err(), ErrorKind::InvalidPoolIndex(4));
+        assert_eq!(pool.resolve(8), "A.x:J");
+        let bytes = write(&pool);
+        let reparsed = parse(9, &bytes[2..]).unwrap();
+        assert_eq!(reparsed.resolve(8), "A.x:J");
+    }
+
+    #[test]
+    fn test_pool_add_compares_floats_by_bits() {
+        let mut pool = ConstantPool::new();
+        assert_eq!(pool.add(ConstantPoolItem::Float(0.0)), Ok(1));
+        assert_eq!(pool.add(ConstantPoolItem::Float(-0.0)), Ok(2));
+        assert_eq!(pool.add(ConstantPoolItem::Float(f32::NAN)), Ok(3));
+        assert_eq!(pool.add(ConstantPoolItem::Float(f32::NAN)), Ok(3));
+        assert_eq!(pool.add(ConstantPoolItem::Double(0.0)), Ok(4));
+        assert_eq!(pool.add(ConstantPoolItem::Double(-0.0)), Ok(6));
+        assert_eq!(pool.add(ConstantPoolItem::Double(f64::NAN)), Ok(8));
+        assert_eq!(pool.add(ConstantPoolItem::Double(f64::NAN)), Ok(8));
+        assert_eq!(pool.add(ConstantPoolItem::Double(-0.0)), Ok(6));
+        assert_eq!(pool.len(), 9);
+        assert!(matches!(pool.get(2), Ok(ConstantPoolItem::Float(v)) if v.is_sign_negative()));
     }
 }
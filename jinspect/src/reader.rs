@@ -1,35 +1,92 @@
-use std::fs::File;
-use std::io::{BufReader, Read};
-use std::str;
+use std::io::{self, Read};
 
-pub fn read_n(r: &mut BufReader<File>, limit: usize) -> Vec<u8> {
-    let mut buf = Vec::<u8>::with_capacity(limit);
-    for _i in 0..limit {
-        buf.push(0);
-    }
-    let _ = r.read_exact(&mut buf);
-    buf
-}
+use crate::error::{ErrorKind, ParseError};
+use crate::mutf8;
 
-pub fn read_u8(r: &mut BufReader<File>) -> u8 {
-    let mut buf: [u8; 1] = [0; 1];
-    let _ = r.read_exact(&mut buf);
-    u8::from_be_bytes(buf)
+/// Big-endian reader that keeps track of the absolute offset in the class file.
+pub struct ClassReader<R> {
+    inner: R,
+    offset: u64,
 }
 
-pub fn read_u16(r: &mut BufReader<File>) -> u16 {
-    let mut buf: [u8; 2] = [0; 2];
-    let _ = r.read_exact(&mut buf);
-    u16::from_be_bytes(buf)
-}
+impl<R: Read> ClassReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_offset(inner, 0)
+    }
 
-pub fn read_u32(r: &mut BufReader<File>) -> u32 {
-    let mut buf: [u8; 4] = [0; 4];
-    let _ = r.read_exact(&mut buf);
-    u32::from_be_bytes(buf)
-}
+    /// Reader over a sub-structure (e.g. an attribute body) that starts at `offset`.
+    pub fn with_offset(inner: R, offset: u64) -> Self {
+        ClassReader { inner, offset }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn error(&self, kind: ErrorKind) -> ParseError {
+        ParseError::new(self.offset, kind)
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), ParseError> {
+        match self.inner.read_exact(buf) {
+            Ok(()) => {
+                self.offset += buf.len() as u64;
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                Err(self.error(ErrorKind::Truncated))
+            }
+            Err(e) => Err(self.error(ErrorKind::Io(e.to_string()))),
+        }
+    }
 
-pub fn read_str(r: &mut BufReader<File>, length: usize) -> String {
-    let buf = read_n(r, length);
-    str::from_utf8(&buf).unwrap().to_string()
+    pub fn read_n(&mut self, limit: usize) -> Result<Vec<u8>, ParseError> {
+        let mut buf = Vec::new();
+        // Read through `take` so a corrupt length can not make us allocate gigabytes up front.
+        match (&mut self.inner).take(limit as u64).read_to_end(&mut buf) {
+            Ok(n) if n == limit => {
+                self.offset += n as u64;
+                Ok(buf)
+            }
+            Ok(n) => {
+                self.offset += n as u64;
+                Err(self.error(ErrorKind::Truncated))
+            }
+            Err(e) => Err(self.error(ErrorKind::Io(e.to_string()))),
+        }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, ParseError> {
+        let mut buf: [u8; 1] = [0; 1];
+        self.fill(&mut buf)?;
+        Ok(u8::from_be_bytes(buf))
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, ParseError> {
+        let mut buf: [u8; 2] = [0; 2];
+        self.fill(&mut buf)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, ParseError> {
+        let mut buf: [u8; 4] = [0; 4];
+        self.fill(&mut buf)?;
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    /// Reads a string encoded in the JVM's modified UTF-8, returns it with its raw bytes.
+    pub fn read_str(&mut self, length: usize) -> Result<(String, Vec<u8>), ParseError> {
+        let start = self.offset;
+        let buf = self.read_n(length)?;
+        match mutf8::decode(&buf) {
+            Ok(value) => Ok((value, buf)),
+            Err(position) => Err(ParseError::new(start + position as u64, ErrorKind::BadUtf8)),
+        }
+    }
+
+    /// Reads a `u16` count followed by that many `u16` values.
+    pub fn read_u16_table(&mut self) -> Result<Vec<u16>, ParseError> {
+        let count = self.read_u16()?;
+        (0..count).map(|_| self.read_u16()).collect()
+    }
 }
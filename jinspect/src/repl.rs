@@ -0,0 +1,85 @@
+// Line editor for `jinspect explore`: reads commands with history and tab completion of command,
+// member and class names, the commands themselves are run by `jinspect::explorer`.
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+
+use jinspect::explorer::Explorer;
+use jinspect::signature::java_name;
+use jinspect::ClassFile;
+
+const PROMPT: &str = "jinspect> ";
+
+struct ExplorerHelper {
+    explorer: Explorer,
+}
+
+impl Completer for ExplorerHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = self.explorer.complete(&line[..pos]);
+        let candidates = candidates
+            .into_iter()
+            .map(|name| Pair {
+                display: name.clone(),
+                replacement: name,
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ExplorerHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ExplorerHelper {}
+
+impl Validator for ExplorerHelper {}
+
+impl Helper for ExplorerHelper {}
+
+/// Runs the prompt until `quit`, `exit` or end of input.
+pub fn explore(class: ClassFile) -> Result<(), String> {
+    let mut editor = Editor::<ExplorerHelper, DefaultHistory>::new()
+        .map_err(|e| format!("could not start line editor: {e}"))?;
+    let explorer = Explorer::new(class);
+    println!(
+        "INFO: Exploring {}, type help for the commands",
+        java_name(&explorer.class().this_class_name())
+    );
+    editor.set_helper(Some(ExplorerHelper { explorer }));
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(format!("could not read command: {e}")),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        if matches!(line, "quit" | "exit") {
+            break;
+        }
+        let helper = editor.helper().expect("set above");
+        match helper.explorer.execute(line) {
+            Ok(output) => print!("{}", output),
+            Err(e) => eprintln!("ERROR: {e}"),
+        }
+    }
+    Ok(())
+}
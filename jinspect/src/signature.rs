@@ -0,0 +1,621 @@
+// Field/method descriptors (JVMS 4.3) and generic Signature attributes (JVMS 4.7.9.1) parsed
+// into a small type AST. `Display` renders the types the way Java source spells them.
+
+use std::fmt;
+
+use crate::error::ErrorKind;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BaseType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JavaType {
+    Base(BaseType),
+    Class(ClassType),
+    TypeVariable(String),
+    Array(Box<JavaType>),
+    Void, // only as a method return type
+}
+
+/// `java/util/Map<K, V>.Entry<K, V>` is package `java/util` and the segments `Map<K, V>`, `Entry<K, V>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassType {
+    pub package: String, // internal form, empty for the default package
+    pub segments: Vec<SimpleClassType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimpleClassType {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeArgument {
+    Any, // ?
+    Exact(JavaType),
+    Extends(JavaType),
+    Super(JavaType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeParameter {
+    pub name: String,
+    pub class_bound: Option<JavaType>,
+    pub interface_bounds: Vec<JavaType>,
+}
+
+/// A method descriptor or generic method signature, descriptors never have type parameters or throws.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<JavaType>,
+    pub return_type: JavaType,
+    pub throws: Vec<JavaType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: JavaType,
+    pub interfaces: Vec<JavaType>,
+}
+
+/// Array dimensions of one type, the limit JVMS 4.3.2 sets for descriptors.
+const MAX_ARRAY_DIMENSIONS: usize = 255;
+
+/// Type arguments nested in type arguments, far beyond what source code spells and low enough
+/// that the recursive parser and `Display` can not exhaust the stack.
+const MAX_TYPE_ARGUMENT_DEPTH: usize = 32;
+
+struct SignatureReader<'a> {
+    input: &'a str,
+    pos: usize,
+    generic: bool, // descriptors do not allow type variables and type arguments
+    depth: usize,  // type arguments being read
+}
+
+impl<'a> SignatureReader<'a> {
+    fn new(input: &'a str, generic: bool) -> Self {
+        SignatureReader {
+            input,
+            pos: 0,
+            generic,
+            depth: 0,
+        }
+    }
+
+    fn error(&self, reason: &str) -> ErrorKind {
+        let what = if self.generic {
+            "signature"
+        } else {
+            "descriptor"
+        };
+        ErrorKind::Malformed(format!(
+            "malformed {} {:?}: {} at position {}",
+            what, self.input, reason, self.pos
+        ))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, ErrorKind> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), ErrorKind> {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected as char)))
+        }
+    }
+
+    fn finish(&self) -> Result<(), ErrorKind> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("trailing characters")),
+        }
+    }
+
+    /// Reads up to the next delimiter, identifiers may contain any non-delimiter character.
+    fn identifier(&mut self) -> Result<String, ErrorKind> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if matches!(c, b'.' | b';' | b'[' | b'/' | b'<' | b'>' | b':') {
+                break;
+            }
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error("expected an identifier"));
+        }
+        Ok(self.input[start..self.pos].to_string())
+    }
+
+    fn java_type(&mut self) -> Result<JavaType, ErrorKind> {
+        let mut dimensions = 0;
+        while self.peek() == Some(b'[') {
+            self.pos += 1;
+            dimensions += 1;
+            if dimensions > MAX_ARRAY_DIMENSIONS {
+                return Err(self.error("more than 255 array dimensions"));
+            }
+        }
+        let mut ret = self.element_type()?;
+        for _ in 0..dimensions {
+            ret = JavaType::Array(Box::new(ret));
+        }
+        Ok(ret)
+    }
+
+    fn element_type(&mut self) -> Result<JavaType, ErrorKind> {
+        let base = match self.next()? {
+            b'B' => BaseType::Byte,
+            b'C' => BaseType::Char,
+            b'D' => BaseType::Double,
+            b'F' => BaseType::Float,
+            b'I' => BaseType::Int,
+            b'J' => BaseType::Long,
+            b'S' => BaseType::Short,
+            b'Z' => BaseType::Boolean,
+            b'L' => return Ok(JavaType::Class(self.class_type()?)),
+            b'T' if self.generic => {
+                let name = self.identifier()?;
+                self.expect(b';')?;
+                return Ok(JavaType::TypeVariable(name));
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unknown type"));
+            }
+        };
+        Ok(JavaType::Base(base))
+    }
+
+    fn reference_type(&mut self) -> Result<JavaType, ErrorKind> {
+        match self.peek() {
+            Some(b'L') | Some(b'[') | Some(b'T') => self.java_type(),
+            _ => Err(self.error("expected a reference type")),
+        }
+    }
+
+    fn type_arguments(&mut self) -> Result<Vec<TypeArgument>, ErrorKind> {
+        let mut acc = Vec::<TypeArgument>::new();
+        if !self.generic || self.peek() != Some(b'<') {
+            return Ok(acc);
+        }
+        if self.depth == MAX_TYPE_ARGUMENT_DEPTH {
+            return Err(self.error("type arguments nested too deeply"));
+        }
+        self.pos += 1;
+        self.depth += 1;
+        while self.peek() != Some(b'>') {
+            let argument = match self.peek() {
+                Some(b'*') => {
+                    self.pos += 1;
+                    TypeArgument::Any
+                }
+                Some(b'+') => {
+                    self.pos += 1;
+                    TypeArgument::Extends(self.reference_type()?)
+                }
+                Some(b'-') => {
+                    self.pos += 1;
+                    TypeArgument::Super(self.reference_type()?)
+                }
+                _ => TypeArgument::Exact(self.reference_type()?),
+            };
+            acc.push(argument);
+        }
+        self.pos += 1;
+        self.depth -= 1;
+        if acc.is_empty() {
+            return Err(self.error("empty type arguments"));
+        }
+        Ok(acc)
+    }
+
+    fn class_type(&mut self) -> Result<ClassType, ErrorKind> {
+        let mut package = String::new();
+        let mut name = self.identifier()?;
+        while self.peek() == Some(b'/') {
+            self.pos += 1;
+            if !package.is_empty() {
+                package.push('/');
+            }
+            package.push_str(&name);
+            name = self.identifier()?;
+        }
+        let mut segments = vec![SimpleClassType {
+            name,
+            type_arguments: self.type_arguments()?,
+        }];
+        while self.generic && self.peek() == Some(b'.') {
+            self.pos += 1;
+            let name = self.identifier()?;
+            segments.push(SimpleClassType {
+                name,
+                type_arguments: self.type_arguments()?,
+            });
+        }
+        self.expect(b';')?;
+        Ok(ClassType { package, segments })
+    }
+
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, ErrorKind> {
+        let mut acc = Vec::<TypeParameter>::new();
+        if self.peek() != Some(b'<') {
+            return Ok(acc);
+        }
+        self.pos += 1;
+        while self.peek() != Some(b'>') {
+            let name = self.identifier()?;
+            self.expect(b':')?;
+            // The class bound may be empty when the parameter is only bounded by interfaces.
+            let class_bound = match self.peek() {
+                Some(b':') => None,
+                _ => Some(self.reference_type()?),
+            };
+            let mut interface_bounds = Vec::<JavaType>::new();
+            while self.peek() == Some(b':') {
+                self.pos += 1;
+                interface_bounds.push(self.reference_type()?);
+            }
+            acc.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+        }
+        self.pos += 1;
+        if acc.is_empty() {
+            return Err(self.error("empty type parameters"));
+        }
+        Ok(acc)
+    }
+
+    fn method(&mut self) -> Result<MethodSignature, ErrorKind> {
+        let type_parameters = if self.generic {
+            self.type_parameters()?
+        } else {
+            Vec::new()
+        };
+        self.expect(b'(')?;
+        let mut parameters = Vec::<JavaType>::new();
+        while self.peek() != Some(b')') {
+            parameters.push(self.java_type()?);
+        }
+        self.pos += 1;
+        let return_type = if self.peek() == Some(b'V') {
+            self.pos += 1;
+            JavaType::Void
+        } else {
+            self.java_type()?
+        };
+        let mut throws = Vec::<JavaType>::new();
+        while self.generic && self.peek() == Some(b'^') {
+            self.pos += 1;
+            throws.push(self.reference_type()?);
+        }
+        Ok(MethodSignature {
+            type_parameters,
+            parameters,
+            return_type,
+            throws,
+        })
+    }
+}
+
+/// Parses a field descriptor such as `[Ljava/lang/String;`.
+pub fn parse_field_descriptor(descriptor: &str) -> Result<JavaType, ErrorKind> {
+    let mut rdr = SignatureReader::new(descriptor, false);
+    let ret = rdr.java_type()?;
+    rdr.finish()?;
+    Ok(ret)
+}
+
+/// Parses a method descriptor such as `([Ljava/lang/String;)V`.
+pub fn parse_method_descriptor(descriptor: &str) -> Result<MethodSignature, ErrorKind> {
+    let mut rdr = SignatureReader::new(descriptor, false);
+    let ret = rdr.method()?;
+    rdr.finish()?;
+    Ok(ret)
+}
+
+/// Parses the Signature attribute of a field, e.g. `Ljava/util/List<+TT;>;`.
+pub fn parse_field_signature(signature: &str) -> Result<JavaType, ErrorKind> {
+    let mut rdr = SignatureReader::new(signature, true);
+    let ret = rdr.reference_type()?;
+    rdr.finish()?;
+    Ok(ret)
+}
+
+/// Parses the Signature attribute of a method, e.g. `<T:Ljava/lang/Object;>(TT;)V^TE;`.
+pub fn parse_method_signature(signature: &str) -> Result<MethodSignature, ErrorKind> {
+    let mut rdr = SignatureReader::new(signature, true);
+    let ret = rdr.method()?;
+    rdr.finish()?;
+    Ok(ret)
+}
+
+/// Parses the Signature attribute of a class, e.g. `<T:Ljava/lang/Object;>Ljava/lang/Object;`.
+pub fn parse_class_signature(signature: &str) -> Result<ClassSignature, ErrorKind> {
+    let mut rdr = SignatureReader::new(signature, true);
+    let type_parameters = rdr.type_parameters()?;
+    let superclass = JavaType::Class(match rdr.next()? {
+        b'L' => rdr.class_type()?,
+        _ => return Err(rdr.error("expected a class type")),
+    });
+    let mut interfaces = Vec::<JavaType>::new();
+    while rdr.peek().is_some() {
+        interfaces.push(rdr.reference_type()?);
+    }
+    Ok(ClassSignature {
+        type_parameters,
+        superclass,
+        interfaces,
+    })
+}
+
+/// Java source name of an internal class name, `java/util/Map$Entry` => `java.util.Map$Entry`.
+pub fn java_name(internal_name: &str) -> String {
+    internal_name.replace('/', ".")
+}
+
+fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+    let acc: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    acc.join(separator)
+}
+
+/// `<T, U extends java.lang.Number>`, empty when there are no type parameters.
+pub fn type_parameters_to_string(items: &[TypeParameter]) -> String {
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", join(items, ", "))
+    }
+}
+
+impl ClassType {
+    /// Internal name of the class without type arguments, e.g. `java/util/Map$Entry`.
+    pub fn internal_name(&self) -> String {
+        let names: Vec<&str> = self.segments.iter().map(|s| s.name.as_str()).collect();
+        let name = names.join("$");
+        if self.package.is_empty() {
+            name
+        } else {
+            format!("{}/{}", self.package, name)
+        }
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.internal_name() == "java/lang/Object"
+    }
+}
+
+impl JavaType {
+    pub fn is_object(&self) -> bool {
+        matches!(self, Self::Class(class) if class.is_object())
+    }
+
+    /// Erased descriptor, e.g. `[Ljava/util/List;` for `List<String>[]`, type variables erase
+    /// to `Object`.
+    pub fn descriptor(&self) -> String {
+        match self {
+            Self::Base(base) => match base {
+                BaseType::Byte => "B",
+                BaseType::Char => "C",
+                BaseType::Double => "D",
+                BaseType::Float => "F",
+                BaseType::Int => "I",
+                BaseType::Long => "J",
+                BaseType::Short => "S",
+                BaseType::Boolean => "Z",
+            }
+            .to_string(),
+            Self::Class(class) => format!("L{};", class.internal_name()),
+            Self::Array(component) => format!("[{}", component.descriptor()),
+            Self::TypeVariable(_) => "Ljava/lang/Object;".to_string(),
+            Self::Void => "V".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for BaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Byte => "byte",
+            Self::Char => "char",
+            Self::Double => "double",
+            Self::Float => "float",
+            Self::Int => "int",
+            Self::Long => "long",
+            Self::Short => "short",
+            Self::Boolean => "boolean",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for JavaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base(base) => write!(f, "{}", base),
+            Self::Class(class) => write!(f, "{}", class),
+            Self::TypeVariable(name) => write!(f, "{}", name),
+            Self::Array(component) => write!(f, "{}[]", component),
+            Self::Void => write!(f, "void"),
+        }
+    }
+}
+
+impl fmt::Display for SimpleClassType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.type_arguments.is_empty() {
+            write!(f, "<{}>", join(&self.type_arguments, ", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ClassType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.package.is_empty() {
+            write!(f, "{}.", java_name(&self.package))?;
+        }
+        write!(f, "{}", join(&self.segments, "."))
+    }
+}
+
+impl fmt::Display for TypeArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "?"),
+            Self::Exact(item) => write!(f, "{}", item),
+            Self::Extends(item) => write!(f, "? extends {}", item),
+            Self::Super(item) => write!(f, "? super {}", item),
+        }
+    }
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bounds: Vec<&JavaType> = self.class_bound.iter().collect();
+        bounds.extend(self.interface_bounds.iter());
+        // `T extends Object` is what the compiler records for an unbounded `T`.
+        if let [bound] = bounds.as_slice() {
+            if bound.is_object() {
+                return write!(f, "{}", self.name);
+            }
+        }
+        let bounds: Vec<String> = bounds.iter().map(|bound| bound.to_string()).collect();
+        if bounds.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} extends {}", self.name, bounds.join(" & "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_field_descriptors() {
+        assert_eq!(
+            parse_field_descriptor("I"),
+            Ok(JavaType::Base(BaseType::Int))
+        );
+        let t = parse_field_descriptor("[[Ljava/lang/String;").unwrap();
+        assert_eq!(t.to_string(), "java.lang.String[][]");
+        let t = parse_field_descriptor("LApp;").unwrap();
+        assert_eq!(t.to_string(), "App");
+        let t = parse_field_descriptor("Ljava/util/Map$Entry;").unwrap();
+        assert_eq!(t.to_string(), "java.util.Map$Entry");
+    }
+
+    #[test]
+    fn test_parse_method_descriptor() {
+        let m = parse_method_descriptor("([Ljava/lang/String;)V").unwrap();
+        assert_eq!(m.return_type, JavaType::Void);
+        assert_eq!(join(&m.parameters, ", "), "java.lang.String[]");
+        let m = parse_method_descriptor("(IDJLjava/lang/Object;)[Z").unwrap();
+        assert_eq!(
+            join(&m.parameters, ", "),
+            "int, double, long, java.lang.Object"
+        );
+        assert_eq!(m.return_type.to_string(), "boolean[]");
+    }
+
+    #[test]
+    fn test_descriptors_reject_generics_and_garbage() {
+        assert!(parse_field_descriptor("TT;").is_err());
+        assert!(parse_field_descriptor("Ljava/util/List<TT;>;").is_err());
+        assert!(parse_field_descriptor("Ljava/lang/String").is_err());
+        assert!(parse_field_descriptor("II").is_err());
+        assert!(parse_field_descriptor("V").is_err());
+        assert!(parse_method_descriptor("(V)V").is_err());
+        assert!(parse_method_descriptor("()").is_err());
+        assert!(parse_method_descriptor("I").is_err());
+
+        let deepest = format!("{}I", "[".repeat(255));
+        assert_eq!(
+            parse_field_descriptor(&deepest).unwrap().descriptor(),
+            deepest
+        );
+        let e = parse_field_descriptor(&format!("{}I", "[".repeat(60_000))).unwrap_err();
+        assert!(e.to_string().contains("more than 255 array dimensions"));
+        let nested = |depth: usize| {
+            let open = "Ljava/util/List<".repeat(depth);
+            let close = ">;".repeat(depth);
+            parse_field_signature(&format!("{}TT;{}", open, close))
+        };
+        assert_eq!(
+            nested(2).unwrap().to_string(),
+            "java.util.List<java.util.List<T>>"
+        );
+        let e = nested(20_000).unwrap_err();
+        assert!(e.to_string().contains("type arguments nested too deeply"));
+    }
+
+    #[test]
+    fn test_parse_field_signature_with_wildcards() {
+        let t = parse_field_signature("Ljava/util/List<+TT;>;").unwrap();
+        assert_eq!(t.to_string(), "java.util.List<? extends T>");
+        let t = parse_field_signature("Ljava/util/Map<Ljava/lang/String;-Ljava/lang/Number;>;")
+            .unwrap();
+        assert_eq!(
+            t.to_string(),
+            "java.util.Map<java.lang.String, ? super java.lang.Number>"
+        );
+        let t = parse_field_signature("[Ljava/lang/Class<*>;").unwrap();
+        assert_eq!(t.to_string(), "java.lang.Class<?>[]");
+        let t = parse_field_signature("LOuter<TK;>.Inner<TV;>;").unwrap();
+        assert_eq!(t.to_string(), "Outer<K>.Inner<V>");
+        assert!(parse_field_signature("I").is_err());
+    }
+
+    #[test]
+    fn test_parse_method_signature() {
+        let m = parse_method_signature(
+            "<T::Ljava/lang/Comparable<-TT;>;E:Ljava/lang/Exception;>(Ljava/util/List<+TT;>;)TT;^TE;",
+        )
+        .unwrap();
+        assert_eq!(
+            type_parameters_to_string(&m.type_parameters),
+            "<T extends java.lang.Comparable<? super T>, E extends java.lang.Exception>"
+        );
+        assert_eq!(join(&m.parameters, ", "), "java.util.List<? extends T>");
+        assert_eq!(m.return_type.to_string(), "T");
+        assert_eq!(join(&m.throws, ", "), "E");
+    }
+
+    #[test]
+    fn test_parse_class_signature() {
+        let c = parse_class_signature(
+            "<T:Ljava/lang/Object;>Ljava/util/AbstractList<TT;>;Ljava/util/RandomAccess;",
+        )
+        .unwrap();
+        assert_eq!(type_parameters_to_string(&c.type_parameters), "<T>");
+        assert_eq!(c.superclass.to_string(), "java.util.AbstractList<T>");
+        assert_eq!(join(&c.interfaces, ", "), "java.util.RandomAccess");
+        assert!(parse_class_signature("<>Ljava/lang/Object;").is_err());
+    }
+}
@@ -0,0 +1,873 @@
+// Java source skeleton of a class: package, imports, annotations, the class declaration, fields
+// with their constant initializers, method signatures and member classes. Method bodies are
+// omitted. Referenced classes are imported when their simple names are unambiguous.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::annotation::{Annotation, ElementValue};
+use crate::attribute::{Attribute, AttributeInfo, InnerClass};
+use crate::classfile::{ClassFile, Field, Method};
+use crate::declaration;
+use crate::dependency;
+use crate::flag::{AccessFlag, ACC_ENUM};
+use crate::literal;
+use crate::pool::{ConstantPool, ConstantPoolItem};
+use crate::signature::{self, java_name, ClassType, JavaType, TypeArgument, TypeParameter};
+
+const ACC_BRIDGE: u16 = 0x0040;
+const ACC_NATIVE: u16 = 0x0100;
+const ACC_ABSTRACT: u16 = 0x0400;
+const ACC_SYNTHETIC: u16 = 0x1000;
+
+const INDENT: &str = "    ";
+
+fn is_synthetic(flags: &AccessFlag) -> bool {
+    flags.contains(ACC_SYNTHETIC)
+        || (flags.contains(ACC_BRIDGE) && matches!(flags, AccessFlag::Method(_)))
+}
+
+fn non_finite(value: f64) -> Option<&'static str> {
+    if value.is_nan() {
+        Some("NaN")
+    } else if value == f64::INFINITY {
+        Some("POSITIVE_INFINITY")
+    } else if value == f64::NEG_INFINITY {
+        Some("NEGATIVE_INFINITY")
+    } else {
+        None
+    }
+}
+
+/// The initializer of a field with a ConstantValue attribute, e.g. ` = 'a'` or ` = 10L`.
+fn initializer(field: &Field, pool: &ConstantPool) -> Option<String> {
+    let index = field
+        .attributes
+        .iter()
+        .find_map(|item| match item.attribute {
+            Attribute::ConstantValue(index) => Some(index),
+            _ => None,
+        })?;
+    let value = match (pool.get(index).ok()?, field.descriptor(pool).as_str()) {
+        (ConstantPoolItem::Integer(value), "Z") => (*value != 0).to_string(),
+        (ConstantPoolItem::Integer(value), "C") => literal::char_literal(*value as u16),
+        (ConstantPoolItem::Integer(value), _) => literal::int_literal(*value),
+        (ConstantPoolItem::Long(value), _) => format!("{}L", value),
+        (ConstantPoolItem::Float(value), _) => match non_finite(*value as f64) {
+            Some(name) => format!("Float.{}", name),
+            None => literal::float_literal(*value),
+        },
+        (ConstantPoolItem::Double(value), _) => match non_finite(*value) {
+            Some(name) => format!("Double.{}", name),
+            None => literal::double_to_string(*value),
+        },
+        (ConstantPoolItem::String(value), _) => {
+            literal::string_literal(pool.get_utf8(*value).ok()?)
+        }
+        _ => return None,
+    };
+    Some(format!(" = {}", value))
+}
+
+fn type_classes(item: &JavaType, acc: &mut BTreeSet<String>) {
+    match item {
+        JavaType::Class(class) => {
+            // Every enclosing class can appear as a token, e.g. `Map` in `Map<K, V>.Entry`.
+            for end in 1..=class.segments.len() {
+                let prefix = ClassType {
+                    package: class.package.clone(),
+                    segments: class.segments[..end].to_vec(),
+                };
+                acc.insert(prefix.internal_name());
+            }
+            for argument in class.segments.iter().flat_map(|item| &item.type_arguments) {
+                match argument {
+                    TypeArgument::Exact(item)
+                    | TypeArgument::Extends(item)
+                    | TypeArgument::Super(item) => type_classes(item, acc),
+                    TypeArgument::Any => {}
+                }
+            }
+        }
+        JavaType::Array(component) => type_classes(component, acc),
+        _ => {}
+    }
+}
+
+fn parameter_classes(items: &[TypeParameter], acc: &mut BTreeSet<String>) {
+    for item in items.iter() {
+        item.class_bound
+            .iter()
+            .chain(item.interface_bounds.iter())
+            .for_each(|bound| type_classes(bound, acc));
+    }
+}
+
+fn descriptor_classes(descriptor: &str, acc: &mut BTreeSet<String>) {
+    if let Ok(item) = signature::parse_field_descriptor(descriptor) {
+        type_classes(&item, acc);
+    }
+}
+
+fn value_classes(value: &ElementValue, pool: &ConstantPool, acc: &mut BTreeSet<String>) {
+    match value {
+        ElementValue::Enum(index, _) | ElementValue::Class(index) => {
+            descriptor_classes(&pool.resolve(*index), acc)
+        }
+        ElementValue::Annotation(annotation) => annotation_classes(annotation, pool, acc),
+        ElementValue::Array(values) => values
+            .iter()
+            .for_each(|value| value_classes(value, pool, acc)),
+        ElementValue::Const(_, _) => {}
+    }
+}
+
+fn annotation_classes(annotation: &Annotation, pool: &ConstantPool, acc: &mut BTreeSet<String>) {
+    acc.insert(annotation.type_name(pool));
+    for pair in annotation.elements.iter() {
+        value_classes(&pair.value, pool, acc);
+    }
+}
+
+fn attribute_classes(
+    attributes: &[AttributeInfo],
+    pool: &ConstantPool,
+    acc: &mut BTreeSet<String>,
+) {
+    for item in attributes.iter() {
+        match &item.attribute {
+            Attribute::RuntimeVisibleAnnotations(items)
+            | Attribute::RuntimeInvisibleAnnotations(items) => items
+                .iter()
+                .for_each(|item| annotation_classes(item, pool, acc)),
+            Attribute::RuntimeVisibleParameterAnnotations(parameters)
+            | Attribute::RuntimeInvisibleParameterAnnotations(parameters) => parameters
+                .iter()
+                .flatten()
+                .for_each(|item| annotation_classes(item, pool, acc)),
+            _ => {}
+        }
+    }
+}
+
+/// Classes the declarations can name: constant pool references, classes in generic signatures
+/// and in annotations.
+fn referenced_classes(class: &ClassFile) -> BTreeSet<String> {
+    let pool = &class.constant_pool;
+    let mut acc = dependency::class_dependencies(class);
+    acc.insert(class.this_class_name());
+    if let Some(generic) = class
+        .signature()
+        .and_then(|value| signature::parse_class_signature(&value).ok())
+    {
+        parameter_classes(&generic.type_parameters, &mut acc);
+        type_classes(&generic.superclass, &mut acc);
+        generic
+            .interfaces
+            .iter()
+            .for_each(|item| type_classes(item, &mut acc));
+    }
+    attribute_classes(&class.attributes, pool, &mut acc);
+    for field in class.fields.iter() {
+        if let Some(generic) = field
+            .signature(pool)
+            .and_then(|value| signature::parse_field_signature(&value).ok())
+        {
+            type_classes(&generic, &mut acc);
+        }
+        attribute_classes(&field.attributes, pool, &mut acc);
+    }
+    for method in class.methods.iter() {
+        if let Some(generic) = method
+            .signature(pool)
+            .and_then(|value| signature::parse_method_signature(&value).ok())
+        {
+            parameter_classes(&generic.type_parameters, &mut acc);
+            generic
+                .parameters
+                .iter()
+                .chain(generic.throws.iter())
+                .chain(std::iter::once(&generic.return_type))
+                .for_each(|item| type_classes(item, &mut acc));
+        }
+        attribute_classes(&method.attributes, pool, &mut acc);
+    }
+    acc
+}
+
+/// Maps the Java names of referenced classes to the names used in the skeleton and collects
+/// the imports that make them valid.
+struct Names {
+    display: BTreeMap<String, String>, // keyed by both `a.B$C` and `a.B.C`
+    imports: BTreeSet<String>,
+}
+
+impl Names {
+    fn new(class: &ClassFile, own_name: Option<&str>, texts: &[&str]) -> Self {
+        let this_name = java_name(&class.this_class_name());
+        let this_package = java_name(dependency::package_of(&class.this_class_name()));
+        let mut known = BTreeMap::<String, String>::new();
+        for name in referenced_classes(class).iter().map(|name| java_name(name)) {
+            known.insert(name.replace('$', "."), name.clone());
+            known.insert(name.clone(), name);
+        }
+        let used: BTreeSet<String> = texts
+            .iter()
+            .flat_map(|text| tokens(text))
+            .filter_map(|token| longest_prefix(&known, token).map(|(name, _)| name.clone()))
+            .chain(std::iter::once(this_name.clone()))
+            .collect();
+        // Top level classes grouped by simple name, only unambiguous ones are imported.
+        let mut simple = BTreeMap::<String, BTreeSet<String>>::new();
+        for name in used.iter() {
+            let top = name.split('$').next().unwrap_or(name);
+            let short = top.rsplit('.').next().unwrap_or(top);
+            simple
+                .entry(short.to_string())
+                .or_default()
+                .insert(top.to_string());
+        }
+        let mut display = BTreeMap::<String, String>::new();
+        let mut imports = BTreeSet::<String>::new();
+        for name in used.iter() {
+            let top = name.split('$').next().unwrap_or(name);
+            let (package, short) = top.rsplit_once('.').unwrap_or(("", top));
+            let value = if simple[short].len() == 1 {
+                if package != this_package && package != "java.lang" {
+                    imports.insert(top.to_string());
+                }
+                nested_name(name[package.len()..].trim_start_matches('.'))
+            } else {
+                nested_name(name)
+            };
+            display.insert(name.clone(), value);
+        }
+        if let Some(own_name) = own_name {
+            // Constructors are declared with the binary name, e.g. `Outer$Inner(Outer)`.
+            let binary = this_name
+                .rsplit('.')
+                .next()
+                .unwrap_or(&this_name)
+                .to_string();
+            display.insert(binary, own_name.to_string());
+            display.insert(this_name, own_name.to_string());
+        }
+        for (token, name) in known.iter() {
+            if let Some(value) = display.get(name).cloned() {
+                display.insert(token.clone(), value);
+            }
+        }
+        Names { display, imports }
+    }
+
+    /// Replaces the class names in a declaration, string and char literals are kept as is.
+    fn apply(&self, text: &str) -> String {
+        let mut acc = String::with_capacity(text.len());
+        for (literal, part) in split_literals(text) {
+            if literal {
+                acc.push_str(part);
+                continue;
+            }
+            let mut start = 0;
+            for (end, c) in part
+                .char_indices()
+                .chain(std::iter::once((part.len(), ' ')))
+            {
+                if is_name_char(c) {
+                    continue;
+                }
+                let token = &part[start..end];
+                match longest_prefix(&self.display, token) {
+                    Some((display, rest)) => {
+                        acc.push_str(display);
+                        acc.push_str(rest);
+                    }
+                    None => acc.push_str(token),
+                }
+                if end < part.len() {
+                    acc.push(c);
+                }
+                start = end + c.len_utf8();
+            }
+        }
+        acc
+    }
+}
+
+/// Splits annotation values into code and quoted literals, flagged `true`.
+fn split_literals(text: &str) -> Vec<(bool, &str)> {
+    let mut acc = Vec::<(bool, &str)>::new();
+    let mut start = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(open) if c == open => {
+                acc.push((true, &text[start..i + 1]));
+                start = i + 1;
+                quote = None;
+            }
+            Some(_) => {}
+            None if c == '"' || c == '\'' => {
+                acc.push((false, &text[start..i]));
+                start = i;
+                quote = Some(c);
+            }
+            None => {}
+        }
+    }
+    acc.push((quote.is_some(), &text[start..]));
+    acc
+}
+
+/// `Outer$Inner` is written `Outer.Inner`, anonymous and local classes keep their binary name.
+fn nested_name(name: &str) -> String {
+    let local = name
+        .split('$')
+        .skip(1)
+        .any(|part| part.starts_with(|c: char| c.is_ascii_digit()));
+    if local {
+        name.to_string()
+    } else {
+        name.replace('$', ".")
+    }
+}
+
+/// The value of the longest key that is `token` or a prefix of it ending before a `.`, with
+/// the remainder, e.g. `a.E.FIELD` => (value of `a.E`, `.FIELD`).
+fn longest_prefix<'a, 'b>(
+    map: &'a BTreeMap<String, String>,
+    token: &'b str,
+) -> Option<(&'a String, &'b str)> {
+    let mut end = token.len();
+    loop {
+        if let Some(value) = map.get(&token[..end]) {
+            return Some((value, &token[end..]));
+        }
+        end = token[..end].rfind('.')?;
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '.'
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    split_literals(text)
+        .into_iter()
+        .filter(|(literal, _)| !literal)
+        .flat_map(|(_, part)| part.split(|c: char| !is_name_char(c)))
+        .map(|token| token.trim_end_matches('.'))
+        .filter(|token| !token.is_empty())
+}
+
+fn inner_class_name(entry: &InnerClass, pool: &ConstantPool) -> String {
+    pool.get_class_name(entry.inner_class_info_index)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Lines of a member: its annotations followed by the declaration.
+fn member_lines(annotations: Vec<String>, declaration: String) -> Vec<String> {
+    annotations
+        .into_iter()
+        .chain(std::iter::once(declaration))
+        .collect()
+}
+
+fn method_lines(class: &ClassFile, method: &Method) -> Vec<String> {
+    let pool = &class.constant_pool;
+    let mut declaration = declaration::method_declaration(class, method);
+    let default = method
+        .attributes
+        .iter()
+        .find_map(|item| match &item.attribute {
+            Attribute::AnnotationDefault(value) => Some(value),
+            _ => None,
+        });
+    if let Some(value) = default {
+        declaration.push_str(&format!(" default {}", value.to_java(pool)));
+    }
+    let flags = &method.access_flags;
+    let declaration = if method.name(pool) == "<clinit>" {
+        "static { /* compiled code */ }".to_string()
+    } else if flags.contains(ACC_ABSTRACT) || flags.contains(ACC_NATIVE) {
+        format!("{};", declaration)
+    } else {
+        format!("{} {{ /* compiled code */ }}", declaration)
+    };
+    member_lines(
+        declaration::annotations(&method.attributes, pool),
+        declaration,
+    )
+}
+
+/// Renders the skeleton of a class as Java source.
+const ACC_OPEN: u16 = 0x0020;
+const ACC_MANDATED: u16 = 0x8000;
+
+/// A `module-info` declaration, directives implied by the compiler (`requires java.base`) are
+/// left out and versions and the main class become comments. `None` unless the class
+/// has a Module attribute.
+pub fn module_source(class: &ClassFile) -> Option<String> {
+    let pool = &class.constant_pool;
+    let mut main_class = None;
+    let mut module = None;
+    for item in class.attributes.iter() {
+        match &item.attribute {
+            Attribute::Module(attribute) => module = Some(attribute),
+            Attribute::ModuleMainClass(index) => main_class = Some(*index),
+            _ => {}
+        }
+    }
+    let module = module?;
+    let implied = |flags: u16| flags & (ACC_SYNTHETIC | ACC_MANDATED) != 0;
+    let class_name = |index: u16| java_name(pool.get_class_name(index).unwrap_or_default());
+    let package_name = |index: u16| java_name(&pool.resolve(index));
+
+    let mut acc = String::new();
+    if module.version_index != 0 {
+        acc.push_str(&format!(
+            "// version {}\n",
+            pool.resolve(module.version_index)
+        ));
+    }
+    if let Some(index) = main_class {
+        acc.push_str(&format!("// main class {}\n", class_name(index)));
+    }
+    for annotation in declaration::annotations(&class.attributes, pool) {
+        acc.push_str(&format!("{}\n", annotation));
+    }
+    let open = if module.flags & ACC_OPEN != 0 {
+        "open "
+    } else {
+        ""
+    };
+    acc.push_str(&format!(
+        "{}module {} {{\n",
+        open,
+        pool.resolve(module.name_index)
+    ));
+    for item in module.requires.iter().filter(|item| !implied(item.flags)) {
+        let flags = AccessFlag::Requires(item.flags & !ACC_SYNTHETIC);
+        let flags = if flags.value() == 0 {
+            String::new()
+        } else {
+            format!("{} ", flags)
+        };
+        acc.push_str(&format!(
+            "{}requires {}{};",
+            INDENT,
+            flags,
+            pool.resolve(item.module_index)
+        ));
+        if item.version_index != 0 {
+            acc.push_str(&format!(" // {}", pool.resolve(item.version_index)));
+        }
+        acc.push('\n');
+    }
+    for (keyword, items) in [("exports", &module.exports), ("opens", &module.opens)] {
+        for item in items.iter().filter(|item| !implied(item.flags)) {
+            acc.push_str(&format!(
+                "{}{} {}",
+                INDENT,
+                keyword,
+                package_name(item.package_index)
+            ));
+            if !item.to.is_empty() {
+                let modules: Vec<String> = item.to.iter().map(|i| pool.resolve(*i)).collect();
+                acc.push_str(&format!(" to {}", modules.join(", ")));
+            }
+            acc.push_str(";\n");
+        }
+    }
+    for index in module.uses.iter() {
+        acc.push_str(&format!("{}uses {};\n", INDENT, class_name(*index)));
+    }
+    for item in module.provides.iter() {
+        let with: Vec<String> = item.with.iter().map(|index| class_name(*index)).collect();
+        acc.push_str(&format!(
+            "{}provides {} with {};\n",
+            INDENT,
+            class_name(item.service_index),
+            with.join(", ")
+        ));
+    }
+    acc.push_str("}\n");
+    Some(acc)
+}
+
+pub fn class_source(class: &ClassFile) -> String {
+    class_source_with(class, &mut |_| None)
+}
+
+/// Same as [`class_source`], `find_class` looks up member classes by internal name to declare
+/// nested records as such.
+pub fn class_source_with(
+    class: &ClassFile,
+    find_class: &mut dyn FnMut(&str) -> Option<ClassFile>,
+) -> String {
+    if let Some(source) = module_source(class) {
+        return source;
+    }
+    let pool = &class.constant_pool;
+    let this_class = class.this_class_name();
+    let inner_classes: &[InnerClass] = class
+        .attributes
+        .iter()
+        .find_map(|item| match &item.attribute {
+            Attribute::InnerClasses(entries) => Some(entries.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let own_entry = inner_classes
+        .iter()
+        .find(|entry| inner_class_name(entry, pool) == this_class);
+
+    // Declarations with qualified names, initializers are kept apart so that string constants
+    // are never rewritten.
+    let mut comments = Vec::<String>::new();
+    let header = match own_entry {
+        Some(entry) => {
+            let flags = AccessFlag::InnerClass(entry.inner_class_access_flags);
+            let declaration = declaration::nested_class_declaration(class, &flags);
+            match pool.get_class_name(entry.outer_class_info_index) {
+                Ok(outer) => comments.push(format!("// member of {}", java_name(outer))),
+                Err(_) => {
+                    let enclosing = class
+                        .attributes
+                        .iter()
+                        .find_map(|item| match item.attribute {
+                            Attribute::EnclosingMethod(outer, method) => Some((outer, method)),
+                            _ => None,
+                        });
+                    if let Some((outer, method)) = enclosing {
+                        let outer = java_name(pool.get_class_name(outer).unwrap_or_default());
+                        match pool.get_name_and_type(method) {
+                            Ok((name, descriptor)) => comments
+                                .push(format!("// declared in {}.{}{}", outer, name, descriptor)),
+                            Err(_) => comments.push(format!("// declared in {}", outer)),
+                        }
+                    }
+                }
+            }
+            declaration
+        }
+        None => declaration::class_declaration(class),
+    };
+    let header = member_lines(declaration::annotations(&class.attributes, pool), header);
+
+    let fields: Vec<&Field> = class
+        .fields
+        .iter()
+        .filter(|field| !is_synthetic(&field.access_flags))
+        .collect();
+    let (constants, fields): (Vec<&Field>, Vec<&Field>) = fields
+        .into_iter()
+        .partition(|field| field.access_flags.contains(ACC_ENUM));
+    let constants: Vec<Vec<String>> = constants
+        .iter()
+        .map(|field| {
+            member_lines(
+                declaration::annotations(&field.attributes, pool),
+                field.name(pool),
+            )
+        })
+        .collect();
+    let fields: Vec<(Vec<String>, String)> = fields
+        .iter()
+        .map(|field| {
+            let lines = member_lines(
+                declaration::annotations(&field.attributes, pool),
+                declaration::field_declaration(class, field),
+            );
+            (lines, initializer(field, pool).unwrap_or_default())
+        })
+        .collect();
+    let methods: Vec<Vec<String>> = class
+        .methods
+        .iter()
+        .filter(|method| !is_synthetic(&method.access_flags))
+        .map(|method| method_lines(class, method))
+        .collect();
+    let mut members = Vec::<String>::new();
+    let mut locals = Vec::<String>::new();
+    for entry in inner_classes.iter() {
+        let name = inner_class_name(entry, pool);
+        let declared_here =
+            pool.get_class_name(entry.outer_class_info_index).ok() == Some(this_class.as_str());
+        if declared_here {
+            let flags = AccessFlag::InnerClass(entry.inner_class_access_flags);
+            let simple = pool.get_utf8(entry.inner_name_index).unwrap_or_default();
+            let is_record = find_class(&name).is_some_and(|member| declaration::is_record(&member));
+            members.push(format!(
+                "{} {{ /* {} */ }}",
+                declaration::inner_class_declaration(&flags, simple, is_record),
+                name
+            ));
+        } else if entry.outer_class_info_index == 0 && name.starts_with(&format!("{}$", this_class))
+        {
+            match pool.get_utf8(entry.inner_name_index) {
+                Ok(simple) if !simple.is_empty() => {
+                    locals.push(format!("// local class {}: {}", simple, name))
+                }
+                _ => locals.push(format!("// anonymous class {}", name)),
+            }
+        }
+    }
+
+    let mut texts: Vec<&str> = header.iter().map(String::as_str).collect();
+    texts.extend(constants.iter().flatten().map(String::as_str));
+    texts.extend(
+        fields
+            .iter()
+            .flat_map(|(lines, _)| lines)
+            .map(String::as_str),
+    );
+    texts.extend(methods.iter().flatten().map(String::as_str));
+    // Inside a named nested class its simple name refers to itself.
+    let own_name = own_entry
+        .and_then(|entry| pool.get_utf8(entry.inner_name_index).ok())
+        .filter(|name| !name.is_empty());
+    let names = Names::new(class, own_name, &texts);
+
+    let mut acc = String::new();
+    let package = dependency::package_of(&this_class);
+    if !package.is_empty() {
+        acc.push_str(&format!("package {};\n\n", java_name(package)));
+    }
+    for import in names.imports.iter() {
+        acc.push_str(&format!("import {};\n", import));
+    }
+    if !names.imports.is_empty() {
+        acc.push('\n');
+    }
+    for line in comments.iter() {
+        acc.push_str(&format!("{}\n", line));
+    }
+    for line in header.iter() {
+        acc.push_str(&names.apply(line));
+        acc.push('\n');
+    }
+    acc.truncate(acc.len() - 1);
+    acc.push_str(" {\n");
+    let mut sections = Vec::<Vec<String>>::new();
+    if !constants.is_empty() {
+        let last = constants.len() - 1;
+        let mut lines = Vec::<String>::new();
+        for (i, constant) in constants.iter().enumerate() {
+            let separator = if i == last { ";" } else { "," };
+            for (j, line) in constant.iter().enumerate() {
+                let end = if j + 1 == constant.len() {
+                    separator
+                } else {
+                    ""
+                };
+                lines.push(format!("{}{}", names.apply(line), end));
+            }
+        }
+        sections.push(lines);
+    }
+    if !fields.is_empty() {
+        let mut lines = Vec::<String>::new();
+        for (declaration, initializer) in fields.iter() {
+            for (j, line) in declaration.iter().enumerate() {
+                if j + 1 == declaration.len() {
+                    lines.push(format!("{}{};", names.apply(line), initializer));
+                } else {
+                    lines.push(names.apply(line));
+                }
+            }
+        }
+        sections.push(lines);
+    }
+    if !methods.is_empty() {
+        sections.push(
+            methods
+                .iter()
+                .flatten()
+                .map(|line| names.apply(line))
+                .collect(),
+        );
+    }
+    members.extend(locals);
+    if !members.is_empty() {
+        sections.push(members);
+    }
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            acc.push('\n');
+        }
+        for line in section.iter() {
+            acc.push_str(&format!("{}{}\n", INDENT, line));
+        }
+    }
+    acc.push_str("}\n");
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{class_source, class_source_with};
+    use crate::assembler::assemble;
+    use crate::attribute::{Attribute, AttributeInfo, Exports, Module, Provides, Requires};
+    use crate::classfile::ClassFile;
+    use crate::flag::{AccessFlag, ACC_MODULE};
+    use crate::pool::{ConstantPool, ConstantPoolItem};
+
+    fn module_entry(pool: &mut ConstantPool, name: &str) -> u16 {
+        let name = pool.add_utf8(name).unwrap();
+        pool.add(ConstantPoolItem::Module(name)).unwrap()
+    }
+
+    fn sample(name: &str) -> ClassFile {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("samples")
+            .join(name);
+        ClassFile::parse(fs::read(path).unwrap().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_source_imports_and_constants() {
+        let class = assemble(
+            r#"
+.class public abstract com/acme/Config
+.field public static final NAME Ljava/lang/String; = "a\tb"
+.field static final SEP C = 59
+.field static final MAX J = 10
+.field protected entries Ljava/util/List;
+.field other Lcom/acme/other/List;
+.method public abstract load(Ljava/io/File;)Ljava/util/Map;
+.end method
+"#,
+        )
+        .unwrap();
+        let expected = "\
+package com.acme;
+
+import java.io.File;
+import java.util.Map;
+
+public abstract class Config {
+    public static final String NAME = \"a\\tb\";
+    static final char SEP = ';';
+    static final long MAX = 10L;
+    protected java.util.List entries;
+    com.acme.other.List other;
+
+    public abstract Map load(File);
+}
+";
+        assert_eq!(class_source(&class), expected);
+    }
+
+    #[test]
+    fn test_source_of_nested_classes() {
+        let source = class_source(&sample("Sample.class"));
+        assert!(
+            source.contains("public class Sample<T extends Comparable<T>> implements Runnable {")
+        );
+        assert!(
+            source.contains("    @Deprecated\n    public static int sw(int) throws IOException")
+        );
+        assert!(source.contains("    static final float F = Float.NaN;\n"));
+        assert!(source.contains("    class Inner { /* Sample$Inner */ }\n"));
+        // Without the member class file a record looks like a final class.
+        assert!(source.contains("    static final class Point { /* Sample$Point */ }\n"));
+        let source = class_source_with(&sample("Sample.class"), &mut |name| {
+            Some(sample(&format!("{}.class", name)))
+        });
+        assert!(source.contains("    record Point { /* Sample$Point */ }\n"));
+        assert!(source.contains("    // anonymous class Sample$1\n"));
+
+        let source = class_source(&sample("Sample$Inner.class"));
+        assert!(source.starts_with("// member of Sample\nclass Inner {\n"));
+        assert!(source.contains("    Inner(Sample) { /* compiled code */ }\n"));
+    }
+
+    #[test]
+    fn test_source_of_module() {
+        let mut class = assemble(".class module-info\n").unwrap();
+        class.access_flags = AccessFlag::Class(ACC_MODULE);
+        let pool = &mut class.constant_pool;
+        let name_index = module_entry(pool, "com.acme");
+        let base = module_entry(pool, "java.base");
+        let sql = module_entry(pool, "java.sql");
+        let package = pool.add_utf8("com/acme/spi").unwrap();
+        let package = pool.add(ConstantPoolItem::Package(package)).unwrap();
+        let module = Module {
+            name_index,
+            flags: 0x0020,
+            version_index: pool.add_utf8("1.2").unwrap(),
+            requires: vec![
+                Requires {
+                    module_index: base,
+                    flags: 0x8000,
+                    version_index: 0,
+                },
+                Requires {
+                    module_index: sql,
+                    flags: 0x0020,
+                    version_index: pool.add_utf8("17").unwrap(),
+                },
+            ],
+            exports: vec![Exports {
+                package_index: package,
+                flags: 0,
+                to: vec![base, sql],
+            }],
+            opens: vec![],
+            uses: vec![pool.add_class("com/acme/spi/Codec").unwrap()],
+            provides: vec![Provides {
+                service_index: pool.add_class("com/acme/spi/Codec").unwrap(),
+                with: vec![pool.add_class("com/acme/Gzip").unwrap()],
+            }],
+        };
+        let attributes = [
+            ("Module", Attribute::Module(module)),
+            ("ModulePackages", Attribute::ModulePackages(vec![package])),
+            (
+                "ModuleMainClass",
+                Attribute::ModuleMainClass(pool.add_class("com/acme/Main").unwrap()),
+            ),
+        ];
+        for (name, attribute) in attributes {
+            let name_index = pool.add_utf8(name).unwrap();
+            class.attributes.push(AttributeInfo {
+                name_index,
+                attribute,
+            });
+        }
+
+        let class = ClassFile::parse(class.to_bytes().unwrap().as_slice()).unwrap();
+        let pool = &class.constant_pool;
+        let module = &class.attributes[0].attribute;
+        assert_eq!(module.resolve(pool), "com.acme@1.2 open");
+        assert_eq!(
+            module.details(pool),
+            [
+                "requires mandated java.base",
+                "requires transitive java.sql@17",
+                "exports com/acme/spi to java.base, java.sql",
+                "uses com/acme/spi/Codec",
+                "provides com/acme/spi/Codec with com/acme/Gzip",
+            ]
+        );
+        assert_eq!(class.attributes[1].attribute.resolve(pool), "com/acme/spi");
+        let expected = "\
+// version 1.2
+// main class com.acme.Main
+open module com.acme {
+    requires transitive java.sql; // 17
+    exports com.acme.spi to java.base, java.sql;
+    uses com.acme.spi.Codec;
+    provides com.acme.spi.Codec with com.acme.Gzip;
+}
+";
+        assert_eq!(class_source(&class), expected);
+    }
+}
@@ -0,0 +1,71 @@
+// Class files and archives shared by the unit tests.
+
+use std::io::Write;
+
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+/// `public class A extends java.lang.Object` without members, class file version 52.
+#[rustfmt::skip]
+pub const MINIMAL_CLASS: [u8; 53] = [
+    0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x00, 0x00, 0x34,
+    0x00, 0x05,
+    1, 0x00, 0x01, b'A',
+    7, 0x00, 0x01,
+    1, 0x00, 0x10, b'j', b'a', b'v', b'a', b'/', b'l', b'a', b'n', b'g', b'/',
+    b'O', b'b', b'j', b'e', b'c', b't',
+    7, 0x00, 0x03,
+    0x00, 0x21, 0x00, 0x02, 0x00, 0x04,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Builds a ZIP archive in memory, entries are (name, content, deflate).
+pub fn zip(entries: &[(&str, &[u8], bool)]) -> Vec<u8> {
+    let mut acc = Vec::<u8>::new();
+    let mut directory = Vec::<u8>::new();
+    for (name, content, deflate) in entries.iter() {
+        let data = if *deflate {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap()
+        } else {
+            content.to_vec()
+        };
+        let mut crc = Crc::new();
+        crc.update(content);
+        let method: u16 = if *deflate { 8 } else { 0 };
+        let offset = acc.len() as u32;
+        let mut common = Vec::<u8>::new();
+        common.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        common.extend_from_slice(&0u16.to_le_bytes()); // flags
+        common.extend_from_slice(&method.to_le_bytes());
+        common.extend_from_slice(&[0, 0, 0, 0]); // time, date
+        common.extend_from_slice(&crc.sum().to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra length
+
+        acc.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        acc.extend_from_slice(&common);
+        acc.extend_from_slice(name.as_bytes());
+        acc.extend_from_slice(&data);
+
+        directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        directory.extend_from_slice(&common);
+        directory.extend_from_slice(&[0; 10]); // comment length, disk, attributes
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let directory_offset = acc.len() as u32;
+    acc.extend_from_slice(&directory);
+    acc.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    acc.extend_from_slice(&[0; 4]);
+    acc.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    acc.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    acc.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    acc.extend_from_slice(&directory_offset.to_le_bytes());
+    acc.extend_from_slice(&0u16.to_le_bytes());
+    acc
+}
@@ -0,0 +1,1267 @@
+// Type-checking bytecode verifier (JVMS 4.10.1). Every instruction is checked against the
+// operand stack and local variable types flowing into it, branch targets, exception handlers and
+// merge points must have a StackMapTable frame the incoming types are assignable to.
+//
+// The class hierarchy is not available, so one class type is assumed assignable to another,
+// only arrays, primitives and uninitialized objects are checked precisely.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::attribute::{Attribute, Code, ExceptionHandler, StackMapFrame, VerificationType};
+use crate::bytecode::{self, Instruction, Operand};
+use crate::classfile::{ClassFile, Method};
+use crate::opcode::*;
+use crate::pool::{ConstantPool, ConstantPoolItem};
+use crate::signature::{self, BaseType, JavaType};
+
+const ACC_STATIC: u16 = 0x0008;
+const ACC_NATIVE: u16 = 0x0100;
+const ACC_ABSTRACT: u16 = 0x0400;
+
+const OBJECT: &str = "java/lang/Object";
+const THROWABLE: &str = "java/lang/Throwable";
+
+/// First class file version whose methods carry StackMapTable frames (Java 6).
+pub const STACK_MAP_VERSION: u16 = 50;
+
+/// A verification failure, `offset` is `None` for errors about the method as a whole.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VerifyError {
+    pub method: String, // name and descriptor, e.g. `main([Ljava/lang/String;)V`
+    pub offset: Option<u32>,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} at offset {}: {}", self.method, offset, self.message),
+            None => write!(f, "{}: {}", self.method, self.message),
+        }
+    }
+}
+
+/// Verification type of a local variable or operand stack entry. Long and double values are
+/// one stack entry but take two local variable slots, the second one is `Top`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    Uninitialized(u32), // offset of the `new` instruction
+    Reference(String),  // class name or array descriptor, as in CONSTANT_Class
+}
+
+impl fmt::Display for VType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Top => write!(f, "top"),
+            Self::Integer => write!(f, "int"),
+            Self::Float => write!(f, "float"),
+            Self::Long => write!(f, "long"),
+            Self::Double => write!(f, "double"),
+            Self::Null => write!(f, "null"),
+            Self::UninitializedThis => write!(f, "uninitializedThis"),
+            Self::Uninitialized(offset) => write!(f, "uninitialized({})", offset),
+            Self::Reference(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl VType {
+    fn is_category2(&self) -> bool {
+        matches!(self, Self::Long | Self::Double)
+    }
+
+    fn is_reference(&self) -> bool {
+        matches!(self, Self::Null | Self::Reference(_))
+    }
+
+    fn of(item: &JavaType) -> Self {
+        match item {
+            JavaType::Base(BaseType::Float) => Self::Float,
+            JavaType::Base(BaseType::Long) => Self::Long,
+            JavaType::Base(BaseType::Double) => Self::Double,
+            JavaType::Base(_) => Self::Integer,
+            JavaType::Class(class) => Self::Reference(class.internal_name()),
+            JavaType::Array(_) => Self::Reference(item.descriptor()),
+            JavaType::TypeVariable(_) => Self::Reference(OBJECT.to_string()),
+            JavaType::Void => Self::Top,
+        }
+    }
+}
+
+/// `Ljava/lang/String;` is `java/lang/String`, array descriptors stay as they are.
+fn class_name(descriptor: &str) -> &str {
+    descriptor
+        .strip_prefix('L')
+        .and_then(|name| name.strip_suffix(';'))
+        .unwrap_or(descriptor)
+}
+
+fn reference_assignable(from: &str, to: &str) -> bool {
+    // Array components are compared one dimension at a time, in a loop since names come from
+    // the class file and may have any number of dimensions.
+    let (mut from, mut to) = (from, to);
+    loop {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(from_component), Some(to_component)) => {
+                let is_reference = |item: &str| item.starts_with(['L', '[']);
+                if !is_reference(from_component) || !is_reference(to_component) {
+                    return from_component == to_component;
+                }
+                from = class_name(from_component);
+                to = class_name(to_component);
+            }
+            (Some(_), None) => return to == "java/lang/Cloneable" || to == "java/io/Serializable",
+            (None, Some(_)) => return false,
+            // Without the class hierarchy any class may be a subtype of any other.
+            (None, None) => return true,
+        }
+    }
+}
+
+fn assignable(from: &VType, to: &VType) -> bool {
+    match (from, to) {
+        _ if from == to => true,
+        (_, VType::Top) => true,
+        (VType::Null, VType::Reference(_)) => true,
+        (VType::Reference(from), VType::Reference(to)) => reference_assignable(from, to),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    locals: Vec<VType>, // one entry per slot, padded with `Top` to max_locals
+    stack: Vec<VType>,
+}
+
+impl Frame {
+    fn pop(&mut self) -> Result<VType, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "operand stack underflow".to_string())
+    }
+
+    fn pop_expect(&mut self, expected: &VType) -> Result<VType, String> {
+        let found = self.pop()?;
+        if assignable(&found, expected) {
+            Ok(found)
+        } else {
+            Err(format!(
+                "expected {} on the operand stack, found {}",
+                expected, found
+            ))
+        }
+    }
+
+    fn pop_category1(&mut self) -> Result<VType, String> {
+        let found = self.pop()?;
+        if found.is_category2() {
+            Err(format!("expected a category 1 value, found {}", found))
+        } else {
+            Ok(found)
+        }
+    }
+
+    fn pop_reference(&mut self) -> Result<VType, String> {
+        let found = self.pop()?;
+        if found.is_reference() {
+            Ok(found)
+        } else {
+            Err(format!("expected a reference, found {}", found))
+        }
+    }
+
+    /// Pops an array reference, `element` picks the array types accepted (e.g. `[B` and `[Z`).
+    fn pop_array(&mut self, element: fn(&str) -> bool) -> Result<VType, String> {
+        let found = self.pop()?;
+        match &found {
+            VType::Null => Ok(found),
+            VType::Reference(name) if name.strip_prefix('[').is_some_and(element) => Ok(found),
+            _ => Err(format!("expected an array, found {}", found)),
+        }
+    }
+
+    fn load(&self, index: u16, expected: &VType) -> Result<VType, String> {
+        let index = index as usize;
+        let found = self
+            .locals
+            .get(index)
+            .ok_or_else(|| format!("local variable {} exceeds max_locals", index))?;
+        let accepted = match expected {
+            // aload also loads uninitialized objects, e.g. `this` before super() is called.
+            VType::Reference(_) => {
+                found.is_reference()
+                    || matches!(found, VType::UninitializedThis | VType::Uninitialized(_))
+            }
+            _ => found == expected,
+        };
+        if !accepted {
+            return Err(format!(
+                "expected {} in local variable {}, found {}",
+                expected, index, found
+            ));
+        }
+        Ok(found.clone())
+    }
+
+    fn store(&mut self, index: u16, value: VType) -> Result<(), String> {
+        let index = index as usize;
+        let width = if value.is_category2() { 2 } else { 1 };
+        if index + width > self.locals.len() {
+            return Err(format!("local variable {} exceeds max_locals", index));
+        }
+        // Overwriting the second half of a long or double invalidates it.
+        if index > 0 && self.locals[index - 1].is_category2() {
+            self.locals[index - 1] = VType::Top;
+        }
+        if width == 2 {
+            self.locals[index + 1] = VType::Top;
+        }
+        self.locals[index] = value;
+        Ok(())
+    }
+
+    fn replace(&mut self, from: &VType, to: &VType) {
+        for item in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if item == from {
+                *item = to.clone();
+            }
+        }
+    }
+}
+
+fn stack_size(stack: &[VType]) -> usize {
+    stack
+        .iter()
+        .map(|item| if item.is_category2() { 2 } else { 1 })
+        .sum()
+}
+
+/// Checks that a frame can flow into a StackMapTable frame.
+fn frame_assignable(from: &Frame, to: &Frame) -> Result<(), String> {
+    if from.stack.len() != to.stack.len() {
+        return Err(format!(
+            "operand stack has {} entries, the frame expects {}",
+            from.stack.len(),
+            to.stack.len()
+        ));
+    }
+    for (i, (from, to)) in from.stack.iter().zip(to.stack.iter()).enumerate() {
+        if !assignable(from, to) {
+            return Err(format!(
+                "operand stack entry {} is {}, the frame expects {}",
+                i, from, to
+            ));
+        }
+    }
+    for (i, (from, to)) in from.locals.iter().zip(to.locals.iter()).enumerate() {
+        if !assignable(from, to) {
+            return Err(format!(
+                "local variable {} is {}, the frame expects {}",
+                i, from, to
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn primitive(kind: u8) -> VType {
+    match kind {
+        0 => VType::Integer,
+        1 => VType::Long,
+        2 => VType::Float,
+        3 => VType::Double,
+        _ => VType::Reference(OBJECT.to_string()),
+    }
+}
+
+struct MethodVerifier<'a> {
+    pool: &'a ConstantPool,
+    this_class: String,
+    name: String,
+    is_static: bool,
+    return_type: JavaType,
+    parameters: Vec<JavaType>,
+    code: &'a Code,
+    instructions: Vec<Instruction>,
+    starts: HashMap<u32, usize>, // instruction offset => index
+    frames: HashMap<u32, Frame>,
+}
+
+impl<'a> MethodVerifier<'a> {
+    fn max_stack(&self) -> usize {
+        self.code.max_stack as usize
+    }
+
+    fn push(&self, frame: &mut Frame, value: VType) -> Result<(), String> {
+        frame.stack.push(value);
+        if stack_size(&frame.stack) > self.max_stack() {
+            return Err(format!(
+                "operand stack overflow, max_stack is {}",
+                self.max_stack()
+            ));
+        }
+        Ok(())
+    }
+
+    fn push_all(&self, frame: &mut Frame, values: &[&VType]) -> Result<(), String> {
+        values
+            .iter()
+            .try_for_each(|value| self.push(frame, (*value).clone()))
+    }
+
+    /// Locals of the implicit initial frame: `this` and the parameters, one entry per value.
+    fn initial_entries(&self) -> Vec<VType> {
+        let mut acc = Vec::<VType>::new();
+        if !self.is_static {
+            if self.name == "<init>" && self.this_class != OBJECT {
+                acc.push(VType::UninitializedThis);
+            } else {
+                acc.push(VType::Reference(self.this_class.clone()));
+            }
+        }
+        acc.extend(self.parameters.iter().map(VType::of));
+        acc
+    }
+
+    fn expand(&self, entries: &[VType], stack: Vec<VType>) -> Result<Frame, String> {
+        let mut locals = Vec::<VType>::with_capacity(self.code.max_locals as usize);
+        for entry in entries.iter() {
+            let category2 = entry.is_category2();
+            locals.push(entry.clone());
+            if category2 {
+                locals.push(VType::Top);
+            }
+        }
+        if locals.len() > self.code.max_locals as usize {
+            return Err(format!(
+                "frame has {} local variable slots, max_locals is {}",
+                locals.len(),
+                self.code.max_locals
+            ));
+        }
+        if stack_size(&stack) > self.max_stack() {
+            return Err(format!(
+                "frame has a deeper operand stack than max_stack {}",
+                self.max_stack()
+            ));
+        }
+        locals.resize(self.code.max_locals as usize, VType::Top);
+        Ok(Frame { locals, stack })
+    }
+
+    fn verification_type(&self, item: &VerificationType) -> Result<VType, String> {
+        Ok(match item {
+            VerificationType::Top => VType::Top,
+            VerificationType::Integer => VType::Integer,
+            VerificationType::Float => VType::Float,
+            VerificationType::Double => VType::Double,
+            VerificationType::Long => VType::Long,
+            VerificationType::Null => VType::Null,
+            VerificationType::UninitializedThis => VType::UninitializedThis,
+            VerificationType::Object(index) => VType::Reference(
+                self.pool
+                    .get_class_name(*index)
+                    .map_err(|e| e.to_string())?
+                    .to_string(),
+            ),
+            VerificationType::Uninitialized(offset) => VType::Uninitialized(*offset as u32),
+        })
+    }
+
+    fn verification_types(&self, items: &[VerificationType]) -> Result<Vec<VType>, String> {
+        items
+            .iter()
+            .map(|item| self.verification_type(item))
+            .collect()
+    }
+
+    /// Expands the StackMapTable into full frames keyed by bytecode offset.
+    fn read_frames(&mut self) -> Result<(), (Option<u32>, String)> {
+        let tables: Vec<&Vec<StackMapFrame>> = self
+            .code
+            .attributes
+            .iter()
+            .filter_map(|item| match &item.attribute {
+                Attribute::StackMapTable(frames) => Some(frames),
+                _ => None,
+            })
+            .collect();
+        if tables.len() > 1 {
+            return Err((None, "more than one StackMapTable attribute".to_string()));
+        }
+        let mut entries = self.initial_entries();
+        let mut offset: i64 = -1;
+        for frame in tables.first().map_or(&[][..], |frames| frames.as_slice()) {
+            offset += frame.offset_delta() as i64 + 1;
+            let at = Some(offset as u32);
+            let stack = match frame {
+                StackMapFrame::Same(_) | StackMapFrame::SameExtended(_) => Vec::new(),
+                StackMapFrame::SameLocals1StackItem(_, item)
+                | StackMapFrame::SameLocals1StackItemExtended(_, item) => {
+                    vec![self.verification_type(item).map_err(|e| (at, e))?]
+                }
+                StackMapFrame::Chop(frame_type, _) => {
+                    let chopped = (251 - frame_type) as usize;
+                    if chopped > entries.len() {
+                        return Err((at, "chop frame removes more locals than exist".to_string()));
+                    }
+                    entries.truncate(entries.len() - chopped);
+                    Vec::new()
+                }
+                StackMapFrame::Append(_, locals) => {
+                    entries.extend(self.verification_types(locals).map_err(|e| (at, e))?);
+                    Vec::new()
+                }
+                StackMapFrame::Full(_, locals, stack) => {
+                    entries = self.verification_types(locals).map_err(|e| (at, e))?;
+                    self.verification_types(stack).map_err(|e| (at, e))?
+                }
+            };
+            if !self.starts.contains_key(&(offset as u32)) {
+                let msg = "stack map frame is not at the start of an instruction".to_string();
+                return Err((at, msg));
+            }
+            let expanded = self.expand(&entries, stack).map_err(|e| (at, e))?;
+            self.frames.insert(offset as u32, expanded);
+        }
+        Ok(())
+    }
+
+    fn check_target(&self, frame: &Frame, target: i32) -> Result<(), String> {
+        let target_frame = u32::try_from(target)
+            .ok()
+            .filter(|target| self.starts.contains_key(target))
+            .ok_or_else(|| {
+                format!(
+                    "branch target {} is not the start of an instruction",
+                    target
+                )
+            })
+            .and_then(|target| {
+                self.frames
+                    .get(&target)
+                    .ok_or_else(|| format!("no stack map frame at branch target {}", target))
+            })?;
+        frame_assignable(frame, target_frame)
+            .map_err(|e| format!("branch to {} does not match its frame: {}", target, e))
+    }
+
+    fn check_handlers(&self, frame: &Frame, offset: u32) -> Result<(), String> {
+        let covering = self
+            .code
+            .exception_table
+            .iter()
+            .filter(|h| h.start_pc as u32 <= offset && offset < h.end_pc as u32);
+        for handler in covering {
+            let catch_type = if handler.catch_type == 0 {
+                THROWABLE
+            } else {
+                self.pool
+                    .get_class_name(handler.catch_type)
+                    .map_err(|e| e.to_string())?
+            };
+            let thrown = Frame {
+                locals: frame.locals.clone(),
+                stack: vec![VType::Reference(catch_type.to_string())],
+            };
+            let target = self
+                .frames
+                .get(&(handler.handler_pc as u32))
+                .ok_or_else(|| format!("no stack map frame at handler {}", handler.handler_pc))?;
+            frame_assignable(&thrown, target).map_err(|e| {
+                format!(
+                    "exception handler at {} does not match its frame: {}",
+                    handler.handler_pc, e
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn check_handler_ranges(&self, handler: &ExceptionHandler) -> Result<(), String> {
+        let is_start = |pc: u16| self.starts.contains_key(&(pc as u32));
+        let end_ok = is_start(handler.end_pc) || handler.end_pc as usize == self.code.code.len();
+        if handler.start_pc >= handler.end_pc || !is_start(handler.start_pc) || !end_ok {
+            return Err(format!(
+                "exception handler range {}..{} is not a range of instructions",
+                handler.start_pc, handler.end_pc
+            ));
+        }
+        if !is_start(handler.handler_pc) {
+            return Err(format!(
+                "exception handler {} is not the start of an instruction",
+                handler.handler_pc
+            ));
+        }
+        Ok(())
+    }
+
+    fn constant(&self, index: u16, opcode: u8) -> Result<VType, String> {
+        let item = self.pool.get(index).map_err(|e| e.to_string())?;
+        let value = match item {
+            ConstantPoolItem::Integer(_) => VType::Integer,
+            ConstantPoolItem::Float(_) => VType::Float,
+            ConstantPoolItem::Long(_) => VType::Long,
+            ConstantPoolItem::Double(_) => VType::Double,
+            ConstantPoolItem::String(_) => VType::Reference("java/lang/String".to_string()),
+            ConstantPoolItem::Class(_) => VType::Reference("java/lang/Class".to_string()),
+            ConstantPoolItem::MethodType(_) => {
+                VType::Reference("java/lang/invoke/MethodType".to_string())
+            }
+            ConstantPoolItem::MethodHandle(_, _) => {
+                VType::Reference("java/lang/invoke/MethodHandle".to_string())
+            }
+            ConstantPoolItem::Dynamic(_, nat) => {
+                let (_, descriptor) = self
+                    .pool
+                    .get_name_and_type(*nat)
+                    .map_err(|e| e.to_string())?;
+                VType::of(
+                    &signature::parse_field_descriptor(descriptor).map_err(|e| e.to_string())?,
+                )
+            }
+            item => {
+                return Err(format!(
+                    "{} can not load a {} constant",
+                    mnemonic(opcode).unwrap_or("?"),
+                    item.kind()
+                ))
+            }
+        };
+        if value.is_category2() != (opcode == LDC2_W) {
+            return Err(format!(
+                "{} can not load a {} constant",
+                mnemonic(opcode).unwrap_or("?"),
+                value
+            ));
+        }
+        Ok(value)
+    }
+
+    fn invoke(&self, frame: &mut Frame, instruction: &Instruction) -> Result<(), String> {
+        let opcode = instruction.opcode;
+        let index = match instruction.operand {
+            Operand::Constant(index)
+            | Operand::InvokeInterface(index, _)
+            | Operand::InvokeDynamic(index) => index,
+            _ => return Err("missing method reference".to_string()),
+        };
+        let (class, name, descriptor) = if opcode == INVOKEDYNAMIC {
+            let nat = match self.pool.get(index) {
+                Ok(ConstantPoolItem::InvokeDynamic(_, nat)) => *nat,
+                _ => return Err(format!("#{} is not an InvokeDynamic constant", index)),
+            };
+            let (name, descriptor) = self
+                .pool
+                .get_name_and_type(nat)
+                .map_err(|e| e.to_string())?;
+            ("", name, descriptor)
+        } else {
+            self.pool.get_member_ref(index).map_err(|e| e.to_string())?
+        };
+        let types = signature::parse_method_descriptor(descriptor).map_err(|e| e.to_string())?;
+        for parameter in types.parameters.iter().rev() {
+            frame.pop_expect(&VType::of(parameter))?;
+        }
+        if name == "<init>" && opcode != INVOKESPECIAL || name == "<clinit>" {
+            return Err(format!(
+                "{} can not call {}",
+                mnemonic(opcode).unwrap_or("?"),
+                name
+            ));
+        }
+        if opcode != INVOKESTATIC && opcode != INVOKEDYNAMIC {
+            let receiver = frame.pop()?;
+            if name == "<init>" {
+                let initialized = match &receiver {
+                    VType::UninitializedThis => VType::Reference(self.this_class.clone()),
+                    VType::Uninitialized(offset) => {
+                        let new = self
+                            .starts
+                            .get(offset)
+                            .map(|index| &self.instructions[*index])
+                            .filter(|item| item.opcode == NEW);
+                        match new.map(|item| &item.operand) {
+                            Some(Operand::Constant(index)) => VType::Reference(
+                                self.pool
+                                    .get_class_name(*index)
+                                    .map_err(|e| e.to_string())?
+                                    .to_string(),
+                            ),
+                            _ => {
+                                return Err(format!(
+                                    "{} does not refer to a new instruction",
+                                    receiver
+                                ))
+                            }
+                        }
+                    }
+                    _ => {
+                        return Err(format!(
+                            "<init> called on {}, expected an uninitialized object",
+                            receiver
+                        ))
+                    }
+                };
+                frame.replace(&receiver, &initialized);
+            } else if !assignable(&receiver, &VType::Reference(class.to_string())) {
+                return Err(format!(
+                    "expected {} as receiver, found {}",
+                    class, receiver
+                ));
+            }
+        }
+        if types.return_type != JavaType::Void {
+            self.push(frame, VType::of(&types.return_type))?;
+        }
+        Ok(())
+    }
+
+    fn field(&self, frame: &mut Frame, opcode: u8, index: u16) -> Result<(), String> {
+        let (class, _, descriptor) = self.pool.get_member_ref(index).map_err(|e| e.to_string())?;
+        let value =
+            VType::of(&signature::parse_field_descriptor(descriptor).map_err(|e| e.to_string())?);
+        if opcode == PUTSTATIC || opcode == PUTFIELD {
+            frame.pop_expect(&value)?;
+        }
+        if opcode == GETFIELD || opcode == PUTFIELD {
+            let target = frame.pop()?;
+            // Constructors may access their own fields before calling super().
+            let own = target == VType::UninitializedThis && class == self.this_class;
+            if !own && !assignable(&target, &VType::Reference(class.to_string())) {
+                return Err(format!(
+                    "expected {} as field owner, found {}",
+                    class, target
+                ));
+            }
+        }
+        if opcode == GETSTATIC || opcode == GETFIELD {
+            self.push(frame, value)?;
+        }
+        Ok(())
+    }
+
+    fn return_value(&self, frame: &mut Frame, opcode: u8) -> Result<(), String> {
+        let expected = match (&self.return_type, opcode) {
+            (JavaType::Void, RETURN) => {
+                if self.name == "<init>" && frame.locals.contains(&VType::UninitializedThis) {
+                    return Err("constructor returns before calling super() or this()".to_string());
+                }
+                return Ok(());
+            }
+            (JavaType::Base(BaseType::Long), LRETURN)
+            | (JavaType::Base(BaseType::Float), FRETURN)
+            | (JavaType::Base(BaseType::Double), DRETURN) => VType::of(&self.return_type),
+            (JavaType::Base(BaseType::Long | BaseType::Float | BaseType::Double), _) => {
+                return Err(self.return_mismatch(opcode))
+            }
+            (JavaType::Base(_), IRETURN) => VType::Integer,
+            (JavaType::Class(_) | JavaType::Array(_) | JavaType::TypeVariable(_), ARETURN) => {
+                VType::of(&self.return_type)
+            }
+            _ => return Err(self.return_mismatch(opcode)),
+        };
+        frame.pop_expect(&expected)?;
+        Ok(())
+    }
+
+    fn return_mismatch(&self, opcode: u8) -> String {
+        format!(
+            "{} in a method returning {}",
+            mnemonic(opcode).unwrap_or("?"),
+            self.return_type
+        )
+    }
+
+    /// Applies one instruction, returns the outgoing frame or `None` if execution does not
+    /// continue with the next instruction.
+    fn execute(
+        &self,
+        mut frame: Frame,
+        instruction: &Instruction,
+    ) -> Result<Option<Frame>, String> {
+        let opcode = instruction.opcode;
+        let int = VType::Integer;
+        let local = |operand: &Operand| match operand {
+            Operand::Local(index) | Operand::Iinc(index, _) => *index,
+            _ => 0,
+        };
+        match opcode {
+            NOP => {}
+            ACONST_NULL => self.push(&mut frame, VType::Null)?,
+            ICONST_M1..=ICONST_5 | BIPUSH | SIPUSH => self.push(&mut frame, int)?,
+            LCONST_0 | LCONST_1 => self.push(&mut frame, VType::Long)?,
+            FCONST_0..=FCONST_2 => self.push(&mut frame, VType::Float)?,
+            DCONST_0 | DCONST_1 => self.push(&mut frame, VType::Double)?,
+            LDC | LDC_W | LDC2_W => {
+                let value = self.constant(constant_index(&instruction.operand), opcode)?;
+                self.push(&mut frame, value)?;
+            }
+            ILOAD..=ALOAD => {
+                let value = frame.load(local(&instruction.operand), &primitive(opcode - ILOAD))?;
+                self.push(&mut frame, value)?;
+            }
+            ILOAD_0..=ALOAD_3 => {
+                let kind = (opcode - ILOAD_0) / 4;
+                let index = ((opcode - ILOAD_0) % 4) as u16;
+                let value = frame.load(index, &primitive(kind))?;
+                self.push(&mut frame, value)?;
+            }
+            IALOAD..=SALOAD => {
+                frame.pop_expect(&int)?;
+                let array = match opcode {
+                    IALOAD => frame.pop_array(|e| e == "I")?,
+                    LALOAD => frame.pop_array(|e| e == "J")?,
+                    FALOAD => frame.pop_array(|e| e == "F")?,
+                    DALOAD => frame.pop_array(|e| e == "D")?,
+                    AALOAD => frame.pop_array(|e| e.starts_with(['L', '[']))?,
+                    BALOAD => frame.pop_array(|e| e == "B" || e == "Z")?,
+                    CALOAD => frame.pop_array(|e| e == "C")?,
+                    _ => frame.pop_array(|e| e == "S")?,
+                };
+                let value = match (opcode, &array) {
+                    (AALOAD, VType::Reference(name)) => {
+                        VType::Reference(class_name(&name[1..]).to_string())
+                    }
+                    (AALOAD, _) => VType::Null,
+                    (LALOAD, _) => VType::Long,
+                    (FALOAD, _) => VType::Float,
+                    (DALOAD, _) => VType::Double,
+                    _ => int,
+                };
+                self.push(&mut frame, value)?;
+            }
+            ISTORE..=ASTORE => {
+                let value = store_value(&mut frame, opcode - ISTORE)?;
+                frame.store(local(&instruction.operand), value)?;
+            }
+            ISTORE_0..=ASTORE_3 => {
+                let value = store_value(&mut frame, (opcode - ISTORE_0) / 4)?;
+                frame.store(((opcode - ISTORE_0) % 4) as u16, value)?;
+            }
+            IASTORE..=SASTORE => {
+                match opcode {
+                    AASTORE => frame.pop_reference().map(|_| ())?,
+                    _ => frame
+                        .pop_expect(&primitive(array_kind(opcode)))
+                        .map(|_| ())?,
+                }
+                frame.pop_expect(&int)?;
+                match opcode {
+                    IASTORE => frame.pop_array(|e| e == "I")?,
+                    LASTORE => frame.pop_array(|e| e == "J")?,
+                    FASTORE => frame.pop_array(|e| e == "F")?,
+                    DASTORE => frame.pop_array(|e| e == "D")?,
+                    AASTORE => frame.pop_array(|e| e.starts_with(['L', '[']))?,
+                    BASTORE => frame.pop_array(|e| e == "B" || e == "Z")?,
+                    CASTORE => frame.pop_array(|e| e == "C")?,
+                    _ => frame.pop_array(|e| e == "S")?,
+                };
+            }
+            POP => {
+                frame.pop_category1()?;
+            }
+            POP2 => {
+                if !frame.pop()?.is_category2() {
+                    frame.pop_category1()?;
+                }
+            }
+            DUP => {
+                let v1 = frame.pop_category1()?;
+                self.push_all(&mut frame, &[&v1, &v1])?;
+            }
+            DUP_X1 => {
+                let v1 = frame.pop_category1()?;
+                let v2 = frame.pop_category1()?;
+                self.push_all(&mut frame, &[&v1, &v2, &v1])?;
+            }
+            DUP_X2 => {
+                let v1 = frame.pop_category1()?;
+                let v2 = frame.pop()?;
+                if v2.is_category2() {
+                    self.push_all(&mut frame, &[&v1, &v2, &v1])?;
+                } else {
+                    let v3 = frame.pop_category1()?;
+                    self.push_all(&mut frame, &[&v1, &v3, &v2, &v1])?;
+                }
+            }
+            DUP2 => {
+                let v1 = frame.pop()?;
+                if v1.is_category2() {
+                    self.push_all(&mut frame, &[&v1, &v1])?;
+                } else {
+                    let v2 = frame.pop_category1()?;
+                    self.push_all(&mut frame, &[&v2, &v1, &v2, &v1])?;
+                }
+            }
+            DUP2_X1 => {
+                let v1 = frame.pop()?;
+                if v1.is_category2() {
+                    let v2 = frame.pop_category1()?;
+                    self.push_all(&mut frame, &[&v1, &v2, &v1])?;
+                } else {
+                    let v2 = frame.pop_category1()?;
+                    let v3 = frame.pop_category1()?;
+                    self.push_all(&mut frame, &[&v2, &v1, &v3, &v2, &v1])?;
+                }
+            }
+            DUP2_X2 => {
+                let v1 = frame.pop()?;
+                if v1.is_category2() {
+                    let v2 = frame.pop()?;
+                    if v2.is_category2() {
+                        self.push_all(&mut frame, &[&v1, &v2, &v1])?;
+                    } else {
+                        let v3 = frame.pop_category1()?;
+                        self.push_all(&mut frame, &[&v1, &v3, &v2, &v1])?;
+                    }
+                } else {
+                    let v2 = frame.pop_category1()?;
+                    let v3 = frame.pop()?;
+                    if v3.is_category2() {
+                        self.push_all(&mut frame, &[&v2, &v1, &v3, &v2, &v1])?;
+                    } else {
+                        let v4 = frame.pop_category1()?;
+                        self.push_all(&mut frame, &[&v2, &v1, &v4, &v3, &v2, &v1])?;
+                    }
+                }
+            }
+            SWAP => {
+                let v1 = frame.pop_category1()?;
+                let v2 = frame.pop_category1()?;
+                self.push_all(&mut frame, &[&v1, &v2])?;
+            }
+            IADD..=DREM | IAND..=LXOR => {
+                let value = match opcode {
+                    IADD..=DREM => primitive((opcode - IADD) % 4),
+                    _ => primitive((opcode - IAND) % 2),
+                };
+                frame.pop_expect(&value)?;
+                frame.pop_expect(&value)?;
+                self.push(&mut frame, value)?;
+            }
+            INEG..=DNEG => {
+                let value = primitive(opcode - INEG);
+                frame.pop_expect(&value)?;
+                self.push(&mut frame, value)?;
+            }
+            ISHL..=LUSHR => {
+                let value = primitive((opcode - ISHL) % 2);
+                frame.pop_expect(&int)?;
+                frame.pop_expect(&value)?;
+                self.push(&mut frame, value)?;
+            }
+            IINC => {
+                frame.load(local(&instruction.operand), &int)?;
+            }
+            I2L..=D2F => {
+                // i2l i2f i2d l2i l2f l2d f2i f2l f2d d2i d2l d2f
+                let from = (opcode - I2L) / 3;
+                let to = (opcode - I2L) % 3;
+                let to = if to >= from { to + 1 } else { to };
+                frame.pop_expect(&primitive(from))?;
+                self.push(&mut frame, primitive(to))?;
+            }
+            I2B | I2C | I2S => {
+                frame.pop_expect(&int)?;
+                self.push(&mut frame, int)?;
+            }
+            LCMP..=DCMPG => {
+                let value = match opcode {
+                    LCMP => VType::Long,
+                    FCMPL | FCMPG => VType::Float,
+                    _ => VType::Double,
+                };
+                frame.pop_expect(&value)?;
+                frame.pop_expect(&value)?;
+                self.push(&mut frame, int)?;
+            }
+            IFEQ..=IF_ACMPNE | IFNULL | IFNONNULL => {
+                match opcode {
+                    IFEQ..=IFLE => frame.pop_expect(&int).map(|_| ())?,
+                    IF_ICMPEQ..=IF_ICMPLE => {
+                        frame.pop_expect(&int)?;
+                        frame.pop_expect(&int).map(|_| ())?
+                    }
+                    IF_ACMPEQ | IF_ACMPNE => {
+                        frame.pop_reference()?;
+                        frame.pop_reference().map(|_| ())?
+                    }
+                    _ => frame.pop_reference().map(|_| ())?,
+                }
+                if let Operand::Branch(target) = instruction.operand {
+                    self.check_target(&frame, target)?;
+                }
+            }
+            GOTO | GOTO_W => {
+                if let Operand::Branch(target) = instruction.operand {
+                    self.check_target(&frame, target)?;
+                }
+                return Ok(None);
+            }
+            JSR | JSR_W | RET => {
+                return Err(format!(
+                    "{} is not allowed in class files with StackMapTable frames",
+                    instruction.mnemonic()
+                ))
+            }
+            TABLESWITCH | LOOKUPSWITCH => {
+                frame.pop_expect(&int)?;
+                let targets: Vec<i32> = match &instruction.operand {
+                    Operand::TableSwitch(default, _, _, targets) => {
+                        targets.iter().chain([default]).copied().collect()
+                    }
+                    Operand::LookupSwitch(default, pairs) => pairs
+                        .iter()
+                        .map(|(_, target)| *target)
+                        .chain([*default])
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                if let Operand::LookupSwitch(_, pairs) = &instruction.operand {
+                    if pairs.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                        return Err("lookupswitch keys are not sorted".to_string());
+                    }
+                }
+                for target in targets {
+                    self.check_target(&frame, target)?;
+                }
+                return Ok(None);
+            }
+            IRETURN..=RETURN => {
+                self.return_value(&mut frame, opcode)?;
+                return Ok(None);
+            }
+            GETSTATIC..=PUTFIELD => {
+                self.field(&mut frame, opcode, constant_index(&instruction.operand))?
+            }
+            INVOKEVIRTUAL..=INVOKEDYNAMIC => self.invoke(&mut frame, instruction)?,
+            NEW => self.push(&mut frame, VType::Uninitialized(instruction.offset))?,
+            NEWARRAY => {
+                frame.pop_expect(&int)?;
+                let element = match instruction.operand {
+                    Operand::ArrayType(4) => "Z",
+                    Operand::ArrayType(5) => "C",
+                    Operand::ArrayType(6) => "F",
+                    Operand::ArrayType(7) => "D",
+                    Operand::ArrayType(8) => "B",
+                    Operand::ArrayType(9) => "S",
+                    Operand::ArrayType(10) => "I",
+                    Operand::ArrayType(11) => "J",
+                    _ => return Err("invalid newarray element type".to_string()),
+                };
+                self.push(&mut frame, VType::Reference(format!("[{}", element)))?;
+            }
+            ANEWARRAY => {
+                frame.pop_expect(&int)?;
+                let class = self.class_operand(&instruction.operand)?;
+                let array = if class.starts_with('[') {
+                    format!("[{}", class)
+                } else {
+                    format!("[L{};", class)
+                };
+                self.push(&mut frame, VType::Reference(array))?;
+            }
+            ARRAYLENGTH => {
+                frame.pop_array(|_| true)?;
+                self.push(&mut frame, int)?;
+            }
+            ATHROW => {
+                frame.pop_expect(&VType::Reference(THROWABLE.to_string()))?;
+                return Ok(None);
+            }
+            CHECKCAST => {
+                frame.pop_reference()?;
+                let class = self.class_operand(&instruction.operand)?;
+                self.push(&mut frame, VType::Reference(class))?;
+            }
+            INSTANCEOF => {
+                frame.pop_reference()?;
+                self.push(&mut frame, int)?;
+            }
+            MONITORENTER | MONITOREXIT => {
+                frame.pop_reference()?;
+            }
+            MULTIANEWARRAY => {
+                let (index, dimensions) = match instruction.operand {
+                    Operand::MultiANewArray(index, dimensions) => (index, dimensions),
+                    _ => (0, 0),
+                };
+                let class = self.pool.get_class_name(index).map_err(|e| e.to_string())?;
+                let depth = class.chars().take_while(|c| *c == '[').count();
+                if dimensions == 0 || dimensions as usize > depth {
+                    return Err(format!("{} can not have {} dimensions", class, dimensions));
+                }
+                for _ in 0..dimensions {
+                    frame.pop_expect(&int)?;
+                }
+                self.push(&mut frame, VType::Reference(class.to_string()))?;
+            }
+            _ => return Err(format!("unexpected opcode 0x{:02x}", opcode)),
+        }
+        Ok(Some(frame))
+    }
+
+    fn class_operand(&self, operand: &Operand) -> Result<String, String> {
+        self.pool
+            .get_class_name(constant_index(operand))
+            .map(|name| name.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    fn run(&mut self) -> Result<(), (Option<u32>, String)> {
+        if self.code.code.is_empty() {
+            return Err((None, "code is empty".to_string()));
+        }
+        self.read_frames()?;
+        for handler in self.code.exception_table.iter() {
+            self.check_handler_ranges(handler)
+                .map_err(|e| (Some(handler.start_pc as u32), e))?;
+        }
+        let initial = self
+            .expand(&self.initial_entries(), Vec::new())
+            .map_err(|e| (None, e))?;
+        let mut current = Some(initial);
+        for instruction in self.instructions.iter() {
+            let offset = instruction.offset;
+            let at = |e: String| (Some(offset), e);
+            if let Some(map_frame) = self.frames.get(&offset) {
+                if let Some(frame) = current.as_ref() {
+                    frame_assignable(frame, map_frame)
+                        .map_err(|e| at(format!("stack map frame does not match: {}", e)))?;
+                }
+                current = Some(map_frame.clone());
+            }
+            let frame = current.take().ok_or_else(|| {
+                at("no stack map frame after an unconditional branch".to_string())
+            })?;
+            self.check_handlers(&frame, offset).map_err(at)?;
+            current = self.execute(frame, instruction).map_err(at)?;
+        }
+        if current.is_some() {
+            let last = self.instructions.last().map(|item| item.offset);
+            return Err((last, "execution falls off the end of the code".to_string()));
+        }
+        Ok(())
+    }
+}
+
+fn constant_index(operand: &Operand) -> u16 {
+    match operand {
+        Operand::Constant(index) => *index,
+        _ => 0,
+    }
+}
+
+/// Value kind of a primitive array store, e.g. `bastore` stores an int.
+fn array_kind(opcode: u8) -> u8 {
+    match opcode {
+        LASTORE => 1,
+        FASTORE => 2,
+        DASTORE => 3,
+        _ => 0,
+    }
+}
+
+fn store_value(frame: &mut Frame, kind: u8) -> Result<VType, String> {
+    if kind == 4 {
+        // astore also stores uninitialized objects.
+        let found = frame.pop()?;
+        match found {
+            VType::Null
+            | VType::Reference(_)
+            | VType::UninitializedThis
+            | VType::Uninitialized(_) => Ok(found),
+            _ => Err(format!("expected a reference, found {}", found)),
+        }
+    } else {
+        frame.pop_expect(&primitive(kind))
+    }
+}
+
+/// Verifies one method, the first problem found is reported.
+pub fn verify_method(class: &ClassFile, method: &Method) -> Result<(), VerifyError> {
+    let pool = &class.constant_pool;
+    let name = method.name(pool);
+    let descriptor = method.descriptor(pool);
+    let error = |offset: Option<u32>, message: String| VerifyError {
+        method: format!("{}{}", name, descriptor),
+        offset,
+        message,
+    };
+    let has_body =
+        !method.access_flags.contains(ACC_ABSTRACT) && !method.access_flags.contains(ACC_NATIVE);
+    let code = match (method.code(), has_body) {
+        (Some(code), true) => code,
+        (None, false) => return Ok(()),
+        (Some(_), false) => {
+            return Err(error(
+                None,
+                "abstract or native method has code".to_string(),
+            ))
+        }
+        (None, true) => return Err(error(None, "missing Code attribute".to_string())),
+    };
+    let types =
+        signature::parse_method_descriptor(&descriptor).map_err(|e| error(None, e.to_string()))?;
+    let instructions = bytecode::decode(&code.code)
+        .map_err(|e| error(Some(e.offset as u32), e.kind.to_string()))?;
+    let starts = instructions
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.offset, i))
+        .collect();
+    let mut verifier = MethodVerifier {
+        pool,
+        this_class: class.this_class_name(),
+        name: name.clone(),
+        is_static: method.access_flags.contains(ACC_STATIC),
+        return_type: types.return_type,
+        parameters: types.parameters,
+        code,
+        instructions,
+        starts,
+        frames: HashMap::new(),
+    };
+    verifier
+        .run()
+        .map_err(|(offset, message)| error(offset, message))
+}
+
+/// Verifies every method of a class. Check the version against `STACK_MAP_VERSION` first: older
+/// classes have no StackMapTable, the JVM verifies them by type inference and `jinspect verify`
+/// skips them.
+pub fn verify_class(class: &ClassFile) -> Vec<VerifyError> {
+    class
+        .methods
+        .iter()
+        .filter_map(|method| verify_method(class, method).err())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{verify_class, VerifyError};
+    use crate::assembler::assemble;
+    use crate::attribute::Attribute;
+    use crate::classfile::ClassFile;
+
+    fn read_sample(name: &str) -> ClassFile {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("samples")
+            .join(name);
+        ClassFile::parse(fs::read(path).unwrap().as_slice()).unwrap()
+    }
+
+    fn error(method: &str, offset: Option<u32>, message: &str) -> VerifyError {
+        VerifyError {
+            method: method.to_string(),
+            offset,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_verify_samples() {
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples");
+        for entry in fs::read_dir(samples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "class") {
+                continue;
+            }
+            let class = ClassFile::parse(fs::read(&path).unwrap().as_slice()).unwrap();
+            assert_eq!(verify_class(&class), vec![], "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_verify_reports_type_errors() {
+        let source = r#"
+.class public Bad
+.super java/lang/Object
+.version 52
+
+.method public <init>()V
+    .limit stack 1
+    return
+.end method
+
+.method public static f()Ljava/lang/Object;
+    .limit stack 1
+    iconst_1
+    areturn
+.end method
+
+.method public static g(J)J
+    .limit stack 1
+    lload_0
+    lreturn
+.end method
+
+.method public static h(I)I
+    .limit stack 1
+    iload_0
+    ireturn
+    iconst_0
+.end method
+"#;
+        let class = assemble(source).unwrap();
+        assert_eq!(
+            verify_class(&class),
+            vec![
+                error(
+                    "<init>()V",
+                    Some(0),
+                    "constructor returns before calling super() or this()"
+                ),
+                error(
+                    "f()Ljava/lang/Object;",
+                    Some(1),
+                    "expected java/lang/Object on the operand stack, found int"
+                ),
+                error("g(J)J", Some(0), "operand stack overflow, max_stack is 1"),
+                error(
+                    "h(I)I",
+                    Some(2),
+                    "no stack map frame after an unconditional branch"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_requires_stack_map_frames() {
+        let mut class = read_sample("Sample.class");
+        for method in class.methods.iter_mut() {
+            for item in method.attributes.iter_mut() {
+                if let Attribute::Code(code) = &mut item.attribute {
+                    code.attributes
+                        .retain(|item| !matches!(item.attribute, Attribute::StackMapTable(_)));
+                }
+            }
+        }
+        let errors = verify_class(&class);
+        assert!(!errors.is_empty());
+        for error in errors.iter() {
+            assert!(error.offset.is_some(), "{}", error);
+            assert!(error.message.starts_with("no stack map frame"), "{}", error);
+        }
+    }
+}
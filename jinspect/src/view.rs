@@ -0,0 +1,431 @@
+// Zero-copy class file parser over a byte slice, e.g. a memory-mapped file or an archive entry
+// that is already in memory. Parsing only records where the pool entries, members and
+// attributes are: UTF8 constants are borrowed from the input when their modified UTF-8 is also
+// standard UTF-8 and attributes are decoded on demand. Pool references are checked when they are
+// resolved, apart from this and super class, so a `ClassView` accepts some files that
+// `ClassFile::parse` rejects.
+
+use std::borrow::Cow;
+use std::str;
+
+use crate::attribute::{self, Attribute, AttributeInfo, AttributeNames};
+use crate::classfile::Header;
+use crate::error::{Context, ErrorKind, ParseError};
+use crate::flag::AccessFlag;
+use crate::mutf8;
+use crate::pool::{self, ConstantPool, ConstantPoolItem};
+use crate::reader::ClassReader;
+
+/// Big-endian cursor that hands out sub-slices of the input instead of copies.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], ParseError> {
+        match self.bytes.get(self.pos..self.pos + length) {
+            Some(slice) => {
+                self.pos += length;
+                Ok(slice)
+            }
+            None => Err(ParseError::new(
+                self.bytes.len() as u64,
+                ErrorKind::Truncated,
+            )),
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ParseError> {
+        let slice = self.take(2)?;
+        Ok(u16::from_be_bytes([slice[0], slice[1]]))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ParseError> {
+        let slice = self.take(4)?;
+        Ok(u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]))
+    }
+}
+
+/// An attribute whose body has not been decoded yet.
+#[derive(Debug, Clone, Copy)]
+pub struct RawAttribute<'a> {
+    pub name_index: u16,
+    pub offset: u64, // of the body in the class file
+    pub body: &'a [u8],
+}
+
+impl<'a> RawAttribute<'a> {
+    pub fn name(&self, class: &ClassView<'a>) -> Result<Cow<'a, str>, ErrorKind> {
+        class.utf8(self.name_index)
+    }
+
+    /// Decodes the body like `ClassFile::parse` does, nested attributes included.
+    pub fn decode(&self, class: &ClassView<'a>) -> Result<AttributeInfo, ParseError> {
+        // The name index is the first field of the six byte attribute header.
+        let name = self
+            .name(class)
+            .map_err(|kind| ParseError::new(self.offset - 6, kind))?;
+        let attribute = attribute::decode_attribute(&name, self.body, self.offset, class)?
+            .unwrap_or_else(|| Attribute::Unknown(name.into_owned(), self.body.to_vec()));
+        Ok(AttributeInfo {
+            name_index: self.name_index,
+            attribute,
+        })
+    }
+}
+
+fn find_attribute<'a, 'b>(
+    class: &ClassView<'a>,
+    attributes: &'b [RawAttribute<'a>],
+    name: &str,
+) -> Option<&'b RawAttribute<'a>> {
+    attributes
+        .iter()
+        .find(|item| item.name(class).is_ok_and(|item| item == name))
+}
+
+/// A field or method, its name and descriptor are resolved on request.
+#[derive(Debug, Clone)]
+pub struct MemberView<'a> {
+    pub access_flags: AccessFlag,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<RawAttribute<'a>>,
+}
+
+impl<'a> MemberView<'a> {
+    pub fn name(&self, class: &ClassView<'a>) -> Result<Cow<'a, str>, ErrorKind> {
+        class.utf8(self.name_index)
+    }
+
+    pub fn descriptor(&self, class: &ClassView<'a>) -> Result<Cow<'a, str>, ErrorKind> {
+        class.utf8(self.descriptor_index)
+    }
+
+    pub fn find_attribute(&self, class: &ClassView<'a>, name: &str) -> Option<&RawAttribute<'a>> {
+        find_attribute(class, &self.attributes, name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassView<'a> {
+    bytes: &'a [u8],
+    pub header: Header,
+    // Offset of the tag of every pool entry by index, zero for index 0 and unusable slots.
+    entries: Vec<usize>,
+    pub access_flags: AccessFlag,
+    pub this_class: u16,
+    pub super_class: u16, // zero for java/lang/Object
+    interfaces: &'a [u8],
+    pub fields: Vec<MemberView<'a>>,
+    pub methods: Vec<MemberView<'a>>,
+    pub attributes: Vec<RawAttribute<'a>>,
+}
+
+impl<'a> ClassView<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ParseError> {
+        let mut rdr = ClassReader::new(bytes);
+        let header = Header::parse(&mut rdr).context("header")?;
+        let cur = &mut Cursor {
+            bytes,
+            pos: rdr.offset() as usize,
+        };
+        let entries = Self::index_pool(cur)?;
+        let access_flags = AccessFlag::Class(cur.read_u16().context("access flags")?);
+        let mut class = ClassView {
+            bytes,
+            header,
+            entries,
+            access_flags,
+            this_class: 0,
+            super_class: 0,
+            interfaces: &[],
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        };
+        let offset = cur.pos as u64;
+        class.this_class = cur.read_u16().context("this class")?;
+        class
+            .class_name(class.this_class)
+            .map_err(|kind| ParseError::new(offset, kind))
+            .context("this class")?;
+        let offset = cur.pos as u64;
+        class.super_class = cur.read_u16().context("super class")?;
+        if class.super_class != 0 {
+            class
+                .class_name(class.super_class)
+                .map_err(|kind| ParseError::new(offset, kind))
+                .context("super class")?;
+        }
+        let interfaces_count = cur.read_u16().context("interfaces")?;
+        class.interfaces = cur
+            .take(interfaces_count as usize * 2)
+            .context("interfaces")?;
+        class.fields = Self::parse_members(cur, AccessFlag::Field, "field")?;
+        class.methods = Self::parse_members(cur, AccessFlag::Method, "method")?;
+        class.attributes = Self::parse_attributes(cur).context("class attributes")?;
+        Ok(class)
+    }
+
+    /// Records the offset of every entry, only the tags and lengths are read.
+    fn index_pool(cur: &mut Cursor<'a>) -> Result<Vec<usize>, ParseError> {
+        let count = cur.read_u16().context("constant pool count")?;
+        let mut entries = Vec::<usize>::with_capacity(count as usize);
+        entries.push(0);
+        let mut index: u16 = 1;
+        while index < count {
+            let offset = cur.pos;
+            let entry = || format!("constant pool entry #{}", index);
+            let tag = cur.read_u8().with_context(entry)?;
+            let length = match tag {
+                pool::CONSTANTPOOL_UTF8 => cur.read_u16().with_context(entry)? as usize,
+                pool::CONSTANTPOOL_CLASS
+                | pool::CONSTANTPOOL_STRING
+                | pool::CONSTANTPOOL_METHODTYPE
+                | pool::CONSTANTPOOL_MODULE
+                | pool::CONSTANTPOOL_PACKAGE => 2,
+                pool::CONSTANTPOOL_METHODHANDLE => 3,
+                pool::CONSTANTPOOL_LONG | pool::CONSTANTPOOL_DOUBLE => 8,
+                pool::CONSTANTPOOL_INTEGER
+                | pool::CONSTANTPOOL_FLOAT
+                | pool::CONSTANTPOOL_FIELDREF
+                | pool::CONSTANTPOOL_METHODREF
+                | pool::CONSTANTPOOL_INTERFACEMETHODREF
+                | pool::CONSTANTPOOL_NAMEANDTYPE
+                | pool::CONSTANTPOOL_DYNAMIC
+                | pool::CONSTANTPOOL_INVOKEDYNAMIC => 4,
+                _ => {
+                    return Err(ParseError::new(
+                        offset as u64,
+                        ErrorKind::UnknownPoolTag(tag),
+                    ))
+                    .with_context(entry)
+                }
+            };
+            cur.take(length).with_context(entry)?;
+            entries.push(offset);
+            index += 1;
+            if tag == pool::CONSTANTPOOL_LONG || tag == pool::CONSTANTPOOL_DOUBLE {
+                if index >= count {
+                    let kind = ErrorKind::Malformed(
+                        "constant has no room for its second slot".to_string(),
+                    );
+                    return Err(ParseError::new(offset as u64, kind))
+                        .with_context(|| format!("constant pool entry #{}", index - 1));
+                }
+                entries.push(0);
+                index += 1;
+            }
+        }
+        Ok(entries)
+    }
+
+    fn parse_members(
+        cur: &mut Cursor<'a>,
+        flags: fn(u16) -> AccessFlag,
+        kind: &str,
+    ) -> Result<Vec<MemberView<'a>>, ParseError> {
+        let count = cur.read_u16().with_context(|| format!("{}s", kind))?;
+        (0..count)
+            .map(|i| Self::parse_member(cur, flags).with_context(|| format!("{} #{}", kind, i)))
+            .collect()
+    }
+
+    fn parse_member(
+        cur: &mut Cursor<'a>,
+        flags: fn(u16) -> AccessFlag,
+    ) -> Result<MemberView<'a>, ParseError> {
+        Ok(MemberView {
+            access_flags: flags(cur.read_u16()?),
+            name_index: cur.read_u16().context("name")?,
+            descriptor_index: cur.read_u16().context("descriptor")?,
+            attributes: Self::parse_attributes(cur)?,
+        })
+    }
+
+    fn parse_attributes(cur: &mut Cursor<'a>) -> Result<Vec<RawAttribute<'a>>, ParseError> {
+        let count = cur.read_u16().context("attributes count")?;
+        (0..count)
+            .map(|_| {
+                let name_index = cur.read_u16()?;
+                let length = cur.read_u32()?;
+                let offset = cur.pos as u64;
+                Ok(RawAttribute {
+                    name_index,
+                    offset,
+                    body: cur.take(length as usize).context("attribute")?,
+                })
+            })
+            .collect()
+    }
+
+    fn offset_of(&self, index: u16) -> Result<usize, ErrorKind> {
+        match self.entries.get(index as usize) {
+            Some(&offset) if offset != 0 => Ok(offset),
+            _ => Err(ErrorKind::InvalidPoolIndex(index)),
+        }
+    }
+
+    /// Number of pool slots including index zero, the `constant_pool_count` of the class file.
+    pub fn pool_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Decodes the pool entry at `index`, UTF8 values are copied, see `utf8` to borrow them.
+    pub fn get(&self, index: u16) -> Result<ConstantPoolItem, ErrorKind> {
+        let offset = self.offset_of(index)?;
+        let mut rdr = ClassReader::with_offset(&self.bytes[offset..], offset as u64);
+        ConstantPool::parse_item(&mut rdr)
+            .map(|(item, _)| item)
+            .map_err(|e| e.kind)
+    }
+
+    /// The usable pool entries with their indexes.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, ConstantPoolItem)> + '_ {
+        (1..self.entries.len() as u16).filter_map(|index| Some((index, self.get(index).ok()?)))
+    }
+
+    /// The value of a UTF8 entry, borrowed from the input unless it contains characters that
+    /// modified UTF-8 encodes differently (NUL and supplementary characters).
+    pub fn utf8(&self, index: u16) -> Result<Cow<'a, str>, ErrorKind> {
+        let offset = self.offset_of(index)?;
+        let bytes = self.bytes;
+        if bytes[offset] != pool::CONSTANTPOOL_UTF8 {
+            return Err(ErrorKind::WrongPoolEntry(
+                index,
+                "UTF8",
+                self.get(index)?.kind(),
+            ));
+        }
+        let length = u16::from_be_bytes([bytes[offset + 1], bytes[offset + 2]]) as usize;
+        let value = &bytes[offset + 3..offset + 3 + length];
+        if !value.iter().any(|b| *b == 0 || *b >= 0xF0) {
+            if let Ok(value) = str::from_utf8(value) {
+                return Ok(Cow::Borrowed(value));
+            }
+        }
+        mutf8::decode(value)
+            .map(Cow::Owned)
+            .map_err(|_| ErrorKind::BadUtf8)
+    }
+
+    pub fn class_name(&self, index: u16) -> Result<Cow<'a, str>, ErrorKind> {
+        match self.get(index)? {
+            ConstantPoolItem::Class(name) => self.utf8(name),
+            item => Err(ErrorKind::WrongPoolEntry(index, "Class", item.kind())),
+        }
+    }
+
+    pub fn this_class_name(&self) -> Cow<'a, str> {
+        // Checked by `parse`.
+        self.class_name(self.this_class).unwrap_or_default()
+    }
+
+    pub fn super_class_name(&self) -> Option<Cow<'a, str>> {
+        match self.super_class {
+            0 => None,
+            index => self.class_name(index).ok(),
+        }
+    }
+
+    /// Pool indexes of the implemented interfaces.
+    pub fn interfaces(&self) -> impl Iterator<Item = u16> + 'a {
+        self.interfaces
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<&MemberView<'a>> {
+        self.methods
+            .iter()
+            .find(|method| method.name(self).is_ok_and(|item| item == name))
+    }
+
+    pub fn find_attribute(&self, name: &str) -> Option<&RawAttribute<'a>> {
+        find_attribute(self, &self.attributes, name)
+    }
+}
+
+impl AttributeNames for ClassView<'_> {
+    fn attribute_name(&self, index: u16) -> Result<Cow<'_, str>, ErrorKind> {
+        self.utf8(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::fs;
+    use std::path::Path;
+
+    use super::ClassView;
+    use crate::classfile::ClassFile;
+    use crate::error::ErrorKind;
+    use crate::testdata::MINIMAL_CLASS;
+
+    #[test]
+    fn test_view_matches_streaming_parser() {
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples");
+        for entry in fs::read_dir(samples).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "class") {
+                continue;
+            }
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::parse(bytes.as_slice()).unwrap();
+            let pool = &class.constant_pool;
+            let view = ClassView::parse(&bytes).unwrap();
+            assert_eq!(view.this_class_name(), class.this_class_name());
+            assert_eq!(
+                view.super_class_name().map(Cow::into_owned),
+                class.super_class_name()
+            );
+            assert_eq!(view.interfaces().collect::<Vec<_>>(), class.interfaces);
+            assert_eq!(view.pool_count(), pool.len() + 1);
+            assert_eq!(view.iter().count(), pool.iter().count());
+            for (method, expected) in view.methods.iter().zip(class.methods.iter()) {
+                assert_eq!(method.name(&view).unwrap(), expected.name(pool));
+                assert_eq!(method.descriptor(&view).unwrap(), expected.descriptor(pool));
+                for (raw, expected) in method.attributes.iter().zip(expected.attributes.iter()) {
+                    let decoded = raw.decode(&view).unwrap();
+                    assert_eq!(decoded.attribute.name(), expected.attribute.name());
+                    assert_eq!(
+                        decoded.attribute.details(pool),
+                        expected.attribute.details(pool)
+                    );
+                }
+            }
+            assert_eq!(view.attributes.len(), class.attributes.len());
+        }
+    }
+
+    #[test]
+    fn test_view_borrows_utf8() {
+        let view = ClassView::parse(&MINIMAL_CLASS).unwrap();
+        assert!(matches!(
+            view.utf8(3),
+            Ok(Cow::Borrowed("java/lang/Object"))
+        ));
+        assert_eq!(
+            view.utf8(2).unwrap_err(),
+            ErrorKind::WrongPoolEntry(2, "UTF8", "Class")
+        );
+        assert_eq!(view.utf8(5).unwrap_err(), ErrorKind::InvalidPoolIndex(5));
+
+        let mut bytes = MINIMAL_CLASS.to_vec();
+        bytes[13] = 0x80; // a lone continuation byte instead of `A`
+        let view = ClassView::parse(&bytes).unwrap_err();
+        assert_eq!(view.kind, ErrorKind::BadUtf8);
+        assert_eq!(view.context, vec!["this class"]);
+
+        let e = ClassView::parse(&MINIMAL_CLASS[..MINIMAL_CLASS.len() - 1]).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Truncated);
+        assert_eq!(e.context, vec!["class attributes", "attributes count"]);
+    }
+}
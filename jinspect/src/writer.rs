@@ -0,0 +1,67 @@
+use std::io::{self, Write};
+
+use crate::mutf8;
+
+/// Big-endian writer, the counterpart of `ClassReader`.
+pub struct ClassWriter<W> {
+    inner: W,
+}
+
+fn too_large(what: &str, value: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} {} does not fit into the class file format", what, value),
+    )
+}
+
+impl<W: Write> ClassWriter<W> {
+    pub fn new(inner: W) -> Self {
+        ClassWriter { inner }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn write_bytes(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.inner.write_all(buf)
+    }
+
+    pub fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    pub fn write_u16(&mut self, value: u16) -> io::Result<()> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    pub fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    /// Writes a `u16` count, `what` names the table in the error if it has too many entries.
+    pub fn write_count(&mut self, what: &str, count: usize) -> io::Result<()> {
+        let count = u16::try_from(count).map_err(|_| too_large(what, count))?;
+        self.write_u16(count)
+    }
+
+    /// Writes a `u32` length followed by the bytes.
+    pub fn write_length_prefixed(&mut self, what: &str, buf: &[u8]) -> io::Result<()> {
+        let length = u32::try_from(buf.len()).map_err(|_| too_large(what, buf.len()))?;
+        self.write_u32(length)?;
+        self.write_bytes(buf)
+    }
+
+    /// Writes a `u16` length followed by the string in the JVM's modified UTF-8.
+    pub fn write_str(&mut self, value: &str) -> io::Result<()> {
+        let bytes = mutf8::encode(value);
+        self.write_count("string length", bytes.len())?;
+        self.write_bytes(&bytes)
+    }
+
+    /// Writes a `u16` count followed by that many `u16` values.
+    pub fn write_u16_table(&mut self, what: &str, values: &[u16]) -> io::Result<()> {
+        self.write_count(what, values.len())?;
+        values.iter().try_for_each(|value| self.write_u16(*value))
+    }
+}